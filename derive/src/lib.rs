@@ -0,0 +1,156 @@
+// the explicit return style is used throughout this crate
+#![allow(clippy::needless_return)]
+
+//
+// #[derive(FromDispatch)] and #[derive(IntoDispatch)], which map a struct's fields onto an
+// automation object's properties.  the traits, the field conversions and the attributes are
+// described in hello_com_rust's object module, this only writes out the impls
+//
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr, parse_macro_input};
+
+#[proc_macro_derive(FromDispatch, attributes(dispatch))]
+pub fn derive_from_dispatch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    return from_dispatch(&input).unwrap_or_else(|e| e.to_compile_error()).into();
+}
+
+#[proc_macro_derive(IntoDispatch, attributes(dispatch))]
+pub fn derive_into_dispatch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    return into_dispatch(&input).unwrap_or_else(|e| e.to_compile_error()).into();
+}
+
+fn from_dispatch(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let mut fields = Vec::new();
+    for field in struct_fields(input)? {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let property = &field.property;
+        let value = if field.skip {
+            quote!(::std::default::Default::default())
+        } else if field.default {
+            quote!(<::std::option::Option<#ty> as ::hello_com_rust::FromProperty>::from_property(dispatch, #property)?.unwrap_or_default())
+        } else {
+            quote!(<#ty as ::hello_com_rust::FromProperty>::from_property(dispatch, #property)?)
+        };
+        fields.push(quote!(#ident: #value));
+    }
+    return Ok(quote! {
+        impl #impl_generics ::hello_com_rust::FromDispatch for #name #type_generics #where_clause {
+            fn from_dispatch(dispatch: &::hello_com_rust::Dispatch) -> ::std::result::Result<Self, ::hello_com_rust::Error> {
+                return ::std::result::Result::Ok(#name {
+                    #(#fields,)*
+                });
+            }
+        }
+    });
+}
+
+fn into_dispatch(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let mut puts = Vec::new();
+    for field in struct_fields(input)? {
+        if field.skip || field.read_only {
+            continue;
+        }
+        let ident = &field.ident;
+        let property = &field.property;
+        puts.push(quote!(::hello_com_rust::IntoProperty::write_property(&self.#ident, dispatch, #property)?;));
+    }
+    return Ok(quote! {
+        impl #impl_generics ::hello_com_rust::IntoDispatch for #name #type_generics #where_clause {
+            fn put_into(&self, dispatch: &::hello_com_rust::Dispatch) -> ::std::result::Result<(), ::hello_com_rust::Error> {
+                #(#puts)*
+                return ::std::result::Result::Ok(());
+            }
+        }
+    });
+}
+
+struct Field {
+    ident: syn::Ident,
+    ty: syn::Type,
+    // the name of the property it's read from and written to
+    property: String,
+    skip: bool,
+    default: bool,
+    read_only: bool,
+}
+
+fn struct_fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "only structs with named fields can be mapped onto an automation object")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "only structs can be mapped onto an automation object")),
+    };
+
+    let mut pascal_case = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("dispatch")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                let rule: LitStr = meta.value()?.parse()?;
+                if rule.value() != "PascalCase" {
+                    return Err(syn::Error::new_spanned(rule, "the only rename_all is \"PascalCase\""));
+                }
+                pascal_case = true;
+                return Ok(());
+            }
+            return Err(meta.error("unknown dispatch attribute, expected rename_all"));
+        })?;
+    }
+
+    let mut fields = Vec::new();
+    for field in &named.named {
+        let ident = field.ident.clone().expect("named fields have names");
+        let unraw = ident.to_string().trim_start_matches("r#").to_string();
+        let mut mapped = Field {
+            property: if pascal_case { to_pascal_case(&unraw) } else { unraw },
+            ident,
+            ty: field.ty.clone(),
+            skip: false,
+            default: false,
+            read_only: false,
+        };
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("dispatch")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let property: LitStr = meta.value()?.parse()?;
+                    mapped.property = property.value();
+                } else if meta.path.is_ident("skip") {
+                    mapped.skip = true;
+                } else if meta.path.is_ident("default") {
+                    mapped.default = true;
+                } else if meta.path.is_ident("read_only") {
+                    mapped.read_only = true;
+                } else {
+                    return Err(meta.error("unknown dispatch attribute, expected rename, skip, default or read_only"));
+                }
+                return Ok(());
+            })?;
+        }
+        fields.push(mapped);
+    }
+    return Ok(fields);
+}
+
+// account_ref -> AccountRef
+fn to_pascal_case(name: &str) -> String {
+    let mut pascal = String::with_capacity(name.len());
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            pascal.extend(first.to_uppercase());
+            pascal.push_str(chars.as_str());
+        }
+    }
+    return pascal;
+}
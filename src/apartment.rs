@@ -0,0 +1,106 @@
+use std::marker::PhantomData;
+use windows::Win32::System::Com::{COINIT, COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED, COINIT_SPEED_OVER_MEMORY, CoInitializeEx, CoUninitialize};
+use crate::{Error, hresult};
+
+//
+// COM has to be initialized on every thread that uses it, choosing the kind of apartment the
+// thread joins, and uninitialized again once the thread's done with it.  ComApartment does both:
+//
+//   let _apartment = ComApartment::sta()?;
+//   let excel = co_create_dispatch(&clsid_from_prog_id("Excel.Application")?)?;
+//   ...
+//   // excel has to be dropped before the apartment, so declare the apartment first
+//
+// initializing a thread that's already initialized is fine, each guard balances its own init.
+// a thread that's already in the other kind of apartment (RPC_E_CHANGED_MODE, e.g. a host
+// initialized it before calling us) can still use COM, so that's not an error either; the guard
+// reports the apartment the thread is really in and leaves it initialized on drop.
+//
+// the guard is tied to its thread (it's !Send), since CoUninitialize has to be called on the
+// thread that called CoInitializeEx
+//
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ApartmentKind {
+    // single threaded (COINIT_APARTMENTTHREADED), what most servers, Excel and Sage included,
+    // expect.  objects must only be used from the thread that created them, and the thread has to
+    // pump messages if it waits for long
+    SingleThreaded,
+    // multithreaded (COINIT_MULTITHREADED), objects can be used from any thread in the apartment
+    MultiThreaded,
+}
+
+impl ApartmentKind {
+    fn coinit(self) -> COINIT {
+        return match self {
+            ApartmentKind::SingleThreaded => COINIT_APARTMENTTHREADED,
+            ApartmentKind::MultiThreaded => COINIT_MULTITHREADED,
+        };
+    }
+
+    fn other(self) -> ApartmentKind {
+        return match self {
+            ApartmentKind::SingleThreaded => ApartmentKind::MultiThreaded,
+            ApartmentKind::MultiThreaded => ApartmentKind::SingleThreaded,
+        };
+    }
+}
+
+// COM initialized on the current thread until it's dropped
+#[derive(Debug)]
+pub struct ComApartment {
+    kind: ApartmentKind,
+    // false when the thread was already in the other kind of apartment, which isn't ours to end
+    uninitialize: bool,
+    // a raw pointer makes it !Send and !Sync
+    thread: PhantomData<*const ()>,
+}
+
+impl ComApartment {
+    // a single threaded apartment, see ApartmentKind
+    pub fn sta() -> Result<ComApartment,Error> {
+        return ComApartment::new(ApartmentKind::SingleThreaded, false);
+    }
+
+    // the multithreaded apartment, see ApartmentKind
+    pub fn mta() -> Result<ComApartment,Error> {
+        return ComApartment::new(ApartmentKind::MultiThreaded, false);
+    }
+
+    // speed_over_memory is COINIT_SPEED_OVER_MEMORY, which trades memory for speed in e.g. the
+    // allocator.  it has no effect on a thread that's already initialized
+    pub fn new(kind: ApartmentKind, speed_over_memory: bool) -> Result<ComApartment,Error> {
+        let mut coinit = kind.coinit();
+        if speed_over_memory {
+            coinit |= COINIT_SPEED_OVER_MEMORY;
+        }
+        // S_FALSE (already initialized the same way) is a success, and needs uninitializing just
+        // the same as S_OK
+        let result = unsafe { CoInitializeEx(None, coinit) };
+        let (kind, uninitialize) = match result {
+            Ok(()) => (kind, true),
+            Err(e) if e.code().0 == hresult::RPC_E_CHANGED_MODE => (kind.other(), false),
+            Err(e) => return Err(e.into()),
+        };
+        return Ok(ComApartment { kind, uninitialize, thread: PhantomData });
+    }
+
+    // the kind of apartment the thread is in, which is the other kind from the one asked for when
+    // something else had already initialized the thread
+    pub fn kind(&self) -> ApartmentKind {
+        return self.kind;
+    }
+
+    // whether the guard initialized the thread (possibly again), and so uninitializes it on drop
+    pub fn is_owned(&self) -> bool {
+        return self.uninitialize;
+    }
+}
+
+impl Drop for ComApartment {
+    fn drop(&mut self) {
+        if self.uninitialize {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::Variant;
+
+// an argument to a method call.  by value is the usual case, by reference lets the callee write a
+// new value back into the caller's variant (an [out] or [in,out] parameter)
+pub enum Argument<'a> {
+    ByVal(&'a Variant),
+    // passed as VT_BYREF of the variant's current type, e.g. VT_BYREF | VT_BSTR for a string (an
+    // empty or null variant is passed as VT_BYREF | VT_VARIANT since it has no type to offer)
+    ByRef(&'a mut Variant),
+    // always passed as VT_BYREF | VT_VARIANT, for parameters declared as VARIANT*
+    ByRefVariant(&'a mut Variant),
+}
+
+impl<'a> Argument<'a> {
+    pub fn by_val(value: &'a Variant) -> Argument<'a> {
+        Argument::ByVal(value)
+    }
+
+    pub fn by_ref(value: &'a mut Variant) -> Argument<'a> {
+        Argument::ByRef(value)
+    }
+
+    pub fn by_ref_variant(value: &'a mut Variant) -> Argument<'a> {
+        Argument::ByRefVariant(value)
+    }
+
+    pub fn value(&self) -> &Variant {
+        return match self {
+            Argument::ByVal(value) => value,
+            Argument::ByRef(value) | Argument::ByRefVariant(value) => value,
+        };
+    }
+
+    pub fn is_by_ref(&self) -> bool {
+        return !matches!(self, Argument::ByVal(_));
+    }
+
+    // replaces the caller's variant with what the callee wrote back, ignored for by value arguments
+    pub fn set_value(&mut self, value: Variant) {
+        match self {
+            Argument::ByVal(_) => {},
+            Argument::ByRef(target) | Argument::ByRefVariant(target) => **target = value,
+        }
+    }
+}
+
+impl<'a> From<&'a Variant> for Argument<'a> {
+    fn from(value: &'a Variant) -> Argument<'a> {
+        Argument::ByVal(value)
+    }
+}
+
+impl<'a> From<&'a mut Variant> for Argument<'a> {
+    fn from(value: &'a mut Variant) -> Argument<'a> {
+        Argument::ByRef(value)
+    }
+}
@@ -0,0 +1,179 @@
+use std::ffi::c_void;
+use serde::{Deserialize, Serialize};
+use crate::{Argument, Dispatch, Error, hresult, Variant};
+use crate::path::MemberPath;
+use crate::typelib::TypeInfo;
+
+// the special dispids automation reserves, the same values on every platform
+pub const DISPID_VALUE: i32 = 0;
+pub const DISPID_PROPERTYPUT: i32 = -3;
+pub const DISPID_NEWENUM: i32 = -4;
+
+// what an invoke is doing with the member, the wFlags of IDispatch::Invoke
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InvokeKind {
+    Method,
+    PropertyGet,
+    PropertyPut,
+    // assigns an object reference rather than a value (vb's Set)
+    PropertyPutRef,
+}
+
+impl InvokeKind {
+    pub fn is_put(self) -> bool {
+        return matches!(self, InvokeKind::PropertyPut | InvokeKind::PropertyPutRef);
+    }
+}
+
+// an automation object, the equivalent of IDispatch.  a backend only has to implement the name
+// lookup and invoke (plus cloning and identity so a Dispatch can hold it), all the gets, puts and
+// calls are built on those two exactly the way COM lays them out:
+//  - arguments are positional, in the caller's order
+//  - named arguments pair up with named_dispids, in the same order
+//  - a put's value is always the first named argument, with DISPID_PROPERTYPUT, and any index
+//    arguments of the property are positional
+pub trait Automation {
+    // the member's dispid followed by those of the given parameter names
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error>;
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error>;
+
+    // another reference to the same object (for COM this AddRef's it)
+    fn clone_object(&self) -> Box<dyn Automation>;
+
+    // the same value for every reference to the same object, used for equality
+    fn identity(&self) -> usize;
+
+    // the items of a collection, from its _NewEnum member, or None if it doesn't have one.  any
+    // other failure getting the enumerator is an error
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        return Ok(None);
+    }
+
+    // the raw IDispatch pointer when this is a real COM object, needed to pass it to another one
+    fn as_raw(&self) -> Option<*mut c_void> {
+        return None;
+    }
+
+    // the object's own description of its members, from IDispatch::GetTypeInfo.  objects aren't
+    // obliged to give one
+    fn type_info(&self) -> Result<TypeInfo,Error> {
+        return Err(Error::com(hresult::E_NOTIMPL, "the object doesn't describe itself"));
+    }
+
+    fn get_id_of_name(&self, name: &str) -> Result<i32,Error> {
+        return Ok(self.get_ids_of_names(name, &[])?[0]);
+    }
+
+    // the dispids of several members, in the same order.  IDispatch::GetIDsOfNames can only look up
+    // one member at a time (any other names are taken as its parameters), so by default this asks
+    // for each in turn, but a backend that can do better (like a proxy to a COM thread) overrides it
+    fn get_ids_of_members(&self, names: &[&str]) -> Result<Vec<i32>,Error> {
+        return names.iter().map(|name| self.get_id_of_name(name)).collect();
+    }
+
+    fn get_property(&self, name: &str) -> Result<Variant,Error> {
+        return self.get_property_with_args(name, &[]);
+    }
+
+    // gets a parameterised (indexed) property, e.g. excel's Range("A1") or Cells(row, col)
+    fn get_property_with_args(&self, name: &str, args: &[Variant]) -> Result<Variant,Error> {
+        let dispid = self.get_id_of_name(name)?;
+        let mut arguments: Vec<Argument> = args.iter().map(Argument::ByVal).collect();
+        return self.invoke(dispid, InvokeKind::PropertyGet, &mut arguments, &[], &mut []);
+    }
+
+    fn put_property(&self, name: &str, value: &Variant) -> Result<(),Error> {
+        return self.put_property_named(name, value, &[]);
+    }
+
+    // puts a parameterised (indexed) property, e.g. Fields.Item("NAME") = value
+    fn put_property_with_args(&self, name: &str, args: &[Variant], value: &Variant) -> Result<(),Error> {
+        let dispid = self.get_id_of_name(name)?;
+        return put_property_with_dispids(self, InvokeKind::PropertyPut, &[dispid], args, value, &[]);
+    }
+
+    // assigns an object reference to a property (vb's Set), e.g. a chart's data source.  the value
+    // is normally a Variant::Dispatch
+    fn put_property_ref(&self, name: &str, value: &Variant) -> Result<(),Error> {
+        let dispid = self.get_id_of_name(name)?;
+        return put_property_with_dispids(self, InvokeKind::PropertyPutRef, &[dispid], &[], value, &[]);
+    }
+
+    // puts a property that takes extra optional parameters, passed by name
+    fn put_property_named(&self, name: &str, value: &Variant, named_values: &[(&str, Variant)]) -> Result<(),Error> {
+        let dispids = self.get_ids_of_names(name, &param_names(named_values))?;
+        return put_property_with_dispids(self, InvokeKind::PropertyPut, &dispids, &[], value, named_values);
+    }
+
+    fn call_method(&self, name: &str, values: &[Variant]) -> Result<Variant,Error> {
+        return self.call_method_named(name, values, &[]);
+    }
+
+    // same as call_method, but any argument passed by reference is updated with the value the
+    // callee wrote back to it
+    fn call_method_by_ref(&self, name: &str, arguments: &mut [Argument]) -> Result<Variant,Error> {
+        let dispid = self.get_id_of_name(name)?;
+        return self.invoke(dispid, InvokeKind::Method, arguments, &[], &mut []);
+    }
+
+    // calls a method with positional values followed by optional parameters passed by name, so
+    // e.g. only the few of Workbooks.Open's many parameters that matter need to be given
+    fn call_method_named(&self, name: &str, values: &[Variant], named_values: &[(&str, Variant)]) -> Result<Variant,Error> {
+        let dispids = self.get_ids_of_names(name, &param_names(named_values))?;
+        let mut arguments: Vec<Argument> = values.iter().map(Argument::ByVal).collect();
+        let mut named_arguments: Vec<Argument> = named_values.iter().map(|(_, v)| Argument::ByVal(v)).collect();
+        return self.invoke(dispids[0], InvokeKind::Method, &mut arguments, &dispids[1..], &mut named_arguments);
+    }
+
+    // gets the value at the end of a dotted path, e.g. "Workbooks.Item(1).ActiveSheet.Name", see
+    // MemberPath
+    fn eval(&self, path: &str) -> Result<Variant,Error> {
+        return MemberPath::parse(path)?.eval(self);
+    }
+
+    // puts a value at the end of a dotted path, e.g. "ActiveSheet.Range(\"A1\").Value"
+    fn set(&self, path: &str, value: &Variant) -> Result<(),Error> {
+        return MemberPath::parse(path)?.set(self, value);
+    }
+}
+
+// the items of a collection, the equivalent of IEnumVARIANT
+pub trait Enumerator {
+    // fetches up to count items at once, fewer (possibly none) means the end has been reached
+    fn next_batch(&mut self, count: usize) -> Result<Vec<Variant>,Error>;
+}
+
+// an object that might not support automation, the equivalent of IUnknown
+pub trait UnknownObject {
+    // asks the object whether it also supports automation
+    fn query_dispatch(&self) -> Result<Dispatch,Error>;
+
+    fn clone_object(&self) -> Box<dyn UnknownObject>;
+
+    fn identity(&self) -> usize;
+
+    // the raw IUnknown pointer when this is a real COM object
+    fn as_raw(&self) -> Option<*mut c_void> {
+        return None;
+    }
+}
+
+fn param_names<'a>(named_values: &[(&'a str, Variant)]) -> Vec<&'a str> {
+    return named_values.iter().map(|(param_name, _)| *param_name).collect();
+}
+
+// dispids are the property's followed by those of the named values
+fn put_property_with_dispids<A: Automation + ?Sized>(object: &A, kind: InvokeKind, dispids: &[i32], args: &[Variant], value: &Variant, named_values: &[(&str, Variant)]) -> Result<(),Error> {
+    // https://learn.microsoft.com/en-us/previous-versions/windows/desktop/automat/getting-and-setting-properties
+    // the value being put is always the first named argument, with the special DISPID_PROPERTYPUT,
+    // and any index arguments follow it positionally
+    let mut named_dispids = vec![DISPID_PROPERTYPUT];
+    named_dispids.extend_from_slice(&dispids[1..]);
+    let mut named_arguments = vec![Argument::ByVal(value)];
+    named_arguments.extend(named_values.iter().map(|(_, v)| Argument::ByVal(v)));
+    let mut arguments: Vec<Argument> = args.iter().map(Argument::ByVal).collect();
+
+    object.invoke(dispids[0], kind, &mut arguments, &named_dispids, &mut named_arguments)?;
+    return Ok(());
+}
@@ -1,17 +1,29 @@
+// the real example needs windows, elsewhere only a stub main is built
+#![cfg_attr(not(windows), allow(unused_imports))]
+
 use std::thread;
 use std::time::Duration;
+#[cfg(windows)]
 use windows::core::GUID;
 use hello_com_rust::*;
 
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("this example drives a real COM server, so it only runs on windows");
+}
+
+#[cfg(windows)]
 fn main() {
     println!("initializing com...");
-    co_initialize().unwrap();
+    // every object has to be dropped before the apartment, so it's declared first
+    let _apartment = ComApartment::sta().unwrap();
 
     let prog_id = "Excel.Application";
     let clsid: GUID = clsid_from_prog_id(prog_id).unwrap();
     println!("resolved prog_id {} -> clsid {:?}", prog_id, clsid);
 
-    let excel = co_create_dispatch(&clsid).unwrap();
+    // excel turns calls away while it's busy (a dialog's open, or it's recalculating), so retry them
+    let excel = co_create_dispatch(&clsid).unwrap().with_retry(RetryPolicy::new().with_deadline(Duration::from_secs(30)));
     println!("created excel: {}", excel);
 
     let visible_prop1 = excel.get_property("Visible").unwrap();
@@ -23,18 +35,42 @@ fn main() {
     println!("pausing for 2 secs...");
     thread::sleep(Duration::from_secs(2));
 
-    let workbooks = excel.get_property("Workbooks").unwrap().to_dispatch().unwrap();
+    let workbooks = excel.get_property("Workbooks").unwrap().into_dispatch().unwrap();
     println!("workbooks: {}", workbooks);
 
-    let new_workbook = workbooks.call_method("Add", &[]).unwrap().to_dispatch().unwrap();
+    let new_workbook = workbooks.call_method("Add", &[]).unwrap().into_dispatch().unwrap();
     println!("new_workbook: {}", new_workbook);
 
-    let active_sheet = new_workbook.get_property("ActiveSheet").unwrap().to_dispatch().unwrap();
+    let active_sheet = new_workbook.get_property("ActiveSheet").unwrap().into_dispatch().unwrap();
     println!("active_sheet: {}", active_sheet);
 
     active_sheet.put_property("Name", &Variant::from("My Test Sheet!")).unwrap();
     println!("successfully set sheet name");
 
+    let sheets = new_workbook.get_property("Sheets").unwrap().into_dispatch().unwrap();
+    for sheet in &sheets {
+        let sheet = sheet.unwrap().into_dispatch().unwrap();
+        println!("sheet: {}", sheet.get_property("Name").unwrap());
+    }
+
+    // write and then read back a whole block of cells in a single call each
+    let range = active_sheet.get_property_with_args("Range", &[Variant::from("A1:C2")]).unwrap().into_dispatch().unwrap();
+    let cells = Variant::try_from(vec![
+        vec![Variant::from("Item"), Variant::from("Qty"), Variant::from("Price")],
+        vec![Variant::from("Widget"), Variant::from(3), Variant::from(2.5)],
+    ]).unwrap();
+    range.put_property("Value", &cells).unwrap();
+    println!("wrote cells: {}", cells);
+
+    let values = range.get_property("Value").unwrap();
+    let rows = Vec::<Vec<Variant>>::try_from(&values).unwrap();
+    println!("read back {} row(s): {:?}", rows.len(), rows);
+
+    // the same kind of chain, written as a path
+    excel.set("ActiveSheet.Cells(3, 1).Value", &Variant::from("Total")).unwrap();
+    println!("cell A3: {}", excel.eval("ActiveSheet.Cells(3, 1).Value").unwrap());
+    println!("first sheet: {}", excel.eval("Workbooks.Item(1).Sheets.Item(1).Name").unwrap());
+
     println!("pausing for 5 secs");
     thread::sleep(Duration::from_secs(5));
 
@@ -1,13 +1,60 @@
+// the real example needs windows, elsewhere only a stub main is built
+#![cfg_attr(not(windows), allow(unused_imports, dead_code))]
+
 use std::thread;
 use std::time::Duration;
+#[cfg(windows)]
 use windows::core::GUID;
 use hello_com_rust::*;
 
+// the enums from QBXMLRP2's type library, with just the values used here.  the type library only
+// comes with the QuickBooks SDK, so they're written out by hand in the same shape codegen gives
+// enums (a newtype over i32 with a constant for each value) rather than generated by a build script
+#[allow(non_upper_case_globals, clippy::needless_return)]
+mod qbxmlrp2 {
+    use hello_com_rust::Variant;
+
+    // QBXMLRPConnectionType
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct QBXMLRPConnectionType(pub i32);
+
+    impl QBXMLRPConnectionType {
+        pub const localQBD: QBXMLRPConnectionType = QBXMLRPConnectionType(1);
+    }
+
+    impl From<QBXMLRPConnectionType> for Variant {
+        fn from(value: QBXMLRPConnectionType) -> Variant {
+            return Variant::I4(value.0);
+        }
+    }
+
+    // QBFileMode
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct QBFileMode(pub i32);
+
+    impl QBFileMode {
+        pub const qbFileOpenMultiUser: QBFileMode = QBFileMode(1);
+    }
+
+    impl From<QBFileMode> for Variant {
+        fn from(value: QBFileMode) -> Variant {
+            return Variant::I4(value.0);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("this example drives a real COM server, so it only runs on windows");
+}
+
+#[cfg(windows)]
 fn main() {
     let data_file = "C:\\Users\\Public\\Documents\\Intuit\\QuickBooks\\Company Files\\Fizzed Consulting.qbw";
 
     println!("initializing com...");
-    co_initialize().unwrap();
+    // every object has to be dropped before the apartment, so it's declared first
+    let _apartment = ComApartment::sta().unwrap();
 
     let prog_id = "QBXMLRP2.RequestProcessor";
     let clsid: GUID = clsid_from_prog_id(prog_id).unwrap();
@@ -17,12 +64,12 @@ fn main() {
     println!("request_processor: {}", request_processor);
 
     let connected = request_processor.call_method("OpenConnection2", &[
-        Variant::from(""), Variant::from("Dagger Desktop"), Variant::from(1)
+        Variant::from(""), Variant::from("Dagger Desktop"), Variant::from(qbxmlrp2::QBXMLRPConnectionType::localQBD)
     ]).unwrap();
     println!("connected: {}", connected);
 
     let session = request_processor.call_method("BeginSession", &[
-        Variant::from(data_file), Variant::from(1)
+        Variant::from(data_file), Variant::from(qbxmlrp2::QBFileMode::qbFileOpenMultiUser)
     ]).unwrap();
     println!("session: {}", session);
 
@@ -1,8 +1,26 @@
+// the real example needs windows, elsewhere only a stub main is built
+#![cfg_attr(not(windows), allow(unused_imports))]
+
 use std::{thread};
 use std::time::{Duration, Instant};
+#[cfg(windows)]
 use windows::core::GUID;
 use hello_com_rust::*;
 
+// an sdo Field, filled in from its Name and Value properties
+#[cfg(windows)]
+#[derive(FromDispatch)]
+struct SdoField {
+    name: String,
+    value: Variant,
+}
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("this example drives a real COM server, so it only runs on windows");
+}
+
+#[cfg(windows)]
 fn main() {
     //
     // NOTE: JetBrains RustRover IDE causes a weird issue when connecting to SDO, but only in the IDE
@@ -18,19 +36,26 @@ fn main() {
     let data_dir = "C:\\PROGRAMDATA\\SAGE\\ACCOUNTS\\2023\\COMPANY.000\\ACCDATA\\";
 
     println!("initializing com...");
-    co_initialize().unwrap();
+    // every object has to be dropped before the apartment, so it's declared first
+    let _apartment = ComApartment::sta().unwrap();
 
     // version 29 of sdo (other versions have other clsid)
     let clsid = GUID::from("663048C4-DAEA-4125-9F02-4F1DFB8F4666");
     println!("resolved clsid {:?}", clsid);
 
-    let sdo_engine = co_create_dispatch(&clsid).unwrap();
+    // set SDO_RECORD to a file to log the whole session to it, so it can be replayed elsewhere
+    let recorder = std::env::var("SDO_RECORD").ok().map(|path| record::Recorder::create(path).unwrap());
+
+    let mut sdo_engine = co_create_dispatch(&clsid).unwrap();
+    if let Some(recorder) = &recorder {
+        sdo_engine = recorder.record(&sdo_engine);
+    }
     println!("sdo_engine: {}", sdo_engine);
 
-    let sdo_workspaces = sdo_engine.get_property("Workspaces").unwrap().to_dispatch().unwrap();
+    let sdo_workspaces = sdo_engine.get_property("Workspaces").unwrap().into_dispatch().unwrap();
     println!("sdo_workspaces: {}", sdo_workspaces);
 
-    let sdo_workspace = sdo_workspaces.call_method("Add", &[Variant::from("Dext Commerce")]).unwrap().to_dispatch().unwrap();
+    let sdo_workspace = sdo_workspaces.call_method("Add", &[Variant::from("Dext Commerce")]).unwrap().into_dispatch().unwrap();
     println!("sdo_workspace: {}", sdo_workspace);
 
     // let sageDir2 = "C:\\ProgramData\\Sage\\Accounts\\2023\\";
@@ -49,26 +74,29 @@ fn main() {
 
     let now = Instant::now();
 
-    let setup_data = sdo_workspace.call_method("CreateObject", &[Variant::from("SetupData")]).unwrap().to_dispatch().unwrap();
+    let setup_data = sdo_workspace.call_method("CreateObject", &[Variant::from("SetupData")]).unwrap().into_dispatch().unwrap();
     println!("setup_data: {}", setup_data);
 
     let moved = setup_data.call_method("MoveFirst", &[]).unwrap();
     println!("moved: {}", moved);
 
-    let fields = setup_data.get_property("Fields").unwrap().to_dispatch().unwrap();
+    let fields = setup_data.get_property("Fields").unwrap().into_dispatch().unwrap();
     println!("fields: {}", fields);
 
-    let field_count = fields.get_property("Count").unwrap().to_i32().unwrap();
-    println!("field_count: {}", field_count);
+    // every field is the same type of object, so share one dispid cache between them all and the
+    // names are only looked up on the first field
+    let field_dispids = DispidCache::new();
 
-    for i in 0..field_count {
-        let item = fields.call_method("Item", &[Variant::from(i+1)]).unwrap().to_dispatch().unwrap();
-        let _name = item.get_property("Name").unwrap();
-        let _value = item.get_property("Value").unwrap();
-        //println!("field: {} => {:?}", _name, _value);
+    let mut dumped = Vec::new();
+    for field in &fields {
+        let item = field.unwrap().into_dispatch().unwrap().with_dispid_cache(&field_dispids);
+        dumped.push(SdoField::from_dispatch(&item).unwrap());
     }
 
-    println!("took {:.2?} to dump setupData", now.elapsed());
+    println!("took {:.2?} to dump {} setupData fields", now.elapsed(), dumped.len());
+    if let Some(first) = dumped.first() {
+        println!("first field: {} => {:?}", first.name, first.value);
+    }
 
     println!("pausing for 5 secs");
     thread::sleep(Duration::from_secs(5));
@@ -76,5 +104,9 @@ fn main() {
     sdo_workspace.call_method("Disconnect", &[]).unwrap();
     println!("disconnected");
 
+    if let Some(recorder) = &recorder {
+        recorder.finish().unwrap();
+    }
+
     println!("done, exiting!");
 }
\ No newline at end of file
@@ -0,0 +1,28 @@
+// writes typed rust wrappers for a type library, see codegen.  runs anywhere, the type library can
+// be one copied off a windows machine:
+//
+//   tlbgen "C:\Program Files (x86)\Common Files\Sage SData\sdo.tlb" src/sdo.rs
+//
+// without an output file the wrappers are written to stdout
+
+use std::process::ExitCode;
+use hello_com_rust::codegen;
+use hello_com_rust::typelib::TypeLibrary;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.len() > 2 || args[0].starts_with('-') {
+        eprintln!("usage: tlbgen <type library> [output.rs]");
+        return ExitCode::FAILURE;
+    }
+
+    let result = match args.get(1) {
+        Some(output) => codegen::generate_file(&args[0], output),
+        None => TypeLibrary::open(&args[0]).map(|library| print!("{}", codegen::generate(&library))),
+    };
+    if let Err(e) = result {
+        eprintln!("tlbgen: {}", e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
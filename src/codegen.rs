@@ -0,0 +1,573 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::result::Result;
+use crate::{Error, hresult, InvokeKind, VarType};
+use crate::automation::DISPID_NEWENUM;
+use crate::typelib::{FUNCFLAG_FRESTRICTED, Function, Param, TYPEFLAG_FCANCREATE, TypeDesc, TypeInfo, TypeKind, TypeLibrary, TypeRef, Variable, VT_HRESULT, VT_LPSTR, VT_LPWSTR, VT_VOID};
+
+//
+// generates typed rust wrappers from a type library, so a misspelt member is a compile error rather
+// than a DISP_E_UNKNOWNNAME at runtime.  the output is one module named after the library:
+//  - every dispinterface and dual interface becomes a struct around a Dispatch with a method per
+//    member (Name, set_name for its put, and so on), invoked by the dispid in the type library
+//  - every enum becomes a newtype over i32 with its constants, which converts into a Variant
+//  - every coclass becomes a struct with its CLSID, and a create() on windows when it's creatable
+//
+// it's written by the tlbgen binary, or from a build script:
+//
+//   // build.rs
+//   hello_com_rust::codegen::build("typelibs/sdo.tlb", "sdo.rs").unwrap();
+//
+//   // main.rs
+//   include!(concat!(env!("OUT_DIR"), "/sdo.rs"));
+//   let workspace = sdo::IWorkSpace::from(sdo_engine.get_property("Workspaces")?.into_dispatch()?);
+//   workspace.connect(data_path, "manager", "", None)?;
+//
+// arguments take plain rust types where there's one for the parameter's type (&str for a BSTR,
+// the generated enum for an enum, the generated struct for an interface pointer) and &Variant
+// otherwise, optional ones are an Option and None leaves them out.  results are converted the same
+// way where the Variant has a conversion for them, otherwise the Variant is returned as is.  a
+// vtable function's [out, retval] parameter is its result
+//
+
+// where the generated code finds this crate
+const CRATE: &str = "hello_com_rust";
+
+// every keyword and reserved word, including gen from the 2024 edition since the generated code
+// could be included in a crate on any edition
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+    "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// base interfaces and aliases are followed at most this deep, a broken library could loop
+const MAX_DEPTH: usize = 16;
+
+// the rust source of the wrappers for the library
+pub fn generate(library: &TypeLibrary) -> String {
+    return Generator::new(library).generate();
+}
+
+// reads the type library and writes the wrappers for it to output
+pub fn generate_file<P: AsRef<Path>, Q: AsRef<Path>>(type_library: P, output: Q) -> Result<(),Error> {
+    let library = TypeLibrary::open(type_library)?;
+    let output = output.as_ref();
+    fs::write(output, generate(&library))
+        .map_err(|e| Error::com(hresult::E_FAIL, format!("failed writing {}: {}", output.display(), e)))?;
+    return Ok(());
+}
+
+// for build scripts, writes the wrappers to file_name in OUT_DIR and has cargo rerun the build
+// script when the type library changes.  returns the path written
+pub fn build<P: AsRef<Path>>(type_library: P, file_name: &str) -> Result<PathBuf,Error> {
+    let out_dir = std::env::var_os("OUT_DIR")
+        .ok_or_else(|| Error::com(hresult::E_FAIL, "OUT_DIR isn't set, codegen::build is meant to be called from a build script"))?;
+    let output = Path::new(&out_dir).join(file_name);
+    generate_file(type_library.as_ref(), &output)?;
+    println!("cargo:rerun-if-changed={}", type_library.as_ref().display());
+    return Ok(output);
+}
+
+// the module name for a library, e.g. "SageDataObject50" is sage_data_object50
+pub fn module_name(library: &TypeLibrary) -> String {
+    return snake_case(&library.name);
+}
+
+// what a parameter or result is on the rust side
+enum Target {
+    // a type the Variant conversions know
+    Base(VarType),
+    // a string, whatever its flavour in the library
+    String,
+    // a generated enum
+    Enum(String),
+    // a generated interface wrapper
+    Object(String),
+    Dispatch,
+    Unknown,
+    // anything else (arrays, records, pointers to non-automation interfaces) stays a Variant
+    Other,
+}
+
+struct Generator<'a> {
+    library: &'a TypeLibrary,
+    // the rust name of each type in the library, None for the ones that aren't generated
+    names: Vec<Option<String>>,
+    out: String,
+}
+
+impl<'a> Generator<'a> {
+    fn new(library: &'a TypeLibrary) -> Generator<'a> {
+        let mut used = HashSet::new();
+        let names = library.types.iter().map(|info| {
+            let generated = match info.kind {
+                TypeKind::Enum | TypeKind::Dispatch | TypeKind::CoClass => true,
+                TypeKind::Interface => info.is_dual(),
+                TypeKind::Alias => true,
+                _ => false,
+            };
+            return if generated { Some(unique(identifier(&info.name), &mut used)) } else { None };
+        }).collect();
+        return Generator { library, names, out: String::new() };
+    }
+
+    fn generate(mut self) -> String {
+        let library = self.library;
+        let mut header = format!("// generated from the {} {}.{} type library", library.name, library.major_version, library.minor_version);
+        if let Some(doc) = &library.doc {
+            header.push_str(&format!(" ({})", doc));
+        }
+        let mut out = format!("{} by hello-com-rust's codegen, don't edit\n\n", header);
+        out.push_str("#[allow(dead_code, unused_imports, non_camel_case_types, non_snake_case, non_upper_case_globals, clippy::all)]\n");
+        let _ = writeln!(out, "pub mod {} {{", module_name(library));
+        let _ = writeln!(out, "    use {}::{{Argument, Automation, Currency, Decimal, Dispatch, DispatchIter, DISPID_PROPERTYPUT, Error, InvokeKind, OleDate, Unknown, Variant}};", CRATE);
+
+        for (index, info) in library.types.iter().enumerate() {
+            let name = match &self.names[index] {
+                Some(name) => name.clone(),
+                None => continue,
+            };
+            match info.kind {
+                TypeKind::Enum => self.enumeration(&name, info),
+                TypeKind::CoClass => self.coclass(&name, info),
+                TypeKind::Alias => self.alias(&name, info),
+                _ => self.interface(&name, info),
+            }
+        }
+        for line in self.out.lines() {
+            if line.is_empty() {
+                out.push('\n');
+            } else {
+                let _ = writeln!(out, "    {}", line);
+            }
+        }
+        out.push_str("}\n");
+        return out;
+    }
+
+    fn comment(&mut self, name: &str, doc: &Option<String>) {
+        match doc {
+            Some(doc) if !doc.is_empty() => { let _ = writeln!(self.out, "// {}, {}", name, doc); },
+            _ => { let _ = writeln!(self.out, "// {}", name); },
+        }
+    }
+
+    fn enumeration(&mut self, name: &str, info: &TypeInfo) {
+        self.out.push('\n');
+        self.comment(&info.name, &info.doc);
+        let _ = writeln!(self.out, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]");
+        let _ = writeln!(self.out, "pub struct {}(pub i32);", name);
+        let _ = writeln!(self.out, "\nimpl {} {{", name);
+        let mut used = HashSet::new();
+        for variable in &info.variables {
+            // an enum's values are all integers, anything else is skipped rather than guessed at
+            if let Some(value) = variable.value.as_ref().and_then(|v| v.to_i32().ok()) {
+                let constant = unique(identifier(&variable.name), &mut used);
+                let _ = writeln!(self.out, "    pub const {}: {} = {}({});", constant, name, name, value);
+            }
+        }
+        let _ = writeln!(self.out, "}}");
+        let _ = writeln!(self.out, "\nimpl From<{}> for Variant {{", name);
+        let _ = writeln!(self.out, "    fn from(value: {}) -> Variant {{", name);
+        let _ = writeln!(self.out, "        return Variant::I4(value.0);");
+        let _ = writeln!(self.out, "    }}");
+        let _ = writeln!(self.out, "}}");
+    }
+
+    fn alias(&mut self, name: &str, info: &TypeInfo) {
+        // only aliases of generated types have something to alias on the rust side
+        if let Some(TypeDesc::UserDefined(TypeRef::Local(index))) = &info.alias {
+            if let Some(Some(target)) = self.names.get(*index) {
+                let target = target.clone();
+                self.out.push('\n');
+                self.comment(&info.name, &info.doc);
+                let _ = writeln!(self.out, "pub type {} = {};", name, target);
+            }
+        }
+    }
+
+    fn coclass(&mut self, name: &str, info: &TypeInfo) {
+        let guid = match &info.guid {
+            Some(guid) => guid,
+            None => return,
+        };
+        self.out.push('\n');
+        self.comment(&info.name, &info.doc);
+        let _ = writeln!(self.out, "pub struct {};", name);
+        let _ = writeln!(self.out, "\nimpl {} {{", name);
+        let _ = writeln!(self.out, "    pub const CLSID: &'static str = \"{{{}}}\";", guid);
+        if info.flags & TYPEFLAG_FCANCREATE != 0 {
+            let (result, wrap) = match self.default_interface(info) {
+                Some(interface) => (interface.clone(), format!("{}(dispatch)", interface)),
+                None => ("Dispatch".to_string(), "dispatch".to_string()),
+            };
+            let _ = writeln!(self.out, "\n    // creates one, only real COM objects can be created so this is windows only");
+            let _ = writeln!(self.out, "    #[cfg(windows)]");
+            let _ = writeln!(self.out, "    pub fn create() -> Result<{},Error> {{", result);
+            let _ = writeln!(self.out, "        let dispatch = {}::co_create_dispatch(&{}::clsid_from_string({}::CLSID)?)?;", CRATE, CRATE, name);
+            let _ = writeln!(self.out, "        return Ok({});", wrap);
+            let _ = writeln!(self.out, "    }}");
+        }
+        let _ = writeln!(self.out, "}}");
+    }
+
+    // the generated wrapper for the coclass's default (non-source) interface
+    fn default_interface(&self, info: &TypeInfo) -> Option<&String> {
+        let implemented = info.implements.iter().find(|i| i.is_default() && !i.is_source())
+            .or_else(|| info.implements.iter().find(|i| !i.is_source()))?;
+        return match implemented.reference {
+            TypeRef::Local(index) if self.is_interface(index) => self.names[index].as_ref(),
+            _ => None,
+        };
+    }
+
+    fn is_interface(&self, index: usize) -> bool {
+        return match self.library.types.get(index) {
+            Some(info) => self.names[index].is_some() && (info.kind == TypeKind::Dispatch || info.kind == TypeKind::Interface),
+            None => false,
+        };
+    }
+
+    fn interface(&mut self, name: &str, info: &TypeInfo) {
+        let (functions, variables) = self.members(info);
+        self.out.push('\n');
+        self.comment(&info.name, &info.doc);
+        let _ = writeln!(self.out, "#[derive(Clone, PartialEq)]");
+        let _ = writeln!(self.out, "pub struct {}(pub Dispatch);", name);
+        let _ = writeln!(self.out, "\nimpl From<Dispatch> for {} {{", name);
+        let _ = writeln!(self.out, "    fn from(dispatch: Dispatch) -> {} {{", name);
+        let _ = writeln!(self.out, "        return {}(dispatch);", name);
+        let _ = writeln!(self.out, "    }}");
+        let _ = writeln!(self.out, "}}");
+        let _ = writeln!(self.out, "\nimpl {} {{", name);
+        if let Some(guid) = &info.guid {
+            let _ = writeln!(self.out, "    pub const IID: &'static str = \"{{{}}}\";\n", guid);
+        }
+        let _ = writeln!(self.out, "    pub fn dispatch(&self) -> &Dispatch {{");
+        let _ = writeln!(self.out, "        return &self.0;");
+        let _ = writeln!(self.out, "    }}");
+
+        let mut used: HashSet<String> = ["dispatch", "from"].iter().map(|s| s.to_string()).collect();
+        if functions.iter().any(|f| f.memid == DISPID_NEWENUM) {
+            used.insert("iter".to_string());
+            let _ = writeln!(self.out, "\n    // the items of the collection, through its _NewEnum");
+            let _ = writeln!(self.out, "    pub fn iter(&self) -> DispatchIter {{");
+            let _ = writeln!(self.out, "        return self.0.iter();");
+            let _ = writeln!(self.out, "    }}");
+        }
+        for function in functions {
+            if function.memid != DISPID_NEWENUM && function.flags & FUNCFLAG_FRESTRICTED == 0 {
+                self.function(function, &mut used);
+            }
+        }
+        for variable in variables {
+            self.property(variable, &mut used);
+        }
+        let _ = writeln!(self.out, "}}");
+    }
+
+    // the members of the interface and those it inherits from other generated interfaces, the
+    // inherited ones first.  IDispatch's own members are left out since they're imported
+    fn members(&self, info: &'a TypeInfo) -> (Vec<&'a Function>, Vec<&'a Variable>) {
+        let mut chain = vec![info];
+        let mut current = info;
+        while chain.len() < MAX_DEPTH {
+            let base = current.implements.iter().find_map(|i| match i.reference {
+                TypeRef::Local(index) if self.is_interface(index) => self.library.types.get(index),
+                _ => None,
+            });
+            match base {
+                Some(base) if current.kind != TypeKind::CoClass => {
+                    chain.push(base);
+                    current = base;
+                },
+                _ => break,
+            }
+        }
+        let functions = chain.iter().rev().flat_map(|i| i.functions.iter()).collect();
+        let variables = chain.iter().rev().flat_map(|i| i.variables.iter()).collect();
+        return (functions, variables);
+    }
+
+    fn function(&mut self, function: &Function, used: &mut HashSet<String>) {
+        let base_name = snake_case(&function.name);
+        let method = match function.invoke_kind {
+            InvokeKind::Method | InvokeKind::PropertyGet => base_name,
+            InvokeKind::PropertyPut => format!("set_{}", base_name),
+            InvokeKind::PropertyPutRef => format!("set_{}_ref", base_name),
+        };
+        let method = unique(method, used);
+
+        // a vtable function (of a dual interface) returns an HRESULT and its result through the
+        // [out, retval] parameter, a dispinterface function returns its result directly
+        let params: Vec<&Param> = function.params.iter().filter(|p| !p.is_lcid() && !p.is_retval()).collect();
+        let result = match (&function.return_type, function.retval()) {
+            (TypeDesc::Base(VT_HRESULT | VT_VOID), Some(retval)) => Some(retval.type_desc.pointee()),
+            (TypeDesc::Base(VT_HRESULT | VT_VOID), None) => None,
+            (return_type, _) => Some(return_type),
+        };
+
+        // a put's value is the last parameter, anything before it is an index
+        let (indexes, value) = match function.invoke_kind {
+            InvokeKind::PropertyPut | InvokeKind::PropertyPutRef if !params.is_empty() => (&params[..params.len() - 1], params.last()),
+            _ => (&params[..], None),
+        };
+
+        let mut names = HashSet::new();
+        names.insert("self".to_string());
+        let mut signature = vec!["&self".to_string()];
+        let mut arguments = Vec::new();
+        for (i, param) in indexes.iter().enumerate() {
+            let name = unique(param_name(param, i), &mut names);
+            let (rust_type, argument) = self.argument(&name, param);
+            signature.push(format!("{}: {}", name, rust_type));
+            arguments.push(argument);
+        }
+        let mut named = Vec::new();
+        if let Some(param) = value {
+            let name = unique(param_name(param, indexes.len()), &mut names);
+            let (rust_type, argument) = self.argument(&name, param);
+            signature.push(format!("{}: {}", name, rust_type));
+            named.push(argument);
+        }
+
+        let (result_type, conversion) = match result {
+            Some(type_desc) => self.result(type_desc),
+            None => ("()".to_string(), None),
+        };
+
+        self.out.push('\n');
+        if let Some(doc) = function.doc.as_ref().filter(|doc| !doc.is_empty()) {
+            let _ = writeln!(self.out, "    // {}", doc);
+        }
+        let _ = writeln!(self.out, "    pub fn {}({}) -> Result<{},Error> {{", method, signature.join(", "), result_type);
+        let kind = format!("InvokeKind::{:?}", function.invoke_kind);
+        self.invoke(function.memid, &kind, &arguments, &named, conversion);
+        let _ = writeln!(self.out, "    }}");
+    }
+
+    // a dispinterface's property, a get and (unless it's read only) a put
+    fn property(&mut self, variable: &Variable, used: &mut HashSet<String>) {
+        let base_name = snake_case(&variable.name);
+        let (result_type, conversion) = self.result(&variable.type_desc);
+        let get = unique(base_name.clone(), used);
+        self.out.push('\n');
+        if let Some(doc) = variable.doc.as_ref().filter(|doc| !doc.is_empty()) {
+            let _ = writeln!(self.out, "    // {}", doc);
+        }
+        let _ = writeln!(self.out, "    pub fn {}(&self) -> Result<{},Error> {{", get, result_type);
+        self.invoke(variable.memid, "InvokeKind::PropertyGet", &[], &[], conversion);
+        let _ = writeln!(self.out, "    }}");
+
+        if !variable.is_read_only() {
+            let put = unique(format!("set_{}", base_name), used);
+            let param = Param { name: "value".to_string(), type_desc: variable.type_desc.clone(), flags: 0, default: None };
+            let (rust_type, argument) = self.argument("value", &param);
+            let _ = writeln!(self.out, "\n    pub fn {}(&self, value: {}) -> Result<(),Error> {{", put, rust_type);
+            self.invoke(variable.memid, "InvokeKind::PropertyPut", &[], &[argument], None);
+            let _ = writeln!(self.out, "    }}");
+        }
+    }
+
+    // the body of a member, the invoke and the conversion of its result
+    fn invoke(&mut self, memid: i32, kind: &str, arguments: &[String], named: &[String], conversion: Option<String>) {
+        // more than one argument gets a line each
+        let list = |arguments: &[String]| -> String {
+            return match arguments.len() {
+                0 => "&mut []".to_string(),
+                1 => format!("&mut [{}]", arguments[0]),
+                _ => format!("&mut [\n            {},\n        ]", arguments.join(",\n            ")),
+            };
+        };
+        let named_dispids = if named.is_empty() { "&[]" } else { "&[DISPID_PROPERTYPUT]" };
+        let call = format!("self.0.invoke({}, {}, {}, {}, {})?", memid, kind, list(arguments), named_dispids, list(named));
+        match conversion {
+            Some(conversion) => {
+                let _ = writeln!(self.out, "        let value = {};", call);
+                let _ = writeln!(self.out, "        return Ok({});", conversion.replace("{}", "value"));
+            },
+            None => {
+                let _ = writeln!(self.out, "        {};", call);
+                let _ = writeln!(self.out, "        return Ok(());");
+            },
+        }
+    }
+
+    // the rust type of a parameter and the Argument passed for it
+    fn argument(&self, name: &str, param: &Param) -> (String, String) {
+        // [out] and [in, out] parameters are written back to, and always take a Variant
+        if param.is_out() {
+            let by_ref = match param.type_desc.pointee() {
+                TypeDesc::Base(vt) if *vt == VarType::Variant.to_u16() => "ByRefVariant",
+                _ => "ByRef",
+            };
+            return ("&mut Variant".to_string(), format!("Argument::{}({})", by_ref, name));
+        }
+        let (rust_type, conversion) = match self.target(&param.type_desc) {
+            Target::Base(VarType::Variant) | Target::Base(VarType::Error) | Target::Other => ("&Variant".to_string(), "{}.clone()".to_string()),
+            Target::Base(var_type) => match base_argument(var_type) {
+                Some(rust_type) => (rust_type.to_string(), "Variant::from({})".to_string()),
+                None => ("&Variant".to_string(), "{}.clone()".to_string()),
+            },
+            Target::String => ("&str".to_string(), "Variant::from({})".to_string()),
+            Target::Enum(name) => (name, "Variant::from({})".to_string()),
+            Target::Object(name) => (format!("&{}", name), "Variant::from(&{}.0)".to_string()),
+            Target::Dispatch => ("&Dispatch".to_string(), "Variant::from({})".to_string()),
+            Target::Unknown => ("&Unknown".to_string(), "Variant::from({}.clone())".to_string()),
+        };
+        if param.is_optional() {
+            let value = conversion.replace("{}", name);
+            return (format!("Option<{}>", rust_type), format!("Argument::ByVal(&{}.map_or_else(Variant::missing, |{}| {}))", name, name, value));
+        }
+        return (rust_type, format!("Argument::ByVal(&{})", conversion.replace("{}", name)));
+    }
+
+    // the rust type of a result and how the returned Variant (the {}) becomes it
+    fn result(&self, type_desc: &TypeDesc) -> (String, Option<String>) {
+        let (rust_type, conversion) = match self.target(type_desc) {
+            Target::Base(VarType::Empty) => return ("()".to_string(), None),
+            // the same types the arguments take, coerced like VariantChangeType would
+            Target::Base(var_type) => match base_argument(var_type) {
+                Some(rust_type) => (rust_type.to_string(), format!("{}::try_from(&{{}})?", rust_type)),
+                None => ("Variant".to_string(), "{}".to_string()),
+            },
+            Target::String => ("String".to_string(), "String::try_from(&{})?".to_string()),
+            Target::Enum(name) => (name.clone(), format!("{}(i32::try_from(&{{}})?)", name)),
+            Target::Object(name) => (name.clone(), format!("{}({{}}.into_dispatch()?)", name)),
+            Target::Dispatch => ("Dispatch".to_string(), "{}.into_dispatch()?".to_string()),
+            _ => ("Variant".to_string(), "{}".to_string()),
+        };
+        return (rust_type, Some(conversion));
+    }
+
+    fn target(&self, type_desc: &TypeDesc) -> Target {
+        let type_desc = self.unalias(type_desc);
+        return match type_desc {
+            TypeDesc::Base(vt) if *vt == VT_VOID || *vt == VT_HRESULT => Target::Base(VarType::Empty),
+            TypeDesc::Base(vt) if *vt == VT_LPSTR || *vt == VT_LPWSTR => Target::String,
+            TypeDesc::Base(vt) => match VarType::from_u16(*vt) {
+                Some(VarType::Bstr) => Target::String,
+                Some(VarType::Dispatch) => Target::Dispatch,
+                Some(VarType::Unknown) => Target::Unknown,
+                Some(var_type) => Target::Base(var_type),
+                None => Target::Other,
+            },
+            TypeDesc::UserDefined(TypeRef::Local(index)) => match self.library.types.get(*index) {
+                Some(info) if info.kind == TypeKind::Enum && self.names[*index].is_some() => Target::Enum(self.names[*index].clone().unwrap_or_default()),
+                _ => Target::Other,
+            },
+            TypeDesc::Ptr(inner) => match self.unalias(inner) {
+                TypeDesc::UserDefined(TypeRef::Local(index)) => match self.library.types.get(*index) {
+                    Some(info) if info.kind == TypeKind::CoClass => match self.default_interface(info) {
+                        Some(name) => Target::Object(name.clone()),
+                        None => Target::Dispatch,
+                    },
+                    Some(_) if self.is_interface(*index) => Target::Object(self.names[*index].clone().unwrap_or_default()),
+                    Some(info) if info.kind == TypeKind::Interface => Target::Unknown,
+                    _ => Target::Other,
+                },
+                reference @ TypeDesc::UserDefined(_) => match self.library.type_desc_name(reference).as_str() {
+                    "IDispatch" => Target::Dispatch,
+                    "IUnknown" => Target::Unknown,
+                    _ => Target::Other,
+                },
+                _ => Target::Other,
+            },
+            _ => Target::Other,
+        };
+    }
+
+    // the type an alias stands for
+    fn unalias<'t>(&'t self, type_desc: &'t TypeDesc) -> &'t TypeDesc {
+        let mut type_desc = type_desc;
+        for _ in 0..MAX_DEPTH {
+            match type_desc {
+                TypeDesc::UserDefined(TypeRef::Local(index)) => match self.library.types.get(*index) {
+                    Some(TypeInfo { kind: TypeKind::Alias, alias: Some(alias), .. }) => type_desc = alias,
+                    _ => break,
+                },
+                _ => break,
+            }
+        }
+        return type_desc;
+    }
+}
+
+// the rust type of an argument of a base type, for those with a conversion into a Variant
+fn base_argument(var_type: VarType) -> Option<&'static str> {
+    let rust_type = match var_type {
+        VarType::I1 => "i8",
+        VarType::I2 => "i16",
+        VarType::I4 | VarType::Int => "i32",
+        VarType::I8 => "i64",
+        VarType::UI1 => "u8",
+        VarType::UI2 => "u16",
+        VarType::UI4 | VarType::UInt => "u32",
+        VarType::UI8 => "u64",
+        VarType::R4 => "f32",
+        VarType::R8 => "f64",
+        VarType::Bool => "bool",
+        VarType::Cy => "Currency",
+        VarType::Date => "OleDate",
+        VarType::Decimal => "Decimal",
+        _ => return None,
+    };
+    return Some(rust_type);
+}
+
+fn param_name(param: &Param, index: usize) -> String {
+    if param.name.is_empty() {
+        return format!("arg{}", index + 1);
+    }
+    return snake_case(&param.name);
+}
+
+// a name not already in used, with a number on the end if it has to be
+fn unique(name: String, used: &mut HashSet<String>) -> String {
+    let mut candidate = name.clone();
+    let mut n = 2;
+    while used.contains(&candidate) {
+        candidate = format!("{}_{}", name, n);
+        n += 1;
+    }
+    used.insert(candidate.clone());
+    return candidate;
+}
+
+// the name as it is, made into a valid identifier
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    if KEYWORDS.contains(&identifier.as_str()) {
+        identifier.push('_');
+    }
+    return identifier;
+}
+
+// "OpenConnection2" is open_connection2, "XMLHttp" is xml_http and "_NewEnum" is new_enum
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.trim_start_matches('_').chars().collect();
+    let mut snake = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let after_lower = i > 0 && (chars[i - 1].is_ascii_lowercase() || chars[i - 1].is_ascii_digit());
+            let ends_acronym = i > 0 && chars[i - 1].is_ascii_uppercase() && chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase());
+            if (after_lower || ends_acronym) && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            snake.push(*c);
+        } else if !snake.is_empty() && !snake.ends_with('_') {
+            snake.push('_');
+        }
+    }
+    let snake = snake.trim_end_matches('_');
+    return identifier(if snake.is_empty() { "member" } else { snake });
+}
@@ -0,0 +1,350 @@
+use std::ffi::c_void;
+use std::result::Result;
+use windows::core::{ComInterface, GUID, HSTRING, IUnknown, Interface, PCWSTR};
+use windows::Win32::System::Com::{CLSCTX_SERVER, CLSIDFromProgID, CLSIDFromString, CoCreateInstance, DISPATCH_FLAGS, DISPATCH_METHOD, DISPATCH_PROPERTYGET, DISPATCH_PROPERTYPUT, DISPATCH_PROPERTYPUTREF, DISPPARAMS, EXCEPINFO, IDispatch};
+use windows::Win32::Foundation::{DISP_E_EXCEPTION, DISP_E_PARAMNOTFOUND, DISP_E_TYPEMISMATCH};
+use windows::Win32::System::Ole::IEnumVARIANT;
+use windows::Win32::System::Variant::VARIANT;
+use crate::{hresult, Argument, Automation, ComApartment, Dispatch, Enumerator, Error, InvokeKind, Unknown, UnknownObject, Variant};
+use crate::automation::DISPID_NEWENUM;
+use crate::ffi::{enum_next, OwnedVariant, RawArguments};
+use crate::typelib::{read_type_info, TypeInfo};
+
+//
+// the windows backend, real COM objects through IDispatch
+//
+
+// critical constant used for various com methods that turns out to be very important
+static IID_NULL: GUID = GUID::zeroed();
+// indicates default locale for name lookups of com methods
+pub(crate) static DEFAULT_LOCALE_ID: u32 = 0x0400;
+
+// joins a single threaded apartment for the rest of the thread's life, never uninitializing
+#[deprecated(note = "use ComApartment, which uninitializes when it's dropped")]
+pub fn co_initialize() -> Result<(),Error> {
+    std::mem::forget(ComApartment::sta()?);
+    return Ok(());
+}
+
+pub fn clsid_from_prog_id<S: Into<String>>(prog_id: S) -> Result<GUID,Error> {
+    unsafe {
+        let h_prog_id: HSTRING = HSTRING::from(prog_id.into());
+        let p_prog_id: PCWSTR = PCWSTR::from_raw(h_prog_id.as_ptr());
+        return Ok(CLSIDFromProgID(p_prog_id)?);
+        // HSTRING's get dropped here, so we're all good now!
+    }
+}
+
+// a CLSID in its "{...}" form, as the generated wrappers have them
+pub fn clsid_from_string<S: Into<String>>(clsid: S) -> Result<GUID,Error> {
+    unsafe {
+        let h_clsid: HSTRING = HSTRING::from(clsid.into());
+        return Ok(CLSIDFromString(PCWSTR::from_raw(h_clsid.as_ptr()))?);
+    }
+}
+
+pub fn co_create_instance(clsid: &GUID) -> Result<IDispatch,Error> {
+    unsafe {
+        return Ok(CoCreateInstance(clsid, None, CLSCTX_SERVER)?);
+    }
+}
+
+pub fn co_create_dispatch(clsid: &GUID) -> Result<Dispatch,Error> {
+    let v = co_create_instance(clsid)?;
+    return Ok(Dispatch::from(v));
+}
+
+pub fn get_ids_of_names<S: Into<String>>(dispatch: &IDispatch, name: S) -> Result<i32,Error> {
+    let dispids = get_ids_of_names_with_params(dispatch, name, &[])?;
+    return Ok(dispids[0]);
+}
+
+// resolves a member name along with the names of some of its parameters in a single round trip,
+// the member's dispid is first followed by the parameters' in the same order
+pub fn get_ids_of_names_with_params<S: Into<String>>(dispatch: &IDispatch, name: S, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+    let mut dispids: Vec<i32> = vec![-1; param_names.len() + 1];
+
+    // https://stackoverflow.com/questions/74173128/how-to-get-a-pcwstr-object-from-a-path-or-string
+    // the HSTRING's need to outlive the PCWSTR's pointing at them
+    let mut h_names: Vec<HSTRING> = Vec::with_capacity(dispids.len());
+    h_names.push(HSTRING::from(name.into()));
+    h_names.extend(param_names.iter().map(|param_name| HSTRING::from(*param_name)));
+    let p_names: Vec<PCWSTR> = h_names.iter().map(|h_name| PCWSTR::from_raw(h_name.as_ptr())).collect();
+
+    unsafe {
+        dispatch.GetIDsOfNames(&IID_NULL, p_names.as_ptr(), p_names.len() as u32, DEFAULT_LOCALE_ID, dispids.as_mut_ptr())?;
+    }
+
+    return Ok(dispids);
+}
+
+// the free functions work on a bare IDispatch and look the name up on every call, a Dispatch
+// caches them instead
+
+pub fn get_property<S: Into<String>>(dispatch: &IDispatch, name: S) -> Result<Variant,Error> {
+    return ComDispatch::borrowed(dispatch).get_property(&name.into());
+}
+
+pub fn get_property_with_args<S: Into<String>>(dispatch: &IDispatch, name: S, args: &[Variant]) -> Result<Variant,Error> {
+    return ComDispatch::borrowed(dispatch).get_property_with_args(&name.into(), args);
+}
+
+pub fn put_property<S: Into<String>>(dispatch: &IDispatch, name: S, value: &Variant) -> Result<(),Error> {
+    return ComDispatch::borrowed(dispatch).put_property(&name.into(), value);
+}
+
+pub fn put_property_with_args<S: Into<String>>(dispatch: &IDispatch, name: S, args: &[Variant], value: &Variant) -> Result<(),Error> {
+    return ComDispatch::borrowed(dispatch).put_property_with_args(&name.into(), args, value);
+}
+
+pub fn put_property_ref<S: Into<String>>(dispatch: &IDispatch, name: S, value: &Variant) -> Result<(),Error> {
+    return ComDispatch::borrowed(dispatch).put_property_ref(&name.into(), value);
+}
+
+pub fn put_property_named<S: Into<String>>(dispatch: &IDispatch, name: S, value: &Variant, named_values: &[(&str, Variant)]) -> Result<(),Error> {
+    return ComDispatch::borrowed(dispatch).put_property_named(&name.into(), value, named_values);
+}
+
+pub fn call_method<S: Into<String>>(dispatch: &IDispatch, name: S, values: &[Variant]) -> Result<Variant,Error> {
+    return ComDispatch::borrowed(dispatch).call_method(&name.into(), values);
+}
+
+pub fn call_method_by_ref<S: Into<String>>(dispatch: &IDispatch, name: S, arguments: &mut [Argument]) -> Result<Variant,Error> {
+    return ComDispatch::borrowed(dispatch).call_method_by_ref(&name.into(), arguments);
+}
+
+pub fn call_method_named<S: Into<String>>(dispatch: &IDispatch, name: S, values: &[Variant], named_values: &[(&str, Variant)]) -> Result<Variant,Error> {
+    return ComDispatch::borrowed(dispatch).call_method_named(&name.into(), values, named_values);
+}
+
+// a real COM object, cloning AddRef's the interface and dropping Release's it
+#[derive(Clone)]
+pub struct ComDispatch {
+    dispatch: IDispatch
+}
+
+impl ComDispatch {
+    pub fn new(dispatch: IDispatch) -> ComDispatch {
+        ComDispatch {
+            dispatch
+        }
+    }
+
+    fn borrowed(dispatch: &IDispatch) -> ComDispatch {
+        return ComDispatch::new(dispatch.clone());
+    }
+
+    pub fn get_dispatch(&self) -> &IDispatch {
+        return &self.dispatch;
+    }
+}
+
+impl Automation for ComDispatch {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        return get_ids_of_names_with_params(&self.dispatch, name, param_names);
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        return invoke(&self.dispatch, dispid, dispatch_flags(kind), arguments, named_dispids, named_arguments);
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return self.dispatch.as_raw() as usize;
+    }
+
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        let enumerator = match invoke(&self.dispatch, DISPID_NEWENUM, DISPATCH_METHOD | DISPATCH_PROPERTYGET, &mut [], &[], &mut []) {
+            Ok(enumerator) => enumerator,
+            // not a collection with an enumerator, only those fall back to Count and Item
+            Err(e) if matches!(e.hresult(), hresult::DISP_E_MEMBERNOTFOUND | hresult::DISP_E_UNKNOWNNAME) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let unknown = match &enumerator {
+            Variant::Unknown(unknown) => unknown.as_raw(),
+            Variant::Dispatch(dispatch) => dispatch.as_raw(),
+            _ => None,
+        }.ok_or_else(|| Error::com(hresult::E_NOINTERFACE, format!("_NewEnum returned {:?} rather than an enumerator", enumerator)))?;
+        let unknown = unsafe { IUnknown::from_raw_borrowed(&unknown) }.ok_or_else(|| Error::com(hresult::E_POINTER, "null enumerator"))?;
+        let enumerator: IEnumVARIANT = unknown.cast()?;
+        return Ok(Some(Box::new(ComEnumerator { enumerator })));
+    }
+
+    fn as_raw(&self) -> Option<*mut c_void> {
+        return Some(self.dispatch.as_raw());
+    }
+
+    fn type_info(&self) -> Result<TypeInfo,Error> {
+        return read_type_info(&self.dispatch);
+    }
+}
+
+impl From<IDispatch> for Dispatch {
+    fn from(dispatch: IDispatch) -> Dispatch {
+        Dispatch::new(ComDispatch::new(dispatch))
+    }
+}
+
+struct ComEnumerator {
+    enumerator: IEnumVARIANT
+}
+
+impl Enumerator for ComEnumerator {
+    fn next_batch(&mut self, count: usize) -> Result<Vec<Variant>,Error> {
+        return enum_next(&self.enumerator, count);
+    }
+}
+
+// a VT_UNKNOWN interface, cloning AddRef's and dropping Release's it
+#[derive(Clone)]
+pub struct ComUnknown {
+    unknown: IUnknown
+}
+
+impl ComUnknown {
+    pub fn new(unknown: IUnknown) -> ComUnknown {
+        ComUnknown {
+            unknown
+        }
+    }
+}
+
+impl UnknownObject for ComUnknown {
+    fn query_dispatch(&self) -> Result<Dispatch,Error> {
+        let dispatch: IDispatch = self.unknown.cast()?;
+        return Ok(Dispatch::from(dispatch));
+    }
+
+    fn clone_object(&self) -> Box<dyn UnknownObject> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return self.unknown.as_raw() as usize;
+    }
+
+    fn as_raw(&self) -> Option<*mut c_void> {
+        return Some(self.unknown.as_raw());
+    }
+}
+
+impl From<IUnknown> for Unknown {
+    fn from(unknown: IUnknown) -> Unknown {
+        Unknown::new(ComUnknown::new(unknown))
+    }
+}
+
+// the IDispatch behind a Dispatch (AddRef'ed), only real COM objects can be passed to COM
+pub(crate) fn raw_dispatch(dispatch: &Dispatch) -> Result<IDispatch,Error> {
+    let raw = dispatch.as_raw().ok_or_else(|| Error::com(hresult::E_INVALIDARG, format!("{} is not a COM object and can't be passed to one", dispatch)))?;
+    return unsafe { IDispatch::from_raw_borrowed(&raw).cloned().ok_or_else(|| Error::com(hresult::E_POINTER, "null dispatch")) };
+}
+
+pub(crate) fn raw_unknown(unknown: &Unknown) -> Result<IUnknown,Error> {
+    let raw = unknown.as_raw().ok_or_else(|| Error::com(hresult::E_INVALIDARG, format!("{} is not a COM object and can't be passed to one", unknown)))?;
+    return unsafe { IUnknown::from_raw_borrowed(&raw).cloned().ok_or_else(|| Error::com(hresult::E_POINTER, "null unknown")) };
+}
+
+fn dispatch_flags(kind: InvokeKind) -> DISPATCH_FLAGS {
+    return match kind {
+        // like vb, methods and property gets are both sent as either since plenty of objects
+        // expose one as the other
+        InvokeKind::Method | InvokeKind::PropertyGet => DISPATCH_METHOD | DISPATCH_PROPERTYGET,
+        InvokeKind::PropertyPut => DISPATCH_PROPERTYPUT,
+        InvokeKind::PropertyPutRef => DISPATCH_PROPERTYPUTREF,
+    };
+}
+
+fn invoke(dispatch: &IDispatch, dispid: i32, wflags: DISPATCH_FLAGS, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+    // build array of variant arguments, named ones first and then the positional ones in reverse
+    // order (no idea why the COM api wants them reversed)
+    // the variants we create are cleared when dropped, even if the invoke fails
+    let mut args = RawArguments::new(arguments, named_arguments)?;
+    let mut named_dispids = named_dispids.to_vec();
+
+    // setup parameters we need to pass to the com invoke
+    // https://learn.microsoft.com/en-us/previous-versions/windows/desktop/automat/getting-and-setting-properties
+    let params = DISPPARAMS {
+        cArgs: args.len(),
+        cNamedArgs: named_dispids.len() as u32,
+        rgdispidNamedArgs: if named_dispids.is_empty() { std::ptr::null_mut() } else { named_dispids.as_mut_ptr() },
+        rgvarg: args.as_mut_ptr(),
+    };
+
+    // the result is ignored by puts, so don't ask for one
+    let is_put = (wflags & (DISPATCH_PROPERTYPUT | DISPATCH_PROPERTYPUTREF)).0 != 0;
+    let mut result = OwnedVariant::default();
+    let mut except_info: EXCEPINFO = EXCEPINFO::default();
+
+    let mut arg_err: u32 = 0;
+
+    unsafe {
+        let result_ptr = if is_put { None } else { Some(&mut result.0 as *mut VARIANT) };
+        let invoke_result = dispatch.Invoke(dispid, &IID_NULL, DEFAULT_LOCALE_ID, wflags, &params, result_ptr, Some(&mut except_info), Some(&mut arg_err));
+
+        if let Err(e) = invoke_result {
+            return Err(invoke_error(e, &mut except_info, arg_err, arguments.len(), named_arguments.len()));
+        }
+    }
+
+    args.write_back(arguments, named_arguments)?;
+
+    // copy into our variant (the raw result is cleared once dropped)
+    return Variant::try_from(&result.0);
+}
+
+// turns a failed invoke into the most descriptive error we can, using the EXCEPINFO the object
+// filled in or the index of the argument it didn't like
+fn invoke_error(e: windows::core::Error, except_info: &mut EXCEPINFO, arg_err: u32, positional_count: usize, named_count: usize) -> Error {
+    let hresult = e.code();
+    if hresult == DISP_E_EXCEPTION {
+        unsafe {
+            // some objects only fill in the details when asked to
+            if let Some(deferred_fill_in) = except_info.pfnDeferredFillIn {
+                let _ = deferred_fill_in(except_info);
+            }
+        }
+        let scode = except_info.scode;
+        return Error::Exception {
+            hresult: if scode != 0 { scode } else { hresult.0 },
+            code: except_info.wCode,
+            source: except_info.bstrSource.to_string(),
+            description: except_info.bstrDescription.to_string(),
+            help_file: except_info.bstrHelpFile.to_string(),
+            help_context: except_info.dwHelpContext,
+        };
+    }
+    if hresult == DISP_E_TYPEMISMATCH || hresult == DISP_E_PARAMNOTFOUND {
+        // arg_err indexes the raw arguments, which are the named ones followed by the positional
+        // ones in reverse
+        let arg_err = arg_err as usize;
+        let index = if arg_err < named_count {
+            positional_count + arg_err
+        } else {
+            (positional_count + named_count).saturating_sub(arg_err + 1)
+        };
+        return Error::Argument {
+            hresult: hresult.0,
+            index,
+            message: e.message().to_string(),
+        };
+    }
+    return Error::from(e);
+}
+
+// the flags are private, so they're checked here rather than under tests/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags() {
+        assert_eq!(dispatch_flags(InvokeKind::Method), DISPATCH_METHOD | DISPATCH_PROPERTYGET);
+        assert_eq!(dispatch_flags(InvokeKind::PropertyGet), DISPATCH_METHOD | DISPATCH_PROPERTYGET);
+        assert_eq!(dispatch_flags(InvokeKind::PropertyPut), DISPATCH_PROPERTYPUT);
+        assert_eq!(dispatch_flags(InvokeKind::PropertyPutRef), DISPATCH_PROPERTYPUTREF);
+    }
+}
@@ -0,0 +1,375 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal::prelude::ToPrimitive;
+use crate::{Automation, Currency, Dispatch, DISPID_VALUE, Error, hresult, InvokeKind, OleDate, Variant};
+
+//
+// TryFrom<&Variant> (and TryFrom<Variant>) for the rust types a variant can hold, coercing between
+// types by the rules VariantChangeType documents, so e.g. a VT_BSTR "42" is an i32 and a VT_R8
+// 2.5 rounds to the i32 2.  it's all done here rather than by oleaut32 so the rules are the same on
+// every platform:
+//  - VT_EMPTY is 0, false, "" or the 1899-12-30 epoch, and VT_NULL converts to nothing
+//  - integers are range checked, failing with DISP_E_OVERFLOW, and floats, currencies and decimals
+//    round to them half to even (banker's rounding)
+//  - VARIANT_TRUE is -1, so it's all bits set in the unsigned types, and anything non-zero is true
+//  - an object converts through its default (DISPID_VALUE) property
+//  - anything else that doesn't fit is DISP_E_TYPEMISMATCH
+// strings are formatted and parsed the way VariantChangeType does under LOCALE_INVARIANT: "." is
+// the decimal point and "," separates thousands, booleans are "True" and "False", floats keep 15
+// (f64) or 7 (f32) significant digits, and dates are MM/DD/YYYY HH:MM:SS, leaving out a midnight
+// time or the date of a bare time.  parsing also takes vb's &H and &O prefixes, a number in
+// parentheses as negative, and ISO dates (YYYY-MM-DD, with an optional time)
+//
+
+// the target of a conversion, for the error messages
+trait Target {
+    const NAME: &'static str;
+}
+
+fn mismatch<T: Target>(value: &Variant) -> Error {
+    return Error::result(format!("can't convert {:?} to {}", value, T::NAME));
+}
+
+fn overflow<T: Target, V: std::fmt::Display>(value: V) -> Error {
+    return Error::com(hresult::DISP_E_OVERFLOW, format!("{} does not fit in {}", value, T::NAME));
+}
+
+// an object's value is its default property
+fn default_value<T: Target>(dispatch: &Dispatch) -> Result<Variant,Error> {
+    let value = dispatch.invoke(DISPID_VALUE, InvokeKind::PropertyGet, &mut [], &[], &mut [])?;
+    // an object that's its own value would never end
+    if let Variant::Dispatch(_) = value {
+        return Err(mismatch::<T>(&value));
+    }
+    return Ok(value);
+}
+
+// a number on its way to an integer
+enum Number {
+    Integer(i128),
+    Float(f64),
+    Decimal(Decimal),
+}
+
+fn number<T: Target>(value: &Variant) -> Result<Number,Error> {
+    let number = match value {
+        Variant::Empty => Number::Integer(0),
+        Variant::I1(v) => Number::Integer(*v as i128),
+        Variant::I2(v) => Number::Integer(*v as i128),
+        Variant::I4(v) | Variant::Int(v) => Number::Integer(*v as i128),
+        Variant::I8(v) => Number::Integer(*v as i128),
+        Variant::UI1(v) => Number::Integer(*v as i128),
+        Variant::UI2(v) => Number::Integer(*v as i128),
+        Variant::UI4(v) | Variant::UInt(v) => Number::Integer(*v as i128),
+        Variant::UI8(v) => Number::Integer(*v as i128),
+        Variant::Bool(v) => Number::Integer(if *v { -1 } else { 0 }),
+        Variant::R4(v) => Number::Float(*v as f64),
+        Variant::R8(v) => Number::Float(*v),
+        Variant::Date(v) => Number::Float(v.to_raw()),
+        Variant::Cy(v) => Number::Decimal(v.to_decimal()),
+        Variant::Decimal(v) => Number::Decimal(*v),
+        Variant::Bstr(s) => parse_number(s).ok_or_else(|| mismatch::<T>(value))?,
+        Variant::Dispatch(dispatch) => return number::<T>(&default_value::<T>(dispatch)?),
+        Variant::Null | Variant::Nothing | Variant::Error(_) | Variant::Unknown(_) | Variant::Array(_) => return Err(mismatch::<T>(value)),
+    };
+    return Ok(number);
+}
+
+// rounded half to even
+fn integer<T: Target>(value: &Variant) -> Result<i128,Error> {
+    return match number::<T>(value)? {
+        Number::Integer(v) => Ok(v),
+        Number::Float(v) => {
+            let rounded = v.round_ties_even();
+            // i128's range is far beyond any of the targets, so this only has to keep the cast sane
+            if !rounded.is_finite() || rounded.abs() > 1e38 {
+                return Err(overflow::<T, _>(v));
+            }
+            Ok(rounded as i128)
+        },
+        Number::Decimal(v) => Ok(v.round_dp_with_strategy(0, RoundingStrategy::MidpointNearestEven).to_i128().ok_or_else(|| overflow::<T, _>(v))?),
+    };
+}
+
+fn float<T: Target>(value: &Variant) -> Result<f64,Error> {
+    return match number::<T>(value)? {
+        Number::Integer(v) => Ok(v as f64),
+        Number::Float(v) => Ok(v),
+        Number::Decimal(v) => v.to_f64().ok_or_else(|| overflow::<T, _>(v)),
+    };
+}
+
+fn decimal<T: Target>(value: &Variant) -> Result<Decimal,Error> {
+    return match number::<T>(value)? {
+        Number::Integer(v) => Decimal::try_from_i128_with_scale(v, 0).map_err(|_| overflow::<T, _>(v)),
+        // through the 15 digits VarDecFromR8 keeps, so 0.1 is 0.1 and not 0.1000000000000000055...
+        Number::Float(v) => {
+            let digits = format_float(v, 15);
+            digits.parse::<Decimal>().or_else(|_| Decimal::from_scientific(&digits)).map_err(|_| overflow::<T, _>(v))
+        },
+        Number::Decimal(v) => Ok(v),
+    };
+}
+
+macro_rules! try_from_variant {
+    ($($t:ty => $name:expr, $convert:expr;)*) => {
+        $(
+            impl Target for $t {
+                const NAME: &'static str = $name;
+            }
+
+            impl TryFrom<&Variant> for $t {
+                type Error = Error;
+
+                fn try_from(value: &Variant) -> Result<$t,Error> {
+                    let convert: fn(&Variant) -> Result<$t,Error> = $convert;
+                    return convert(value);
+                }
+            }
+
+            impl TryFrom<Variant> for $t {
+                type Error = Error;
+
+                fn try_from(value: Variant) -> Result<$t,Error> {
+                    return <$t>::try_from(&value);
+                }
+            }
+        )*
+    };
+}
+
+// VARIANT_TRUE is -1, which the unsigned types take as all bits set
+macro_rules! unsigned {
+    ($t:ty) => {
+        |value| {
+            if let Variant::Bool(true) = value {
+                return Ok(<$t>::MAX);
+            }
+            let v = integer::<$t>(value)?;
+            return <$t>::try_from(v).map_err(|_| overflow::<$t, _>(v));
+        }
+    };
+}
+
+macro_rules! signed {
+    ($t:ty) => {
+        |value| {
+            let v = integer::<$t>(value)?;
+            return <$t>::try_from(v).map_err(|_| overflow::<$t, _>(v));
+        }
+    };
+}
+
+try_from_variant! {
+    i8 => "i8", signed!(i8);
+    i16 => "i16", signed!(i16);
+    i32 => "i32", signed!(i32);
+    i64 => "i64", signed!(i64);
+    u8 => "u8", unsigned!(u8);
+    u16 => "u16", unsigned!(u16);
+    u32 => "u32", unsigned!(u32);
+    u64 => "u64", unsigned!(u64);
+    f64 => "f64", float::<f64>;
+    f32 => "f32", |value| {
+        let v = float::<f32>(value)?;
+        if v.is_finite() && v.abs() > f32::MAX as f64 {
+            return Err(overflow::<f32, _>(v));
+        }
+        return Ok(v as f32);
+    };
+    bool => "bool", |value| {
+        return match value {
+            Variant::Bool(v) => Ok(*v),
+            Variant::Bstr(s) if s.trim().eq_ignore_ascii_case("true") => Ok(true),
+            Variant::Bstr(s) if s.trim().eq_ignore_ascii_case("false") => Ok(false),
+            _ => match number::<bool>(value)? {
+                Number::Integer(v) => Ok(v != 0),
+                Number::Float(v) => Ok(v != 0.0),
+                Number::Decimal(v) => Ok(!v.is_zero()),
+            },
+        };
+    };
+    Decimal => "a decimal", decimal::<Decimal>;
+    Currency => "a currency", |value| {
+        if let Variant::Cy(v) = value {
+            return Ok(*v);
+        }
+        let v = decimal::<Currency>(value)?;
+        return Currency::from_decimal_rounded(&v, RoundingStrategy::MidpointNearestEven).map_err(|_| overflow::<Currency, _>(v));
+    };
+    OleDate => "a date", |value| {
+        let raw = match value {
+            Variant::Date(v) => return Ok(*v),
+            Variant::Bstr(s) => return parse_date(s).map(OleDate::from).ok_or_else(|| mismatch::<OleDate>(value)),
+            Variant::Dispatch(dispatch) => return OleDate::try_from(&default_value::<OleDate>(dispatch)?),
+            _ => float::<OleDate>(value)?,
+        };
+        if !(OleDate::MIN..OleDate::MAX).contains(&raw) {
+            return Err(overflow::<OleDate, _>(raw));
+        }
+        return Ok(OleDate::from_raw(raw));
+    };
+    NaiveDateTime => "a date", |value| OleDate::try_from(value)?.to_date_time();
+    NaiveDate => "a date", |value| OleDate::try_from(value)?.to_date();
+    String => "a string", |value| {
+        let string = match value {
+            Variant::Empty => String::new(),
+            Variant::Bstr(s) => s.clone(),
+            Variant::Bool(true) => "True".to_string(),
+            Variant::Bool(false) => "False".to_string(),
+            Variant::R4(v) => format_float(*v as f64, 7),
+            Variant::R8(v) => format_float(*v, 15),
+            Variant::Date(v) => format_date(*v).ok_or_else(|| mismatch::<String>(value))?,
+            Variant::Cy(v) => v.to_decimal().normalize().to_string(),
+            Variant::Decimal(v) => v.normalize().to_string(),
+            Variant::Dispatch(dispatch) => String::try_from(&default_value::<String>(dispatch)?)?,
+            Variant::Null | Variant::Nothing | Variant::Error(_) | Variant::Unknown(_) | Variant::Array(_) => return Err(mismatch::<String>(value)),
+            integer => integer.to_string(),
+        };
+        return Ok(string);
+    };
+}
+
+impl Target for Dispatch {
+    const NAME: &'static str = "an object";
+}
+
+impl TryFrom<&Variant> for Dispatch {
+    type Error = Error;
+
+    fn try_from(value: &Variant) -> Result<Dispatch,Error> {
+        return match value {
+            Variant::Dispatch(dispatch) => Ok(dispatch.clone()),
+            Variant::Unknown(unknown) => unknown.to_dispatch(),
+            _ => Err(mismatch::<Dispatch>(value)),
+        };
+    }
+}
+
+// the variant's reference is moved out rather than cloned
+impl TryFrom<Variant> for Dispatch {
+    type Error = Error;
+
+    fn try_from(value: Variant) -> Result<Dispatch,Error> {
+        return match value {
+            Variant::Dispatch(dispatch) => Ok(dispatch),
+            value => Dispatch::try_from(&value),
+        };
+    }
+}
+
+// printf's %.{digits}G, which is how VarBstrFromR8 and VarBstrFromR4 write floats: the shortest
+// of fixed or scientific notation for that many significant digits, without trailing zeros
+fn format_float(value: f64, digits: usize) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    let scientific = format!("{:.*e}", digits - 1, value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    if exponent < -5 || exponent >= digits as i32 {
+        let mantissa = trim_zeros(mantissa);
+        return format!("{}E{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs());
+    }
+    let decimals = (digits as i32 - 1 - exponent).max(0) as usize;
+    return trim_zeros(&format!("{:.*}", decimals, value)).to_string();
+}
+
+fn trim_zeros(number: &str) -> &str {
+    if !number.contains('.') {
+        return number;
+    }
+    return number.trim_end_matches('0').trim_end_matches('.');
+}
+
+// MM/DD/YYYY HH:MM:SS, without the date of a bare time or a midnight time
+fn format_date(value: OleDate) -> Option<String> {
+    let date_time = value.to_date_time().ok()?;
+    let (date, time) = (date_time.date(), date_time.time());
+    let date_part = date.format("%m/%d/%Y").to_string();
+    let time_part = time.format("%H:%M:%S").to_string();
+    if value.to_raw().trunc() == 0.0 {
+        return Some(time_part);
+    }
+    if time == NaiveTime::MIN {
+        return Some(date_part);
+    }
+    return Some(format!("{} {}", date_part, time_part));
+}
+
+// the invariant locale's number format, as VarParseNumFromStr takes it
+fn parse_number(text: &str) -> Option<Number> {
+    let mut text = text.trim();
+    let mut negative = false;
+    if let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        negative = true;
+        text = inner.trim();
+    }
+    if let Some(rest) = text.strip_prefix('-') {
+        negative = !negative;
+        text = rest.trim_start();
+    } else if let Some(rest) = text.strip_prefix('+') {
+        text = rest.trim_start();
+    }
+
+    // vb's hex and octal literals
+    let radix = match text.get(..2).map(|prefix| prefix.to_ascii_uppercase()) {
+        Some(prefix) if prefix == "&H" => Some(16),
+        Some(prefix) if prefix == "&O" => Some(8),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        let value = i128::from_str_radix(&text[2..], radix).ok()?;
+        return Some(Number::Integer(if negative { -value } else { value }));
+    }
+
+    // digits with thousands separators, then an optional fraction and exponent
+    let (whole, rest) = text.split_at(text.find(['.', 'e', 'E']).unwrap_or(text.len()));
+    let (fraction, exponent) = rest.split_at(rest.find(['e', 'E']).unwrap_or(rest.len()));
+    let fraction = fraction.strip_prefix('.').unwrap_or(fraction);
+    let exponent = exponent.get(1..).map(|e| e.strip_prefix(['+', '-']).unwrap_or(e));
+    if whole.starts_with(',') || !whole.chars().all(|c| c.is_ascii_digit() || c == ',')
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+        || whole.len() + fraction.len() == 0
+        || exponent.is_some_and(|e| e.is_empty() || !e.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let digits = format!("{}{}{}", if negative { "-" } else { "" }, whole.replace(',', ""), rest);
+    if rest.is_empty() {
+        if let Ok(value) = digits.parse::<i128>() {
+            return Some(Number::Integer(value));
+        }
+    }
+    if exponent.is_none() {
+        if let Ok(value) = digits.parse::<Decimal>() {
+            return Some(Number::Decimal(value));
+        }
+    }
+    return digits.parse::<f64>().ok().filter(|v| v.is_finite()).map(Number::Float);
+}
+
+// MM/DD/YYYY or YYYY-MM-DD, either optionally followed by a time, or a time on its own (which is
+// on the epoch day).  times are HH:MM or HH:MM:SS, on a 12 hour clock when followed by AM or PM
+fn parse_date(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    let time_only = text.split_once(':').is_some_and(|(before, _)| !before.contains(['/', '-']));
+    let (date_part, time_part) = match text.split_once([' ', 'T']) {
+        _ if time_only => ("", Some(text)),
+        Some((date, time)) => (date, Some(time.trim())),
+        None => (text, None),
+    };
+    let date = if date_part.is_empty() {
+        OleDate::epoch()
+    } else {
+        NaiveDate::parse_from_str(date_part, "%m/%d/%Y").or_else(|_| NaiveDate::parse_from_str(date_part, "%Y-%m-%d")).ok()?
+    };
+    let time = match time_part {
+        None => NaiveTime::MIN,
+        Some(time) => ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p", "%I:%M:%S%p", "%I:%M%p"].iter()
+            .find_map(|format| NaiveTime::parse_from_str(time, format).ok())?,
+    };
+    return Some(date.and_time(time));
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::fmt::Formatter;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use crate::Error;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// VT_DATE, an OLE automation date.  it's a f64 counting days since the 1899-12-30 epoch, where the
+// fraction is the time of day.  for dates before the epoch the whole part goes negative but the
+// fraction still counts forward from midnight, so -1.25 is 1899-12-29 06:00 (not 1899-12-28 18:00)
+// and both -0.5 and 0.5 are noon on the epoch day.
+//
+// the raw f64 is kept as-is so values read from COM round trip exactly, while conversions to and
+// from chrono are rounded to the millisecond (the most precision OLE automation itself honors)
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct OleDate(f64);
+
+impl OleDate {
+    // 0100-01-01 00:00:00, the earliest date VariantTimeToSystemTime accepts
+    pub const MIN: f64 = -657_434.0;
+    // the day after 9999-12-31, the latest date VariantTimeToSystemTime accepts
+    pub const MAX: f64 = 2_958_466.0;
+
+    pub fn from_raw(value: f64) -> OleDate {
+        OleDate(value)
+    }
+
+    pub fn to_raw(self) -> f64 {
+        return self.0;
+    }
+
+    pub fn epoch() -> NaiveDate {
+        return NaiveDate::from_ymd_opt(1899, 12, 30).unwrap();
+    }
+
+    pub fn from_date_time(value: &NaiveDateTime) -> OleDate {
+        let mut days = value.date().signed_duration_since(OleDate::epoch()).num_days();
+        let time = value.time();
+        let mut millis = time.num_seconds_from_midnight() as i64 * 1_000 + (time.nanosecond() as i64 + 500_000) / 1_000_000;
+        // rounding (or a leap second) can carry into the next day
+        if millis >= MILLIS_PER_DAY {
+            days += 1;
+            millis -= MILLIS_PER_DAY;
+        }
+        let fraction = millis as f64 / MILLIS_PER_DAY as f64;
+        let value = if days < 0 { days as f64 - fraction } else { days as f64 + fraction };
+        return OleDate(value);
+    }
+
+    pub fn from_date(value: &NaiveDate) -> OleDate {
+        return OleDate::from_date_time(&value.and_time(NaiveTime::MIN));
+    }
+
+    pub fn to_date_time(self) -> Result<NaiveDateTime,Error> {
+        if !self.0.is_finite() || self.0 < OleDate::MIN || self.0 >= OleDate::MAX {
+            return Err(Error::result(format!("ole date {} is outside the supported range", self.0)));
+        }
+        let days = self.0.trunc();
+        let millis = ((self.0 - days).abs() * MILLIS_PER_DAY as f64).round() as i64;
+        let midnight = OleDate::epoch().and_time(NaiveTime::MIN) + Duration::days(days as i64);
+        return Ok(midnight + Duration::milliseconds(millis));
+    }
+
+    // the date part only, any time of day is dropped
+    pub fn to_date(self) -> Result<NaiveDate,Error> {
+        return Ok(self.to_date_time()?.date());
+    }
+}
+
+impl fmt::Display for OleDate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.to_date_time() {
+            Ok(date_time) => write!(f, "{}", date_time),
+            Err(_) => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl From<NaiveDateTime> for OleDate {
+    fn from(value: NaiveDateTime) -> OleDate {
+        OleDate::from_date_time(&value)
+    }
+}
+
+impl From<NaiveDate> for OleDate {
+    fn from(value: NaiveDate) -> OleDate {
+        OleDate::from_date(&value)
+    }
+}
+
+impl TryFrom<OleDate> for NaiveDateTime {
+    type Error = Error;
+
+    fn try_from(value: OleDate) -> Result<NaiveDateTime,Error> {
+        value.to_date_time()
+    }
+}
+
+impl TryFrom<OleDate> for NaiveDate {
+    type Error = Error;
+
+    fn try_from(value: OleDate) -> Result<NaiveDate,Error> {
+        value.to_date()
+    }
+}
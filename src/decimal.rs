@@ -0,0 +1,149 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use rust_decimal::{Decimal, RoundingStrategy};
+use crate::Error;
+
+// VT_CY, a 64-bit integer scaled by 10,000 (so exactly 4 decimal places).  all conversions and
+// arithmetic are exact, anything that would need rounding or overflows the 64 bits is an error
+// unless rounding is explicitly asked for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Currency(i64);
+
+impl Currency {
+    pub const SCALE: i64 = 10_000;
+    pub const DECIMAL_PLACES: u32 = 4;
+    pub const MIN: Currency = Currency(i64::MIN);
+    pub const MAX: Currency = Currency(i64::MAX);
+
+    pub fn from_raw(value: i64) -> Currency {
+        Currency(value)
+    }
+
+    pub fn to_raw(self) -> i64 {
+        return self.0;
+    }
+
+    pub fn from_units(units: i64) -> Result<Currency,Error> {
+        return units.checked_mul(Currency::SCALE)
+            .map(Currency)
+            .ok_or_else(|| overflow(units));
+    }
+
+    // fails if the value has more than 4 significant decimal places or is out of range
+    pub fn from_decimal(value: &Decimal) -> Result<Currency,Error> {
+        let normalized = value.normalize();
+        if normalized.scale() > Currency::DECIMAL_PLACES {
+            return Err(Error::result(format!("{} has more than {} decimal places and would need rounding to fit a currency", value, Currency::DECIMAL_PLACES)));
+        }
+        let factor = 10i128.pow(Currency::DECIMAL_PLACES - normalized.scale());
+        return normalized.mantissa().checked_mul(factor)
+            .and_then(|raw| i64::try_from(raw).ok())
+            .map(Currency)
+            .ok_or_else(|| overflow(value));
+    }
+
+    // rounds to 4 decimal places with the given strategy (VarCyFromDec uses banker's rounding,
+    // RoundingStrategy::MidpointNearestEven), only fails if the value is out of range
+    pub fn from_decimal_rounded(value: &Decimal, strategy: RoundingStrategy) -> Result<Currency,Error> {
+        let rounded = value.round_dp_with_strategy(Currency::DECIMAL_PLACES, strategy);
+        return Currency::from_decimal(&rounded);
+    }
+
+    // always exact, a currency fits comfortably in a decimal's 96 bits
+    pub fn to_decimal(self) -> Decimal {
+        return Decimal::from_i128_with_scale(self.0 as i128, Currency::DECIMAL_PLACES);
+    }
+
+    pub fn checked_add(self, other: Currency) -> Result<Currency,Error> {
+        return self.0.checked_add(other.0)
+            .map(Currency)
+            .ok_or_else(|| Error::result(format!("currency overflow adding {} and {}", self, other)));
+    }
+
+    pub fn checked_sub(self, other: Currency) -> Result<Currency,Error> {
+        return self.0.checked_sub(other.0)
+            .map(Currency)
+            .ok_or_else(|| Error::result(format!("currency overflow subtracting {} from {}", other, self)));
+    }
+
+    pub fn checked_neg(self) -> Result<Currency,Error> {
+        return self.0.checked_neg()
+            .map(Currency)
+            .ok_or_else(|| Error::result(format!("currency overflow negating {}", self)));
+    }
+
+    // multiplying by a whole number never needs rounding
+    pub fn checked_mul(self, factor: i64) -> Result<Currency,Error> {
+        return self.0.checked_mul(factor)
+            .map(Currency)
+            .ok_or_else(|| Error::result(format!("currency overflow multiplying {} by {}", self, factor)));
+    }
+}
+
+fn overflow<T: fmt::Display>(value: T) -> Error {
+    return Error::result(format!("{} is outside the range of a currency", value));
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_decimal().normalize())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Currency,Error> {
+        let value = Decimal::from_str_exact(s.trim())
+            .map_err(|e| Error::result(format!("invalid currency '{}': {}", s, e)))?;
+        return Currency::from_decimal(&value);
+    }
+}
+
+impl TryFrom<Decimal> for Currency {
+    type Error = Error;
+
+    fn try_from(value: Decimal) -> Result<Currency,Error> {
+        Currency::from_decimal(&value)
+    }
+}
+
+impl From<Currency> for Decimal {
+    fn from(value: Currency) -> Decimal {
+        value.to_decimal()
+    }
+}
+
+// the pieces of a VT_DECIMAL (sign, scale and 96-bit magnitude), which map exactly onto Decimal
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn decimal_from_parts(negative: bool, scale: u8, hi: u32, lo: u64) -> Result<Decimal,Error> {
+    if scale as u32 > Decimal::MAX_SCALE {
+        return Err(Error::result(format!("decimal scale {} is larger than {}", scale, Decimal::MAX_SCALE)));
+    }
+    return Ok(Decimal::from_parts(lo as u32, (lo >> 32) as u32, hi, negative, scale as u32));
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn decimal_to_parts(value: &Decimal) -> (bool, u8, u32, u64) {
+    let magnitude = value.mantissa().unsigned_abs();
+    return (value.is_sign_negative(), value.scale() as u8, (magnitude >> 64) as u32, magnitude as u64);
+}
+
+// the parts are crate private, so they're checked here rather than under tests/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts() {
+        let value = Decimal::from_str("-79228162514264337593543950.335").unwrap();
+        let (negative, scale, hi, lo) = decimal_to_parts(&value);
+        assert_eq!((negative, scale, hi, lo), (true, 3, u32::MAX, u64::MAX));
+        assert_eq!(decimal_from_parts(negative, scale, hi, lo).unwrap(), value);
+        assert_eq!(decimal_from_parts(false, 2, 0, 12345).unwrap(), Decimal::from_str("123.45").unwrap());
+        assert_eq!(decimal_from_parts(false, 28, 0, 1).unwrap(), Decimal::from_str("0.0000000000000000000000000001").unwrap());
+        assert!(decimal_from_parts(false, 29, 0, 1).is_err());
+        assert!(decimal_from_parts(true, u8::MAX, 0, 1).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// remembers name to DISPID mappings so repeated gets, puts and calls skip the GetIDsOfNames round
+// trip.  names are case insensitive in automation, so they're keyed in lowercase.  clones share the
+// same mappings, which lets every clone of a Dispatch (or several objects of the same type, like
+// the items of a collection) resolve each name only once
+#[derive(Clone, Debug, Default)]
+pub struct DispidCache {
+    dispids: Rc<RefCell<HashMap<String, i32>>>
+}
+
+impl DispidCache {
+    pub fn new() -> DispidCache {
+        DispidCache::default()
+    }
+
+    fn key(name: &str, param_name: Option<&str>) -> String {
+        return match param_name {
+            Some(param_name) => format!("{}\0{}", name.to_lowercase(), param_name.to_lowercase()),
+            None => name.to_lowercase(),
+        };
+    }
+
+    pub fn get(&self, name: &str) -> Option<i32> {
+        return self.dispids.borrow().get(&DispidCache::key(name, None)).copied();
+    }
+
+    // the member's dispid followed by its parameters', or None if any of them isn't cached yet
+    pub fn get_with_params(&self, name: &str, param_names: &[&str]) -> Option<Vec<i32>> {
+        let dispids = self.dispids.borrow();
+        let mut ids = Vec::with_capacity(param_names.len() + 1);
+        ids.push(*dispids.get(&DispidCache::key(name, None))?);
+        for param_name in param_names {
+            ids.push(*dispids.get(&DispidCache::key(name, Some(param_name)))?);
+        }
+        return Some(ids);
+    }
+
+    pub fn insert(&self, name: &str, dispid: i32) {
+        self.dispids.borrow_mut().insert(DispidCache::key(name, None), dispid);
+    }
+
+    // dispids are in the same order as from get_with_params
+    pub fn insert_with_params(&self, name: &str, param_names: &[&str], dispids: &[i32]) {
+        let mut cache = self.dispids.borrow_mut();
+        if let Some(dispid) = dispids.first() {
+            cache.insert(DispidCache::key(name, None), *dispid);
+        }
+        for (param_name, dispid) in param_names.iter().zip(dispids.iter().skip(1)) {
+            cache.insert(DispidCache::key(name, Some(param_name)), *dispid);
+        }
+    }
+
+    // forgets a member (and its parameters), e.g. after an IDispatchEx object removed or replaced it
+    pub fn remove(&self, name: &str) {
+        let key = DispidCache::key(name, None);
+        let param_prefix = format!("{}\0", key);
+        self.dispids.borrow_mut().retain(|k, _| *k != key && !k.starts_with(&param_prefix));
+    }
+
+    pub fn clear(&self) {
+        self.dispids.borrow_mut().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.dispids.borrow().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.dispids.borrow().is_empty();
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+// the HRESULT's this crate looks at, kept as plain constants so they don't depend on windows
+pub mod hresult {
+    pub const S_OK: i32 = 0;
+    pub const E_NOTIMPL: i32 = 0x80004001u32 as i32;
+    pub const E_NOINTERFACE: i32 = 0x80004002u32 as i32;
+    pub const E_POINTER: i32 = 0x80004003u32 as i32;
+    pub const E_FAIL: i32 = 0x80004005u32 as i32;
+    pub const E_OUTOFMEMORY: i32 = 0x8007000Eu32 as i32;
+    pub const E_INVALIDARG: i32 = 0x80070057u32 as i32;
+    pub const DISP_E_UNKNOWNINTERFACE: i32 = 0x80020001u32 as i32;
+    pub const DISP_E_MEMBERNOTFOUND: i32 = 0x80020003u32 as i32;
+    pub const DISP_E_PARAMNOTFOUND: i32 = 0x80020004u32 as i32;
+    pub const DISP_E_TYPEMISMATCH: i32 = 0x80020005u32 as i32;
+    pub const DISP_E_UNKNOWNNAME: i32 = 0x80020006u32 as i32;
+    pub const DISP_E_NONAMEDARGS: i32 = 0x80020007u32 as i32;
+    pub const DISP_E_BADVARTYPE: i32 = 0x80020008u32 as i32;
+    pub const DISP_E_EXCEPTION: i32 = 0x80020009u32 as i32;
+    pub const DISP_E_OVERFLOW: i32 = 0x8002000Au32 as i32;
+    pub const DISP_E_BADINDEX: i32 = 0x8002000Bu32 as i32;
+    pub const DISP_E_BADPARAMCOUNT: i32 = 0x8002000Eu32 as i32;
+    pub const DISP_E_PARAMNOTOPTIONAL: i32 = 0x8002000Fu32 as i32;
+    pub const TYPE_E_INVDATAREAD: i32 = 0x80028018u32 as i32;
+    pub const TYPE_E_CANTLOADLIBRARY: i32 = 0x80029C4Au32 as i32;
+    pub const RPC_E_CALL_REJECTED: i32 = 0x80010001u32 as i32;
+    pub const RPC_E_SERVERCALL_RETRYLATER: i32 = 0x8001010Au32 as i32;
+    pub const RPC_E_CHANGED_MODE: i32 = 0x80010106u32 as i32;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Error {
+    // a COM call failed with this HRESULT
+    Com {
+        hresult: i32,
+        message: String,
+    },
+    // the automation object raised an exception while running the member (DISP_E_EXCEPTION), with
+    // the details it filled into the EXCEPINFO.  the hresult is the EXCEPINFO's scode if it set one
+    Exception {
+        hresult: i32,
+        code: u16,
+        source: String,
+        description: String,
+        help_file: String,
+        help_context: u32,
+    },
+    // an argument was the wrong type (DISP_E_TYPEMISMATCH) or a required one was missing
+    // (DISP_E_PARAMNOTFOUND).  the index counts the positional arguments from zero, followed by
+    // any named ones in the order they were given (the value being put is the first named one)
+    Argument {
+        hresult: i32,
+        index: usize,
+        message: String,
+    },
+    // a value could not be converted or represented, e.g. the wrong variant type or an overflow
+    Conversion(String),
+}
+
+impl Error {
+    // a value that can't be converted, which reports DISP_E_TYPEMISMATCH.  any other failure is an
+    // Error::com with the HRESULT that fits it
+    pub fn result<S: Into<String>>(message: S) -> Error {
+        Error::Conversion(message.into())
+    }
+
+    pub fn com<S: Into<String>>(hresult: i32, message: S) -> Error {
+        Error::Com {
+            hresult,
+            message: message.into()
+        }
+    }
+
+    pub fn hresult(&self) -> i32 {
+        return match self {
+            Error::Com { hresult, .. } => *hresult,
+            Error::Exception { hresult, .. } => *hresult,
+            Error::Argument { hresult, .. } => *hresult,
+            Error::Conversion(_) => hresult::DISP_E_TYPEMISMATCH,
+        };
+    }
+
+    pub fn message(&self) -> String {
+        return match self {
+            Error::Com { message, .. } => message.clone(),
+            Error::Exception { description, .. } => description.clone(),
+            Error::Argument { message, .. } => message.clone(),
+            Error::Conversion(message) => message.clone(),
+        };
+    }
+}
+
+impl std::error::Error for Error { }
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Com { hresult, message } => write!(f, "{} ({:#010x})", message, hresult),
+            Error::Exception { hresult, source, description, .. } => {
+                if source.is_empty() {
+                    write!(f, "{} ({:#010x})", description, hresult)
+                } else {
+                    write!(f, "{}: {} ({:#010x})", source, description, hresult)
+                }
+            },
+            Error::Argument { hresult, index, message } => write!(f, "argument {}: {} ({:#010x})", index, message, hresult),
+            Error::Conversion(message) => write!(f, "{}", message),
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use std::ffi::c_void;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use rust_decimal::Decimal;
+use windows::core::{BSTR, IUnknown};
+use windows::Win32::Foundation::{DECIMAL, DECIMAL_0, DECIMAL_0_0, DECIMAL_1, VARIANT_BOOL};
+use windows::Win32::System::Com::{CY, IDispatch, SAFEARRAY, SAFEARRAYBOUND};
+use windows::Win32::System::Ole::{IEnumVARIANT, SafeArrayAccessData, SafeArrayCreate, SafeArrayDestroy, SafeArrayGetDim, SafeArrayGetElemsize, SafeArrayGetLBound, SafeArrayGetUBound, SafeArrayGetVartype, SafeArrayUnaccessData};
+use windows::Win32::System::Variant::{VARENUM, VARIANT, VT_ARRAY, VT_BYREF, VT_DECIMAL, VT_TYPEMASK, VT_VARIANT, VariantClear};
+use crate::{Argument, Dispatch, Error, Unknown};
+use crate::com::{raw_dispatch, raw_unknown};
+use crate::error::hresult;
+use crate::date::OleDate;
+use crate::decimal::{Currency, decimal_from_parts, decimal_to_parts};
+use crate::variant::{ArrayBound, SafeArray, VarType, Variant};
+
+//
+// conversions between our safe Variant and the raw windows VARIANT, this is the only place that
+// should need to poke around inside the VARIANT unions
+//
+
+// owns a raw VARIANT we created (or were handed by COM) and VariantClear's it when dropped, which
+// frees any BSTR or SAFEARRAY and releases any interface it holds
+#[repr(transparent)]
+#[derive(Default)]
+pub(crate) struct OwnedVariant(pub VARIANT);
+
+impl Drop for OwnedVariant {
+    fn drop(&mut self) {
+        unsafe {
+            // nothing useful can be done if clearing fails
+            let _ = VariantClear(&mut self.0);
+        }
+    }
+}
+
+impl OwnedVariant {
+    pub fn from_variant(value: &Variant) -> Result<OwnedVariant,Error> {
+        return Ok(OwnedVariant(value.to_variant()?));
+    }
+}
+
+// the arguments of one invoke as raw VARIANTs, the named ones first (in the same order as their
+// dispids) and then the positional ones in the reversed order Invoke wants them
+pub(crate) struct RawArguments {
+    args: Vec<OwnedVariant>,
+    // what each by-ref argument points at, positional then named in the caller's order (boxed so
+    // it never moves)
+    refs: Vec<Option<Box<OwnedVariant>>>,
+}
+
+impl RawArguments {
+    pub fn new(arguments: &[Argument], named_arguments: &[Argument]) -> Result<RawArguments,Error> {
+        let mut refs = Vec::with_capacity(arguments.len() + named_arguments.len());
+        for argument in arguments.iter().chain(named_arguments.iter()) {
+            let storage = match argument {
+                Argument::ByVal(_) => None,
+                Argument::ByRef(value) | Argument::ByRefVariant(value) => Some(Box::new(OwnedVariant::from_variant(value)?)),
+            };
+            refs.push(storage);
+        }
+
+        let (positional_refs, named_refs) = refs.split_at_mut(arguments.len());
+        let mut args = Vec::with_capacity(arguments.len() + named_arguments.len());
+        for (argument, storage) in named_arguments.iter().zip(named_refs.iter_mut()) {
+            args.push(raw_argument(argument, storage)?);
+        }
+        for (argument, storage) in arguments.iter().zip(positional_refs.iter_mut()).rev() {
+            args.push(raw_argument(argument, storage)?);
+        }
+
+        return Ok(RawArguments { args, refs });
+    }
+
+    pub fn len(&self) -> u32 {
+        return self.args.len() as u32;
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut VARIANT {
+        // OwnedVariant is a transparent wrapper
+        return self.args.as_mut_ptr() as *mut VARIANT;
+    }
+
+    // copies whatever the callee left in the by-ref storage back into the caller's variants
+    pub fn write_back(&self, arguments: &mut [Argument], named_arguments: &mut [Argument]) -> Result<(),Error> {
+        let (positional_refs, named_refs) = self.refs.split_at(arguments.len());
+        for (argument, storage) in arguments.iter_mut().zip(positional_refs.iter()) {
+            if let Some(storage) = storage {
+                argument.set_value(Variant::try_from(&storage.0)?);
+            }
+        }
+        for (argument, storage) in named_arguments.iter_mut().zip(named_refs.iter()) {
+            if let Some(storage) = storage {
+                argument.set_value(Variant::try_from(&storage.0)?);
+            }
+        }
+        return Ok(());
+    }
+}
+
+fn raw_argument(argument: &Argument, storage: &mut Option<Box<OwnedVariant>>) -> Result<OwnedVariant,Error> {
+    return match (argument, storage) {
+        (Argument::ByRef(value), Some(storage)) => Ok(by_ref(storage, !value.is_empty() && !value.is_null())),
+        (Argument::ByRefVariant(_), Some(storage)) => Ok(by_ref(storage, false)),
+        (argument, _) => OwnedVariant::from_variant(argument.value()),
+    };
+}
+
+// a VT_BYREF variant pointing into storage, either at the stored value itself (so typed as
+// VT_BYREF | its type) or at the whole stored VARIANT (so VT_BYREF | VT_VARIANT).  clearing a
+// by-ref variant never frees what it points at, so the storage keeps ownership
+fn by_ref(storage: &mut OwnedVariant, typed: bool) -> OwnedVariant {
+    let mut raw = VARIANT::default();
+    unsafe {
+        let inner = &mut *raw.Anonymous.Anonymous;
+        if typed {
+            let vt = storage.0.Anonymous.Anonymous.vt;
+            inner.vt = VARENUM(vt.0 | VT_BYREF.0);
+            inner.Anonymous.byref = if vt == VT_DECIMAL {
+                // the decimal overlays the whole VARIANT
+                &mut storage.0 as *mut VARIANT as *mut c_void
+            } else {
+                &mut (*storage.0.Anonymous.Anonymous).Anonymous as *mut _ as *mut c_void
+            };
+        } else {
+            inner.vt = VARENUM(VT_VARIANT.0 | VT_BYREF.0);
+            inner.Anonymous.pvarVal = &mut storage.0;
+        }
+    }
+    return OwnedVariant(raw);
+}
+
+// fetches up to count items from an enumerator in one round trip.  fewer than count (possibly none)
+// means the enumerator has reached the end
+pub(crate) fn enum_next(enumerator: &IEnumVARIANT, count: usize) -> Result<Vec<Variant>,Error> {
+    let mut raw: Vec<OwnedVariant> = (0..count).map(|_| OwnedVariant::default()).collect();
+    let mut fetched: u32 = 0;
+    unsafe {
+        // OwnedVariant is transparent, so the vec can be handed over as the VARIANT array
+        let rgvar = std::slice::from_raw_parts_mut(raw.as_mut_ptr() as *mut VARIANT, count);
+        enumerator.Next(rgvar, &mut fetched).ok()?;
+    }
+
+    // the raw variants (including any past fetched) are cleared when dropped
+    return raw.iter()
+        .take(fetched as usize)
+        .map(|v| Variant::try_from(&v.0))
+        .collect();
+}
+
+impl From<windows::core::Error> for Error {
+    fn from(e: windows::core::Error) -> Error {
+        Error::com(e.code().0, e.message().to_string())
+    }
+}
+
+fn bad_var_type(vt: u16) -> Error {
+    return Error::com(hresult::DISP_E_BADVARTYPE, format!("unsupported variant type {:#06x}", vt));
+}
+
+impl TryFrom<&VARIANT> for Variant {
+    type Error = Error;
+
+    // copies the value out of the raw VARIANT, the VARIANT is still owned by the caller
+    fn try_from(value: &VARIANT) -> Result<Variant,Error> {
+        unsafe {
+            let inner = &value.Anonymous.Anonymous;
+            let vt = inner.vt.0;
+            let data = &inner.Anonymous as *const _ as *const c_void;
+
+            if vt & VT_ARRAY.0 != 0 {
+                let psa = if vt & VT_BYREF.0 != 0 {
+                    // a by-ref array the callee left unset (or pointed nowhere at all) has no value
+                    let pparray = inner.Anonymous.pparray;
+                    if pparray.is_null() || (*pparray).is_null() {
+                        return Ok(Variant::Null);
+                    }
+                    *pparray
+                } else {
+                    inner.Anonymous.parray
+                };
+                return Ok(Variant::Array(safe_array_from_raw(psa)?));
+            }
+
+            let var_type = VarType::from_u16(vt & VT_TYPEMASK.0).ok_or_else(|| bad_var_type(vt))?;
+
+            if vt & VT_BYREF.0 != 0 {
+                let target = inner.Anonymous.byref as *const c_void;
+                if target.is_null() {
+                    return Ok(Variant::Empty);
+                }
+                return read_element(var_type, target);
+            }
+
+            return match var_type {
+                // the decimal overlays the whole VARIANT (its reserved field is where vt lives)
+                VarType::Decimal => Ok(Variant::Decimal(decimal_from_raw(&value.Anonymous.decVal)?)),
+                // a VARIANT can't hold another VARIANT by value
+                VarType::Variant => Err(bad_var_type(vt)),
+                _ => read_element(var_type, data)
+            };
+        }
+    }
+}
+
+impl TryFrom<VARIANT> for Variant {
+    type Error = Error;
+
+    // takes ownership of the raw VARIANT and clears it once the value is copied out
+    fn try_from(value: VARIANT) -> Result<Variant,Error> {
+        let owned = OwnedVariant(value);
+        return Variant::try_from(&owned.0);
+    }
+}
+
+impl Variant {
+    // builds a new raw VARIANT that the caller owns and must VariantClear
+    pub fn to_variant(&self) -> Result<VARIANT,Error> {
+        let mut raw = VARIANT::default();
+        unsafe {
+            match self {
+                Variant::Decimal(value) => {
+                    raw.Anonymous.decVal = decimal_to_raw(value);
+                    (*raw.Anonymous.Anonymous).vt = VARENUM(VarType::Decimal.to_u16());
+                }
+                Variant::Array(array) => {
+                    let psa = safe_array_to_raw(array)?;
+                    (*raw.Anonymous.Anonymous).Anonymous.parray = psa;
+                    (*raw.Anonymous.Anonymous).vt = VARENUM(self.vt());
+                }
+                _ => {
+                    let inner = &mut *raw.Anonymous.Anonymous;
+                    write_element(self, &mut inner.Anonymous as *mut _ as *mut c_void)?;
+                    inner.vt = VARENUM(self.vt());
+                }
+            }
+        }
+        return Ok(raw);
+    }
+}
+
+fn decimal_from_raw(value: &DECIMAL) -> Result<Decimal,Error> {
+    unsafe {
+        let sign_scale = value.Anonymous1.Anonymous;
+        return decimal_from_parts(sign_scale.sign & 0x80 != 0, sign_scale.scale, value.Hi32, value.Anonymous2.Lo64)
+            .map_err(|e| Error::com(hresult::DISP_E_BADVARTYPE, e.message()));
+    }
+}
+
+fn decimal_to_raw(value: &Decimal) -> DECIMAL {
+    let (negative, scale, hi, lo) = decimal_to_parts(value);
+    DECIMAL {
+        wReserved: 0,
+        Anonymous1: DECIMAL_0 { Anonymous: DECIMAL_0_0 { scale, sign: if negative { 0x80 } else { 0 } } },
+        Hi32: hi,
+        Anonymous2: DECIMAL_1 { Lo64: lo },
+    }
+}
+
+// reads a single value of the given type stored at data (a VARIANT's value union, a by-ref target
+// or a SAFEARRAY element), interface pointers are AddRef'ed and strings copied
+unsafe fn read_element(vt: VarType, data: *const c_void) -> Result<Variant,Error> {
+    let value = match vt {
+        VarType::Empty => Variant::Empty,
+        VarType::Null => Variant::Null,
+        VarType::I1 => Variant::I1(*(data as *const i8)),
+        VarType::I2 => Variant::I2(*(data as *const i16)),
+        VarType::I4 => Variant::I4(*(data as *const i32)),
+        VarType::I8 => Variant::I8(*(data as *const i64)),
+        VarType::UI1 => Variant::UI1(*(data as *const u8)),
+        VarType::UI2 => Variant::UI2(*(data as *const u16)),
+        VarType::UI4 => Variant::UI4(*(data as *const u32)),
+        VarType::UI8 => Variant::UI8(*(data as *const u64)),
+        VarType::Int => Variant::Int(*(data as *const i32)),
+        VarType::UInt => Variant::UInt(*(data as *const u32)),
+        VarType::R4 => Variant::R4(*(data as *const f32)),
+        VarType::R8 => Variant::R8(*(data as *const f64)),
+        VarType::Bool => Variant::Bool((*(data as *const VARIANT_BOOL)).as_bool()),
+        VarType::Bstr => Variant::Bstr((*(data as *const BSTR)).to_string()),
+        VarType::Cy => Variant::Cy(Currency::from_raw((*(data as *const CY)).int64)),
+        VarType::Date => Variant::Date(OleDate::from_raw(*(data as *const f64))),
+        VarType::Decimal => Variant::Decimal(decimal_from_raw(&*(data as *const DECIMAL))?),
+        VarType::Error => Variant::Error(*(data as *const i32)),
+        // a null object reference (e.g. "Nothing" in VB) has no object to wrap
+        VarType::Dispatch => match &*(data as *const Option<IDispatch>) {
+            Some(dispatch) => Variant::Dispatch(Dispatch::from(dispatch.clone())),
+            None => Variant::Nothing
+        },
+        VarType::Unknown => match &*(data as *const Option<IUnknown>) {
+            Some(unknown) => Variant::Unknown(Unknown::from(unknown.clone())),
+            None => Variant::Nothing
+        },
+        VarType::Variant => Variant::try_from(&*(data as *const VARIANT))?,
+    };
+    return Ok(value);
+}
+
+// writes a single value into zeroed memory at data, ownership of any new BSTR or interface
+// reference moves into that memory (and is later freed by VariantClear or SafeArrayDestroy).  only
+// fails for an object that isn't a real COM one, before anything is written
+unsafe fn write_element(value: &Variant, data: *mut c_void) -> Result<(),Error> {
+    match value {
+        // the memory is zeroed, which for Nothing is already the null pointer
+        Variant::Empty | Variant::Null | Variant::Nothing => {},
+        Variant::I1(v) => *(data as *mut i8) = *v,
+        Variant::I2(v) => *(data as *mut i16) = *v,
+        Variant::I4(v) | Variant::Int(v) | Variant::Error(v) => *(data as *mut i32) = *v,
+        Variant::I8(v) => *(data as *mut i64) = *v,
+        Variant::UI1(v) => *(data as *mut u8) = *v,
+        Variant::UI2(v) => *(data as *mut u16) = *v,
+        Variant::UI4(v) | Variant::UInt(v) => *(data as *mut u32) = *v,
+        Variant::UI8(v) => *(data as *mut u64) = *v,
+        Variant::R4(v) => *(data as *mut f32) = *v,
+        Variant::R8(v) => *(data as *mut f64) = *v,
+        Variant::Date(v) => *(data as *mut f64) = v.to_raw(),
+        Variant::Bool(v) => *(data as *mut VARIANT_BOOL) = VARIANT_BOOL::from(*v),
+        Variant::Bstr(v) => ptr::write(data as *mut ManuallyDrop<BSTR>, ManuallyDrop::new(BSTR::from(v.as_str()))),
+        Variant::Cy(v) => *(data as *mut CY) = CY { int64: v.to_raw() },
+        Variant::Decimal(v) => *(data as *mut DECIMAL) = decimal_to_raw(v),
+        Variant::Dispatch(v) => ptr::write(data as *mut ManuallyDrop<Option<IDispatch>>, ManuallyDrop::new(Some(raw_dispatch(v)?))),
+        Variant::Unknown(v) => ptr::write(data as *mut ManuallyDrop<Option<IUnknown>>, ManuallyDrop::new(Some(raw_unknown(v)?))),
+        // arrays are only ever written directly into a VARIANT by to_variant
+        Variant::Array(_) => {},
+    }
+    return Ok(());
+}
+
+unsafe fn safe_array_from_raw(psa: *const SAFEARRAY) -> Result<SafeArray,Error> {
+    if psa.is_null() {
+        return Err(Error::com(hresult::DISP_E_BADVARTYPE, "array variant has no SAFEARRAY"));
+    }
+
+    let raw_vt = SafeArrayGetVartype(psa)?;
+    let vt = VarType::from_u16(raw_vt.0).filter(|vt| vt.is_array_element()).ok_or_else(|| bad_var_type(raw_vt.0))?;
+
+    let dims = SafeArrayGetDim(psa);
+    let mut bounds = Vec::with_capacity(dims as usize);
+    for dim in 1..=dims {
+        let lower = SafeArrayGetLBound(psa, dim)?;
+        let upper = SafeArrayGetUBound(psa, dim)?;
+        bounds.push(ArrayBound::new(lower, (upper - lower + 1).max(0) as u32));
+    }
+
+    let count: usize = bounds.iter().map(|bound| bound.len as usize).product();
+    let element_size = SafeArrayGetElemsize(psa) as usize;
+    let mut elements = Vec::with_capacity(count);
+
+    let mut data: *mut c_void = ptr::null_mut();
+    SafeArrayAccessData(psa, &mut data)?;
+    let mut read_result = Ok(());
+    for i in 0..count {
+        match read_element(vt, (data as *const u8).add(i * element_size) as *const c_void) {
+            Ok(element) => elements.push(element),
+            Err(e) => {
+                read_result = Err(e);
+                break;
+            }
+        }
+    }
+    SafeArrayUnaccessData(psa)?;
+    read_result?;
+
+    return SafeArray::new(vt, bounds, elements).map_err(|e| Error::com(hresult::DISP_E_BADVARTYPE, e.message()));
+}
+
+unsafe fn safe_array_to_raw(array: &SafeArray) -> Result<*mut SAFEARRAY,Error> {
+    let bounds: Vec<SAFEARRAYBOUND> = array.bounds().iter()
+        .map(|bound| SAFEARRAYBOUND { cElements: bound.len, lLbound: bound.lower })
+        .collect();
+
+    let psa = SafeArrayCreate(VARENUM(array.vt().to_u16()), bounds.len() as u32, bounds.as_ptr());
+    if psa.is_null() {
+        return Err(Error::com(hresult::E_OUTOFMEMORY, "out of memory creating SAFEARRAY"));
+    }
+
+    let element_size = SafeArrayGetElemsize(psa) as usize;
+    let mut data: *mut c_void = ptr::null_mut();
+    if let Err(e) = SafeArrayAccessData(psa, &mut data) {
+        let _ = SafeArrayDestroy(psa);
+        return Err(Error::from(e));
+    }
+
+    let mut write_result = Ok(());
+    for (i, element) in array.elements().iter().enumerate() {
+        let target = (data as *mut u8).add(i * element_size) as *mut c_void;
+        if array.vt() == VarType::Variant {
+            match element.to_variant() {
+                Ok(raw) => ptr::write(target as *mut VARIANT, raw),
+                Err(e) => {
+                    write_result = Err(e);
+                    break;
+                }
+            }
+        } else if let Err(e) = write_element(element, target) {
+            write_result = Err(e);
+            break;
+        }
+    }
+
+    let _ = SafeArrayUnaccessData(psa);
+    if let Err(e) = write_result {
+        // frees any elements we already wrote
+        let _ = SafeArrayDestroy(psa);
+        return Err(e);
+    }
+
+    return Ok(psa);
+}
+
+// RawArguments is crate private, so its layout is checked here rather than under tests/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_argument_order() {
+        let (a, b, c) = (Variant::from(1), Variant::from(2), Variant::from(3));
+        let (value, named) = (Variant::from("value"), Variant::from("named"));
+        let positional = [Argument::ByVal(&a), Argument::ByVal(&b), Argument::ByVal(&c)];
+        let named_arguments = [Argument::ByVal(&value), Argument::ByVal(&named)];
+        let mut args = RawArguments::new(&positional, &named_arguments).unwrap();
+
+        // the named ones in order, then the positional ones backwards
+        let raw = unsafe { std::slice::from_raw_parts(args.as_mut_ptr(), args.len() as usize) };
+        let order: Vec<Variant> = raw.iter().map(|v| Variant::try_from(v).unwrap()).collect();
+        assert_eq!(order, vec![value.clone(), named.clone(), c.clone(), b.clone(), a.clone()]);
+    }
+
+    #[test]
+    fn raw_by_ref_write_back() {
+        let value = Variant::from(1);
+        let (mut positional, mut named) = (Variant::from(2), Variant::from(3));
+        let mut arguments = [Argument::ByVal(&value), Argument::ByRef(&mut positional)];
+        let mut named_arguments = [Argument::ByRef(&mut named)];
+        let mut args = RawArguments::new(&arguments, &named_arguments).unwrap();
+
+        // the callee writes through the by-ref pointers, as found in the raw layout
+        let raw = unsafe { std::slice::from_raw_parts_mut(args.as_mut_ptr(), args.len() as usize) };
+        unsafe {
+            *(*raw[0].Anonymous.Anonymous).Anonymous.plVal = 30;
+            *(*raw[1].Anonymous.Anonymous).Anonymous.plVal = 20;
+        }
+        args.write_back(&mut arguments, &mut named_arguments).unwrap();
+        assert_eq!((arguments[1].value(), named_arguments[0].value()), (&Variant::from(20), &Variant::from(30)));
+    }
+}
@@ -0,0 +1,122 @@
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use windows::core::{ComInterface, GUID, HRESULT, Interface, IUnknown, IUnknown_Vtbl};
+use windows::Win32::Foundation::{E_NOINTERFACE, S_OK};
+use windows::Win32::Media::HTASK;
+use windows::Win32::Media::Audio::{CoRegisterMessageFilter, IMessageFilter, IMessageFilter_Vtbl};
+use windows::Win32::System::Com::{INTERFACEINFO, PENDINGMSG_WAITDEFPROCESS, SERVERCALL_ISHANDLED, SERVERCALL_RETRYLATER};
+use crate::{Error, RetryPolicy};
+
+//
+// an IMessageFilter for the thread's apartment, so COM retries the calls a busy server turns away
+// by itself instead of failing them with RPC_E_CALL_REJECTED:
+//
+//   let _apartment = ComApartment::sta()?;
+//   let _filter = MessageFilter::register(RetryPolicy::new().with_deadline(Duration::from_secs(60)))?;
+//
+// COM asks the filter what to do each time the server says to retry later, and the policy's
+// backoff and deadline decide it, the same as for Dispatch::with_retry.  it only works in a single
+// threaded apartment, and like the apartment the guard is tied to its thread, putting back
+// whatever filter was there before when it's dropped
+//
+
+// the COM object itself, laid out the way COM expects with the vtable first
+#[repr(C)]
+struct Filter {
+    vtable: *const IMessageFilter_Vtbl,
+    references: AtomicU32,
+    policy: RetryPolicy,
+    // the tick count COM last gave for the call being retried and which attempt that was, a
+    // smaller tick count means it's moved on to another call
+    retrying: Cell<(u32, u32)>,
+}
+
+static VTABLE: IMessageFilter_Vtbl = IMessageFilter_Vtbl {
+    base__: IUnknown_Vtbl {
+        QueryInterface: query_interface,
+        AddRef: add_ref,
+        Release: release,
+    },
+    HandleInComingCall: handle_incoming_call,
+    RetryRejectedCall: retry_rejected_call,
+    MessagePending: message_pending,
+};
+
+unsafe extern "system" fn query_interface(this: *mut c_void, iid: &GUID, interface: *mut *const c_void) -> HRESULT {
+    if *iid == IUnknown::IID || *iid == IMessageFilter::IID {
+        add_ref(this);
+        *interface = this;
+        return S_OK;
+    }
+    *interface = ptr::null();
+    return E_NOINTERFACE;
+}
+
+unsafe extern "system" fn add_ref(this: *mut c_void) -> u32 {
+    let filter = &*(this as *const Filter);
+    return filter.references.fetch_add(1, Ordering::Relaxed) + 1;
+}
+
+unsafe extern "system" fn release(this: *mut c_void) -> u32 {
+    let remaining = (*(this as *const Filter)).references.fetch_sub(1, Ordering::Release) - 1;
+    if remaining == 0 {
+        std::sync::atomic::fence(Ordering::Acquire);
+        drop(Box::from_raw(this as *mut Filter));
+    }
+    return remaining;
+}
+
+// calls into the apartment are always accepted, it's only the outgoing ones this is about
+unsafe extern "system" fn handle_incoming_call(_this: *mut c_void, _call_type: u32, _caller: HTASK, _tick_count: u32, _interface_info: *const INTERFACEINFO) -> u32 {
+    return SERVERCALL_ISHANDLED.0 as u32;
+}
+
+// the milliseconds to wait before retrying, or u32::MAX (-1) to give up and fail the call
+unsafe extern "system" fn retry_rejected_call(this: *mut c_void, _callee: HTASK, tick_count: u32, reject_type: u32) -> u32 {
+    // SERVERCALL_REJECTED means the server won't take the call at all, so only retry later's worth
+    // trying again
+    if reject_type != SERVERCALL_RETRYLATER.0 as u32 {
+        return u32::MAX;
+    }
+    let filter = &*(this as *const Filter);
+    let (last_tick_count, attempt) = filter.retrying.get();
+    let attempt = if tick_count < last_tick_count { 1 } else { attempt + 1 };
+    filter.retrying.set((tick_count, attempt));
+    return match filter.policy.retry_delay(attempt, Duration::from_millis(tick_count as u64)) {
+        Some(delay) => u32::try_from(delay.as_millis()).unwrap_or(u32::MAX - 1),
+        None => u32::MAX,
+    };
+}
+
+// lets the default processing handle any messages arriving while a call's waiting
+unsafe extern "system" fn message_pending(_this: *mut c_void, _callee: HTASK, _tick_count: u32, _pending_type: u32) -> u32 {
+    return PENDINGMSG_WAITDEFPROCESS.0 as u32;
+}
+
+// the message filter registered for the current thread until it's dropped
+pub struct MessageFilter {
+    previous: Option<IMessageFilter>,
+    // a raw pointer makes it !Send and !Sync
+    thread: PhantomData<*const ()>,
+}
+
+impl MessageFilter {
+    pub fn register(policy: RetryPolicy) -> Result<MessageFilter,Error> {
+        let filter = Box::new(Filter { vtable: &VTABLE, references: AtomicU32::new(1), policy, retrying: Cell::new((0, 0)) });
+        // takes over the reference the filter was created with, COM adds its own when registering
+        let filter = unsafe { IMessageFilter::from_raw(Box::into_raw(filter) as *mut c_void) };
+        let mut previous = None;
+        unsafe { CoRegisterMessageFilter(&filter, Some(&mut previous))? };
+        return Ok(MessageFilter { previous, thread: PhantomData });
+    }
+}
+
+impl Drop for MessageFilter {
+    fn drop(&mut self) {
+        let _ = unsafe { CoRegisterMessageFilter(self.previous.as_ref(), None) };
+    }
+}
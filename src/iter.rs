@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use crate::{Automation, Dispatch, Enumerator, Error, Variant};
+
+// how many items are fetched from an enumerator per round trip unless told otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 64;
+
+// iterates the items of an automation collection (Workbooks, Sheets, sdo Fields etc.).  the
+// collection's _NewEnum enumerator is used when it has one, fetching items in batches, otherwise it
+// falls back to reading Count and calling Item(1) to Item(Count).  an error (including one getting
+// the enumerator) is returned as an item and ends the iteration
+pub struct DispatchIter {
+    source: Source,
+}
+
+enum Source {
+    Enumerator {
+        enumerator: Box<dyn Enumerator>,
+        batch_size: usize,
+        batch: VecDeque<Variant>,
+        finished: bool,
+    },
+    Indexed {
+        collection: Dispatch,
+        // automation collections are 1-based
+        next: i32,
+        count: i32,
+    },
+    Failed(Option<Error>),
+}
+
+impl DispatchIter {
+    pub(crate) fn new(collection: &Dispatch) -> DispatchIter {
+        let source = match collection.new_enum() {
+            Ok(Some(enumerator)) => Source::Enumerator {
+                enumerator,
+                batch_size: DEFAULT_BATCH_SIZE,
+                batch: VecDeque::new(),
+                finished: false,
+            },
+            Ok(None) => match collection.get_property("Count").and_then(|count| count.to_i32()) {
+                Ok(count) => Source::Indexed { collection: collection.clone(), next: 1, count },
+                Err(e) => Source::Failed(Some(e)),
+            },
+            Err(e) => Source::Failed(Some(e)),
+        };
+        return DispatchIter { source };
+    }
+
+    // how many items to ask the enumerator for at a time, ignored by the Count/Item fallback
+    pub fn with_batch_size(mut self, size: usize) -> DispatchIter {
+        if let Source::Enumerator { batch_size, .. } = &mut self.source {
+            *batch_size = size.max(1);
+        }
+        return self;
+    }
+
+    // true if the collection is being walked with its enumerator rather than Count/Item
+    pub fn is_enumerator(&self) -> bool {
+        return matches!(self.source, Source::Enumerator { .. });
+    }
+}
+
+impl Iterator for DispatchIter {
+    type Item = Result<Variant,Error>;
+
+    fn next(&mut self) -> Option<Result<Variant,Error>> {
+        match &mut self.source {
+            Source::Enumerator { enumerator, batch_size, batch, finished } => {
+                if batch.is_empty() && !*finished {
+                    match enumerator.next_batch(*batch_size) {
+                        Ok(items) => {
+                            *finished = items.len() < *batch_size;
+                            batch.extend(items);
+                        },
+                        Err(e) => {
+                            self.source = Source::Failed(None);
+                            return Some(Err(e));
+                        },
+                    }
+                }
+                return batch.pop_front().map(Ok);
+            },
+            Source::Indexed { collection, next, count } => {
+                if *next > *count {
+                    return None;
+                }
+                let item = collection.call_method("Item", &[Variant::from(*next)]);
+                *next += 1;
+                if item.is_err() {
+                    *next = *count + 1;
+                }
+                return Some(item);
+            },
+            Source::Failed(error) => {
+                return error.take().map(Err);
+            },
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return match &self.source {
+            Source::Enumerator { batch, finished: true, .. } => (batch.len(), Some(batch.len())),
+            Source::Enumerator { batch, .. } => (batch.len(), None),
+            Source::Indexed { next, count, .. } => {
+                let remaining = (*count - *next + 1).max(0) as usize;
+                (remaining, Some(remaining))
+            },
+            Source::Failed(error) => (error.is_some() as usize, Some(error.is_some() as usize)),
+        };
+    }
+}
+
+impl IntoIterator for &Dispatch {
+    type Item = Result<Variant,Error>;
+    type IntoIter = DispatchIter;
+
+    fn into_iter(self) -> DispatchIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,455 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use crate::{Argument, Automation, Dispatch, Enumerator, Error, hresult, InvokeKind, Variant};
+use crate::automation::{DISPID_PROPERTYPUT, DISPID_VALUE};
+use crate::typelib::TypeInfo;
+
+//
+// a scriptable in-memory automation backend, so code written against Dispatch can be exercised
+// without windows.  objects are built up with properties, methods and collection items, handed to
+// the code under test as a Dispatch, and afterwards the calls that were made can be checked:
+//
+//   let sheet = MockObject::new("Worksheet").with_property("Name", "Sheet1");
+//   let excel = MockObject::new("Application").with_property("ActiveSheet", sheet.to_dispatch());
+//   rename(&excel.to_dispatch());
+//   assert_eq!(sheet.property("Name"), Some(Variant::from("Totals")));
+//
+
+// what a method handler gets, and what's recorded for every invoke.  names are as registered, the
+// arguments are in the caller's order and a handler can change them to write back by-ref ones
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockCall {
+    pub name: String,
+    pub kind: InvokeKind,
+    pub args: Vec<Variant>,
+    pub named_args: Vec<(String, Variant)>,
+    // the value being put, for property puts
+    pub value: Option<Variant>,
+}
+
+impl MockCall {
+    pub fn arg(&self, index: usize) -> Option<&Variant> {
+        return self.args.get(index);
+    }
+
+    pub fn named_arg(&self, name: &str) -> Option<&Variant> {
+        return self.named_args.iter()
+            .find(|(param_name, _)| param_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value);
+    }
+}
+
+impl fmt::Display for MockCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {}(", self.kind, self.name)?;
+        let mut first = true;
+        for arg in &self.args {
+            write!(f, "{}{:?}", if first { "" } else { ", " }, arg)?;
+            first = false;
+        }
+        for (name, arg) in &self.named_args {
+            write!(f, "{}{}:={:?}", if first { "" } else { ", " }, name, arg)?;
+            first = false;
+        }
+        write!(f, ")")?;
+        if let Some(value) = &self.value {
+            write!(f, " = {:?}", value)?;
+        }
+        return Ok(());
+    }
+}
+
+type Handler = Rc<dyn Fn(&mut MockCall) -> Result<Variant,Error>>;
+
+enum Member {
+    // a plain value that gets return and puts replace
+    Property(Variant),
+    // anything else, called for every kind of invoke on the member
+    Method(Handler),
+}
+
+struct MockMember {
+    name: String,
+    member: Member,
+}
+
+struct MockState {
+    name: String,
+    // a member's dispid is its index plus one, and a member called Value is also DISPID_VALUE
+    members: Vec<MockMember>,
+    // keyed by "member\0param" in lowercase, parameters are given dispids the first time they're
+    // looked up and keep the name they were first looked up with
+    params: HashMap<String, (i32, String)>,
+    items: Option<Vec<Variant>>,
+    type_info: Option<TypeInfo>,
+    calls: Vec<MockCall>,
+    // the Dispatch references handed out that are still alive, and all that ever were
+    references: usize,
+    add_refs: usize,
+    // how many more calls to turn away with the hresult, and how many have been so far
+    rejecting: (usize, i32),
+    rejected: usize,
+    // how many GetIDsOfNames requests have reached the object, a bulk lookup being one
+    lookups: usize,
+}
+
+impl MockState {
+    // turns the call away the way a busy server does, if it's been told to
+    fn reject(&mut self) -> Result<(),Error> {
+        let (count, hresult) = self.rejecting;
+        if count == 0 {
+            return Ok(());
+        }
+        self.rejecting.0 -= 1;
+        self.rejected += 1;
+        return Err(Error::com(hresult, format!("{} rejected the call", self.name)));
+    }
+
+    fn lookup(&mut self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        let index = self.members.iter()
+            .position(|m| m.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::com(hresult::DISP_E_UNKNOWNNAME, format!("{} has no member {}", self.name, name)))?;
+
+        let mut dispids = vec![index as i32 + 1];
+        for param_name in param_names {
+            let next = self.params.len() as i32 + 1;
+            let (dispid, _) = self.params.entry(MockObject::param_key(name, param_name)).or_insert((next, param_name.to_string()));
+            dispids.push(*dispid);
+        }
+        return Ok(dispids);
+    }
+
+    fn member_index(&self, dispid: i32) -> Option<usize> {
+        if dispid == DISPID_VALUE {
+            return self.members.iter().position(|m| m.name.eq_ignore_ascii_case("Value"));
+        }
+        return usize::try_from(dispid - 1).ok().filter(|i| *i < self.members.len());
+    }
+}
+
+// an in-memory automation object.  clones are references to the same object, just like COM
+#[derive(Clone)]
+pub struct MockObject {
+    state: Rc<RefCell<MockState>>
+}
+
+impl MockObject {
+    pub fn new(name: &str) -> MockObject {
+        MockObject {
+            state: Rc::new(RefCell::new(MockState {
+                name: name.to_string(),
+                members: Vec::new(),
+                params: HashMap::new(),
+                items: None,
+                type_info: None,
+                calls: Vec::new(),
+                references: 0,
+                add_refs: 0,
+                rejecting: (0, 0),
+                rejected: 0,
+                lookups: 0,
+            }))
+        }
+    }
+
+    pub fn name(&self) -> String {
+        return self.state.borrow().name.clone();
+    }
+
+    // a new reference to the object, counted like a COM object's AddRef and Release
+    pub fn to_dispatch(&self) -> Dispatch {
+        return Dispatch::new(MockReference::new(self));
+    }
+
+    // how many references from to_dispatch (and clones of them) are still alive, which is 0 once
+    // everything holding the object has been dropped
+    pub fn ref_count(&self) -> usize {
+        return self.state.borrow().references;
+    }
+
+    // how many references have ever been taken, so a test can tell a move from a clone and a drop
+    pub fn add_ref_count(&self) -> usize {
+        return self.state.borrow().add_refs;
+    }
+
+    // releases a reference without a Dispatch being dropped, like a stray Release from code that
+    // never took one.  releasing more references than were taken panics, here or when the last
+    // Dispatch is dropped, the way a real object would be freed while still in use
+    pub fn release(&self) {
+        let mut state = self.state.borrow_mut();
+        state.references = state.references.checked_sub(1)
+            .unwrap_or_else(|| panic!("{} was released more times than it was referenced", state.name));
+    }
+
+    pub fn with_property<V: Into<Variant>>(self, name: &str, value: V) -> MockObject {
+        self.define(name, Member::Property(value.into()));
+        return self;
+    }
+
+    // the handler is called for every get, put or call of the member, it can look at call.kind to
+    // tell them apart
+    pub fn with_method<F>(self, name: &str, handler: F) -> MockObject
+    where F: Fn(&mut MockCall) -> Result<Variant,Error> + 'static {
+        self.define(name, Member::Method(Rc::new(handler)));
+        return self;
+    }
+
+    // makes this a collection of the items, with an enumerator and the usual Count and 1-based Item
+    // members
+    pub fn with_items(self, items: Vec<Variant>) -> MockObject {
+        // the members only hold a weak reference back to the object, so it isn't kept alive forever
+        let state = Rc::downgrade(&self.state);
+        self.state.borrow_mut().items = Some(items);
+        self.define("Count", Member::Method(Rc::new({
+            let state = state.clone();
+            move |_call: &mut MockCall| {
+                let state = state.upgrade().ok_or_else(released)?;
+                let count = state.borrow().items.as_ref().map(|items| items.len()).unwrap_or(0);
+                return Ok(Variant::from(count as i32));
+            }
+        })));
+        self.define("Item", Member::Method(Rc::new(move |call: &mut MockCall| {
+            let index = call.arg(0).ok_or_else(|| Error::com(hresult::DISP_E_PARAMNOTOPTIONAL, "Item needs an index"))?.to_i32()?;
+            let state = state.upgrade().ok_or_else(released)?;
+            let state = state.borrow();
+            let items = state.items.as_deref().unwrap_or(&[]);
+            return usize::try_from(index - 1).ok()
+                .and_then(|i| items.get(i))
+                .cloned()
+                .ok_or_else(|| Error::com(hresult::DISP_E_BADINDEX, format!("no item {}", index)));
+        })));
+        return self;
+    }
+
+    // what type_info hands back, e.g. one read from a type library or built by hand.  it isn't
+    // checked against the members, so the memids should be the dispids they were given
+    pub fn with_type_info(self, type_info: TypeInfo) -> MockObject {
+        self.state.borrow_mut().type_info = Some(type_info);
+        return self;
+    }
+
+    // the current value of a plain property
+    pub fn property(&self, name: &str) -> Option<Variant> {
+        return match self.state.borrow().members.iter().find(|m| m.name.eq_ignore_ascii_case(name)) {
+            Some(MockMember { member: Member::Property(value), .. }) => Some(value.clone()),
+            _ => None,
+        };
+    }
+
+    pub fn set_property<V: Into<Variant>>(&self, name: &str, value: V) {
+        self.define(name, Member::Property(value.into()));
+    }
+
+    // every invoke made on the object so far, in order
+    pub fn calls(&self) -> Vec<MockCall> {
+        return self.state.borrow().calls.clone();
+    }
+
+    pub fn calls_to(&self, name: &str) -> Vec<MockCall> {
+        return self.state.borrow().calls.iter()
+            .filter(|call| call.name.eq_ignore_ascii_case(name))
+            .cloned()
+            .collect();
+    }
+
+    pub fn clear_calls(&self) {
+        self.state.borrow_mut().calls.clear();
+    }
+
+    // the next count lookups, invokes or _NewEnums fail with the hresult without reaching the
+    // members (so they aren't in calls), like a server that's busy, e.g. RPC_E_CALL_REJECTED
+    pub fn reject_calls(&self, count: usize, hresult: i32) {
+        self.state.borrow_mut().rejecting = (count, hresult);
+    }
+
+    // how many lookups and invokes have been rejected so far
+    pub fn rejected_count(&self) -> usize {
+        return self.state.borrow().rejected;
+    }
+
+    // how many name lookups have reached the object, which a Dispatch's dispid cache keeps down
+    pub fn lookup_count(&self) -> usize {
+        return self.state.borrow().lookups;
+    }
+
+    // adds the member, or replaces it keeping its dispid
+    fn define(&self, name: &str, member: Member) {
+        let mut state = self.state.borrow_mut();
+        match state.members.iter_mut().find(|m| m.name.eq_ignore_ascii_case(name)) {
+            Some(existing) => existing.member = member,
+            None => state.members.push(MockMember { name: name.to_string(), member }),
+        }
+    }
+
+    fn param_key(name: &str, param_name: &str) -> String {
+        return format!("{}\0{}", name.to_lowercase(), param_name.to_lowercase());
+    }
+
+    // builds the call from the raw invoke, resolving the dispids back into names
+    fn to_call(&self, dispid: i32, kind: InvokeKind, arguments: &[Argument], named_dispids: &[i32], named_arguments: &[Argument]) -> Result<MockCall,Error> {
+        let state = self.state.borrow();
+        let member = state.member_index(dispid)
+            .map(|i| &state.members[i])
+            .ok_or_else(|| Error::com(hresult::DISP_E_MEMBERNOTFOUND, format!("{} has no member with dispid {}", state.name, dispid)))?;
+        let prefix = MockObject::param_key(&member.name, "");
+
+        let mut call = MockCall {
+            name: member.name.clone(),
+            kind,
+            args: arguments.iter().map(|a| a.value().clone()).collect(),
+            named_args: Vec::new(),
+            value: None,
+        };
+        for (i, (named_dispid, argument)) in named_dispids.iter().zip(named_arguments.iter()).enumerate() {
+            if *named_dispid == DISPID_PROPERTYPUT && kind.is_put() {
+                call.value = Some(argument.value().clone());
+                continue;
+            }
+            let param_name = state.params.iter()
+                .find(|(key, (id, _))| *id == *named_dispid && key.starts_with(&prefix))
+                .map(|(_, (_, param_name))| param_name.clone())
+                .ok_or_else(|| Error::Argument {
+                    hresult: hresult::DISP_E_PARAMNOTFOUND,
+                    index: arguments.len() + i,
+                    message: format!("{} has no parameter with dispid {}", member.name, named_dispid),
+                })?;
+            call.named_args.push((param_name, argument.value().clone()));
+        }
+        return Ok(call);
+    }
+}
+
+impl Automation for MockObject {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        let mut state = self.state.borrow_mut();
+        state.reject()?;
+        state.lookups += 1;
+        return state.lookup(name, param_names);
+    }
+
+    // answered in one go, like a backend that can resolve several members in one round trip
+    fn get_ids_of_members(&self, names: &[&str]) -> Result<Vec<i32>,Error> {
+        let mut state = self.state.borrow_mut();
+        state.reject()?;
+        state.lookups += 1;
+        return names.iter().map(|name| Ok(state.lookup(name, &[])?[0])).collect();
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        self.state.borrow_mut().reject()?;
+        let mut call = self.to_call(dispid, kind, arguments, named_dispids, named_arguments)?;
+        self.state.borrow_mut().calls.push(call.clone());
+
+        // the handler is cloned out so it can use the object itself without a double borrow
+        let index = self.state.borrow().member_index(dispid).unwrap_or_default();
+        let member = match &self.state.borrow().members[index].member {
+            Member::Property(value) => Member::Property(value.clone()),
+            Member::Method(handler) => Member::Method(handler.clone()),
+        };
+        let result = match member {
+            Member::Property(_) if !call.args.is_empty() => {
+                return Err(Error::com(hresult::DISP_E_BADPARAMCOUNT, format!("{} doesn't take arguments", call.name)));
+            },
+            Member::Property(value) if !kind.is_put() => value,
+            Member::Property(_) => {
+                let value = call.value.clone().unwrap_or_default();
+                self.set_property(&call.name, value);
+                Variant::Empty
+            },
+            Member::Method(handler) => handler(&mut call)?,
+        };
+
+        // by-ref arguments get whatever the handler left in the call
+        for (argument, value) in arguments.iter_mut().zip(call.args) {
+            argument.set_value(value);
+        }
+        return Ok(result);
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return Rc::as_ptr(&self.state) as *const () as usize;
+    }
+
+    // _NewEnum is an invoke like any other, so it can be rejected too
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        let mut state = self.state.borrow_mut();
+        state.reject()?;
+        let Some(items) = state.items.clone() else { return Ok(None) };
+        return Ok(Some(Box::new(MockEnumerator { items: items.into_iter() })));
+    }
+
+    fn type_info(&self) -> Result<TypeInfo,Error> {
+        let state = self.state.borrow();
+        return state.type_info.clone().ok_or_else(|| Error::com(hresult::E_NOTIMPL, format!("{} doesn't describe itself", state.name)));
+    }
+}
+
+// a counted reference to a mock object, what to_dispatch hands out
+struct MockReference {
+    object: MockObject,
+}
+
+impl MockReference {
+    fn new(object: &MockObject) -> MockReference {
+        let mut state = object.state.borrow_mut();
+        state.references += 1;
+        state.add_refs += 1;
+        return MockReference { object: object.clone() };
+    }
+}
+
+impl Drop for MockReference {
+    fn drop(&mut self) {
+        self.object.release();
+    }
+}
+
+impl Automation for MockReference {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        return self.object.get_ids_of_names(name, param_names);
+    }
+
+    fn get_ids_of_members(&self, names: &[&str]) -> Result<Vec<i32>,Error> {
+        return self.object.get_ids_of_members(names);
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        return self.object.invoke(dispid, kind, arguments, named_dispids, named_arguments);
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(MockReference::new(&self.object));
+    }
+
+    fn identity(&self) -> usize {
+        return self.object.identity();
+    }
+
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        return self.object.new_enum();
+    }
+
+    fn type_info(&self) -> Result<TypeInfo,Error> {
+        return self.object.type_info();
+    }
+}
+
+fn released() -> Error {
+    return Error::com(hresult::E_FAIL, "object has been released");
+}
+
+struct MockEnumerator {
+    items: std::vec::IntoIter<Variant>,
+}
+
+impl Enumerator for MockEnumerator {
+    fn next_batch(&mut self, count: usize) -> Result<Vec<Variant>,Error> {
+        return Ok(self.items.by_ref().take(count).collect());
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use crate::{Automation, Currency, Dispatch, Error, hresult, OleDate, Variant};
+
+//
+// mapping automation objects onto rust structs, which hello-com-rust-derive generates the impls
+// for:
+//
+//   #[derive(FromDispatch, IntoDispatch)]
+//   #[dispatch(rename_all = "PascalCase")]
+//   struct Customer {
+//       account_ref: String,
+//       balance: Decimal,
+//       #[dispatch(rename = "EMail")]
+//       email: Option<String>,
+//       // a property holding another object
+//       address: Address,
+//   }
+//
+//   let customer = Customer::from_dispatch(&record)?;
+//
+// every field is read from the property of the same name (names are case insensitive, like
+// everything in automation) and converted with FromVariant.  the attributes are:
+//  - rename_all = "PascalCase" on the struct, so account_ref is read from AccountRef
+//  - rename = "..." on a field, the property's name
+//  - default, a field that gets its Default when the object has no such property or it's empty
+//  - skip, a field that's never read or written and is always its Default
+//  - read_only, a field that's read but never written back, e.g. a Count
+// an Option field is None when the property is missing or empty, and isn't written back when None
+//
+
+// a struct that can be filled in from an automation object's properties
+pub trait FromDispatch: Sized {
+    fn from_dispatch(dispatch: &Dispatch) -> Result<Self,Error>;
+}
+
+// a struct whose fields can be written back to an automation object's properties
+pub trait IntoDispatch {
+    fn put_into(&self, dispatch: &Dispatch) -> Result<(),Error>;
+}
+
+// a value that can be read out of a variant, converting between types the way vb would
+pub trait FromVariant: Sized {
+    fn from_variant(value: Variant) -> Result<Self,Error>;
+}
+
+// a field of a FromDispatch struct, read from the named property
+pub trait FromProperty: Sized {
+    fn from_property(dispatch: &Dispatch, name: &str) -> Result<Self,Error>;
+}
+
+// a field of an IntoDispatch struct, written to the named property
+pub trait IntoProperty {
+    fn write_property(&self, dispatch: &Dispatch, name: &str) -> Result<(),Error>;
+}
+
+impl<T: FromVariant> FromProperty for T {
+    fn from_property(dispatch: &Dispatch, name: &str) -> Result<T,Error> {
+        let value = dispatch.get_property(name)?;
+        return T::from_variant(value).map_err(|e| match e {
+            Error::Conversion(message) => Error::Conversion(format!("{}: {}", name, message)),
+            e => e,
+        });
+    }
+}
+
+impl<T: FromVariant> FromProperty for Option<T> {
+    fn from_property(dispatch: &Dispatch, name: &str) -> Result<Option<T>,Error> {
+        return match dispatch.get_property(name) {
+            Ok(Variant::Empty) | Ok(Variant::Null) | Ok(Variant::Nothing) => Ok(None),
+            Ok(value) => T::from_variant(value).map(Some),
+            Err(e) if is_missing(&e) => Ok(None),
+            Err(e) => Err(e),
+        };
+    }
+}
+
+// a property that isn't there at all, as opposed to one that failed
+fn is_missing(error: &Error) -> bool {
+    return matches!(error.hresult(), hresult::DISP_E_UNKNOWNNAME | hresult::DISP_E_MEMBERNOTFOUND);
+}
+
+// a nested object, read with its own FromDispatch
+impl<T: FromDispatch> FromVariant for T {
+    fn from_variant(value: Variant) -> Result<T,Error> {
+        return T::from_dispatch(&value.into_dispatch()?);
+    }
+}
+
+// the elements of an array, or the items of a collection
+impl<T: FromVariant> FromVariant for Vec<T> {
+    fn from_variant(value: Variant) -> Result<Vec<T>,Error> {
+        return match value {
+            Variant::Array(array) => array.elements().iter().cloned().map(T::from_variant).collect(),
+            Variant::Dispatch(collection) => collection.iter().map(|item| T::from_variant(item?)).collect(),
+            other => Err(Error::result(format!("{:?} is not an array or collection", other))),
+        };
+    }
+}
+
+impl FromVariant for Variant {
+    fn from_variant(value: Variant) -> Result<Variant,Error> {
+        return Ok(value);
+    }
+}
+
+// everything else converts the way VariantChangeType would, with TryFrom<&Variant>
+macro_rules! from_variant {
+    ($($t:ty),*) => {
+        $(
+            impl FromVariant for $t {
+                fn from_variant(value: Variant) -> Result<$t,Error> {
+                    return <$t>::try_from(&value);
+                }
+            }
+        )*
+    };
+}
+
+from_variant!(Dispatch, bool, i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, String, Decimal, Currency, OleDate, NaiveDate, NaiveDateTime);
+
+// a nested object's properties are written to the object the property holds, rather than
+// replacing it
+impl<T: IntoDispatch> IntoProperty for T {
+    fn write_property(&self, dispatch: &Dispatch, name: &str) -> Result<(),Error> {
+        return self.put_into(&dispatch.get_property(name)?.into_dispatch()?);
+    }
+}
+
+impl<T: IntoProperty> IntoProperty for Option<T> {
+    fn write_property(&self, dispatch: &Dispatch, name: &str) -> Result<(),Error> {
+        return match self {
+            Some(value) => value.write_property(dispatch, name),
+            None => Ok(()),
+        };
+    }
+}
+
+// anything a variant can be made from is put as it is
+macro_rules! write_property {
+    ($($t:ty),*) => {
+        $(
+            impl IntoProperty for $t {
+                fn write_property(&self, dispatch: &Dispatch, name: &str) -> Result<(),Error> {
+                    return dispatch.put_property(name, &Variant::from(self.clone()));
+                }
+            }
+        )*
+    };
+}
+
+write_property!(Variant, Dispatch, bool, i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, String, Decimal, Currency, OleDate, NaiveDate, NaiveDateTime);
@@ -0,0 +1,219 @@
+use std::fmt;
+use crate::{Automation, Dispatch, Error, hresult, Variant};
+
+//
+// dotted paths through an object model, so a chain of gets doesn't need a to_dispatch after every
+// step:
+//
+//   let name = excel.eval("Workbooks.Item(1).ActiveSheet.Name")?;
+//   excel.set("ActiveSheet.Range(\"A1\").Value", &Variant::from(42))?;
+//
+// each step is a member name, optionally followed by arguments in parentheses.  the arguments are
+// literals written the way vb writes them:
+//  - strings in double quotes, with a quote inside one doubled up ("say ""hi""")
+//  - integers, which are VT_I4 when they fit and VT_I8 otherwise
+//  - numbers with a decimal point or exponent, which are VT_R8
+//  - True and False
+// every step but the last has to give back an object.  the steps are gets (sent as either a get or
+// a call, like every get), and for a set the last one is a put
+//
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathSegment {
+    pub name: String,
+    pub args: Vec<Variant>,
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.args.is_empty() {
+            return Ok(());
+        }
+        write!(f, "(")?;
+        for (i, arg) in self.args.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            match arg {
+                Variant::Bstr(s) => write!(f, "{}\"{}\"", separator, s.replace('"', "\"\""))?,
+                Variant::Bool(true) => write!(f, "{}True", separator)?,
+                Variant::Bool(false) => write!(f, "{}False", separator)?,
+                other => write!(f, "{}{}", separator, other)?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+// a parsed path, which can be evaluated against any number of objects.  it's only made by parse,
+// so it always has at least one segment
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberPath {
+    segments: Vec<PathSegment>,
+}
+
+impl fmt::Display for MemberPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { "" } else { "." }, segment)?;
+        }
+        return Ok(());
+    }
+}
+
+impl MemberPath {
+    pub fn parse(path: &str) -> Result<MemberPath,Error> {
+        let mut parser = Parser { path, chars: path.char_indices().peekable() };
+        let mut segments = vec![parser.segment()?];
+        loop {
+            parser.skip_whitespace();
+            match parser.chars.next() {
+                None => break,
+                Some((_, '.')) => segments.push(parser.segment()?),
+                Some((at, c)) => return Err(parser.error(at, format!("expected '.' but found '{}'", c))),
+            }
+        }
+        return Ok(MemberPath { segments });
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        return &self.segments;
+    }
+
+    // gets the value at the end of the path
+    pub fn eval<A: Automation + ?Sized>(&self, object: &A) -> Result<Variant,Error> {
+        let (last, steps) = self.segments.split_last().expect("a parsed path has at least one segment");
+        return match self.walk(object, steps)? {
+            Some(parent) => get(&parent, last),
+            None => get(object, last),
+        };
+    }
+
+    // puts the value into the last member of the path
+    pub fn set<A: Automation + ?Sized>(&self, object: &A, value: &Variant) -> Result<(),Error> {
+        let (last, steps) = self.segments.split_last().expect("a parsed path has at least one segment");
+        return match self.walk(object, steps)? {
+            Some(parent) => parent.put_property_with_args(&last.name, &last.args, value),
+            None => object.put_property_with_args(&last.name, &last.args, value),
+        };
+    }
+
+    // the object the steps lead to, None when there aren't any and it's the object itself
+    fn walk<A: Automation + ?Sized>(&self, object: &A, steps: &[PathSegment]) -> Result<Option<Dispatch>,Error> {
+        let mut current: Option<Dispatch> = None;
+        for (i, step) in steps.iter().enumerate() {
+            let value = match &current {
+                Some(parent) => get(parent, step)?,
+                None => get(object, step)?,
+            };
+            let dispatch = match &value {
+                Variant::Dispatch(dispatch) => dispatch.clone(),
+                Variant::Unknown(unknown) => unknown.to_dispatch()?,
+                other => {
+                    let path = MemberPath { segments: self.segments[..=i].to_vec() };
+                    return Err(Error::result(format!("{} is {}, not an object", path, other.var_type())));
+                },
+            };
+            current = Some(dispatch);
+        }
+        return Ok(current);
+    }
+}
+
+fn get<A: Automation + ?Sized>(object: &A, segment: &PathSegment) -> Result<Variant,Error> {
+    return object.get_property_with_args(&segment.name, &segment.args);
+}
+
+struct Parser<'a> {
+    path: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, at: usize, message: String) -> Error {
+        return Error::com(hresult::E_INVALIDARG, format!("invalid path \"{}\" at {}: {}", self.path, at, message));
+    }
+
+    fn end(&self) -> usize {
+        return self.path.len();
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    // the characters while the predicate holds, from the current position
+    fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> &'a str {
+        let start = self.chars.peek().map(|(at, _)| *at).unwrap_or(self.path.len());
+        let mut end = start;
+        while let Some((at, c)) = self.chars.next_if(|(_, c)| predicate(*c)) {
+            end = at + c.len_utf8();
+        }
+        return &self.path[start..end];
+    }
+
+    fn segment(&mut self) -> Result<PathSegment,Error> {
+        self.skip_whitespace();
+        let at = self.chars.peek().map(|(at, _)| *at).unwrap_or(self.end());
+        if !self.chars.peek().is_some_and(|(_, c)| c.is_alphabetic() || *c == '_') {
+            return Err(self.error(at, "expected a member name".to_string()));
+        }
+        let name = self.take_while(|c| c.is_alphanumeric() || c == '_').to_string();
+
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, c)| *c == '(').is_some() {
+            self.skip_whitespace();
+            if self.chars.next_if(|(_, c)| *c == ')').is_none() {
+                loop {
+                    args.push(self.literal()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some((_, ',')) => continue,
+                        Some((_, ')')) => break,
+                        Some((at, c)) => return Err(self.error(at, format!("expected ',' or ')' but found '{}'", c))),
+                        None => return Err(self.error(self.end(), "missing ')'".to_string())),
+                    }
+                }
+            }
+        }
+        return Ok(PathSegment { name, args });
+    }
+
+    fn literal(&mut self) -> Result<Variant,Error> {
+        self.skip_whitespace();
+        let (at, c) = match self.chars.peek() {
+            Some((at, c)) => (*at, *c),
+            None => return Err(self.error(self.end(), "expected an argument".to_string())),
+        };
+        if c == '"' {
+            self.chars.next();
+            let mut value = String::new();
+            loop {
+                match self.chars.next() {
+                    Some((_, '"')) if self.chars.next_if(|(_, c)| *c == '"').is_some() => value.push('"'),
+                    Some((_, '"')) => return Ok(Variant::Bstr(value)),
+                    Some((_, c)) => value.push(c),
+                    None => return Err(self.error(at, "unterminated string".to_string())),
+                }
+            }
+        }
+
+        let token = self.take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '+' | '.' | '_'));
+        if token.eq_ignore_ascii_case("true") {
+            return Ok(Variant::Bool(true));
+        }
+        if token.eq_ignore_ascii_case("false") {
+            return Ok(Variant::Bool(false));
+        }
+        if let Ok(value) = token.parse::<i64>() {
+            return Ok(i32::try_from(value).map(Variant::I4).unwrap_or(Variant::I8(value)));
+        }
+        if token.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.')) {
+            if let Ok(value) = token.parse::<f64>() {
+                return Ok(Variant::R8(value));
+            }
+        }
+        let found = if token.is_empty() { c.to_string() } else { token.to_string() };
+        return Err(self.error(at, format!("expected a string, number, True or False but found '{}'", found)));
+    }
+}
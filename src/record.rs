@@ -0,0 +1,732 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::{Argument, Automation, Dispatch, Enumerator, Error, hresult, InvokeKind, SafeArray, Unknown, UnknownObject, Variant, VarType};
+use crate::automation::DISPID_PROPERTYPUT;
+use crate::serialize::{decode, encode};
+use crate::typelib::TypeInfo;
+
+//
+// record and replay of automation sessions.  a Recorder wraps a real object and writes every name
+// lookup failure, get, put, call and enumeration made through it (and through every object reached
+// from it) to a JSON lines log.  a Replay serves that log back on any platform, so code that drove
+// e.g. sage once on windows can be re-run against the same answers in CI:
+//
+//   let recorder = Recorder::create("sage.jsonl")?;
+//   let sdo_engine = recorder.record(&co_create_dispatch(&clsid)?);
+//   ...
+//   recorder.finish()?;
+//
+//   let replay = Replay::open("sage.jsonl")?;
+//   let sdo_engine = replay.root();
+//   ...
+//   replay.finish()?;
+//
+// replay is strict, each invoke has to match the next one in the log (same object, member, kind and
+// arguments) or it fails with an error describing both.  objects are numbered in the order they're
+// first seen, the ones passed to record are numbered from 0 in the order they were recorded.  an
+// object that's been released gets a new number if it's seen again, since by then its identity
+// could belong to another one.
+//
+// type info is passed through to the real object while recording, but it isn't logged, so the
+// objects of a replay have none
+//
+
+// the dispids handed out for names, well clear of anything a real object is likely to use.  any
+// other dispid is passed straight through and logged as "#dispid"
+const NAME_DISPID_BASE: i32 = 0x4000_0000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    // a name that couldn't be looked up (successful lookups aren't logged, they're cached anyway)
+    Lookup {
+        object: u64,
+        name: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        params: Vec<String>,
+        error: Error,
+    },
+    Invoke {
+        object: u64,
+        name: String,
+        kind: InvokeKind,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<Value>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        named_args: Vec<(String, Value)>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<Value>,
+        // the arguments after the call (positional then named) when any were passed by reference
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        written_back: Option<Vec<Value>>,
+        result: Result<Value,Error>,
+    },
+    NewEnum {
+        object: u64,
+        enumerator: Option<u64>,
+        // getting the enumerator failed, rather than there not being one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<Error>,
+    },
+    Next {
+        enumerator: u64,
+        count: usize,
+        result: Result<Vec<Value>,Error>,
+    },
+}
+
+impl Event {
+    // the part of an invoke that has to match on replay, for error messages
+    fn describe(&self) -> String {
+        return match self {
+            Event::Lookup { object, name, .. } => format!("lookup of {} on object {}", name, object),
+            Event::Invoke { object, name, kind, args, named_args, value, .. } => {
+                let mut all: Vec<String> = args.iter().map(Value::to_string).collect();
+                all.extend(named_args.iter().map(|(name, arg)| format!("{}:={}", name, arg)));
+                let value = value.as_ref().map(|v| format!(" = {}", v)).unwrap_or_default();
+                format!("{:?} {}({}){} on object {}", kind, name, all.join(", "), value, object)
+            },
+            Event::NewEnum { object, .. } => format!("_NewEnum on object {}", object),
+            Event::Next { enumerator, count, .. } => format!("next {} of enumerator {}", count, enumerator),
+        };
+    }
+}
+
+// the dispids given out for member names and "member\0param" names, shared by every object in a
+// session so objects sharing a DispidCache all agree on them
+#[derive(Default)]
+struct Names {
+    ids: HashMap<String, i32>,
+    // as first spelled, by dispid - NAME_DISPID_BASE
+    names: Vec<String>,
+}
+
+impl Names {
+    fn id(&mut self, key: &str, name: &str) -> i32 {
+        let next = NAME_DISPID_BASE + self.names.len() as i32;
+        let id = *self.ids.entry(key.to_lowercase()).or_insert(next);
+        if id == next {
+            self.names.push(name.to_string());
+        }
+        return id;
+    }
+
+    fn ids(&mut self, name: &str, param_names: &[&str]) -> Vec<i32> {
+        let mut ids = vec![self.id(name, name)];
+        ids.extend(param_names.iter().map(|param_name| self.id(&format!("{}\0{}", name, param_name), param_name)));
+        return ids;
+    }
+
+    fn name(&self, dispid: i32) -> String {
+        return usize::try_from(dispid.wrapping_sub(NAME_DISPID_BASE)).ok()
+            .filter(|_| dispid >= NAME_DISPID_BASE)
+            .and_then(|i| self.names.get(i))
+            .cloned()
+            .unwrap_or_else(|| format!("#{}", dispid));
+    }
+}
+
+// the values of the by-ref arguments before the call
+fn by_ref_values(arguments: &[Argument]) -> Vec<Option<Variant>> {
+    return arguments.iter().map(|a| a.is_by_ref().then(|| a.value().clone())).collect();
+}
+
+// arguments are all the positional ones followed by the named ones
+fn written_back(arguments: &[Argument], named_arguments: &[Argument], object_id: &mut dyn FnMut(&Variant) -> u64) -> Option<Vec<Value>> {
+    if !arguments.iter().chain(named_arguments.iter()).any(Argument::is_by_ref) {
+        return None;
+    }
+    return Some(arguments.iter().chain(named_arguments.iter()).map(|a| encode(a.value(), object_id)).collect());
+}
+
+//
+// recording
+//
+
+// an object's number, kept for as long as the identity can't be reused by another object
+struct Numbered {
+    id: u64,
+    // how many recording objects wrap it, the entry goes when the last of them is dropped
+    wrappers: usize,
+    // a reference to an object that was only ever logged (e.g. an unknown, or an argument from
+    // outside the recording), held until the recorder goes since nothing says when it's released
+    held: Option<Variant>,
+}
+
+struct RecordState {
+    writer: Box<dyn Write>,
+    // the first failure writing the log, reported by finish
+    error: Option<Error>,
+    names: Names,
+    // object numbers by identity
+    objects: HashMap<usize, Numbered>,
+    next_object: u64,
+    next_enumerator: u64,
+}
+
+impl RecordState {
+    fn object_id(&mut self, value: &Variant) -> u64 {
+        let identity = match value {
+            Variant::Dispatch(dispatch) => dispatch.identity(),
+            Variant::Unknown(unknown) => unknown.identity(),
+            _ => 0,
+        };
+        if let Some(numbered) = self.objects.get(&identity) {
+            return numbered.id;
+        }
+        let id = self.next_object;
+        self.next_object += 1;
+        self.objects.insert(identity, Numbered { id, wrappers: 0, held: Some(value.clone()) });
+        return id;
+    }
+
+    // the number of an object a recording object is wrapping
+    fn add_wrapper(&mut self, identity: usize) -> u64 {
+        let next = self.next_object;
+        let numbered = self.objects.entry(identity).or_insert(Numbered { id: next, wrappers: 0, held: None });
+        if numbered.id == next {
+            self.next_object += 1;
+        }
+        numbered.wrappers += 1;
+        return numbered.id;
+    }
+
+    fn release_wrapper(&mut self, identity: usize) {
+        if let Some(numbered) = self.objects.get_mut(&identity) {
+            numbered.wrappers -= 1;
+            if numbered.wrappers == 0 && numbered.held.is_none() {
+                self.objects.remove(&identity);
+            }
+        }
+    }
+
+    fn encode(&mut self, value: &Variant) -> Value {
+        return encode(value, &mut |v| self.object_id(v));
+    }
+
+    fn write(&mut self, event: &Event) {
+        if self.error.is_some() {
+            return;
+        }
+        let written = serde_json::to_string(event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(self.writer, "{}", line).and_then(|_| self.writer.flush()).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            self.error = Some(Error::com(hresult::E_FAIL, format!("failed writing recording: {}", e)));
+        }
+    }
+}
+
+// records everything done through the objects it hands out
+#[derive(Clone)]
+pub struct Recorder {
+    state: Rc<RefCell<RecordState>>
+}
+
+impl Recorder {
+    pub fn new<W: Write + 'static>(writer: W) -> Recorder {
+        Recorder {
+            state: Rc::new(RefCell::new(RecordState {
+                writer: Box::new(writer),
+                error: None,
+                names: Names::default(),
+                objects: HashMap::new(),
+                next_object: 0,
+                next_enumerator: 0,
+            }))
+        }
+    }
+
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Recorder,Error> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|e| Error::com(hresult::E_FAIL, format!("failed creating recording {}: {}", path.display(), e)))?;
+        return Ok(Recorder::new(BufWriter::new(file)));
+    }
+
+    // the object to use in place of dispatch, everything done through it is recorded
+    pub fn record(&self, dispatch: &Dispatch) -> Dispatch {
+        return RecordingObject::wrap(&self.state, dispatch);
+    }
+
+    // flushes the log, failing if any of it couldn't be written
+    pub fn finish(&self) -> Result<(),Error> {
+        let mut state = self.state.borrow_mut();
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+        return state.writer.flush()
+            .map_err(|e| Error::com(hresult::E_FAIL, format!("failed writing recording: {}", e)));
+    }
+}
+
+struct RecordingObject {
+    inner: Dispatch,
+    id: u64,
+    state: Rc<RefCell<RecordState>>,
+}
+
+impl RecordingObject {
+    fn wrap(state: &Rc<RefCell<RecordState>>, dispatch: &Dispatch) -> Dispatch {
+        let id = state.borrow_mut().add_wrapper(dispatch.identity());
+        return Dispatch::new(RecordingObject { inner: dispatch.clone(), id, state: state.clone() });
+    }
+
+    // every object in a result is swapped for a recording one, so whatever's done with it next is
+    // recorded too
+    fn wrap_value(&self, value: Variant) -> Result<Variant,Error> {
+        return match value {
+            Variant::Dispatch(dispatch) => Ok(Variant::Dispatch(RecordingObject::wrap(&self.state, &dispatch))),
+            Variant::Array(array) if array.vt() == VarType::Dispatch || array.vt() == VarType::Variant => {
+                let vt = array.vt();
+                let bounds = array.bounds().to_vec();
+                let elements = array.into_elements().into_iter().map(|e| self.wrap_value(e)).collect::<Result<Vec<Variant>,Error>>()?;
+                let array = SafeArray::new(vt, bounds, elements)
+                    .map_err(|e| Error::result(format!("failed wrapping the objects in an array for recording: {}", e)))?;
+                Ok(Variant::Array(array))
+            },
+            value => Ok(value),
+        };
+    }
+
+    // objects the callee wrote back to by-ref arguments are recorded from then on too.  ones it
+    // left alone get back what was passed, which may already be a recording object
+    fn wrap_written_back(&self, arguments: &mut [Argument], before: Vec<Option<Variant>>) -> Result<(),Error> {
+        for (argument, before) in arguments.iter_mut().zip(before) {
+            let Some(before) = before else {
+                continue;
+            };
+            let value = if *argument.value() == before { before } else { self.wrap_value(argument.value().clone())? };
+            argument.set_value(value);
+        }
+        return Ok(());
+    }
+
+    // the real dispid behind one we handed out for the member (or one of its parameters), a
+    // failed lookup being logged just like one from get_ids_of_names
+    fn real_dispid(&self, member: &str, dispid: i32, param_name: Option<&str>) -> Result<i32,Error> {
+        if dispid < NAME_DISPID_BASE {
+            return Ok(dispid);
+        }
+        let (lookup, params) = match param_name {
+            Some(param_name) => (self.inner.get_ids_of_names(member, &[param_name]).map(|ids| ids[1]), vec![param_name.to_string()]),
+            None => (self.inner.get_id_of_name(member), Vec::new()),
+        };
+        if let Err(error) = &lookup {
+            self.state.borrow_mut().write(&Event::Lookup { object: self.id, name: member.to_string(), params, error: error.clone() });
+        }
+        return lookup;
+    }
+}
+
+impl Clone for RecordingObject {
+    fn clone(&self) -> RecordingObject {
+        self.state.borrow_mut().add_wrapper(self.inner.identity());
+        return RecordingObject { inner: self.inner.clone(), id: self.id, state: self.state.clone() };
+    }
+}
+
+impl Drop for RecordingObject {
+    fn drop(&mut self) {
+        self.state.borrow_mut().release_wrapper(self.inner.identity());
+    }
+}
+
+impl Automation for RecordingObject {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        // looked up for real (and cached by the inner Dispatch) so a bad name fails here just the
+        // same as it would without recording
+        if let Err(error) = self.inner.get_ids_of_names(name, param_names) {
+            self.state.borrow_mut().write(&Event::Lookup {
+                object: self.id,
+                name: name.to_string(),
+                params: param_names.iter().map(|p| p.to_string()).collect(),
+                error: error.clone(),
+            });
+            return Err(error);
+        }
+        return Ok(self.state.borrow_mut().names.ids(name, param_names));
+    }
+
+    // looked up in bulk like any other object, but when that fails it's looked up again name by
+    // name so the failure's logged against the name it belongs to, which is how a replay (looking
+    // them up one at a time) finds it
+    fn get_ids_of_members(&self, names: &[&str]) -> Result<Vec<i32>,Error> {
+        if self.inner.get_ids_of_members(names).is_err() {
+            return names.iter().map(|name| self.get_id_of_name(name)).collect();
+        }
+        let mut state = self.state.borrow_mut();
+        return Ok(names.iter().map(|name| state.names.id(name, name)).collect());
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        let name = self.state.borrow().names.name(dispid);
+        let real_dispid = self.real_dispid(&name, dispid, None)?;
+
+        let mut real_named_dispids = Vec::with_capacity(named_dispids.len());
+        let mut named_args = Vec::new();
+        let mut value = None;
+        for (named_dispid, argument) in named_dispids.iter().zip(named_arguments.iter()) {
+            if *named_dispid == DISPID_PROPERTYPUT && kind.is_put() {
+                real_named_dispids.push(DISPID_PROPERTYPUT);
+                value = Some(self.state.borrow_mut().encode(argument.value()));
+                continue;
+            }
+            let param_name = self.state.borrow().names.name(*named_dispid);
+            real_named_dispids.push(self.real_dispid(&name, *named_dispid, Some(&param_name))?);
+            named_args.push((param_name, self.state.borrow_mut().encode(argument.value())));
+        }
+        let args = arguments.iter().map(|a| self.state.borrow_mut().encode(a.value())).collect();
+
+        let before = (by_ref_values(arguments), by_ref_values(named_arguments));
+        let result = self.inner.invoke(real_dispid, kind, arguments, &real_named_dispids, named_arguments)
+            .and_then(|v| self.wrap_value(v))
+            .and_then(|v| {
+                self.wrap_written_back(arguments, before.0)?;
+                self.wrap_written_back(named_arguments, before.1)?;
+                return Ok(v);
+            });
+
+        let mut state = self.state.borrow_mut();
+        let written_back = written_back(arguments, named_arguments, &mut |v| state.object_id(v));
+        let recorded_result = match &result {
+            Ok(v) => Ok(state.encode(v)),
+            Err(e) => Err(e.clone()),
+        };
+        state.write(&Event::Invoke { object: self.id, name, kind, args, named_args, value, written_back, result: recorded_result });
+        return result;
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return self.inner.identity();
+    }
+
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        let inner = self.inner.new_enum();
+        let enumerator = {
+            let mut state = self.state.borrow_mut();
+            let enumerator = matches!(inner, Ok(Some(_))).then(|| {
+                state.next_enumerator += 1;
+                state.next_enumerator - 1
+            });
+            state.write(&Event::NewEnum { object: self.id, enumerator, error: inner.as_ref().err().cloned() });
+            enumerator
+        };
+        let (Some(inner), Some(id)) = (inner?, enumerator) else { return Ok(None) };
+        return Ok(Some(Box::new(RecordingEnumerator { inner, id, object: self.clone() })));
+    }
+
+    fn as_raw(&self) -> Option<*mut std::ffi::c_void> {
+        return self.inner.as_raw();
+    }
+
+    fn type_info(&self) -> Result<TypeInfo,Error> {
+        return self.inner.type_info();
+    }
+}
+
+struct RecordingEnumerator {
+    inner: Box<dyn Enumerator>,
+    id: u64,
+    // the collection, to wrap the items with
+    object: RecordingObject,
+}
+
+impl Enumerator for RecordingEnumerator {
+    fn next_batch(&mut self, count: usize) -> Result<Vec<Variant>,Error> {
+        let result = self.inner.next_batch(count)
+            .and_then(|items| items.into_iter().map(|item| self.object.wrap_value(item)).collect::<Result<Vec<Variant>,Error>>());
+        let mut state = self.object.state.borrow_mut();
+        let recorded_result = match &result {
+            Ok(items) => Ok(items.iter().map(|item| state.encode(item)).collect()),
+            Err(e) => Err(e.clone()),
+        };
+        state.write(&Event::Next { enumerator: self.id, count, result: recorded_result });
+        return result;
+    }
+}
+
+//
+// replay
+//
+
+struct ReplayState {
+    events: Vec<Event>,
+    next: usize,
+    names: Names,
+    // one shared handle per object number, its address is the object's identity
+    objects: HashMap<u64, Rc<u64>>,
+}
+
+impl ReplayState {
+    fn handle(&mut self, id: u64) -> Rc<u64> {
+        return self.objects.entry(id).or_insert_with(|| Rc::new(id)).clone();
+    }
+
+    fn object_id(&self, value: &Variant) -> u64 {
+        let identity = match value {
+            Variant::Dispatch(dispatch) => dispatch.identity(),
+            Variant::Unknown(unknown) => unknown.identity(),
+            _ => 0,
+        };
+        // anything that didn't come from this replay can't match a recorded object
+        return self.objects.values()
+            .find(|handle| Rc::as_ptr(handle) as usize == identity)
+            .map(|handle| **handle)
+            .unwrap_or(u64::MAX);
+    }
+
+    fn peek(&self) -> Option<&Event> {
+        return self.events.get(self.next);
+    }
+
+    // the recorded failure of this lookup if it's the next event, which is then replayed
+    fn lookup_error(&mut self, object: u64, name: &str, param_names: &[&str]) -> Option<Error> {
+        let Some(Event::Lookup { object: recorded_object, name: recorded, params, error }) = self.peek() else {
+            return None;
+        };
+        if *recorded_object != object || !recorded.eq_ignore_ascii_case(name) || params.len() != param_names.len()
+            || !params.iter().zip(param_names.iter()).all(|(a, b)| a.eq_ignore_ascii_case(b)) {
+            return None;
+        }
+        let error = error.clone();
+        self.next += 1;
+        return Some(error);
+    }
+
+    fn mismatch(&self, actual: &str) -> Error {
+        let expected = self.peek().map(Event::describe).unwrap_or_else(|| "the end of the recording".to_string());
+        return Error::com(hresult::E_FAIL, format!("replay expected {} but got {}", expected, actual));
+    }
+}
+
+// plays a recording back, handing out objects that answer exactly as the recorded ones did
+#[derive(Clone)]
+pub struct Replay {
+    state: Rc<RefCell<ReplayState>>
+}
+
+impl Replay {
+    pub fn new<R: BufRead>(reader: R) -> Result<Replay,Error> {
+        let mut events = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| Error::com(hresult::E_FAIL, format!("failed reading recording: {}", e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line)
+                .map_err(|e| Error::com(hresult::E_FAIL, format!("invalid recording at line {}: {}", i + 1, e)))?;
+            events.push(event);
+        }
+        return Ok(Replay {
+            state: Rc::new(RefCell::new(ReplayState { events, next: 0, names: Names::default(), objects: HashMap::new() }))
+        });
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Replay,Error> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| Error::com(hresult::E_FAIL, format!("failed opening recording {}: {}", path.display(), e)))?;
+        return Replay::new(BufReader::new(file));
+    }
+
+    // the first object that was recorded
+    pub fn root(&self) -> Dispatch {
+        return self.object(0);
+    }
+
+    pub fn object(&self, id: u64) -> Dispatch {
+        return ReplayObject::dispatch(&self.state, id);
+    }
+
+    // fails if anything in the recording was never replayed
+    pub fn finish(&self) -> Result<(),Error> {
+        let state = self.state.borrow();
+        return match state.peek() {
+            Some(event) => Err(Error::com(hresult::E_FAIL, format!("{} recorded events were never replayed, starting with {}", state.events.len() - state.next, event.describe()))),
+            None => Ok(()),
+        };
+    }
+}
+
+#[derive(Clone)]
+struct ReplayObject {
+    id: Rc<u64>,
+    state: Rc<RefCell<ReplayState>>,
+}
+
+impl ReplayObject {
+    fn dispatch(state: &Rc<RefCell<ReplayState>>, id: u64) -> Dispatch {
+        let handle = state.borrow_mut().handle(id);
+        return Dispatch::new(ReplayObject { id: handle, state: state.clone() });
+    }
+
+    fn decode(&self, value: &Value) -> Result<Variant,Error> {
+        return decode(value, &mut |vt, id| {
+            let handle = self.state.borrow_mut().handle(id);
+            match vt {
+                VarType::Unknown => Variant::Unknown(Unknown::new(ReplayUnknown { id: handle })),
+                _ => Variant::Dispatch(Dispatch::new(ReplayObject { id: handle, state: self.state.clone() })),
+            }
+        });
+    }
+}
+
+impl Automation for ReplayObject {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        let mut state = self.state.borrow_mut();
+        if let Some(error) = state.lookup_error(*self.id, name, param_names) {
+            return Err(error);
+        }
+        return Ok(state.names.ids(name, param_names));
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        let (written_back, result) = {
+            let mut state = self.state.borrow_mut();
+
+            // the lookups made when the call was recorded, of the member and then each named
+            // parameter, may have failed
+            let member = state.names.name(dispid);
+            let param_names: Vec<String> = named_dispids.iter()
+                .filter(|named_dispid| !(**named_dispid == DISPID_PROPERTYPUT && kind.is_put()))
+                .map(|named_dispid| state.names.name(*named_dispid))
+                .collect();
+            if let Some(error) = state.lookup_error(*self.id, &member, &[]) {
+                return Err(error);
+            }
+            for param_name in &param_names {
+                if let Some(error) = state.lookup_error(*self.id, &member, &[param_name]) {
+                    return Err(error);
+                }
+            }
+
+            let object_id = &mut |v: &Variant| state.object_id(v);
+            let mut value = None;
+            let mut named_args = Vec::new();
+            for (named_dispid, argument) in named_dispids.iter().zip(named_arguments.iter()) {
+                if *named_dispid == DISPID_PROPERTYPUT && kind.is_put() {
+                    value = Some(encode(argument.value(), object_id));
+                } else {
+                    named_args.push((*named_dispid, encode(argument.value(), object_id)));
+                }
+            }
+            let args: Vec<Value> = arguments.iter().map(|a| encode(a.value(), object_id)).collect();
+            let named_args: Vec<(String, Value)> = named_args.into_iter().map(|(d, v)| (state.names.name(d), v)).collect();
+            let actual = Event::Invoke {
+                object: *self.id,
+                name: state.names.name(dispid),
+                kind,
+                args,
+                named_args,
+                value,
+                written_back: None,
+                result: Ok(Value::Null),
+            };
+
+            let recorded = match (state.peek(), &actual) {
+                (Some(Event::Invoke { object, name, kind, args, named_args, value, written_back, result }),
+                 Event::Invoke { object: a_object, name: a_name, kind: a_kind, args: a_args, named_args: a_named_args, value: a_value, .. })
+                    if object == a_object && name.eq_ignore_ascii_case(a_name) && kind == a_kind && args == a_args && value == a_value
+                        && named_args.len() == a_named_args.len()
+                        && named_args.iter().zip(a_named_args.iter()).all(|((n, v), (a_n, a_v))| n.eq_ignore_ascii_case(a_n) && v == a_v) => {
+                    (written_back.clone(), result.clone())
+                },
+                _ => return Err(state.mismatch(&actual.describe())),
+            };
+            state.next += 1;
+            recorded
+        };
+
+        if let Some(written_back) = written_back {
+            let (positional, named) = written_back.split_at(arguments.len().min(written_back.len()));
+            for (argument, value) in arguments.iter_mut().zip(positional.iter()) {
+                if argument.is_by_ref() {
+                    argument.set_value(self.decode(value)?);
+                }
+            }
+            for (argument, value) in named_arguments.iter_mut().zip(named.iter()) {
+                if argument.is_by_ref() {
+                    argument.set_value(self.decode(value)?);
+                }
+            }
+        }
+        return match result {
+            Ok(value) => self.decode(&value),
+            Err(error) => Err(error),
+        };
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return Rc::as_ptr(&self.id) as usize;
+    }
+
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        let mut state = self.state.borrow_mut();
+        return match state.peek() {
+            Some(Event::NewEnum { object, enumerator, error }) if *object == *self.id => {
+                let (enumerator, error) = (*enumerator, error.clone());
+                state.next += 1;
+                if let Some(error) = error {
+                    return Err(error);
+                }
+                Ok(enumerator.map(|id| Box::new(ReplayEnumerator { id, object: self.clone() }) as Box<dyn Enumerator>))
+            },
+            _ => Ok(None),
+        };
+    }
+}
+
+struct ReplayEnumerator {
+    id: u64,
+    object: ReplayObject,
+}
+
+impl Enumerator for ReplayEnumerator {
+    fn next_batch(&mut self, count: usize) -> Result<Vec<Variant>,Error> {
+        let result = {
+            let mut state = self.object.state.borrow_mut();
+            let result = match state.peek() {
+                Some(Event::Next { enumerator, count: recorded, result }) if *enumerator == self.id && *recorded == count => result.clone(),
+                _ => return Err(state.mismatch(&Event::Next { enumerator: self.id, count, result: Ok(Vec::new()) }.describe())),
+            };
+            state.next += 1;
+            result
+        };
+        return result?.iter().map(|item| self.object.decode(item)).collect();
+    }
+}
+
+// a recorded VT_UNKNOWN, there's nothing that can be done with one except pass it back
+#[derive(Clone)]
+struct ReplayUnknown {
+    id: Rc<u64>,
+}
+
+impl UnknownObject for ReplayUnknown {
+    fn query_dispatch(&self) -> Result<Dispatch,Error> {
+        return Err(Error::com(hresult::E_NOINTERFACE, "a replayed unknown can't be used as a dispatch"));
+    }
+
+    fn clone_object(&self) -> Box<dyn UnknownObject> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return Rc::as_ptr(&self.id) as usize;
+    }
+}
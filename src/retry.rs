@@ -0,0 +1,231 @@
+use std::ffi::c_void;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use crate::{Argument, Automation, Enumerator, Error, hresult, InvokeKind, SafeArray, Variant, VarType};
+use crate::typelib::TypeInfo;
+
+//
+// a busy server turns calls away rather than queueing them: Excel rejects every automation call
+// while a dialog's open or it's in the middle of a recalculation, failing them with
+// RPC_E_CALL_REJECTED ("call was rejected by callee") or RPC_E_SERVERCALL_RETRYLATER.  the call
+// never reached the server, so it's always safe to make it again a little later:
+//
+//   let excel = co_create_dispatch(&clsid)?.with_retry(RetryPolicy::new().with_deadline(Duration::from_secs(60)));
+//
+// every lookup, invoke and enumerator fetch on the dispatch (and on the objects it hands out) is
+// then retried with a growing delay between attempts, until it gets through or the policy gives
+// up, when the last rejection is returned.  anything else fails straight away.  on windows the
+// thread keeps dispatching its messages while it waits between attempts, as an STA has to.
+//
+// on windows a MessageFilter with the same policy can be registered for the thread instead, which
+// has COM itself retry the calls before they ever fail
+//
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    deadline: Option<Duration>,
+    // where the time comes from and how the delays are waited out, see with_clock
+    now: fn() -> Instant,
+    sleep: fn(Duration),
+}
+
+// the delays between attempts.  an STA can't just block, the servers it's waiting on (and
+// anything else calling into it) need its messages dispatched
+#[cfg(windows)]
+fn sleep(delay: Duration) {
+    crate::thread::pump::sleep_pumping_messages(delay);
+}
+
+#[cfg(not(windows))]
+fn sleep(delay: Duration) {
+    std::thread::sleep(delay);
+}
+
+// the same attempts and delays, whatever clock they're timed with
+impl PartialEq for RetryPolicy {
+    fn eq(&self, other: &RetryPolicy) -> bool {
+        return (self.max_attempts, self.initial_delay, self.max_delay, self.deadline) == (other.max_attempts, other.initial_delay, other.max_delay, other.deadline);
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        return RetryPolicy::new();
+    }
+}
+
+impl RetryPolicy {
+    // 10 attempts, 100ms apart at first and doubling up to 2s, with no deadline
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 10,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+            deadline: None,
+            now: Instant::now,
+            sleep,
+        }
+    }
+
+    // how many times a call is made in all, including the first (so 1 never retries)
+    pub fn with_max_attempts(self, max_attempts: u32) -> RetryPolicy {
+        return RetryPolicy { max_attempts: max_attempts.max(1), ..self };
+    }
+
+    // the delay before the first retry, which doubles for each one after up to max_delay
+    pub fn with_backoff(self, initial_delay: Duration, max_delay: Duration) -> RetryPolicy {
+        return RetryPolicy { initial_delay, max_delay: max_delay.max(initial_delay), ..self };
+    }
+
+    // gives up once the next attempt would start more than this long after the first
+    pub fn with_deadline(self, deadline: Duration) -> RetryPolicy {
+        return RetryPolicy { deadline: Some(deadline), ..self };
+    }
+
+    // swaps the clock the deadline's measured by and the wait between attempts, e.g. for tests that
+    // don't want to wait for real
+    pub fn with_clock(self, now: fn() -> Instant, sleep: fn(Duration)) -> RetryPolicy {
+        return RetryPolicy { now, sleep, ..self };
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        return self.max_attempts;
+    }
+
+    pub fn deadline(&self) -> Option<Duration> {
+        return self.deadline;
+    }
+
+    // whether the error is a rejection by a busy server, and so worth retrying
+    pub fn is_retryable(error: &Error) -> bool {
+        return matches!(error.hresult(), hresult::RPC_E_CALL_REJECTED | hresult::RPC_E_SERVERCALL_RETRYLATER);
+    }
+
+    // how long to wait after the given attempt (counting from 1) was rejected, elapsed after the
+    // first began, or None to give up
+    pub fn retry_delay(&self, attempt: u32, elapsed: Duration) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let delay = self.initial_delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))).min(self.max_delay);
+        if self.deadline.is_some_and(|deadline| elapsed + delay > deadline) {
+            return None;
+        }
+        return Some(delay);
+    }
+
+    // makes the call, retrying it for as long as the policy allows while it's rejected
+    pub fn run<T, F: FnMut() -> Result<T,Error>>(&self, mut call: F) -> Result<T,Error> {
+        let started = (self.now)();
+        let mut attempt = 1;
+        loop {
+            match call() {
+                Err(e) if RetryPolicy::is_retryable(&e) => match self.retry_delay(attempt, (self.now)() - started) {
+                    Some(delay) => (self.sleep)(delay),
+                    None => return Err(e),
+                },
+                result => return result,
+            }
+            attempt += 1;
+        }
+    }
+}
+
+// what Dispatch::with_retry wraps the object in
+pub(crate) struct Retrying {
+    object: Box<dyn Automation>,
+    policy: Rc<RetryPolicy>,
+}
+
+impl Retrying {
+    pub(crate) fn new(object: Box<dyn Automation>, policy: Rc<RetryPolicy>) -> Retrying {
+        return Retrying { object, policy };
+    }
+}
+
+// objects that come out of the calls are retried the same way, including the ones in arrays
+fn wrap(policy: &Rc<RetryPolicy>, value: Variant) -> Result<Variant,Error> {
+    return match value {
+        Variant::Dispatch(dispatch) => Ok(Variant::Dispatch(dispatch.retrying(policy.clone()))),
+        Variant::Array(array) if matches!(array.vt(), VarType::Dispatch | VarType::Variant) => {
+            let (vt, bounds) = (array.vt(), array.bounds().to_vec());
+            let elements = array.into_elements().into_iter()
+                .map(|element| wrap(policy, element))
+                .collect::<Result<Vec<Variant>,Error>>()?;
+            Ok(Variant::Array(SafeArray::new(vt, bounds, elements)?))
+        },
+        value => Ok(value),
+    };
+}
+
+// and the ones written back to by-ref arguments, leaving alone what the call didn't change so an
+// object that was passed in isn't wrapped twice
+fn wrap_written_back(policy: &Rc<RetryPolicy>, arguments: &mut [Argument], before: &[Option<Variant>]) -> Result<(),Error> {
+    for (argument, before) in arguments.iter_mut().zip(before) {
+        if before.as_ref().is_some_and(|before| before != argument.value()) {
+            let value = wrap(policy, argument.value().clone())?;
+            argument.set_value(value);
+        }
+    }
+    return Ok(());
+}
+
+fn by_ref_values(arguments: &[Argument]) -> Vec<Option<Variant>> {
+    return arguments.iter().map(|argument| argument.is_by_ref().then(|| argument.value().clone())).collect();
+}
+
+impl Automation for Retrying {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        return self.policy.run(|| self.object.get_ids_of_names(name, param_names));
+    }
+
+    fn get_ids_of_members(&self, names: &[&str]) -> Result<Vec<i32>,Error> {
+        return self.policy.run(|| self.object.get_ids_of_members(names));
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        // a rejected call never ran, so the by-ref arguments are untouched for the next attempt
+        let (positional_before, named_before) = (by_ref_values(arguments), by_ref_values(named_arguments));
+        let result = self.policy.run(|| self.object.invoke(dispid, kind, arguments, named_dispids, named_arguments))?;
+        wrap_written_back(&self.policy, arguments, &positional_before)?;
+        wrap_written_back(&self.policy, named_arguments, &named_before)?;
+        return wrap(&self.policy, result);
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(Retrying { object: self.object.clone_object(), policy: self.policy.clone() });
+    }
+
+    fn identity(&self) -> usize {
+        return self.object.identity();
+    }
+
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        let Some(enumerator) = self.policy.run(|| self.object.new_enum())? else { return Ok(None) };
+        return Ok(Some(Box::new(RetryingEnumerator { enumerator, policy: self.policy.clone() })));
+    }
+
+    fn as_raw(&self) -> Option<*mut c_void> {
+        return self.object.as_raw();
+    }
+
+    fn type_info(&self) -> Result<TypeInfo,Error> {
+        return self.policy.run(|| self.object.type_info());
+    }
+}
+
+struct RetryingEnumerator {
+    enumerator: Box<dyn Enumerator>,
+    policy: Rc<RetryPolicy>,
+}
+
+impl Enumerator for RetryingEnumerator {
+    fn next_batch(&mut self, count: usize) -> Result<Vec<Variant>,Error> {
+        let enumerator = &mut self.enumerator;
+        let items = self.policy.run(|| enumerator.next_batch(count))?;
+        return items.into_iter().map(|item| wrap(&self.policy, item)).collect();
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, SerializeMap, SerializeSeq};
+use serde_json::{json, Map, Value};
+use crate::{ArrayBound, Currency, Decimal, Error, OleDate, SafeArray, Variant, VarType};
+
+//
+// serde for variants, in two encodings.  Variant itself serializes to the plain values a web
+// service expects:
+//  - Empty and Null are null, and null deserializes as Null
+//  - integers, floats and booleans are numbers and booleans, and deserialize as I4 (or I8 or UI8
+//    when they don't fit), R8 and Bool
+//  - strings are strings
+//  - dates are ISO 8601 strings, e.g. "2024-02-29T12:00:00", with milliseconds when there are any
+//  - currencies and decimals are strings, e.g. "1.25", so they stay exact
+//  - error codes are {"error": scode}, e.g. {"error": -2146826246} for excel's #N/A
+//  - arrays are nested by their first dimension like Display, so a 2-D array is a list of rows.
+//    a list deserializes as a 1-D array of variants, or a 2-D one when it's a list of equal length
+//    lists
+//  - objects can't be serialized
+// this loses the exact types, e.g. a VT_UI1 comes back a VT_I4, and dates and currencies come back
+// as strings (which TryFrom<&Variant> will still convert).  Tagged is the lossless encoding, the
+// one recordings use, where every value says what it is:
+//
+//   {"type": "vt_ui1", "value": 7}
+//   {"type": "vt_cy", "value": "1.25"}
+//   {"type": "array", "vt": "vt_i4", "bounds": [[1, 2]], "elements": [{"type": "vt_i4", ...}, ...]}
+//
+// dates are their raw f64, non-finite floats are strings, and empty and null have no value
+//
+
+// a variant that serializes with its exact VARENUM type, see above
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tagged(pub Variant);
+
+impl From<Variant> for Tagged {
+    fn from(value: Variant) -> Tagged {
+        Tagged(value)
+    }
+}
+
+impl From<Tagged> for Variant {
+    fn from(value: Tagged) -> Variant {
+        value.0
+    }
+}
+
+impl Serialize for Tagged {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok,S::Error> {
+        let mut object = false;
+        let encoded = encode(&self.0, &mut |_| {
+            object = true;
+            return 0;
+        });
+        if object {
+            return Err(ser::Error::custom("objects can't be serialized"));
+        }
+        return encoded.serialize(serializer);
+    }
+}
+
+impl<'de> Deserialize<'de> for Tagged {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Tagged,D::Error> {
+        let encoded = Value::deserialize(deserializer)?;
+        let mut object = false;
+        let decoded = decode(&encoded, &mut |_, _| {
+            object = true;
+            return Variant::Null;
+        }).map_err(de::Error::custom)?;
+        if object {
+            return Err(de::Error::custom("objects can't be deserialized"));
+        }
+        return Ok(Tagged(decoded));
+    }
+}
+
+impl Serialize for Variant {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok,S::Error> {
+        return match self {
+            Variant::Empty | Variant::Null | Variant::Nothing => serializer.serialize_unit(),
+            Variant::I1(v) => serializer.serialize_i8(*v),
+            Variant::I2(v) => serializer.serialize_i16(*v),
+            Variant::I4(v) | Variant::Int(v) => serializer.serialize_i32(*v),
+            Variant::I8(v) => serializer.serialize_i64(*v),
+            Variant::UI1(v) => serializer.serialize_u8(*v),
+            Variant::UI2(v) => serializer.serialize_u16(*v),
+            Variant::UI4(v) | Variant::UInt(v) => serializer.serialize_u32(*v),
+            Variant::UI8(v) => serializer.serialize_u64(*v),
+            Variant::R4(v) => serializer.serialize_f32(*v),
+            Variant::R8(v) => serializer.serialize_f64(*v),
+            Variant::Bool(v) => serializer.serialize_bool(*v),
+            Variant::Bstr(v) => serializer.serialize_str(v),
+            Variant::Date(v) => {
+                let date_time = v.to_date_time().map_err(ser::Error::custom)?;
+                serializer.collect_str(&date_time.format("%Y-%m-%dT%H:%M:%S%.f"))
+            },
+            Variant::Cy(v) => serializer.collect_str(v),
+            Variant::Decimal(v) => serializer.collect_str(v),
+            Variant::Error(scode) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("error", scode)?;
+                map.end()
+            },
+            Variant::Dispatch(_) | Variant::Unknown(_) => Err(ser::Error::custom("objects can't be serialized")),
+            Variant::Array(array) => Dimension { array, dim: 0, offset: 0, stride: 1 }.serialize(serializer),
+        };
+    }
+}
+
+// one dimension of an array, nested the same way SafeArray::fmt_dim writes it
+struct Dimension<'a> {
+    array: &'a SafeArray,
+    dim: usize,
+    offset: usize,
+    stride: usize,
+}
+
+impl Serialize for Dimension<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok,S::Error> {
+        let len = self.array.bounds()[self.dim].len as usize;
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        for i in 0..len {
+            let index = self.offset + i * self.stride;
+            if self.dim + 1 < self.array.dims() {
+                seq.serialize_element(&Dimension { array: self.array, dim: self.dim + 1, offset: index, stride: self.stride * len })?;
+            } else {
+                seq.serialize_element(&self.array.elements()[index])?;
+            }
+        }
+        return seq.end();
+    }
+}
+
+impl<'de> Deserialize<'de> for Variant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Variant,D::Error> {
+        return deserializer.deserialize_any(VariantVisitor);
+    }
+}
+
+struct VariantVisitor;
+
+impl<'de> Visitor<'de> for VariantVisitor {
+    type Value = Variant;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "null, a boolean, number, string, list or {{\"error\": scode}}")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Variant,E> {
+        return Ok(Variant::Null);
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Variant,E> {
+        return Ok(Variant::Null);
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Variant,D::Error> {
+        return Variant::deserialize(deserializer);
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Variant,E> {
+        return Ok(Variant::Bool(v));
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Variant,E> {
+        return Ok(i32::try_from(v).map(Variant::I4).unwrap_or(Variant::I8(v)));
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Variant,E> {
+        return match i64::try_from(v) {
+            Ok(v) => self.visit_i64(v),
+            Err(_) => Ok(Variant::UI8(v)),
+        };
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Variant,E> {
+        return Ok(Variant::R8(v));
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Variant,E> {
+        return Ok(Variant::Bstr(v.to_string()));
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Variant,E> {
+        return Ok(Variant::Bstr(v));
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Variant,A::Error> {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element::<Variant>()? {
+            elements.push(element);
+        }
+        // a list of equal length lists is the rows of a 2-D array
+        let columns = match elements.first() {
+            Some(Variant::Array(first)) if first.dims() == 1 && !first.is_empty() => first.len(),
+            _ => 0,
+        };
+        let rectangular = columns > 0 && elements.iter().all(|row| matches!(row, Variant::Array(row) if row.dims() == 1 && row.len() == columns));
+        let array = if rectangular {
+            let rows = elements.into_iter()
+                .map(|row| match row {
+                    Variant::Array(row) => row.into_elements(),
+                    _ => vec![],
+                })
+                .collect();
+            SafeArray::from_rows(VarType::Variant, rows)
+        } else {
+            SafeArray::from_vec(VarType::Variant, elements)
+        };
+        return array.map(Variant::Array).map_err(de::Error::custom);
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Variant,A::Error> {
+        let scode = match map.next_key::<String>()? {
+            Some(key) if key == "error" => map.next_value::<i32>()?,
+            _ => return Err(de::Error::custom("the only object a variant can be is {\"error\": scode}")),
+        };
+        if map.next_key::<String>()?.is_some() {
+            return Err(de::Error::custom("an error is only {\"error\": scode}"));
+        }
+        return Ok(Variant::Error(scode));
+    }
+}
+
+// the tagged encoding, which recordings write with objects encoded as their number (found with
+// the callback) so replay hands back exactly what was recorded
+pub(crate) fn encode(value: &Variant, object_id: &mut dyn FnMut(&Variant) -> u64) -> Value {
+    let encoded = match value {
+        // Nothing is a vt_dispatch with no value
+        Variant::Empty | Variant::Null | Variant::Nothing => Value::Null,
+        Variant::I1(v) => json!(v),
+        Variant::I2(v) => json!(v),
+        Variant::I4(v) | Variant::Int(v) | Variant::Error(v) => json!(v),
+        Variant::I8(v) => json!(v),
+        Variant::UI1(v) => json!(v),
+        Variant::UI2(v) => json!(v),
+        Variant::UI4(v) | Variant::UInt(v) => json!(v),
+        Variant::UI8(v) => json!(v),
+        Variant::R4(v) => encode_float(*v as f64),
+        Variant::R8(v) => encode_float(*v),
+        Variant::Date(v) => encode_float(v.to_raw()),
+        Variant::Bool(v) => json!(v),
+        Variant::Bstr(v) => json!(v),
+        Variant::Cy(v) => json!(v.to_string()),
+        Variant::Decimal(v) => json!(v.to_string()),
+        Variant::Dispatch(_) | Variant::Unknown(_) => json!(object_id(value)),
+        Variant::Array(array) => {
+            return json!({
+                "type": "array",
+                "vt": array.vt().name(),
+                "bounds": array.bounds().iter().map(|b| json!([b.lower, b.len])).collect::<Vec<Value>>(),
+                "elements": array.elements().iter().map(|e| encode(e, object_id)).collect::<Vec<Value>>(),
+            });
+        },
+    };
+    let mut tagged = Map::new();
+    tagged.insert("type".to_string(), json!(value.var_type().name()));
+    if !encoded.is_null() {
+        tagged.insert("value".to_string(), encoded);
+    }
+    return Value::Object(tagged);
+}
+
+// json has no infinities or nan, so they're written as strings
+fn encode_float(value: f64) -> Value {
+    return if value.is_finite() { json!(value) } else { json!(value.to_string()) };
+}
+
+// objects are made from their vt and number by the callback
+pub(crate) fn decode(value: &Value, object: &mut dyn FnMut(VarType, u64) -> Variant) -> Result<Variant,Error> {
+    let invalid = || Error::result(format!("invalid recorded value {}", value));
+    let type_name = value.get("type").and_then(Value::as_str).ok_or_else(invalid)?;
+    if type_name == "array" {
+        let vt = value.get("vt").and_then(Value::as_str).and_then(VarType::from_name).ok_or_else(invalid)?;
+        let bounds = value.get("bounds").and_then(Value::as_array).ok_or_else(invalid)?.iter()
+            .map(|b| Some(ArrayBound::new(i32::try_from(b.get(0)?.as_i64()?).ok()?, u32::try_from(b.get(1)?.as_u64()?).ok()?)))
+            .collect::<Option<Vec<ArrayBound>>>()
+            .ok_or_else(invalid)?;
+        let elements = value.get("elements").and_then(Value::as_array).ok_or_else(invalid)?.iter()
+            .map(|e| decode(e, object))
+            .collect::<Result<Vec<Variant>,Error>>()?;
+        return Ok(Variant::Array(SafeArray::new(vt, bounds, elements)?));
+    }
+
+    let vt = VarType::from_name(type_name).ok_or_else(invalid)?;
+    let v = value.get("value").unwrap_or(&Value::Null);
+    let int = || v.as_i64().ok_or_else(invalid);
+    let uint = || v.as_u64().ok_or_else(invalid);
+    let float = || v.as_f64().or_else(|| v.as_str()?.parse().ok()).ok_or_else(invalid);
+    let string = || v.as_str().ok_or_else(invalid);
+    let decoded = match vt {
+        VarType::Empty => Variant::Empty,
+        VarType::Null => Variant::Null,
+        VarType::I1 => Variant::I1(i8::try_from(int()?).map_err(|_| invalid())?),
+        VarType::I2 => Variant::I2(i16::try_from(int()?).map_err(|_| invalid())?),
+        VarType::I4 => Variant::I4(i32::try_from(int()?).map_err(|_| invalid())?),
+        VarType::Int => Variant::Int(i32::try_from(int()?).map_err(|_| invalid())?),
+        VarType::Error => Variant::Error(i32::try_from(int()?).map_err(|_| invalid())?),
+        VarType::I8 => Variant::I8(int()?),
+        VarType::UI1 => Variant::UI1(u8::try_from(uint()?).map_err(|_| invalid())?),
+        VarType::UI2 => Variant::UI2(u16::try_from(uint()?).map_err(|_| invalid())?),
+        VarType::UI4 => Variant::UI4(u32::try_from(uint()?).map_err(|_| invalid())?),
+        VarType::UInt => Variant::UInt(u32::try_from(uint()?).map_err(|_| invalid())?),
+        VarType::UI8 => Variant::UI8(uint()?),
+        VarType::R4 => Variant::R4(float()? as f32),
+        VarType::R8 => Variant::R8(float()?),
+        VarType::Date => Variant::Date(OleDate::from_raw(float()?)),
+        VarType::Bool => Variant::Bool(v.as_bool().ok_or_else(invalid)?),
+        VarType::Bstr => Variant::Bstr(string()?.to_string()),
+        VarType::Cy => Variant::Cy(string()?.parse::<Currency>()?),
+        VarType::Decimal => Variant::Decimal(string()?.parse::<Decimal>().map_err(|_| invalid())?),
+        VarType::Dispatch if v.is_null() => Variant::Nothing,
+        VarType::Dispatch | VarType::Unknown => object(vt, uint()?),
+        VarType::Variant => return Err(invalid()),
+    };
+    return Ok(decoded);
+}
+
@@ -0,0 +1,651 @@
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle, ThreadId};
+use crate::typelib::TypeInfo;
+use crate::{Argument, Automation, Dispatch, Enumerator, Error, hresult, InvokeKind, Unknown, UnknownObject, Variant, VarType};
+
+mod future;
+#[cfg(windows)]
+pub(crate) mod pump;
+mod value;
+
+pub use self::future::ComFuture;
+use self::value::RemoteVariant;
+
+//
+// a thread that owns a single threaded apartment, for programs with many threads that all need the
+// same COM objects.  objects in an STA can only be used on the thread that created them, so they
+// all live on the ComThread and every other thread works through proxies to them:
+//
+//   let com = ComThread::spawn()?;
+//   let excel = com.handle().create(|| co_create_dispatch(&clsid_from_prog_id("Excel.Application")?))?;
+//   // excel is a RemoteDispatch, which can be cloned and sent to any thread
+//   thread::spawn(move || {
+//       let name = excel.eval("ActiveSheet.Name")?;
+//   });
+//
+// a RemoteDispatch implements Automation by sending each get_ids_of_names and invoke to the thread
+// and waiting for the answer, so everything built on Automation (paths, derives, the generated
+// wrappers, iterating collections) works through it unchanged.  values go back and forth as
+// RemoteVariants, and objects in them stay on the thread, arriving as more proxies.  anything
+// else can be run on the thread with ComHandle::run.
+//
+// on windows the thread pumps messages while it waits, as an STA has to.  elsewhere it's a plain
+// thread with no COM at all, which is enough to test code using it against mock objects
+//
+
+type Job = Box<dyn FnOnce(&mut Worker) + Send>;
+
+// the objects handed out from the thread, which live there until every proxy to them is dropped
+enum Object {
+    Dispatch(Dispatch),
+    Unknown(Unknown),
+}
+
+struct Registered {
+    object: Object,
+    // how many times it's been handed out, the proxies give them back as they're dropped
+    handed_out: u64,
+}
+
+// the thread's side, only ever touched by jobs running on it
+struct Worker {
+    objects: HashMap<u64, Registered>,
+    // ids by object identity, so the same object is always handed out with the same id
+    ids: HashMap<usize, u64>,
+    enumerators: HashMap<u64, Box<dyn Enumerator>>,
+    next_id: u64,
+    stopped: bool,
+}
+
+impl Worker {
+    fn new() -> Worker {
+        Worker {
+            objects: HashMap::new(),
+            ids: HashMap::new(),
+            enumerators: HashMap::new(),
+            next_id: 0,
+            stopped: false,
+        }
+    }
+
+    fn run(&mut self, job: Job) {
+        // a panicking job drops its reply, which the caller sees as an error, and the thread
+        // carries on with the next one
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| job(self)));
+    }
+
+    fn register(&mut self, value: &Variant) -> u64 {
+        let (identity, object) = match value {
+            Variant::Dispatch(dispatch) => (dispatch.identity(), Object::Dispatch(dispatch.clone())),
+            Variant::Unknown(unknown) => (unknown.identity(), Object::Unknown(unknown.clone())),
+            _ => return 0,
+        };
+        let id = match self.ids.get(&identity) {
+            Some(id) => *id,
+            None => {
+                self.next_id += 1;
+                self.ids.insert(identity, self.next_id);
+                self.objects.insert(self.next_id, Registered { object, handed_out: 0 });
+                self.next_id
+            },
+        };
+        if let Some(registered) = self.objects.get_mut(&id) {
+            registered.handed_out += 1;
+        }
+        return id;
+    }
+
+    fn release(&mut self, id: u64, count: u64) {
+        let Some(registered) = self.objects.get_mut(&id) else { return };
+        registered.handed_out = registered.handed_out.saturating_sub(count);
+        if registered.handed_out == 0 {
+            if let Some(registered) = self.objects.remove(&id) {
+                let identity = match &registered.object {
+                    Object::Dispatch(dispatch) => dispatch.identity(),
+                    Object::Unknown(unknown) => unknown.identity(),
+                };
+                self.ids.remove(&identity);
+            }
+        }
+    }
+
+    fn encode(&mut self, value: &Variant) -> Result<RemoteVariant,Error> {
+        return RemoteVariant::new(value, &mut |v| Ok(self.register(v)));
+    }
+
+    fn decode(&self, value: &RemoteVariant) -> Result<Variant,Error> {
+        return value.to_variant(&mut |_, id| match self.objects.get(&id) {
+            Some(Registered { object: Object::Dispatch(dispatch), .. }) => Ok(Variant::Dispatch(dispatch.clone())),
+            Some(Registered { object: Object::Unknown(unknown), .. }) => Ok(Variant::Unknown(unknown.clone())),
+            None => Err(released(id)),
+        });
+    }
+
+    fn dispatch(&self, id: u64) -> Result<&Dispatch,Error> {
+        return match self.objects.get(&id) {
+            Some(Registered { object: Object::Dispatch(dispatch), .. }) => Ok(dispatch),
+            _ => Err(released(id)),
+        };
+    }
+}
+
+fn released(id: u64) -> Error {
+    return Error::com(hresult::E_FAIL, format!("object {} on the COM thread has been released", id));
+}
+
+fn stopped() -> Error {
+    return Error::com(hresult::E_FAIL, "the COM thread has stopped");
+}
+
+// wakes the thread when there's a job for it
+#[cfg(not(windows))]
+struct Signal;
+
+#[cfg(not(windows))]
+impl Signal {
+    fn new() -> Result<Signal,Error> {
+        return Ok(Signal);
+    }
+
+    fn notify(&self) {}
+}
+
+#[cfg(windows)]
+use pump::Signal;
+
+// what every handle and proxy shares
+struct Shared {
+    jobs: Sender<Job>,
+    signal: Arc<Signal>,
+    thread: ThreadId,
+    proxies: Mutex<Proxies>,
+}
+
+// the live proxies, so each object has a single one however many times it's handed out.  they're
+// held here until the last Proxy to one is dropped
+#[derive(Default)]
+struct Proxies {
+    // by the proxy's identity, which is what objects passed back to the thread are found by
+    by_identity: HashMap<usize, Arc<RemoteRef>>,
+    // the identities by the object's id on the thread, for the objects coming back from it
+    identities: HashMap<u64, usize>,
+}
+
+// a cloneable, Send + Sync handle to a ComThread, for sending work to it
+#[derive(Clone)]
+pub struct ComHandle {
+    shared: Arc<Shared>,
+}
+
+impl ComHandle {
+    // runs the job on the thread and waits for what it returns.  the job can create and use COM
+    // objects, but they can't leave the thread (make them with create to use them elsewhere)
+    pub fn run<F, R>(&self, job: F) -> Result<R,Error>
+    where F: FnOnce() -> Result<R,Error> + Send + 'static, R: Send + 'static {
+        return self.send(move |_| job());
+    }
+
+    // runs the job on the thread and hands back a proxy to the object it made
+    pub fn create<F>(&self, job: F) -> Result<RemoteDispatch,Error>
+    where F: FnOnce() -> Result<Dispatch,Error> + Send + 'static {
+        let id = self.send(move |worker| {
+            let dispatch = job()?;
+            return Ok(worker.register(&Variant::Dispatch(dispatch)));
+        })?;
+        return Ok(RemoteDispatch { object: self.proxy(id) });
+    }
+
+    // a Send + Sync handle to an object that came from the thread, e.g. out of a property of one
+    // made with create, so it can be passed on to another thread
+    pub fn remote(&self, dispatch: &Dispatch) -> Result<RemoteDispatch,Error> {
+        let object = self.find(dispatch.identity())
+            .ok_or_else(|| Error::com(hresult::E_INVALIDARG, "the object isn't one from this COM thread"))?;
+        return Ok(RemoteDispatch { object });
+    }
+
+    fn send<F, R>(&self, job: F) -> Result<R,Error>
+    where F: FnOnce(&mut Worker) -> Result<R,Error> + Send + 'static, R: Send + 'static {
+        if thread::current().id() == self.shared.thread {
+            return Err(Error::com(hresult::E_FAIL, "the COM thread can't wait on itself, use the objects directly there"));
+        }
+        let (reply, result) = mpsc::channel();
+        self.shared.jobs.send(Box::new(move |worker: &mut Worker| {
+            let _ = reply.send(job(worker));
+        })).map_err(|_| stopped())?;
+        self.shared.signal.notify();
+        return result.recv().map_err(|_| stopped())?;
+    }
+
+    // a job that doesn't need an answer
+    fn post<F: FnOnce(&mut Worker) + Send + 'static>(&self, job: F) {
+        if self.shared.jobs.send(Box::new(job)).is_ok() {
+            self.shared.signal.notify();
+        }
+    }
+
+    // the registry of live proxies, locked
+    fn proxies(&self) -> MutexGuard<'_, Proxies> {
+        return self.shared.proxies.lock().unwrap_or_else(|e| e.into_inner());
+    }
+
+    // the proxy for an object handed out by the thread, which takes over that hand out
+    fn proxy(&self, id: u64) -> Proxy {
+        let mut proxies = self.proxies();
+        let object = match proxies.identities.get(&id).and_then(|identity| proxies.by_identity.get(identity)) {
+            Some(object) => object.clone(),
+            None => {
+                let object = Arc::new(RemoteRef { id, handed_out: AtomicU64::new(0), handle: self.clone() });
+                let identity = Arc::as_ptr(&object) as usize;
+                proxies.by_identity.insert(identity, object.clone());
+                proxies.identities.insert(id, identity);
+                object
+            },
+        };
+        object.handed_out.fetch_add(1, Ordering::Relaxed);
+        return Proxy { object: ManuallyDrop::new(object) };
+    }
+
+    fn find(&self, identity: usize) -> Option<Proxy> {
+        let object = self.proxies().by_identity.get(&identity)?.clone();
+        return Some(Proxy { object: ManuallyDrop::new(object) });
+    }
+
+    fn encode(&self, value: &Variant) -> Result<RemoteVariant,Error> {
+        return RemoteVariant::new(value, &mut |v| {
+            let identity = match v {
+                Variant::Dispatch(dispatch) => dispatch.identity(),
+                Variant::Unknown(unknown) => unknown.identity(),
+                _ => 0,
+            };
+            return match self.find(identity) {
+                Some(proxy) => Ok(proxy.id),
+                None => Err(Error::com(hresult::E_INVALIDARG, "only objects from the COM thread can be passed to it")),
+            };
+        });
+    }
+
+    fn decode(&self, value: &RemoteVariant) -> Result<Variant,Error> {
+        return value.to_variant(&mut |vt, id| {
+            let object = self.proxy(id);
+            return Ok(match vt {
+                VarType::Unknown => Variant::Unknown(Unknown::new(RemoteUnknown { object })),
+                _ => Variant::Dispatch(Dispatch::new(RemoteDispatch { object })),
+            });
+        });
+    }
+}
+
+// one object on the thread, released there when the registry's reference, the last one, goes
+struct RemoteRef {
+    id: u64,
+    handed_out: AtomicU64,
+    handle: ComHandle,
+}
+
+impl Drop for RemoteRef {
+    fn drop(&mut self) {
+        let (id, count) = (self.id, *self.handed_out.get_mut());
+        self.handle.post(move |worker| worker.release(id, count));
+    }
+}
+
+// a reference to a RemoteRef held outside the registry.  the last one to be dropped takes it out
+// of the registry as well, all under the registry's lock so the count it goes by can't change
+// underneath it
+struct Proxy {
+    object: ManuallyDrop<Arc<RemoteRef>>,
+}
+
+impl Proxy {
+    fn identity(&self) -> usize {
+        return Arc::as_ptr(&self.object) as usize;
+    }
+}
+
+impl Deref for Proxy {
+    type Target = RemoteRef;
+
+    fn deref(&self) -> &RemoteRef {
+        return &self.object;
+    }
+}
+
+impl Clone for Proxy {
+    fn clone(&self) -> Proxy {
+        return Proxy { object: ManuallyDrop::new(Arc::clone(&self.object)) };
+    }
+}
+
+impl PartialEq for Proxy {
+    fn eq(&self, other: &Proxy) -> bool {
+        return Arc::ptr_eq(&self.object, &other.object);
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        // nothing uses the object again once it's been taken
+        let object = unsafe { ManuallyDrop::take(&mut self.object) };
+        let handle = object.handle.clone();
+        let mut proxies = handle.proxies();
+        let mut removed = None;
+        // this and the registry's
+        if Arc::strong_count(&object) == 2 {
+            proxies.identities.remove(&object.id);
+            removed = proxies.by_identity.remove(&(Arc::as_ptr(&object) as usize));
+        }
+        drop((object, removed));
+    }
+}
+
+// an object on a ComThread, usable from any thread
+#[derive(Clone)]
+pub struct RemoteDispatch {
+    object: Proxy,
+}
+
+impl RemoteDispatch {
+    // a Dispatch on this thread that forwards to the object, with its own cache of dispids
+    pub fn to_dispatch(&self) -> Dispatch {
+        return Dispatch::new(self.clone());
+    }
+
+    pub fn handle(&self) -> &ComHandle {
+        return &self.object.handle;
+    }
+}
+
+impl PartialEq for RemoteDispatch {
+    fn eq(&self, other: &RemoteDispatch) -> bool {
+        return self.object == other.object;
+    }
+}
+
+// an argument on its way to the thread, and whether it's written back
+struct Encoded {
+    value: RemoteVariant,
+    kind: u8,
+}
+
+const BY_VAL: u8 = 0;
+const BY_REF: u8 = 1;
+const BY_REF_VARIANT: u8 = 2;
+
+impl Encoded {
+    fn new(handle: &ComHandle, argument: &Argument) -> Result<Encoded,Error> {
+        let kind = match argument {
+            Argument::ByVal(_) => BY_VAL,
+            Argument::ByRef(_) => BY_REF,
+            Argument::ByRefVariant(_) => BY_REF_VARIANT,
+        };
+        return Ok(Encoded { value: handle.encode(argument.value())?, kind });
+    }
+}
+
+// builds the arguments back up on the thread, where by-ref ones need somewhere to be written to
+fn rebuild<'a>(values: &'a mut [Variant], kinds: &[u8]) -> Vec<Argument<'a>> {
+    return values.iter_mut().zip(kinds.iter())
+        .map(|(value, kind)| match *kind {
+            BY_REF => Argument::ByRef(value),
+            BY_REF_VARIANT => Argument::ByRefVariant(value),
+            _ => Argument::ByVal(value),
+        })
+        .collect();
+}
+
+// sets the by-ref arguments to what was written to them on the thread, carrying on past any that
+// fail so the rest are still taken over, and giving the first failure
+fn write_back(handle: &ComHandle, arguments: &mut [Argument], written: &[Option<RemoteVariant>]) -> Option<Error> {
+    let mut failed = None;
+    for (argument, value) in arguments.iter_mut().zip(written) {
+        match value.as_ref().map(|value| handle.decode(value)) {
+            Some(Ok(value)) => argument.set_value(value),
+            Some(Err(e)) => failed = failed.or(Some(e)),
+            None => {},
+        }
+    }
+    return failed;
+}
+
+// type info on its way back from the thread, with the parameter defaults and constant values taken
+// out of it to go separately as RemoteVariants
+struct Detached(TypeInfo);
+
+// the Variants are the only part of a TypeInfo that can't be sent, and every one has been taken out
+unsafe impl Send for Detached {}
+
+fn type_info_values(info: &mut TypeInfo) -> impl Iterator<Item = &mut Option<Variant>> {
+    let defaults = info.functions.iter_mut().flat_map(|function| function.params.iter_mut()).map(|param| &mut param.default);
+    return defaults.chain(info.variables.iter_mut().map(|variable| &mut variable.value));
+}
+
+impl Automation for RemoteDispatch {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        let id = self.object.id;
+        let name = name.to_string();
+        let param_names: Vec<String> = param_names.iter().map(|p| p.to_string()).collect();
+        return self.object.handle.send(move |worker| {
+            let param_names: Vec<&str> = param_names.iter().map(String::as_str).collect();
+            return worker.dispatch(id)?.get_ids_of_names(&name, &param_names);
+        });
+    }
+
+    // the whole batch in one trip to the thread, which then looks each up on the object
+    fn get_ids_of_members(&self, names: &[&str]) -> Result<Vec<i32>,Error> {
+        let id = self.object.id;
+        let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+        return self.object.handle.send(move |worker| {
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            return worker.dispatch(id)?.get_ids_of_members(&names);
+        });
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        let handle = &self.object.handle;
+        let positional = arguments.iter().map(|a| Encoded::new(handle, a)).collect::<Result<Vec<Encoded>,Error>>()?;
+        let named = named_arguments.iter().map(|a| Encoded::new(handle, a)).collect::<Result<Vec<Encoded>,Error>>()?;
+        let id = self.object.id;
+        let named_dispids = named_dispids.to_vec();
+        let (result, written) = handle.send(move |worker| {
+            let dispatch = worker.dispatch(id)?.clone();
+            let mut values = positional.iter().chain(named.iter())
+                .map(|a| worker.decode(&a.value))
+                .collect::<Result<Vec<Variant>,Error>>()?;
+            let kinds: Vec<u8> = positional.iter().chain(named.iter()).map(|a| a.kind).collect();
+            let (positional_values, named_values) = values.split_at_mut(positional.len());
+            let mut positional_arguments = rebuild(positional_values, &kinds[..positional.len()]);
+            let mut named_arguments = rebuild(named_values, &kinds[positional.len()..]);
+            let result = dispatch.invoke(dispid, kind, &mut positional_arguments, &named_dispids, &mut named_arguments)?;
+            // what the by-ref arguments now hold, to be written back on the caller's side
+            let written = positional_arguments.iter().chain(named_arguments.iter())
+                .map(|a| a.is_by_ref().then(|| worker.encode(a.value())).transpose())
+                .collect::<Result<Vec<Option<RemoteVariant>>,Error>>()?;
+            return Ok((worker.encode(&result)?, written));
+        })?;
+
+        // every object in the values has been handed out, so they're all decoded (taking them over
+        // to be released with their proxies) before any failure is returned
+        let result = handle.decode(&result);
+        let (positional_written, named_written) = written.split_at(arguments.len());
+        let positional_failed = write_back(handle, arguments, positional_written);
+        let named_failed = write_back(handle, named_arguments, named_written);
+        return match positional_failed.or(named_failed) {
+            Some(e) => Err(e),
+            None => result,
+        };
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return self.object.identity();
+    }
+
+    fn type_info(&self) -> Result<TypeInfo,Error> {
+        let id = self.object.id;
+        let handle = &self.object.handle;
+        let (Detached(mut info), values) = handle.send(move |worker| {
+            let mut info = worker.dispatch(id)?.type_info()?;
+            let values = type_info_values(&mut info)
+                .map(|value| value.take().map(|value| worker.encode(&value)).transpose())
+                .collect::<Result<Vec<Option<RemoteVariant>>,Error>>()?;
+            return Ok((Detached(info), values));
+        })?;
+        // decoded in full before any failure's returned, as for invoke
+        let values: Vec<Option<Result<Variant,Error>>> = values.iter().map(|value| value.as_ref().map(|value| handle.decode(value))).collect();
+        for (slot, value) in type_info_values(&mut info).zip(values) {
+            *slot = value.transpose()?;
+        }
+        return Ok(info);
+    }
+
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        let id = self.object.id;
+        let enumerator = self.object.handle.send(move |worker| {
+            let Some(enumerator) = worker.dispatch(id)?.new_enum()? else { return Ok(None) };
+            worker.next_id += 1;
+            worker.enumerators.insert(worker.next_id, enumerator);
+            return Ok(Some(worker.next_id));
+        })?;
+        return Ok(enumerator.map(|id| Box::new(RemoteEnumerator { id, handle: self.object.handle.clone() }) as Box<dyn Enumerator>));
+    }
+}
+
+// a VT_UNKNOWN on the thread
+struct RemoteUnknown {
+    object: Proxy,
+}
+
+impl UnknownObject for RemoteUnknown {
+    fn query_dispatch(&self) -> Result<Dispatch,Error> {
+        let id = self.object.id;
+        let handle = &self.object.handle;
+        let dispatch = handle.send(move |worker| {
+            let unknown = match worker.objects.get(&id) {
+                Some(Registered { object: Object::Unknown(unknown), .. }) => unknown.clone(),
+                _ => return Err(released(id)),
+            };
+            let dispatch = unknown.to_dispatch()?;
+            return worker.encode(&Variant::Dispatch(dispatch));
+        })?;
+        return handle.decode(&dispatch)?.into_dispatch();
+    }
+
+    fn clone_object(&self) -> Box<dyn UnknownObject> {
+        return Box::new(RemoteUnknown { object: self.object.clone() });
+    }
+
+    fn identity(&self) -> usize {
+        return self.object.identity();
+    }
+}
+
+struct RemoteEnumerator {
+    id: u64,
+    handle: ComHandle,
+}
+
+impl Enumerator for RemoteEnumerator {
+    fn next_batch(&mut self, count: usize) -> Result<Vec<Variant>,Error> {
+        let id = self.id;
+        let items = self.handle.send(move |worker| {
+            let enumerator = worker.enumerators.get_mut(&id).ok_or_else(|| released(id))?;
+            let items = enumerator.next_batch(count)?;
+            return items.iter().map(|item| worker.encode(item)).collect::<Result<Vec<RemoteVariant>,Error>>();
+        })?;
+        return items.iter().map(|item| self.handle.decode(item)).collect();
+    }
+}
+
+impl Drop for RemoteEnumerator {
+    fn drop(&mut self) {
+        let id = self.id;
+        self.handle.post(move |worker| {
+            worker.enumerators.remove(&id);
+        });
+    }
+}
+
+// the thread itself, which is stopped (releasing everything still on it) when this is dropped.
+// handles that outlive it get errors saying it's stopped
+pub struct ComThread {
+    handle: ComHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ComThread {
+    // starts the thread, joining a single threaded apartment on windows
+    pub fn spawn() -> Result<ComThread,Error> {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let (started, start) = mpsc::channel();
+        let signal = Arc::new(Signal::new()?);
+        let waiter = signal.clone();
+        let thread = thread::Builder::new()
+            .name("com".to_string())
+            .spawn(move || run(receiver, waiter, started))
+            .map_err(|e| Error::com(hresult::E_FAIL, format!("failed starting the COM thread: {}", e)))?;
+        start.recv().map_err(|_| stopped())??;
+        let shared = Shared {
+            jobs,
+            signal,
+            thread: thread.thread().id(),
+            proxies: Mutex::new(Proxies::default()),
+        };
+        return Ok(ComThread { handle: ComHandle { shared: Arc::new(shared) }, thread: Some(thread) });
+    }
+
+    pub fn handle(&self) -> ComHandle {
+        return self.handle.clone();
+    }
+}
+
+impl Drop for ComThread {
+    fn drop(&mut self) {
+        self.handle.post(|worker| worker.stopped = true);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// the thread's main loop
+#[cfg(not(windows))]
+fn run(jobs: Receiver<Job>, _signal: Arc<Signal>, started: Sender<Result<(),Error>>) {
+    let _ = started.send(Ok(()));
+    let mut worker = Worker::new();
+    while !worker.stopped {
+        match jobs.recv() {
+            Ok(job) => worker.run(job),
+            Err(_) => break,
+        }
+    }
+}
+
+#[cfg(windows)]
+fn run(jobs: Receiver<Job>, signal: Arc<Signal>, started: Sender<Result<(),Error>>) {
+    let _apartment = match crate::ComApartment::sta() {
+        Ok(apartment) => apartment,
+        Err(e) => {
+            let _ = started.send(Err(e));
+            return;
+        },
+    };
+    let _ = started.send(Ok(()));
+    // dropped before the apartment, releasing anything still on the thread
+    let mut worker = Worker::new();
+    while !worker.stopped {
+        match jobs.try_recv() {
+            Ok(job) => worker.run(job),
+            Err(mpsc::TryRecvError::Empty) => signal.wait_pumping_messages(),
+            Err(mpsc::TryRecvError::Disconnected) => break,
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use crate::{Automation, Dispatch, Error, Variant};
+use super::{ComHandle, RemoteDispatch, RemoteVariant, stopped, Worker};
+
+//
+// async versions of the usual gets, puts and calls on a RemoteDispatch, for calls that can block for
+// seconds (an SDO Connect, a QuickBooks ProcessRequest) from async code:
+//
+//   let connected = workspace.call_method_async("Connect", &[...]).await?;
+//
+// each returns a ComFuture, which queues the call on the COM thread straight away and resolves when
+// it finishes.  nothing here depends on an executor, the thread wakes whatever task polled it, so
+// they work under tokio, async-std, smol or a hand rolled block_on alike.
+//
+// dropping the future cancels the call, e.g. when it loses a select! or a timeout.  a call that's
+// still queued is skipped; one that's already running can't be interrupted (the thread is inside
+// the server until it returns), so it runs to the end and its result is thrown away, releasing any
+// objects in it
+//
+
+struct State {
+    result: Option<Result<RemoteVariant,Error>>,
+    waker: Option<Waker>,
+    // set by the future when it's dropped, and by the thread once it's filled in the result
+    cancelled: bool,
+    completed: bool,
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    return state.lock().unwrap_or_else(|e| e.into_inner());
+}
+
+// the thread's end of a future, which fails it if the job's dropped without running (the thread
+// stopped, or the job panicked)
+struct Completion {
+    state: Arc<Mutex<State>>,
+}
+
+impl Completion {
+    fn is_cancelled(&self) -> bool {
+        return lock(&self.state).cancelled;
+    }
+
+    fn complete(&self, worker: &mut Worker, result: Result<Variant,Error>) {
+        let mut state = lock(&self.state);
+        if state.cancelled || state.completed {
+            return;
+        }
+        // objects in the result are only handed out while the future's still there to take them
+        state.result = Some(result.and_then(|value| worker.encode(&value)));
+        state.completed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        let mut state = lock(&self.state);
+        if !state.completed {
+            state.result = Some(Err(stopped()));
+            state.completed = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// a call running on a ComThread, see RemoteDispatch::call_method_async
+#[must_use = "the call is cancelled if the future is dropped"]
+pub struct ComFuture<T> {
+    state: Arc<Mutex<State>>,
+    handle: ComHandle,
+    finish: fn(&ComHandle, RemoteVariant) -> Result<T,Error>,
+}
+
+impl<T> ComFuture<T> {
+    // queues the job, whose result is finished into the future's output on the caller's side
+    fn start<F>(handle: &ComHandle, job: F, finish: fn(&ComHandle, RemoteVariant) -> Result<T,Error>) -> ComFuture<T>
+    where F: FnOnce(&mut Worker) -> Result<Variant,Error> + Send + 'static {
+        let state = Arc::new(Mutex::new(State { result: None, waker: None, cancelled: false, completed: false }));
+        let completion = Completion { state: state.clone() };
+        handle.post(move |worker| {
+            if completion.is_cancelled() {
+                return;
+            }
+            let result = job(worker);
+            completion.complete(worker, result);
+        });
+        return ComFuture { state, handle: handle.clone(), finish };
+    }
+
+    // a future that's already failed, for a call that couldn't be sent
+    fn failed(handle: &ComHandle, error: Error, finish: fn(&ComHandle, RemoteVariant) -> Result<T,Error>) -> ComFuture<T> {
+        let state = State { result: Some(Err(error)), waker: None, cancelled: false, completed: true };
+        return ComFuture { state: Arc::new(Mutex::new(state)), handle: handle.clone(), finish };
+    }
+}
+
+impl<T> Future for ComFuture<T> {
+    type Output = Result<T,Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T,Error>> {
+        let mut state = lock(&self.state);
+        if !state.completed {
+            state.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let result = state.result.take().expect("ComFuture polled after it finished");
+        drop(state);
+        return Poll::Ready(result.and_then(|value| (self.finish)(&self.handle, value)));
+    }
+}
+
+impl<T> Drop for ComFuture<T> {
+    fn drop(&mut self) {
+        let mut state = lock(&self.state);
+        state.cancelled = true;
+        let result = state.result.take();
+        drop(state);
+        // a result that was never collected still holds its objects, decoding it takes them over
+        // so they're released with the proxies
+        if let Some(Ok(value)) = result {
+            let _ = self.handle.decode(&value);
+        }
+    }
+}
+
+fn variant(handle: &ComHandle, value: RemoteVariant) -> Result<Variant,Error> {
+    return handle.decode(&value);
+}
+
+fn unit(_: &ComHandle, _: RemoteVariant) -> Result<(),Error> {
+    return Ok(());
+}
+
+impl RemoteDispatch {
+    pub fn get_property_async(&self, name: &str) -> ComFuture<Variant> {
+        return self.call_async(name, &[], |dispatch, name, _| dispatch.get_property(name), variant);
+    }
+
+    pub fn get_property_with_args_async(&self, name: &str, args: &[Variant]) -> ComFuture<Variant> {
+        return self.call_async(name, args, |dispatch, name, args| dispatch.get_property_with_args(name, args), variant);
+    }
+
+    pub fn put_property_async(&self, name: &str, value: &Variant) -> ComFuture<()> {
+        return self.call_async(name, std::slice::from_ref(value), |dispatch, name, args| {
+            dispatch.put_property(name, &args[0])?;
+            return Ok(Variant::Empty);
+        }, unit);
+    }
+
+    pub fn call_method_async(&self, name: &str, values: &[Variant]) -> ComFuture<Variant> {
+        return self.call_async(name, values, |dispatch, name, args| dispatch.call_method(name, args), variant);
+    }
+
+    // sends the name and arguments over as RemoteVariants and makes the call on the thread
+    fn call_async<T>(&self, name: &str, args: &[Variant], call: fn(&Dispatch, &str, &[Variant]) -> Result<Variant,Error>, finish: fn(&ComHandle, RemoteVariant) -> Result<T,Error>) -> ComFuture<T> {
+        let handle = &self.object.handle;
+        let args = match args.iter().map(|arg| handle.encode(arg)).collect::<Result<Vec<RemoteVariant>,Error>>() {
+            Ok(args) => args,
+            Err(e) => return ComFuture::failed(handle, e, finish),
+        };
+        let id = self.object.id;
+        let name = name.to_string();
+        return ComFuture::start(handle, move |worker| {
+            let dispatch = worker.dispatch(id)?.clone();
+            let args = args.iter().map(|arg| worker.decode(arg)).collect::<Result<Vec<Variant>,Error>>()?;
+            return call(&dispatch, &name, &args);
+        }, finish);
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HWND};
+use windows::Win32::System::Threading::{CreateEventW, INFINITE, SetEvent};
+use windows::Win32::UI::WindowsAndMessaging::{DispatchMessageW, MSG, MsgWaitForMultipleObjectsEx, MWMO_INPUTAVAILABLE, PeekMessageW, PM_REMOVE, QS_ALLINPUT, TranslateMessage};
+use crate::Error;
+
+// an STA thread mustn't just block while it waits for work, it has to keep dispatching the window
+// messages COM uses to deliver calls into the apartment (events, callbacks, and the replies of
+// out of process servers).  so the thread waits on an auto reset event and its message queue at
+// the same time, and the senders set the event after queueing a job
+pub(super) struct Signal {
+    event: HANDLE,
+}
+
+// the event is only waited on and set, both of which are fine from any thread
+unsafe impl Send for Signal {}
+unsafe impl Sync for Signal {}
+
+impl Signal {
+    pub(super) fn new() -> Result<Signal,Error> {
+        let event = unsafe { CreateEventW(None, false, false, PCWSTR::null())? };
+        return Ok(Signal { event });
+    }
+
+    pub(super) fn notify(&self) {
+        let _ = unsafe { SetEvent(self.event) };
+    }
+
+    // returns when a job might have been queued, dispatching any messages that arrive meanwhile
+    pub(super) fn wait_pumping_messages(&self) {
+        unsafe { MsgWaitForMultipleObjectsEx(Some(&[self.event]), INFINITE, QS_ALLINPUT, MWMO_INPUTAVAILABLE) };
+        dispatch_messages();
+    }
+}
+
+// the same for a thread that's only waiting out a delay, e.g. between the attempts of a retried
+// call, which can't block its apartment any more than the ComThread can
+pub(crate) fn sleep_pumping_messages(delay: Duration) {
+    let until = Instant::now() + delay;
+    loop {
+        let remaining = until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return;
+        }
+        let milliseconds = u32::try_from(remaining.as_millis()).unwrap_or(INFINITE - 1).max(1);
+        unsafe { MsgWaitForMultipleObjectsEx(None, milliseconds, QS_ALLINPUT, MWMO_INPUTAVAILABLE) };
+        dispatch_messages();
+    }
+}
+
+fn dispatch_messages() {
+    let mut message = MSG::default();
+    unsafe {
+        while PeekMessageW(&mut message, HWND::default(), 0, 0, PM_REMOVE).as_bool() {
+            TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+    }
+}
+
+impl Drop for Signal {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.event) };
+    }
+}
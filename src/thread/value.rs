@@ -0,0 +1,108 @@
+use crate::{ArrayBound, Currency, Decimal, Error, OleDate, SafeArray, Variant, VarType};
+
+//
+// a Variant on its way to or from the COM thread.  it's the same as a Variant except for the
+// objects, which can't leave the thread and go by their id there instead, so unlike a Variant it
+// can be sent between threads
+//
+
+#[derive(Clone, Debug)]
+pub(super) enum RemoteVariant {
+    Empty,
+    Null,
+    I1(i8),
+    I2(i16),
+    I4(i32),
+    I8(i64),
+    UI1(u8),
+    UI2(u16),
+    UI4(u32),
+    UI8(u64),
+    Int(i32),
+    UInt(u32),
+    R4(f32),
+    R8(f64),
+    Bool(bool),
+    Bstr(String),
+    Cy(Currency),
+    Date(OleDate),
+    Decimal(Decimal),
+    Error(i32),
+    // the id of the object on the thread
+    Dispatch(u64),
+    Unknown(u64),
+    Nothing,
+    Array(VarType, Vec<ArrayBound>, Vec<RemoteVariant>),
+}
+
+impl RemoteVariant {
+    // object_id gives the id for each object in the value, failing for ones that can't be sent
+    pub(super) fn new(value: &Variant, object_id: &mut dyn FnMut(&Variant) -> Result<u64,Error>) -> Result<RemoteVariant,Error> {
+        return Ok(match value {
+            Variant::Empty => RemoteVariant::Empty,
+            Variant::Null => RemoteVariant::Null,
+            Variant::I1(v) => RemoteVariant::I1(*v),
+            Variant::I2(v) => RemoteVariant::I2(*v),
+            Variant::I4(v) => RemoteVariant::I4(*v),
+            Variant::I8(v) => RemoteVariant::I8(*v),
+            Variant::UI1(v) => RemoteVariant::UI1(*v),
+            Variant::UI2(v) => RemoteVariant::UI2(*v),
+            Variant::UI4(v) => RemoteVariant::UI4(*v),
+            Variant::UI8(v) => RemoteVariant::UI8(*v),
+            Variant::Int(v) => RemoteVariant::Int(*v),
+            Variant::UInt(v) => RemoteVariant::UInt(*v),
+            Variant::R4(v) => RemoteVariant::R4(*v),
+            Variant::R8(v) => RemoteVariant::R8(*v),
+            Variant::Bool(v) => RemoteVariant::Bool(*v),
+            Variant::Bstr(v) => RemoteVariant::Bstr(v.clone()),
+            Variant::Cy(v) => RemoteVariant::Cy(*v),
+            Variant::Date(v) => RemoteVariant::Date(*v),
+            Variant::Decimal(v) => RemoteVariant::Decimal(*v),
+            Variant::Error(v) => RemoteVariant::Error(*v),
+            Variant::Dispatch(_) => RemoteVariant::Dispatch(object_id(value)?),
+            Variant::Unknown(_) => RemoteVariant::Unknown(object_id(value)?),
+            Variant::Nothing => RemoteVariant::Nothing,
+            Variant::Array(array) => {
+                let elements = array.elements().iter()
+                    .map(|element| RemoteVariant::new(element, object_id))
+                    .collect::<Result<Vec<RemoteVariant>,Error>>()?;
+                RemoteVariant::Array(array.vt(), array.bounds().to_vec(), elements)
+            },
+        });
+    }
+
+    // object gives the Variant for each object id, as a Dispatch or Unknown as the vt says
+    pub(super) fn to_variant(&self, object: &mut dyn FnMut(VarType, u64) -> Result<Variant,Error>) -> Result<Variant,Error> {
+        return Ok(match self {
+            RemoteVariant::Empty => Variant::Empty,
+            RemoteVariant::Null => Variant::Null,
+            RemoteVariant::I1(v) => Variant::I1(*v),
+            RemoteVariant::I2(v) => Variant::I2(*v),
+            RemoteVariant::I4(v) => Variant::I4(*v),
+            RemoteVariant::I8(v) => Variant::I8(*v),
+            RemoteVariant::UI1(v) => Variant::UI1(*v),
+            RemoteVariant::UI2(v) => Variant::UI2(*v),
+            RemoteVariant::UI4(v) => Variant::UI4(*v),
+            RemoteVariant::UI8(v) => Variant::UI8(*v),
+            RemoteVariant::Int(v) => Variant::Int(*v),
+            RemoteVariant::UInt(v) => Variant::UInt(*v),
+            RemoteVariant::R4(v) => Variant::R4(*v),
+            RemoteVariant::R8(v) => Variant::R8(*v),
+            RemoteVariant::Bool(v) => Variant::Bool(*v),
+            RemoteVariant::Bstr(v) => Variant::Bstr(v.clone()),
+            RemoteVariant::Cy(v) => Variant::Cy(*v),
+            RemoteVariant::Date(v) => Variant::Date(*v),
+            RemoteVariant::Decimal(v) => Variant::Decimal(*v),
+            RemoteVariant::Error(v) => Variant::Error(*v),
+            RemoteVariant::Dispatch(id) => object(VarType::Dispatch, *id)?,
+            RemoteVariant::Unknown(id) => object(VarType::Unknown, *id)?,
+            RemoteVariant::Nothing => Variant::Nothing,
+            RemoteVariant::Array(vt, bounds, elements) => {
+                let elements = elements.iter()
+                    .map(|element| element.to_variant(object))
+                    .collect::<Result<Vec<Variant>,Error>>()?;
+                Variant::Array(SafeArray::new(*vt, bounds.clone(), elements)?)
+            },
+        });
+    }
+}
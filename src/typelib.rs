@@ -0,0 +1,518 @@
+use std::fs;
+use std::path::Path;
+use serde::{Serialize, Serializer};
+use crate::{ArrayBound, Error, hresult, InvokeKind, Variant, VarType};
+
+#[cfg(windows)]
+mod com;
+mod msft;
+mod sltg;
+
+#[cfg(windows)]
+pub(crate) use com::read_type_info;
+
+//
+// a reader for type libraries (.tlb, .olb) in pure rust, so what an automation server exposes can
+// be looked at on any platform from a copy of its type library:
+//
+//   let library = TypeLibrary::open("SDOEng.tlb")?;
+//   for coclass in library.types_of(TypeKind::CoClass) {
+//       println!("{} {}", coclass.name, coclass.guid.as_deref().unwrap_or(""));
+//   }
+//
+// both formats are read, MSFT (everything midl has produced since the late 90s) and the older SLTG
+// (still found in some vb5/vb6 era servers).  neither is documented by microsoft, the layouts here
+// follow what wine's oleaut32 reads
+//
+
+// the VT_ values that only appear in type descriptions, never in a VARIANT
+pub const VT_VOID: u16 = 24;
+pub const VT_HRESULT: u16 = 25;
+pub const VT_PTR: u16 = 26;
+pub const VT_SAFEARRAY: u16 = 27;
+pub const VT_CARRAY: u16 = 28;
+pub const VT_USERDEFINED: u16 = 29;
+pub const VT_LPSTR: u16 = 30;
+pub const VT_LPWSTR: u16 = 31;
+
+// TYPEFLAGS
+pub const TYPEFLAG_FAPPOBJECT: u16 = 0x1;
+pub const TYPEFLAG_FCANCREATE: u16 = 0x2;
+pub const TYPEFLAG_FHIDDEN: u16 = 0x10;
+pub const TYPEFLAG_FDUAL: u16 = 0x40;
+pub const TYPEFLAG_FNONEXTENSIBLE: u16 = 0x80;
+pub const TYPEFLAG_FOLEAUTOMATION: u16 = 0x100;
+pub const TYPEFLAG_FRESTRICTED: u16 = 0x200;
+pub const TYPEFLAG_FDISPATCHABLE: u16 = 0x1000;
+
+// FUNCFLAGS
+pub const FUNCFLAG_FRESTRICTED: u16 = 0x1;
+pub const FUNCFLAG_FSOURCE: u16 = 0x2;
+pub const FUNCFLAG_FBINDABLE: u16 = 0x4;
+pub const FUNCFLAG_FHIDDEN: u16 = 0x40;
+pub const FUNCFLAG_FDEFAULTBIND: u16 = 0x20;
+
+// PARAMFLAGS
+pub const PARAMFLAG_FIN: u16 = 0x1;
+pub const PARAMFLAG_FOUT: u16 = 0x2;
+pub const PARAMFLAG_FLCID: u16 = 0x4;
+pub const PARAMFLAG_FRETVAL: u16 = 0x8;
+pub const PARAMFLAG_FOPT: u16 = 0x10;
+pub const PARAMFLAG_FHASDEFAULT: u16 = 0x20;
+
+// VARFLAGS
+pub const VARFLAG_FREADONLY: u16 = 0x1;
+pub const VARFLAG_FHIDDEN: u16 = 0x40;
+
+// IMPLTYPEFLAGS
+pub const IMPLTYPEFLAG_FDEFAULT: u16 = 0x1;
+pub const IMPLTYPEFLAG_FSOURCE: u16 = 0x2;
+pub const IMPLTYPEFLAG_FRESTRICTED: u16 = 0x4;
+
+// the library stdole2.tlb defines IUnknown and IDispatch in, which nearly every other one imports
+const STDOLE_GUID: &str = "00020430-0000-0000-C000-000000000046";
+const IUNKNOWN_GUID: &str = "00000000-0000-0000-C000-000000000046";
+const IDISPATCH_GUID: &str = "00020400-0000-0000-C000-000000000046";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TypeLibrary {
+    pub name: String,
+    pub doc: Option<String>,
+    pub guid: Option<String>,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub lcid: u32,
+    pub types: Vec<TypeInfo>,
+}
+
+impl TypeLibrary {
+    // reads either format, telling them apart by their magic
+    pub fn parse(data: &[u8]) -> Result<TypeLibrary,Error> {
+        return match data.get(0..4) {
+            Some(b"MSFT") => msft::parse(data),
+            Some(b"SLTG") => sltg::parse(data),
+            _ => Err(Error::com(hresult::TYPE_E_CANTLOADLIBRARY, "not an MSFT or SLTG type library")),
+        };
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<TypeLibrary,Error> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .map_err(|e| Error::com(hresult::TYPE_E_CANTLOADLIBRARY, format!("failed reading {}: {}", path.display(), e)))?;
+        return TypeLibrary::parse(&data);
+    }
+
+    // the type with the name, which like everything in automation is case insensitive
+    pub fn find(&self, name: &str) -> Option<&TypeInfo> {
+        return self.types.iter().find(|t| t.name.eq_ignore_ascii_case(name));
+    }
+
+    pub fn types_of(&self, kind: TypeKind) -> impl Iterator<Item = &TypeInfo> {
+        return self.types.iter().filter(move |t| t.kind == kind);
+    }
+
+    // the type a reference points at, when it's one of this library's
+    pub fn resolve(&self, reference: &TypeRef) -> Option<&TypeInfo> {
+        return match reference {
+            TypeRef::Local(index) => self.types.get(*index),
+            TypeRef::Imported { .. } | TypeRef::Named { .. } => None,
+        };
+    }
+
+    // the name of a referenced type.  an imported one is only known by its guid or position in the
+    // other library, so apart from stdole's IUnknown and IDispatch it's named after those
+    pub fn type_name(&self, reference: &TypeRef) -> String {
+        return match reference {
+            TypeRef::Local(index) => self.types.get(*index).map(|t| t.name.clone()).unwrap_or_else(|| format!("#{}", index)),
+            TypeRef::Imported { library, guid, index } => {
+                let well_known = match (guid.as_deref(), index) {
+                    (Some(IUNKNOWN_GUID), _) => Some("IUnknown"),
+                    (Some(IDISPATCH_GUID), _) => Some("IDispatch"),
+                    (None, Some(3)) if library.guid.as_deref() == Some(STDOLE_GUID) => Some("IUnknown"),
+                    (None, Some(4)) if library.guid.as_deref() == Some(STDOLE_GUID) => Some("IDispatch"),
+                    _ => None,
+                };
+                match (well_known, guid, index) {
+                    (Some(name), _, _) => name.to_string(),
+                    (None, Some(guid), _) => format!("{}#{{{}}}", library.name, guid),
+                    (None, None, Some(index)) => format!("{}#{}", library.name, index),
+                    (None, None, None) => library.name.clone(),
+                }
+            },
+            TypeRef::Named { name, .. } => name.clone(),
+        };
+    }
+
+    // an idl-like spelling of the type, e.g. "BSTR", "IFields*" or "SAFEARRAY(VARIANT)"
+    pub fn type_desc_name(&self, type_desc: &TypeDesc) -> String {
+        return match type_desc {
+            TypeDesc::Base(vt) => base_type_name(*vt),
+            TypeDesc::Ptr(inner) => format!("{}*", self.type_desc_name(inner)),
+            TypeDesc::SafeArray(inner) => format!("SAFEARRAY({})", self.type_desc_name(inner)),
+            TypeDesc::CArray(inner, bounds) => {
+                let dims: Vec<String> = bounds.iter().map(|b| b.len.to_string()).collect();
+                format!("{}[{}]", self.type_desc_name(inner), dims.join("]["))
+            },
+            TypeDesc::UserDefined(reference) => self.type_name(reference),
+        };
+    }
+}
+
+// TYPEKIND
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum TypeKind {
+    Enum,
+    Record,
+    Module,
+    Interface,
+    Dispatch,
+    CoClass,
+    Alias,
+    Union,
+}
+
+impl TypeKind {
+    pub fn from_u16(kind: u16) -> Option<TypeKind> {
+        let type_kind = match kind {
+            0 => TypeKind::Enum,
+            1 => TypeKind::Record,
+            2 => TypeKind::Module,
+            3 => TypeKind::Interface,
+            4 => TypeKind::Dispatch,
+            5 => TypeKind::CoClass,
+            6 => TypeKind::Alias,
+            7 => TypeKind::Union,
+            _ => return None
+        };
+        return Some(type_kind);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TypeInfo {
+    pub name: String,
+    pub kind: TypeKind,
+    pub guid: Option<String>,
+    pub doc: Option<String>,
+    // TYPEFLAG_*
+    pub flags: u16,
+    pub major_version: u16,
+    pub minor_version: u16,
+    // the base of an interface, or the interfaces a coclass implements
+    pub implements: Vec<ImplType>,
+    // what an alias stands for
+    pub alias: Option<TypeDesc>,
+    pub functions: Vec<Function>,
+    // the constants of an enum or module, the fields of a record or union, or the properties of a
+    // dispinterface that are declared as properties rather than get/put functions
+    pub variables: Vec<Variable>,
+}
+
+impl TypeInfo {
+    // an interface that can be called through IDispatch as well as its vtable
+    pub fn is_dual(&self) -> bool {
+        return self.flags & TYPEFLAG_FDUAL != 0;
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        return self.flags & (TYPEFLAG_FHIDDEN | TYPEFLAG_FRESTRICTED) != 0;
+    }
+
+    // every function with the name, a property usually has a get and a put
+    pub fn functions_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Function> {
+        return self.functions.iter().filter(move |f| f.name.eq_ignore_ascii_case(name));
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&Variable> {
+        return self.variables.iter().find(|v| v.name.eq_ignore_ascii_case(name));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ImplType {
+    pub reference: TypeRef,
+    // IMPLTYPEFLAG_*
+    pub flags: u16,
+}
+
+impl ImplType {
+    pub fn is_default(&self) -> bool {
+        return self.flags & IMPLTYPEFLAG_FDEFAULT != 0;
+    }
+
+    // an events interface the coclass calls rather than implements
+    pub fn is_source(&self) -> bool {
+        return self.flags & IMPLTYPEFLAG_FSOURCE != 0;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Function {
+    pub name: String,
+    pub memid: i32,
+    pub invoke_kind: InvokeKind,
+    pub doc: Option<String>,
+    pub return_type: TypeDesc,
+    pub params: Vec<Param>,
+    // how many of the trailing parameters are optional (cParamsOpt), -1 when the last one takes any
+    // number of arguments
+    pub optional_params: i16,
+    // FUNCFLAG_*
+    pub flags: u16,
+}
+
+impl Function {
+    pub fn is_hidden(&self) -> bool {
+        return self.flags & (FUNCFLAG_FHIDDEN | FUNCFLAG_FRESTRICTED) != 0;
+    }
+
+    // the [out, retval] parameter that holds a vtable function's real return value
+    pub fn retval(&self) -> Option<&Param> {
+        return self.params.iter().find(|p| p.is_retval());
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Param {
+    // empty when the library doesn't name it
+    pub name: String,
+    pub type_desc: TypeDesc,
+    // PARAMFLAG_*
+    pub flags: u16,
+    #[serde(serialize_with = "serialize_value")]
+    pub default: Option<Variant>,
+}
+
+impl Param {
+    pub fn is_optional(&self) -> bool {
+        return self.flags & (PARAMFLAG_FOPT | PARAMFLAG_FHASDEFAULT) != 0;
+    }
+
+    pub fn is_out(&self) -> bool {
+        return self.flags & PARAMFLAG_FOUT != 0;
+    }
+
+    pub fn is_retval(&self) -> bool {
+        return self.flags & PARAMFLAG_FRETVAL != 0;
+    }
+
+    pub fn is_lcid(&self) -> bool {
+        return self.flags & PARAMFLAG_FLCID != 0;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Variable {
+    pub name: String,
+    pub memid: i32,
+    pub doc: Option<String>,
+    pub type_desc: TypeDesc,
+    // VARFLAG_*
+    pub flags: u16,
+    // the value of a constant, None for fields and properties
+    #[serde(serialize_with = "serialize_value")]
+    pub value: Option<Variant>,
+}
+
+impl Variable {
+    pub fn is_read_only(&self) -> bool {
+        return self.flags & VARFLAG_FREADONLY != 0;
+    }
+}
+
+// TYPEDESC
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum TypeDesc {
+    // a type with no further description, e.g. VT_I4, VT_BSTR, VT_VARIANT, VT_VOID or VT_HRESULT
+    Base(u16),
+    Ptr(Box<TypeDesc>),
+    SafeArray(Box<TypeDesc>),
+    // a fixed size c array, in a record
+    CArray(Box<TypeDesc>, Vec<ArrayBound>),
+    UserDefined(TypeRef),
+}
+
+impl TypeDesc {
+    // the variant type, for the types that fit in one
+    pub fn var_type(&self) -> Option<VarType> {
+        return match self {
+            TypeDesc::Base(vt) => VarType::from_u16(*vt),
+            _ => None,
+        };
+    }
+
+    // the type a pointer points at, or the type itself
+    pub fn pointee(&self) -> &TypeDesc {
+        return match self {
+            TypeDesc::Ptr(inner) => inner,
+            _ => self,
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum TypeRef {
+    // one of this library's types, by its index in TypeLibrary::types
+    Local(usize),
+    // a type in another library, known either by its guid or by its position in that library
+    Imported {
+        library: ImportedLibrary,
+        guid: Option<String>,
+        index: Option<u32>,
+    },
+    // a type described by a running object rather than a library, which is only known by name
+    Named {
+        name: String,
+        guid: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ImportedLibrary {
+    // the file name it was imported from, e.g. "stdole2.tlb"
+    pub name: String,
+    pub guid: Option<String>,
+    pub major_version: u16,
+    pub minor_version: u16,
+}
+
+fn base_type_name(vt: u16) -> String {
+    let name = match vt {
+        0 => "EMPTY",
+        1 => "NULL",
+        2 => "short",
+        3 => "long",
+        4 => "float",
+        5 => "double",
+        6 => "CURRENCY",
+        7 => "DATE",
+        8 => "BSTR",
+        9 => "IDispatch*",
+        10 => "SCODE",
+        11 => "VARIANT_BOOL",
+        12 => "VARIANT",
+        13 => "IUnknown*",
+        14 => "DECIMAL",
+        16 => "char",
+        17 => "unsigned char",
+        18 => "unsigned short",
+        19 => "unsigned long",
+        20 => "int64",
+        21 => "uint64",
+        22 => "int",
+        23 => "unsigned int",
+        VT_VOID => "void",
+        VT_HRESULT => "HRESULT",
+        VT_LPSTR => "LPSTR",
+        VT_LPWSTR => "LPWSTR",
+        _ => return format!("vt_{}", vt),
+    };
+    return name.to_string();
+}
+
+// a constant as a variant, from its type and integer value
+fn integer_variant(vt: u16, value: i64) -> Result<Variant,Error> {
+    let variant = match vt {
+        0 => Variant::Empty,
+        1 => Variant::Null,
+        2 => Variant::I2(value as i16),
+        3 | VT_HRESULT => Variant::I4(value as i32),
+        10 => Variant::Error(value as i32),
+        11 => Variant::Bool(value as i16 != 0),
+        16 => Variant::I1(value as i8),
+        17 => Variant::UI1(value as u8),
+        18 => Variant::UI2(value as u16),
+        19 => Variant::UI4(value as u32),
+        20 => Variant::I8(value),
+        21 => Variant::UI8(value as u64),
+        22 => Variant::Int(value as i32),
+        23 => Variant::UInt(value as u32),
+        _ => return Err(invalid(format!("unsupported constant of type {}", base_type_name(vt)))),
+    };
+    return Ok(variant);
+}
+
+// defaults and constants are written the way recorded sessions write values, e.g.
+// {"type": "vt_i4", "value": 1}, so the json says exactly what the library holds
+fn serialize_value<S: Serializer>(value: &Option<Variant>, serializer: S) -> Result<S::Ok,S::Error> {
+    // a type description can't hold an object, so there are never any to number
+    return value.as_ref().map(|v| crate::serialize::encode(v, &mut |_| 0)).serialize(serializer);
+}
+
+fn invalid<S: Into<String>>(message: S) -> Error {
+    return Error::com(hresult::TYPE_E_INVDATAREAD, format!("invalid type library: {}", message.into()));
+}
+
+// bounds checked little endian reads, everything in both formats is little endian
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data }
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8],Error> {
+        return offset.checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| invalid(format!("{} bytes at {:#x} are past the end", len, offset)));
+    }
+
+    // the part of the data a segment or block covers, offsets within it are then relative to it
+    fn slice(&self, offset: usize, len: usize) -> Result<Reader<'a>,Error> {
+        return Ok(Reader::new(self.bytes(offset, len)?));
+    }
+
+    fn rest(&self, offset: usize) -> Result<Reader<'a>,Error> {
+        return self.slice(offset, self.data.len().saturating_sub(offset));
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8,Error> {
+        return Ok(self.bytes(offset, 1)?[0]);
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16,Error> {
+        let bytes = self.bytes(offset, 2)?;
+        return Ok(u16::from_le_bytes([bytes[0], bytes[1]]));
+    }
+
+    fn i16(&self, offset: usize) -> Result<i16,Error> {
+        return Ok(self.u16(offset)? as i16);
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32,Error> {
+        let bytes = self.bytes(offset, 4)?;
+        return Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+
+    fn i32(&self, offset: usize) -> Result<i32,Error> {
+        return Ok(self.u32(offset)? as i32);
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64,Error> {
+        let low = self.u32(offset)? as u64;
+        let high = self.u32(offset + 4)? as u64;
+        return Ok(low | (high << 32));
+    }
+
+    // in the usual registry spelling, without braces
+    fn guid(&self, offset: usize) -> Result<String,Error> {
+        let b = self.bytes(offset + 8, 8)?;
+        return Ok(format!("{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+            self.u32(offset)?, self.u16(offset + 4)?, self.u16(offset + 6)?, b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]));
+    }
+
+    // names and strings are in the library's ansi code page, which for anything but the odd doc
+    // string is plain ascii, so they're read as latin-1
+    fn string(&self, offset: usize, len: usize) -> Result<String,Error> {
+        return Ok(self.bytes(offset, len)?.iter().map(|&b| b as char).collect());
+    }
+
+    fn c_string(&self, offset: usize) -> Result<String,Error> {
+        let rest = self.data.get(offset..).ok_or_else(|| invalid(format!("string at {:#x} is past the end", offset)))?;
+        let len = rest.iter().position(|&b| b == 0).ok_or_else(|| invalid(format!("string at {:#x} isn't terminated", offset)))?;
+        return self.string(offset, len);
+    }
+}
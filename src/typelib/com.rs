@@ -0,0 +1,188 @@
+use windows::core::{BSTR, GUID};
+use windows::Win32::System::Com::{FUNCDESC, IDispatch, ITypeInfo, TYPEATTR, TYPEDESC, VARDESC};
+use crate::{ArrayBound, Error, hresult, InvokeKind, Variant};
+use crate::com::DEFAULT_LOCALE_ID;
+use super::{Function, ImplType, Param, PARAMFLAG_FHASDEFAULT, TypeDesc, TypeInfo, TypeKind, TypeRef, Variable, VT_CARRAY, VT_PTR, VT_SAFEARRAY, VT_USERDEFINED};
+
+//
+// reads the description a running object gives of itself through IDispatch::GetTypeInfo, into the
+// same model a type library is read into.  the types it refers to are only looked up far enough to
+// name them, see TypeRef::Named
+//
+
+// the memid GetDocumentation takes for the type itself rather than one of its members
+const MEMBERID_NIL: i32 = -1;
+const VAR_CONST: i32 = 2;
+
+pub(crate) fn read_type_info(dispatch: &IDispatch) -> Result<TypeInfo,Error> {
+    unsafe {
+        if dispatch.GetTypeInfoCount()? == 0 {
+            return Err(Error::com(hresult::E_NOTIMPL, "the object doesn't describe itself"));
+        }
+        return read_type(&dispatch.GetTypeInfo(0, DEFAULT_LOCALE_ID)?);
+    }
+}
+
+// every description handed out by an ITypeInfo has to be given back to it, so each is copied out
+// and released straight away
+unsafe fn read_type(info: &ITypeInfo) -> Result<TypeInfo,Error> {
+    let attr = info.GetTypeAttr()?;
+    let type_info = read_attributes(info, &*attr);
+    info.ReleaseTypeAttr(attr);
+    return type_info;
+}
+
+unsafe fn read_attributes(info: &ITypeInfo, attr: &TYPEATTR) -> Result<TypeInfo,Error> {
+    let (name, doc) = documentation(info, MEMBERID_NIL)?;
+    let kind = TypeKind::from_u16(attr.typekind.0 as u16)
+        .ok_or_else(|| Error::com(hresult::E_FAIL, format!("{} has unknown type kind {}", name, attr.typekind.0)))?;
+
+    let mut implements = Vec::new();
+    for i in 0..attr.cImplTypes as u32 {
+        implements.push(ImplType {
+            reference: type_ref(info, info.GetRefTypeOfImplType(i)?)?,
+            flags: info.GetImplTypeFlags(i)?.0 as u16,
+        });
+    }
+
+    let mut functions = Vec::new();
+    for i in 0..attr.cFuncs as u32 {
+        let desc = info.GetFuncDesc(i)?;
+        let function = read_function(info, &*desc);
+        info.ReleaseFuncDesc(desc);
+        functions.push(function?);
+    }
+
+    let mut variables = Vec::new();
+    for i in 0..attr.cVars as u32 {
+        let desc = info.GetVarDesc(i)?;
+        let variable = read_variable(info, &*desc);
+        info.ReleaseVarDesc(desc);
+        variables.push(variable?);
+    }
+
+    return Ok(TypeInfo {
+        name,
+        kind,
+        guid: guid(&attr.guid),
+        doc,
+        flags: attr.wTypeFlags,
+        major_version: attr.wMajorVerNum,
+        minor_version: attr.wMinorVerNum,
+        implements,
+        alias: if kind == TypeKind::Alias { Some(type_desc(info, &attr.tdescAlias)?) } else { None },
+        functions,
+        variables,
+    });
+}
+
+unsafe fn read_function(info: &ITypeInfo, desc: &FUNCDESC) -> Result<Function,Error> {
+    let param_count = desc.cParams.max(0) as usize;
+    // the function's name followed by its parameters', the value of a put isn't named
+    let mut names = vec![BSTR::new(); param_count + 1];
+    let mut name_count = 0;
+    info.GetNames(desc.memid, &mut names, &mut name_count)?;
+    let (_, doc) = documentation(info, desc.memid)?;
+
+    let invoke_kind = match desc.invkind.0 {
+        1 => InvokeKind::Method,
+        2 => InvokeKind::PropertyGet,
+        4 => InvokeKind::PropertyPut,
+        8 => InvokeKind::PropertyPutRef,
+        other => return Err(Error::com(hresult::E_FAIL, format!("{} has unknown invoke kind {}", names[0], other))),
+    };
+
+    let mut params = Vec::new();
+    for i in 0..param_count {
+        let elem = &*desc.lprgelemdescParam.add(i);
+        let param_desc = elem.Anonymous.paramdesc;
+        let flags = param_desc.wParamFlags.0;
+        let default = if flags & PARAMFLAG_FHASDEFAULT != 0 && !param_desc.pparamdescex.is_null() {
+            Some(Variant::try_from(&(*param_desc.pparamdescex).varDefaultValue)?)
+        } else {
+            None
+        };
+        params.push(Param {
+            name: if i + 1 < name_count as usize { names[i + 1].to_string() } else { String::new() },
+            type_desc: type_desc(info, &elem.tdesc)?,
+            flags,
+            default,
+        });
+    }
+
+    return Ok(Function {
+        name: names[0].to_string(),
+        memid: desc.memid,
+        invoke_kind,
+        doc,
+        return_type: type_desc(info, &desc.elemdescFunc.tdesc)?,
+        params,
+        optional_params: desc.cParamsOpt,
+        flags: desc.wFuncFlags.0,
+    });
+}
+
+unsafe fn read_variable(info: &ITypeInfo, desc: &VARDESC) -> Result<Variable,Error> {
+    let (name, doc) = documentation(info, desc.memid)?;
+    let value = if desc.varkind.0 == VAR_CONST && !desc.Anonymous.lpvarValue.is_null() {
+        Some(Variant::try_from(&*desc.Anonymous.lpvarValue)?)
+    } else {
+        None
+    };
+    return Ok(Variable {
+        name,
+        memid: desc.memid,
+        doc,
+        type_desc: type_desc(info, &desc.elemdescVar.tdesc)?,
+        flags: desc.wVarFlags.0,
+        value,
+    });
+}
+
+unsafe fn type_desc(info: &ITypeInfo, desc: &TYPEDESC) -> Result<TypeDesc,Error> {
+    let type_desc = match desc.vt.0 {
+        VT_PTR => TypeDesc::Ptr(Box::new(type_desc(info, &*desc.Anonymous.lptdesc)?)),
+        VT_SAFEARRAY => TypeDesc::SafeArray(Box::new(type_desc(info, &*desc.Anonymous.lptdesc)?)),
+        VT_CARRAY => {
+            let array = &*desc.Anonymous.lpadesc;
+            // rgbounds is declared with one element but really has cDims
+            let bounds = std::slice::from_raw_parts(array.rgbounds.as_ptr(), array.cDims as usize);
+            let bounds = bounds.iter().map(|b| ArrayBound::new(b.lLbound, b.cElements)).collect();
+            TypeDesc::CArray(Box::new(type_desc(info, &array.tdescElem)?), bounds)
+        },
+        VT_USERDEFINED => TypeDesc::UserDefined(type_ref(info, desc.Anonymous.hreftype)?),
+        vt => TypeDesc::Base(vt),
+    };
+    return Ok(type_desc);
+}
+
+// only the name and guid of a referenced type are read, describing it fully could mean walking
+// everything the object's library and the ones it imports define
+unsafe fn type_ref(info: &ITypeInfo, href: u32) -> Result<TypeRef,Error> {
+    let referenced = info.GetRefTypeInfo(href)?;
+    let (name, _) = documentation(&referenced, MEMBERID_NIL)?;
+    let attr = referenced.GetTypeAttr()?;
+    let guid = guid(&(*attr).guid);
+    referenced.ReleaseTypeAttr(attr);
+    return Ok(TypeRef::Named { name, guid });
+}
+
+// the name and doc string of the type or one of its members
+unsafe fn documentation(info: &ITypeInfo, memid: i32) -> Result<(String, Option<String>),Error> {
+    let mut name = BSTR::new();
+    let mut doc = BSTR::new();
+    let mut help_context = 0;
+    info.GetDocumentation(memid, Some(&mut name), Some(&mut doc), &mut help_context, None)?;
+    return Ok((name.to_string(), Some(doc.to_string()).filter(|doc| !doc.is_empty())));
+}
+
+// in the usual registry spelling without braces, like the type library readers, and None for a
+// type without one
+fn guid(guid: &GUID) -> Option<String> {
+    if *guid == GUID::zeroed() {
+        return None;
+    }
+    let d = guid.data4;
+    return Some(format!("{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+        guid.data1, guid.data2, guid.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]));
+}
@@ -0,0 +1,367 @@
+use crate::{ArrayBound, Currency, Error, InvokeKind, OleDate, Variant};
+use super::{ImplType, ImportedLibrary, integer_variant, invalid, Function, Param, PARAMFLAG_FHASDEFAULT, Reader, TypeDesc, TypeInfo, TypeKind, TypeLibrary, TypeRef, Variable, VT_CARRAY, VT_PTR, VT_SAFEARRAY, VT_USERDEFINED};
+
+//
+// the MSFT format: a fixed header, the offsets of the type infos, then a directory of segments
+// (tables of type infos, names, strings, guids, type descriptions, constants and so on) that
+// everything else points into by offset
+//
+
+const HEADER_SIZE: usize = 0x54;
+// set in the header's varflags when a help dll offset follows the header
+const HELPDLLFLAG: i32 = 0x100;
+const SEGMENT_COUNT: usize = 15;
+const TYPEINFO_SIZE: usize = 0x64;
+// where a function or variable record keeps its doc string, when the record is long enough to
+const FUNC_RECORD_HELPSTRING: usize = 0x1c;
+const VAR_RECORD_HELPSTRING: usize = 0x18;
+const PARAM_INFO_SIZE: usize = 12;
+// set in a function record's FKCCIC when the parameters have default values
+const FKCCIC_HAS_DEFAULTS: i32 = 0x1000;
+// set in an import's flags when it points at the type's guid rather than its index
+const IMPINFO_OFFSET_IS_GUID: i32 = 0x10000;
+const VAR_CONST: i16 = 2;
+// type descriptions nest (pointers to pointers to ...), this is far deeper than any real one
+const MAX_TYPE_DEPTH: usize = 16;
+
+// the segments, in directory order
+const TYPEINFO_TABLE: usize = 0;
+const IMPORT_INFO: usize = 1;
+const IMPORT_FILES: usize = 2;
+const REF_TABLE: usize = 3;
+const GUID_TABLE: usize = 5;
+const NAME_TABLE: usize = 7;
+const STRING_TABLE: usize = 8;
+const TYPE_DESCS: usize = 9;
+const ARRAY_DESCS: usize = 10;
+const CUSTOM_DATA: usize = 11;
+
+struct Msft<'a> {
+    file: Reader<'a>,
+    segments: Vec<Reader<'a>>,
+    type_count: usize,
+}
+
+pub(super) fn parse(data: &[u8]) -> Result<TypeLibrary,Error> {
+    let file = Reader::new(data);
+    let varflags = file.i32(0x14)?;
+    let type_count = usize::try_from(file.i32(0x20)?).map_err(|_| invalid("negative type info count"))?;
+
+    let mut directory = HEADER_SIZE + type_count * 4;
+    if varflags & HELPDLLFLAG != 0 {
+        directory += 4;
+    }
+    let mut segments = Vec::new();
+    for i in 0..SEGMENT_COUNT {
+        let offset = file.i32(directory + i * 16)?;
+        let length = file.i32(directory + i * 16 + 4)?;
+        // an unused segment has an offset of -1
+        let segment = match (usize::try_from(offset), usize::try_from(length)) {
+            (Ok(offset), Ok(length)) => file.slice(offset, length)?,
+            _ => Reader::new(&[]),
+        };
+        segments.push(segment);
+    }
+
+    let msft = Msft { file, segments, type_count };
+    let version = file.u32(0x18)?;
+    let mut types = Vec::new();
+    for i in 0..type_count {
+        types.push(msft.type_info(i)?);
+    }
+    return Ok(TypeLibrary {
+        name: msft.name(file.i32(0x38)?)?,
+        doc: msft.string(file.i32(0x24)?)?,
+        guid: msft.guid(file.i32(0x08)?)?,
+        major_version: version as u16,
+        minor_version: (version >> 16) as u16,
+        lcid: file.u32(0x0c)?,
+        types,
+    });
+}
+
+// the offsets stored in the file, where -1 (or any negative value) means there isn't one
+fn offset(value: i32) -> Option<usize> {
+    return usize::try_from(value).ok();
+}
+
+impl<'a> Msft<'a> {
+    fn segment(&self, index: usize) -> Reader<'a> {
+        return self.segments[index];
+    }
+
+    // a name table entry is the hreftype it belongs to, the next entry with the same hash, then its
+    // length in the low byte of a dword and the characters
+    fn name(&self, offset: i32) -> Result<String,Error> {
+        let offset = self::offset(offset).ok_or_else(|| invalid("missing name"))?;
+        let names = self.segment(NAME_TABLE);
+        let len = names.u32(offset + 8)? & 0xff;
+        return names.string(offset + 12, len as usize);
+    }
+
+    // a string table entry is its length followed by the characters
+    fn string(&self, offset: i32) -> Result<Option<String>,Error> {
+        let Some(offset) = self::offset(offset) else {
+            return Ok(None);
+        };
+        let strings = self.segment(STRING_TABLE);
+        let len = strings.i16(offset)?;
+        if len <= 0 {
+            return Ok(None);
+        }
+        return Ok(Some(strings.string(offset + 2, len as usize)?));
+    }
+
+    fn guid(&self, offset: i32) -> Result<Option<String>,Error> {
+        return match self::offset(offset) {
+            Some(offset) => Ok(Some(self.segment(GUID_TABLE).guid(offset)?)),
+            None => Ok(None),
+        };
+    }
+
+    fn type_info(&self, index: usize) -> Result<TypeInfo,Error> {
+        let table = self.segment(TYPEINFO_TABLE);
+        let base = index * TYPEINFO_SIZE;
+        let kind = TypeKind::from_u16((table.u32(base)? & 0xf) as u16)
+            .ok_or_else(|| invalid(format!("type info {} has an unknown kind", index)))?;
+        let elements = table.u32(base + 0x18)?;
+        let version = table.u32(base + 0x38)?;
+        let impl_count = table.i16(base + 0x4c)?;
+        let datatype1 = table.i32(base + 0x54)?;
+
+        let mut implements = Vec::new();
+        let mut alias = None;
+        match kind {
+            TypeKind::CoClass => {
+                // a linked list of records in the ref table
+                let refs = self.segment(REF_TABLE);
+                let mut next = datatype1;
+                for _ in 0..impl_count {
+                    let Some(record) = offset(next) else {
+                        break;
+                    };
+                    implements.push(ImplType {
+                        reference: self.type_ref(refs.i32(record)?)?,
+                        flags: refs.u32(record + 4)? as u16,
+                    });
+                    next = refs.i32(record + 12)?;
+                }
+            },
+            TypeKind::Interface | TypeKind::Dispatch if datatype1 != -1 => {
+                implements.push(ImplType { reference: self.type_ref(datatype1)?, flags: 0 });
+            },
+            TypeKind::Alias => alias = Some(self.type_desc(datatype1, 0)?),
+            _ => {},
+        }
+
+        let mut info = TypeInfo {
+            name: self.name(table.i32(base + 0x34)?)?,
+            kind,
+            guid: self.guid(table.i32(base + 0x2c)?)?,
+            doc: self.string(table.i32(base + 0x3c)?)?,
+            flags: table.u32(base + 0x30)? as u16,
+            major_version: version as u16,
+            minor_version: (version >> 16) as u16,
+            implements,
+            alias,
+            functions: Vec::new(),
+            variables: Vec::new(),
+        };
+        let function_count = (elements & 0xffff) as usize;
+        let variable_count = (elements >> 16) as usize;
+        if function_count + variable_count > 0 {
+            let members = offset(table.i32(base + 0x04)?).ok_or_else(|| invalid(format!("{} has no member data", info.name)))?;
+            self.members(&mut info, members, function_count, variable_count)?;
+        }
+        return Ok(info);
+    }
+
+    // the member data is the length of the records, a record per function then per variable, and
+    // then three arrays with an entry per member: the memids, the name offsets and the record offsets
+    fn members(&self, info: &mut TypeInfo, offset: usize, function_count: usize, variable_count: usize) -> Result<(),Error> {
+        let file = self.file;
+        let records_len = usize::try_from(file.i32(offset)?).map_err(|_| invalid("negative member data length"))?;
+        let count = function_count + variable_count;
+        let memid = |i: usize| file.i32(offset + 4 + records_len + i * 4);
+        let name_offset = |i: usize| file.i32(offset + 4 + records_len + (count + i) * 4);
+
+        let mut record = offset + 4;
+        for i in 0..function_count {
+            let len = (file.u32(record)? & 0xffff) as usize;
+            let fkccic = file.i32(record + 16)?;
+            let param_count = file.i16(record + 20)?.max(0) as usize;
+            // the parameters come at the end of the record, after their default values if they have any
+            let has_defaults = fkccic & FKCCIC_HAS_DEFAULTS != 0;
+            let params_len = param_count * (PARAM_INFO_SIZE + if has_defaults { 4 } else { 0 });
+            let fixed_len = len.checked_sub(params_len).ok_or_else(|| invalid(format!("function record at {:#x} is too short", record)))?;
+            let invoke_kind = match (fkccic >> 3) & 0xf {
+                1 => InvokeKind::Method,
+                2 => InvokeKind::PropertyGet,
+                4 => InvokeKind::PropertyPut,
+                8 => InvokeKind::PropertyPutRef,
+                other => return Err(invalid(format!("unknown invoke kind {}", other))),
+            };
+
+            // the second function of a property's get/put pair sometimes leaves out the name
+            let name = match name_offset(i)? {
+                -1 if i > 0 && info.functions[i - 1].invoke_kind != InvokeKind::Method && invoke_kind != InvokeKind::Method => {
+                    info.functions[i - 1].name.clone()
+                },
+                name => self.name(name)?,
+            };
+
+            let param_infos = record + len - param_count * PARAM_INFO_SIZE;
+            let defaults = param_infos - param_count * 4;
+            let mut params = Vec::new();
+            for j in 0..param_count {
+                let param = param_infos + j * PARAM_INFO_SIZE;
+                let flags = file.u32(param + 8)? as u16;
+                let default = if has_defaults && flags & PARAMFLAG_FHASDEFAULT != 0 {
+                    Some(self.value(file.i32(defaults + j * 4)?)?)
+                } else {
+                    None
+                };
+                params.push(Param {
+                    name: match file.i32(param + 4)? {
+                        -1 => String::new(),
+                        name => self.name(name)?,
+                    },
+                    type_desc: self.type_desc(file.i32(param)?, 0)?,
+                    flags,
+                    default,
+                });
+            }
+
+            info.functions.push(Function {
+                name,
+                memid: memid(i)?,
+                invoke_kind,
+                doc: if fixed_len > FUNC_RECORD_HELPSTRING { self.string(file.i32(record + FUNC_RECORD_HELPSTRING)?)? } else { None },
+                return_type: self.type_desc(file.i32(record + 4)?, 0)?,
+                params,
+                optional_params: file.i16(record + 22)?,
+                flags: file.u32(record + 8)? as u16,
+            });
+            record += len;
+        }
+
+        for i in function_count..count {
+            let len = (file.u32(record)? & 0xffff) as usize;
+            let value = match file.i16(record + 12)? {
+                VAR_CONST => Some(self.value(file.i32(record + 16)?)?),
+                _ => None,
+            };
+            info.variables.push(Variable {
+                name: self.name(name_offset(i)?)?,
+                memid: memid(i)?,
+                doc: if len > VAR_RECORD_HELPSTRING { self.string(file.i32(record + VAR_RECORD_HELPSTRING)?)? } else { None },
+                type_desc: self.type_desc(file.i32(record + 4)?, 0)?,
+                flags: file.u32(record + 8)? as u16,
+                value,
+            });
+            record += len;
+        }
+        return Ok(());
+    }
+
+    // a negative type is a plain vt in its low bits, anything else is an offset into the type
+    // descriptions, each a vt followed by either the type it wraps (in the same encoding), an
+    // hreftype or an offset to an array description
+    fn type_desc(&self, type_desc: i32, depth: usize) -> Result<TypeDesc,Error> {
+        if type_desc < 0 {
+            return Ok(TypeDesc::Base((type_desc & 0xfff) as u16));
+        }
+        if depth > MAX_TYPE_DEPTH {
+            return Err(invalid("type description nests too deeply"));
+        }
+        let descs = self.segment(TYPE_DESCS);
+        let offset = type_desc as usize;
+        let vt = descs.u16(offset)? & 0xfff;
+        let inner = descs.i32(offset + 4)?;
+        let type_desc = match vt {
+            VT_PTR => TypeDesc::Ptr(Box::new(self.type_desc(inner, depth + 1)?)),
+            VT_SAFEARRAY => TypeDesc::SafeArray(Box::new(self.type_desc(inner, depth + 1)?)),
+            VT_USERDEFINED => TypeDesc::UserDefined(self.type_ref(inner)?),
+            VT_CARRAY => {
+                // the element type, the number of dimensions, then a count and lower bound for each
+                let arrays = self.segment(ARRAY_DESCS);
+                let array = offset_or_invalid(inner)?;
+                let element = self.type_desc(arrays.i32(array)?, depth + 1)?;
+                let mut bounds = Vec::new();
+                for i in 0..arrays.u16(array + 4)? as usize {
+                    bounds.push(ArrayBound::new(arrays.i32(array + 12 + i * 8)?, arrays.u32(array + 8 + i * 8)?));
+                }
+                TypeDesc::CArray(Box::new(element), bounds)
+            },
+            _ => TypeDesc::Base(vt),
+        };
+        return Ok(type_desc);
+    }
+
+    // an hreftype is either the offset of one of this library's type infos, or with the low bit set
+    // the offset of an import info: its flags, the offset of the file it's from, then either the
+    // type's guid or its index in that file
+    fn type_ref(&self, href: i32) -> Result<TypeRef,Error> {
+        let href = offset_or_invalid(href)?;
+        if href & 3 == 0 {
+            let index = href / TYPEINFO_SIZE;
+            if index >= self.type_count {
+                return Err(invalid(format!("reference to type info {} of {}", index, self.type_count)));
+            }
+            return Ok(TypeRef::Local(index));
+        }
+
+        let imports = self.segment(IMPORT_INFO);
+        let import = href & !3;
+        let flags = imports.i32(import)?;
+        let target = imports.i32(import + 8)?;
+
+        // the file is the guid of the library, its lcid and version, then the name's length (times
+        // four) and the name
+        let files = self.segment(IMPORT_FILES);
+        let file = offset_or_invalid(imports.i32(import + 4)?)?;
+        let name_len = (files.u16(file + 12)? >> 2) as usize;
+        let library = ImportedLibrary {
+            name: files.string(file + 14, name_len)?,
+            guid: self.guid(files.i32(file)?)?,
+            major_version: files.u16(file + 8)?,
+            minor_version: files.u16(file + 10)?,
+        };
+        return Ok(if flags & IMPINFO_OFFSET_IS_GUID != 0 {
+            TypeRef::Imported { library, guid: self.guid(target)?, index: None }
+        } else {
+            TypeRef::Imported { library, guid: None, index: Some(target as u32) }
+        });
+    }
+
+    // a constant is either packed into the offset (a negative one holds the vt in bits 26 to 30 and
+    // the value in the rest) or is an offset into the custom data, a vt followed by the value
+    fn value(&self, value: i32) -> Result<Variant,Error> {
+        if value < 0 {
+            return integer_variant(((value & 0x7c00_0000) >> 26) as u16, (value & 0x03ff_ffff) as i64);
+        }
+        let data = self.segment(CUSTOM_DATA);
+        let offset = value as usize;
+        let vt = data.u16(offset)?;
+        let variant = match vt {
+            4 => Variant::R4(f32::from_bits(data.u32(offset + 2)?)),
+            5 => Variant::R8(f64::from_bits(data.u64(offset + 2)?)),
+            6 => Variant::Cy(Currency::from_raw(data.u64(offset + 2)? as i64)),
+            7 => Variant::Date(OleDate::from_raw(f64::from_bits(data.u64(offset + 2)?))),
+            8 => {
+                let len = data.i32(offset + 2)?;
+                match usize::try_from(len) {
+                    Ok(len) => Variant::Bstr(data.string(offset + 6, len)?),
+                    Err(_) => Variant::Bstr(String::new()),
+                }
+            },
+            20 | 21 => integer_variant(vt, data.u64(offset + 2)? as i64)?,
+            _ => integer_variant(vt, data.i32(offset + 2)? as i64)?,
+        };
+        return Ok(variant);
+    }
+}
+
+fn offset_or_invalid(value: i32) -> Result<usize,Error> {
+    return offset(value).ok_or_else(|| invalid(format!("invalid offset {}", value)));
+}
@@ -0,0 +1,516 @@
+use crate::{ArrayBound, Error, InvokeKind, Variant};
+use super::{ImplType, ImportedLibrary, integer_variant, invalid, Function, Param, PARAMFLAG_FIN, PARAMFLAG_FLCID, PARAMFLAG_FOPT, PARAMFLAG_FOUT, PARAMFLAG_FRETVAL, Reader, TypeDesc, TypeInfo, TypeKind, TypeLibrary, TypeRef, Variable, TYPEFLAG_FDUAL, VARFLAG_FREADONLY, VT_CARRAY, VT_LPSTR, VT_LPWSTR, VT_PTR, VT_SAFEARRAY, VT_USERDEFINED};
+
+//
+// the SLTG format: a header, a directory of blocks (one per type info, then the library block
+// last) and the blocks themselves.  the library block is followed by a summary of every type info
+// and the name table, which everything refers into by offset
+//
+
+const HEADER_SIZE: usize = 0x24;
+const BLOCK_ENTRY_SIZE: usize = 8;
+// a 1 followed by "CompObj\0" and "dir\0"
+const MAGIC_SIZE: usize = 13;
+const INDEX_STRING_SIZE: usize = 11;
+const PAD_SIZE: usize = 9;
+const LIBRARY_MAGIC: u16 = 0x51cc;
+const TYPEINFO_MAGIC: u16 = 0x0501;
+const IMPL_MAGIC: u16 = 0x004a;
+const REF_MAGIC: u8 = 0xdf;
+const NULL_GUID: &str = "00000000-0000-0000-0000-000000000000";
+const FUNCTION_MAGICS: [u8; 3] = [0x4c, 0xcb, 0x8b];
+// or'ed into a function's magic when its flags follow it
+const FUNCTION_FLAGS_PRESENT: u8 = 0x20;
+const VAR_MAGIC: u8 = 0x0a;
+const VAR_WITH_FLAGS_MAGIC: u8 = 0x2a;
+// a variable's flags
+const VAR_TYPE_INLINE: u8 = 0x02;
+const VAR_VALUE_INLINE: u8 = 0x08;
+const VAR_CONST: u8 = 0x10;
+const VAR_DISPATCH: u8 = 0x40;
+const VAR_READONLY: u8 = 0x80;
+// a name that's the same as the previous variable's
+const SAME_NAME: u16 = 0xfffe;
+const NONE: u16 = 0xffff;
+const MAX_TYPE_DEPTH: usize = 16;
+
+// the offsets of a type info's header, its member header and the tail after its members
+mod header {
+    pub const HREF_TABLE: usize = 0x02;
+    pub const ELEM_TABLE: usize = 0x0a;
+    pub const MAJOR_VERSION: usize = 0x12;
+    pub const MINOR_VERSION: usize = 0x14;
+    pub const TYPEFLAGS1: usize = 0x1a;
+    pub const TYPEFLAGS2: usize = 0x1b;
+    pub const TYPEKIND: usize = 0x1d;
+    pub const MEMBER_HEADER_SIZE: usize = 9;
+    pub const MEMBER_EXTRA: usize = 5;
+}
+
+mod tail {
+    pub const FUNCTION_COUNT: usize = 0x00;
+    pub const VARIABLE_COUNT: usize = 0x02;
+    pub const FUNCTIONS: usize = 0x08;
+    pub const VARIABLES: usize = 0x0a;
+    pub const ALIAS: usize = 0x14;
+    pub const SIMPLE_ALIAS: usize = 0x1c;
+}
+
+// what the library block's summary says about a type info
+struct Summary {
+    index_name: String,
+    name: u16,
+    guid: Option<String>,
+}
+
+pub(super) fn parse(data: &[u8]) -> Result<TypeLibrary,Error> {
+    let file = Reader::new(data);
+    let block_count = file.u16(0x04)? as usize;
+    if block_count < 2 {
+        return Err(invalid("no library block"));
+    }
+    let magic = HEADER_SIZE + (block_count - 1) * BLOCK_ENTRY_SIZE;
+    if file.bytes(magic + 1, 12)? != b"CompObj\0dir\0" {
+        return Err(invalid("missing CompObj directory"));
+    }
+    let first_block = magic + MAGIC_SIZE + (block_count - 1) * INDEX_STRING_SIZE + PAD_SIZE;
+
+    // the blocks are in the file in the order of the chain through the directory, the type infos
+    // first and the library last
+    let mut blocks = Vec::new();
+    let mut offset = first_block;
+    let mut entry = file.u16(0x0a)? as usize;
+    while entry != 0 {
+        if entry > block_count - 1 || blocks.len() >= block_count - 1 {
+            return Err(invalid("block directory isn't a chain"));
+        }
+        let entry_offset = HEADER_SIZE + (entry - 1) * BLOCK_ENTRY_SIZE;
+        let len = file.u32(entry_offset)? as usize;
+        let index_name = file.c_string(magic + file.u16(entry_offset + 4)? as usize)?;
+        blocks.push((offset, len, index_name));
+        offset += len;
+        entry = file.u16(entry_offset + 6)? as usize;
+    }
+    let type_count = block_count - 2;
+    let (library_offset, _, _) = *blocks.last().ok_or_else(|| invalid("no library block"))?;
+    if blocks.len() != type_count + 1 {
+        return Err(invalid(format!("{} blocks for {} type infos", blocks.len(), type_count)));
+    }
+
+    // the library block runs to the end of the file, its summaries and name table come after it
+    let library = file.rest(library_offset)?;
+    if library.u16(0)? != LIBRARY_MAGIC {
+        return Err(invalid("library block has the wrong magic"));
+    }
+    let mut pos = 6;
+    read_string(&library, &mut pos)?;
+    let doc = read_string(&library, &mut pos)?;
+    let _help_file = read_string(&library, &mut pos)?;
+    // the help context, syskind, lcid, a dword, the library flags, version and guid
+    let lcid = library.u16(pos + 6)? as u32;
+    let major_version = library.u16(pos + 14)?;
+    let minor_version = library.u16(pos + 16)?;
+    let guid = library.guid(pos + 18)?;
+    pos += 34;
+
+    // 0x40 bytes of something, then a summary per type info
+    pos += 0x40;
+    let mut summaries = Vec::new();
+    for _ in 0..type_count {
+        let index_len = library.u16(pos + 2)?;
+        let mut extra = 0;
+        let index_name = if index_len != NONE {
+            extra += index_len as usize;
+            library.string(pos + 4, index_len as usize)?
+        } else {
+            String::new()
+        };
+        let other_len = library.u16(pos + 4 + extra)?;
+        if other_len != NONE {
+            extra += other_len as usize;
+        }
+        let name = library.u16(pos + 8 + extra)?;
+        let more_bytes = library.u16(pos + 10 + extra)? as usize;
+        extra += more_bytes;
+        // a type without a guid has one of zeros rather than none at all
+        let guid = Some(library.guid(pos + 20 + extra)?).filter(|guid| guid != NULL_GUID);
+        summaries.push(Summary { index_name, name, guid });
+        pos += 38 + extra;
+    }
+
+    // then a word and the offset from the library block to what precedes the name table
+    let mut names = library.u32(pos + 2)? as usize;
+    if library.u16(names)? == 0x0200 {
+        names += 0x20;
+    }
+    names += 0x216 + 2;
+    let names = library.rest(names)?;
+    let sltg = Sltg { names };
+
+    let mut types = Vec::new();
+    for (i, (offset, len, index_name)) in blocks[..type_count].iter().enumerate() {
+        let summary = &summaries[i];
+        if summary.index_name != *index_name {
+            return Err(invalid(format!("type info {} is {} in the directory but {} in the library block", i, index_name, summary.index_name)));
+        }
+        types.push(sltg.type_info(file.slice(*offset, *len)?, summary, type_count)?);
+    }
+
+    return Ok(TypeLibrary {
+        name: sltg.name(library.u16(4)?)?,
+        doc,
+        guid: Some(guid),
+        major_version,
+        minor_version,
+        lcid,
+        types,
+    });
+}
+
+// a string is its length then the characters, or just 0xffff when there isn't one
+fn read_string(reader: &Reader, pos: &mut usize) -> Result<Option<String>,Error> {
+    let len = reader.u16(*pos)?;
+    *pos += 2;
+    if len == NONE {
+        return Ok(None);
+    }
+    let string = reader.string(*pos, len as usize)?;
+    *pos += len as usize;
+    return Ok(Some(string));
+}
+
+struct Sltg<'a> {
+    names: Reader<'a>,
+}
+
+// the references a type info makes to other types, user defined types refer to these by index
+struct Refs {
+    refs: Vec<TypeRef>,
+}
+
+impl Refs {
+    fn get(&self, index: usize) -> Result<TypeRef,Error> {
+        return self.refs.get(index).cloned().ok_or_else(|| invalid(format!("reference {} of {}", index, self.refs.len())));
+    }
+}
+
+impl<'a> Sltg<'a> {
+    fn name(&self, offset: u16) -> Result<String,Error> {
+        return self.names.c_string(offset as usize);
+    }
+
+    fn type_info(&self, block: Reader, summary: &Summary, type_count: usize) -> Result<TypeInfo,Error> {
+        if block.u16(0)? != TYPEINFO_MAGIC {
+            return Err(invalid("type info block has the wrong magic"));
+        }
+        let name = self.name(summary.name)?;
+        let header_kind = TypeKind::from_u16(block.u8(header::TYPEKIND)? as u16)
+            .ok_or_else(|| invalid(format!("{} has an unknown kind", name)))?;
+        let flags = (block.u8(header::TYPEFLAGS1)? >> 3) as u16 | ((block.u8(header::TYPEFLAGS2)? as u16) << 5);
+
+        let refs = match block.u32(header::HREF_TABLE)? {
+            0xffff_ffff => Refs { refs: Vec::new() },
+            offset => self.refs(&block, offset as usize, type_count)?,
+        };
+
+        // the members, then the tail which says where each kind of member starts
+        let member_header = block.u32(header::ELEM_TABLE)? as usize;
+        let members = block.rest(member_header + header::MEMBER_HEADER_SIZE)?;
+        let tail = members.slice(block.u32(member_header + header::MEMBER_EXTRA)? as usize, 0x36)?;
+        let function_count = tail.u16(tail::FUNCTION_COUNT)? as usize;
+        let variable_count = tail.u16(tail::VARIABLE_COUNT)? as usize;
+        let functions_offset = tail.u16(tail::FUNCTIONS)?;
+        let variables_offset = tail.u16(tail::VARIABLES)?;
+
+        let mut info = TypeInfo {
+            name,
+            // a dual interface is reported as the dispinterface, the same as oleaut32 does
+            kind: if flags & TYPEFLAG_FDUAL != 0 { TypeKind::Dispatch } else { header_kind },
+            guid: summary.guid.clone(),
+            doc: None,
+            flags,
+            major_version: block.u16(header::MAJOR_VERSION)?,
+            minor_version: block.u16(header::MINOR_VERSION)?,
+            implements: Vec::new(),
+            alias: None,
+            functions: Vec::new(),
+            variables: Vec::new(),
+        };
+
+        match header_kind {
+            TypeKind::CoClass | TypeKind::Interface if members.u16(0)? == IMPL_MAGIC => {
+                info.implements = self.impl_types(&members, &refs)?;
+            },
+            TypeKind::Alias => {
+                let alias = tail.u16(tail::ALIAS)?;
+                info.alias = Some(if tail.u16(tail::SIMPLE_ALIAS)? != 0 {
+                    TypeDesc::Base(alias)
+                } else {
+                    self.type_desc(&members, &mut (alias as usize), &refs, 0)?
+                });
+            },
+            _ => {},
+        }
+        if variable_count > 0 && variables_offset != NONE && header_kind != TypeKind::CoClass {
+            info.variables = self.variables(&members, variables_offset as usize, variable_count, &refs)?;
+        }
+        if function_count > 0 && functions_offset != NONE && header_kind != TypeKind::CoClass {
+            info.functions = self.functions(&members, functions_offset as usize, function_count, &refs)?;
+        }
+        return Ok(info);
+    }
+
+    // the ref table is a header, 8 bytes a reference, a few more bytes and then a string for each
+    // reference, "*\R<library>*#<index>" in hex where a library of ffff is this one and anything
+    // else is the offset of "*\G{<guid>}#<major>.<minor>#<lcid>#<path>#" in the name table
+    fn refs(&self, block: &Reader, offset: usize, type_count: usize) -> Result<Refs,Error> {
+        if block.u8(offset)? != REF_MAGIC {
+            return Err(invalid("reference table has the wrong magic"));
+        }
+        let number = block.u32(offset + 0x44)? as usize;
+        let mut pos = offset + 0x4f + number;
+        let mut refs = Vec::new();
+        for _ in 0..number / 8 {
+            let reference = read_string(block, &mut pos)?.unwrap_or_default();
+            let parsed = reference.strip_prefix("*\\R")
+                .and_then(|rest| rest.split_once("*#"))
+                .and_then(|(library, index)| Some((u32::from_str_radix(library, 16).ok()?, u32::from_str_radix(index, 16).ok()?)));
+            let Some((library, index)) = parsed else {
+                return Err(invalid(format!("can't make sense of reference {}", reference)));
+            };
+            if library == 0xffff {
+                if index as usize >= type_count {
+                    return Err(invalid(format!("reference to type info {} of {}", index, type_count)));
+                }
+                refs.push(TypeRef::Local(index as usize));
+            } else {
+                refs.push(TypeRef::Imported { library: self.imported_library(library as usize)?, guid: None, index: Some(index) });
+            }
+        }
+        return Ok(Refs { refs });
+    }
+
+    fn imported_library(&self, offset: usize) -> Result<ImportedLibrary,Error> {
+        let spec = self.names.c_string(offset)?;
+        let invalid_spec = || invalid(format!("can't make sense of imported library {}", spec));
+        let rest = spec.strip_prefix("*\\G{").ok_or_else(invalid_spec)?;
+        let (guid, rest) = rest.split_once("}#").ok_or_else(invalid_spec)?;
+        let mut parts = rest.trim_end_matches('#').splitn(3, '#');
+        let (major, minor) = parts.next().and_then(|v| v.split_once('.')).ok_or_else(invalid_spec)?;
+        let _lcid = parts.next();
+        let path = parts.next().ok_or_else(invalid_spec)?;
+        return Ok(ImportedLibrary {
+            // just the file name, the path is wherever it was on the machine that built it
+            name: path.rsplit(['\\', '/']).next().unwrap_or(path).to_string(),
+            guid: Some(guid.to_uppercase()),
+            major_version: major.parse().map_err(|_| invalid_spec())?,
+            minor_version: minor.parse().map_err(|_| invalid_spec())?,
+        });
+    }
+
+    // a chain of impl infos, each 0x16 bytes with the offset of the next one
+    fn impl_types(&self, members: &Reader, refs: &Refs) -> Result<Vec<ImplType>,Error> {
+        let mut impl_types = Vec::new();
+        let mut pos = 0;
+        loop {
+            impl_types.push(ImplType {
+                reference: refs.get(members.u16(pos + 0x0a)? as usize)?,
+                flags: members.u8(pos + 0x06)? as u16,
+            });
+            let next = members.u16(pos + 0x02)?;
+            if next == NONE || impl_types.len() > refs.refs.len() {
+                break;
+            }
+            pos = next as usize;
+        }
+        return Ok(impl_types);
+    }
+
+    fn functions(&self, members: &Reader, offset: usize, count: usize, refs: &Refs) -> Result<Vec<Function>,Error> {
+        let mut functions = Vec::new();
+        let mut pos = offset;
+        for _ in 0..count {
+            let magic = members.u8(pos)?;
+            if !FUNCTION_MAGICS.contains(&(magic & !FUNCTION_FLAGS_PRESENT)) {
+                return Err(invalid(format!("function has the wrong magic {:#x}", magic)));
+            }
+            let name = self.name(members.u16(pos + 0x04)?)?;
+            let invoke_kind = match members.u8(pos + 0x01)? >> 4 {
+                1 => InvokeKind::Method,
+                2 => InvokeKind::PropertyGet,
+                4 => InvokeKind::PropertyPut,
+                8 => InvokeKind::PropertyPutRef,
+                other => return Err(invalid(format!("unknown invoke kind {}", other))),
+            };
+            let param_count = (members.u8(pos + 0x10)? >> 3) as usize;
+            let ret_next_opt = members.u8(pos + 0x11)?;
+            let optional_params = ((ret_next_opt & 0x7e) >> 1) as i16;
+
+            // the return type is either right there or at an offset
+            let mut return_type = if ret_next_opt & 0x80 != 0 { pos + 0x12 } else { members.u16(pos + 0x12)? as usize };
+            let (_, return_type) = self.element(members, &mut return_type, refs)?;
+
+            // each parameter is a name followed by either its type or the offset of its type.  which
+            // one is given away by the name pointing at its second letter when the type follows
+            let mut params = Vec::new();
+            let mut arg = members.u16(pos + 0x0e)? as usize;
+            for i in 0..param_count {
+                let name_offset = members.u16(arg)?;
+                let (name, type_follows) = match name_offset {
+                    NONE => (None, true),
+                    0xfffe => (None, false),
+                    _ => {
+                        let before = self.names.u8((name_offset as usize).saturating_sub(1))?;
+                        if before != 0 && !before.is_ascii_alphanumeric() {
+                            (Some(name_offset as usize), false)
+                        } else {
+                            (Some(name_offset as usize - 1), true)
+                        }
+                    },
+                };
+                arg += 2;
+                let (mut flags, type_desc) = if type_follows {
+                    self.element(members, &mut arg, refs)?
+                } else {
+                    let mut type_offset = members.u16(arg)? as usize;
+                    arg += 2;
+                    self.element(members, &mut type_offset, refs)?
+                };
+                if (param_count - i) as i16 <= optional_params {
+                    flags |= PARAMFLAG_FOPT;
+                }
+                params.push(Param {
+                    name: match name {
+                        Some(offset) => self.names.c_string(offset)?,
+                        None => String::new(),
+                    },
+                    type_desc,
+                    flags,
+                    default: None,
+                });
+            }
+
+            functions.push(Function {
+                name,
+                memid: members.i32(pos + 0x06)?,
+                invoke_kind,
+                doc: None,
+                return_type,
+                params,
+                optional_params,
+                flags: if magic & FUNCTION_FLAGS_PRESENT != 0 { members.u16(pos + 0x16)? } else { 0 },
+            });
+            pos = members.u16(pos + 0x02)? as usize;
+        }
+        return Ok(functions);
+    }
+
+    fn variables(&self, members: &Reader, offset: usize, count: usize, refs: &Refs) -> Result<Vec<Variable>,Error> {
+        let mut variables: Vec<Variable> = Vec::new();
+        let mut pos = offset;
+        for _ in 0..count {
+            let magic = members.u8(pos)?;
+            if magic != VAR_MAGIC && magic != VAR_WITH_FLAGS_MAGIC {
+                return Err(invalid(format!("variable has the wrong magic {:#x}", magic)));
+            }
+            let var_flags = members.u8(pos + 0x01)?;
+            let name = match members.u16(pos + 0x04)? {
+                SAME_NAME => variables.last().map(|v| v.name.clone()).unwrap_or_default(),
+                name => self.name(name)?,
+            };
+            let mut type_offset = if var_flags & VAR_TYPE_INLINE != 0 { pos + 0x08 } else { members.u16(pos + 0x08)? as usize };
+            let (_, type_desc) = self.element(members, &mut type_offset, refs)?;
+
+            // a constant's value is either the offset field itself or at that offset
+            let byte_offset = members.u16(pos + 0x06)?;
+            let value = if var_flags & VAR_DISPATCH == 0 && var_flags & VAR_CONST != 0 {
+                Some(if var_flags & VAR_VALUE_INLINE != 0 {
+                    Variant::Int(byte_offset as i16 as i32)
+                } else {
+                    let at = byte_offset as usize;
+                    match &type_desc {
+                        TypeDesc::Base(8 | VT_LPSTR | VT_LPWSTR) => {
+                            let mut pos = at;
+                            Variant::Bstr(read_string(members, &mut pos)?.unwrap_or_default())
+                        },
+                        TypeDesc::Base(vt @ (2 | 3 | 18 | 19 | 22 | 23)) => integer_variant(*vt, members.i32(at)? as i64)?,
+                        _ => return Err(invalid(format!("unsupported constant of type {:?}", type_desc))),
+                    }
+                })
+            } else {
+                None
+            };
+
+            let mut flags = if magic == VAR_WITH_FLAGS_MAGIC { members.u16(pos + 0x12)? } else { 0 };
+            if var_flags & VAR_READONLY != 0 {
+                flags |= VARFLAG_FREADONLY;
+            }
+            variables.push(Variable {
+                name,
+                memid: members.i32(pos + 0x0a)?,
+                doc: None,
+                type_desc,
+                flags,
+                value,
+            });
+            pos = members.u16(pos + 0x02)? as usize;
+        }
+        return Ok(variables);
+    }
+
+    // a type as a parameter or return value, with the parameter flags folded into its first word
+    fn element(&self, members: &Reader, pos: &mut usize, refs: &Refs) -> Result<(u16,TypeDesc),Error> {
+        let word = members.u16(*pos)?;
+        let mut flags = if word & 0xc000 == 0xc000 {
+            0
+        } else if word & 0x8000 != 0 {
+            PARAMFLAG_FIN | PARAMFLAG_FOUT
+        } else if word & 0x4000 != 0 {
+            PARAMFLAG_FOUT
+        } else if word & 0x2000 != 0 {
+            PARAMFLAG_FLCID
+        } else {
+            PARAMFLAG_FIN
+        };
+        if word & 0x80 != 0 {
+            flags |= PARAMFLAG_FRETVAL;
+        }
+        return Ok((flags, self.type_desc(members, pos, refs, 0)?));
+    }
+
+    // a type is a word with the vt in its low bits and a pointer flag, followed for the types that
+    // wrap another by more words
+    fn type_desc(&self, members: &Reader, pos: &mut usize, refs: &Refs, depth: usize) -> Result<TypeDesc,Error> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(invalid("type description nests too deeply"));
+        }
+        let word = members.u16(*pos)?;
+        *pos += 2;
+        let type_desc = match word & 0x3f {
+            VT_PTR => TypeDesc::Ptr(Box::new(self.type_desc(members, pos, refs, depth + 1)?)),
+            VT_USERDEFINED => {
+                let reference = members.u16(*pos)? as usize / 4;
+                *pos += 2;
+                TypeDesc::UserDefined(refs.get(reference)?)
+            },
+            VT_SAFEARRAY => {
+                // the offset of a SAFEARRAY that isn't needed, then the element type
+                *pos += 2;
+                TypeDesc::SafeArray(Box::new(self.type_desc(members, pos, refs, depth + 1)?))
+            },
+            VT_CARRAY => {
+                // the offset of a 32 bit SAFEARRAY with the bounds, then the element type
+                let array = members.u16(*pos)? as usize;
+                *pos += 2;
+                let mut bounds = Vec::new();
+                for i in 0..members.u16(array)? as usize {
+                    bounds.push(ArrayBound::new(members.i32(array + 0x14 + i * 8)?, members.u32(array + 0x10 + i * 8)?));
+                }
+                TypeDesc::CArray(Box::new(self.type_desc(members, pos, refs, depth + 1)?), bounds)
+            },
+            vt => TypeDesc::Base(vt),
+        };
+        // the flag wraps whatever the rest of the word says in a pointer
+        return Ok(if word & 0xe00 == 0xe00 { TypeDesc::Ptr(Box::new(type_desc)) } else { type_desc });
+    }
+}
@@ -0,0 +1,646 @@
+use std::fmt;
+use std::fmt::Formatter;
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use crate::{Dispatch, Error, hresult, Unknown};
+use crate::date::OleDate;
+use crate::decimal::Currency;
+
+// flag or'ed into a VARTYPE when the value is a SAFEARRAY of that element type
+pub const VT_ARRAY_FLAG: u16 = 0x2000;
+
+// mirror of the windows VARENUM values we know how to carry around, kept in plain rust so the
+// variant model has no dependency on the COM runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum VarType {
+    Empty = 0,
+    Null = 1,
+    I2 = 2,
+    I4 = 3,
+    R4 = 4,
+    R8 = 5,
+    Cy = 6,
+    Date = 7,
+    Bstr = 8,
+    Dispatch = 9,
+    Error = 10,
+    Bool = 11,
+    Variant = 12,
+    Unknown = 13,
+    Decimal = 14,
+    I1 = 16,
+    UI1 = 17,
+    UI2 = 18,
+    UI4 = 19,
+    I8 = 20,
+    UI8 = 21,
+    Int = 22,
+    UInt = 23,
+}
+
+impl VarType {
+    pub fn from_u16(vt: u16) -> Option<VarType> {
+        let var_type = match vt {
+            0 => VarType::Empty,
+            1 => VarType::Null,
+            2 => VarType::I2,
+            3 => VarType::I4,
+            4 => VarType::R4,
+            5 => VarType::R8,
+            6 => VarType::Cy,
+            7 => VarType::Date,
+            8 => VarType::Bstr,
+            9 => VarType::Dispatch,
+            10 => VarType::Error,
+            11 => VarType::Bool,
+            12 => VarType::Variant,
+            13 => VarType::Unknown,
+            14 => VarType::Decimal,
+            16 => VarType::I1,
+            17 => VarType::UI1,
+            18 => VarType::UI2,
+            19 => VarType::UI4,
+            20 => VarType::I8,
+            21 => VarType::UI8,
+            22 => VarType::Int,
+            23 => VarType::UInt,
+            _ => return None
+        };
+        return Some(var_type);
+    }
+
+    pub fn to_u16(self) -> u16 {
+        return self as u16;
+    }
+
+    // the reverse of name, e.g. "vt_i4"
+    pub fn from_name(name: &str) -> Option<VarType> {
+        return (0..=VarType::UInt.to_u16())
+            .filter_map(VarType::from_u16)
+            .find(|vt| vt.name() == name);
+    }
+
+    // whether a SAFEARRAY is allowed to have elements of this type
+    pub fn is_array_element(self) -> bool {
+        return !matches!(self, VarType::Empty | VarType::Null);
+    }
+
+    pub fn name(self) -> &'static str {
+        return match self {
+            VarType::Empty => "vt_empty",
+            VarType::Null => "vt_null",
+            VarType::I2 => "vt_i2",
+            VarType::I4 => "vt_i4",
+            VarType::R4 => "vt_r4",
+            VarType::R8 => "vt_r8",
+            VarType::Cy => "vt_cy",
+            VarType::Date => "vt_date",
+            VarType::Bstr => "vt_bstr",
+            VarType::Dispatch => "vt_dispatch",
+            VarType::Error => "vt_error",
+            VarType::Bool => "vt_bool",
+            VarType::Variant => "vt_variant",
+            VarType::Unknown => "vt_unknown",
+            VarType::Decimal => "vt_decimal",
+            VarType::I1 => "vt_i1",
+            VarType::UI1 => "vt_ui1",
+            VarType::UI2 => "vt_ui2",
+            VarType::UI4 => "vt_ui4",
+            VarType::I8 => "vt_i8",
+            VarType::UI8 => "vt_ui8",
+            VarType::Int => "vt_int",
+            VarType::UInt => "vt_uint",
+        };
+    }
+}
+
+impl fmt::Display for VarType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct ArrayBound {
+    pub lower: i32,
+    pub len: u32,
+}
+
+impl ArrayBound {
+    pub fn new(lower: i32, len: u32) -> ArrayBound {
+        ArrayBound { lower, len }
+    }
+}
+
+// VT_ARRAY, a SAFEARRAY where every element has the same type.  the elements are stored with the
+// first dimension varying fastest, which is the same order as the SAFEARRAY memory layout
+#[derive(Clone, Debug, PartialEq)]
+pub struct SafeArray {
+    vt: VarType,
+    bounds: Vec<ArrayBound>,
+    elements: Vec<Variant>,
+}
+
+impl SafeArray {
+    pub fn new(vt: VarType, bounds: Vec<ArrayBound>, elements: Vec<Variant>) -> Result<SafeArray,Error> {
+        if !vt.is_array_element() {
+            return Err(Error::com(hresult::DISP_E_BADVARTYPE, format!("{} is not a valid array element type", vt)));
+        }
+        if bounds.is_empty() {
+            return Err(Error::com(hresult::E_INVALIDARG, "array must have at least one dimension"));
+        }
+        let count = bounds.iter().try_fold(1usize, |count, bound| count.checked_mul(bound.len as usize));
+        if count != Some(elements.len()) {
+            return Err(Error::com(hresult::E_INVALIDARG, format!("array bounds do not match its {} element(s)", elements.len())));
+        }
+        if let Some(element) = elements.iter().find(|element| !SafeArray::can_hold(vt, element)) {
+            return Err(Error::result(format!("array of {} cannot hold element {:?}", vt, element)));
+        }
+        return Ok(SafeArray { vt, bounds, elements });
+    }
+
+    // whether an array of vt can hold the element
+    fn can_hold(vt: VarType, element: &Variant) -> bool {
+        // an element left as Null is written as a null object reference too, so both go in arrays
+        // of objects
+        let is_object_array = matches!(vt, VarType::Dispatch | VarType::Unknown);
+        return vt == VarType::Variant || element.vt() == vt.to_u16() || (is_object_array && (element.is_null() || element.is_nothing()));
+    }
+
+    // one dimensional array with a lower bound of zero
+    pub fn from_vec(vt: VarType, elements: Vec<Variant>) -> Result<SafeArray,Error> {
+        let bound = ArrayBound::new(0, elements.len() as u32);
+        return SafeArray::new(vt, vec![bound], elements);
+    }
+
+    pub fn vt(&self) -> VarType {
+        return self.vt;
+    }
+
+    pub fn bounds(&self) -> &[ArrayBound] {
+        return &self.bounds;
+    }
+
+    pub fn dims(&self) -> usize {
+        return self.bounds.len();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.elements.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.elements.is_empty();
+    }
+
+    pub fn elements(&self) -> &[Variant] {
+        return &self.elements;
+    }
+
+    pub fn into_elements(self) -> Vec<Variant> {
+        return self.elements;
+    }
+
+    // 2-D array (rows x columns) with lower bounds of zero, e.g. for writing to an excel Range.Value
+    pub fn from_rows(vt: VarType, rows: Vec<Vec<Variant>>) -> Result<SafeArray,Error> {
+        let row_count = rows.len();
+        let col_count = rows.first().map(|row| row.len()).unwrap_or(0);
+        if let Some(row) = rows.iter().find(|row| row.len() != col_count) {
+            return Err(Error::com(hresult::E_INVALIDARG, format!("all rows must have {} column(s) but found one with {}", col_count, row.len())));
+        }
+
+        // rows go into the first dimension, so walk down each column
+        let mut cells: Vec<std::vec::IntoIter<Variant>> = rows.into_iter().map(|row| row.into_iter()).collect();
+        let mut elements = Vec::with_capacity(row_count * col_count);
+        for _ in 0..col_count {
+            for row in cells.iter_mut() {
+                elements.extend(row.next());
+            }
+        }
+
+        let bounds = vec![ArrayBound::new(0, row_count as u32), ArrayBound::new(0, col_count as u32)];
+        return SafeArray::new(vt, bounds, elements);
+    }
+
+    // 2-D array as rows x columns, the first dimension being the rows (as with excel's Range.Value)
+    pub fn to_rows(&self) -> Result<Vec<Vec<Variant>>,Error> {
+        if self.dims() != 2 {
+            return Err(Error::com(hresult::E_INVALIDARG, format!("array has {} dimension(s), expected 2", self.dims())));
+        }
+        let row_count = self.bounds[0].len as usize;
+        let col_count = self.bounds[1].len as usize;
+        let rows = (0..row_count)
+            .map(|r| (0..col_count).map(|c| self.elements[r + c * row_count].clone()).collect())
+            .collect();
+        return Ok(rows);
+    }
+
+    // position in elements() of the element at the given indices, which include the lower bounds
+    pub fn offset(&self, indices: &[i32]) -> Option<usize> {
+        if indices.len() != self.bounds.len() {
+            return None;
+        }
+        let mut offset = 0usize;
+        let mut stride = 1usize;
+        for (index, bound) in indices.iter().zip(self.bounds.iter()) {
+            let position = (*index as i64) - (bound.lower as i64);
+            if position < 0 || position >= bound.len as i64 {
+                return None;
+            }
+            offset += position as usize * stride;
+            stride *= bound.len as usize;
+        }
+        return Some(offset);
+    }
+
+    pub fn get(&self, indices: &[i32]) -> Option<&Variant> {
+        return self.offset(indices).map(|offset| &self.elements[offset]);
+    }
+
+    pub fn set(&mut self, indices: &[i32], value: Variant) -> Result<(),Error> {
+        if !SafeArray::can_hold(self.vt, &value) {
+            return Err(Error::result(format!("array of {} cannot hold element {:?}", self.vt, value)));
+        }
+        let offset = self.offset(indices).ok_or_else(|| Error::com(hresult::DISP_E_BADINDEX, format!("indices {:?} are out of the array bounds", indices)))?;
+        self.elements[offset] = value;
+        return Ok(());
+    }
+
+    // writes the elements nested by the first dimension, e.g. [[a, b], [c, d]] for a 2x2 array
+    fn fmt_dim(&self, f: &mut Formatter<'_>, dim: usize, offset: usize, stride: usize, debug: bool) -> fmt::Result {
+        write!(f, "[")?;
+        let bound = self.bounds[dim];
+        for i in 0..bound.len as usize {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let index = offset + i * stride;
+            if dim + 1 < self.bounds.len() {
+                self.fmt_dim(f, dim + 1, index, stride * bound.len as usize, debug)?;
+            } else if debug {
+                write!(f, "{:?}", self.elements[index])?;
+            } else {
+                write!(f, "{}", self.elements[index])?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+#[derive(Clone, Default, PartialEq)]
+pub enum Variant {
+    #[default]
+    Empty,                                  // VT_EMPTY
+    Null,                                   // VT_NULL
+    I1(i8),                                 // VT_I1, cVal
+    I2(i16),                                // VT_I2, iVal
+    I4(i32),                                // VT_I4, lVal
+    I8(i64),                                // VT_I8, llVal
+    UI1(u8),                                // VT_UI1, bVal
+    UI2(u16),                               // VT_UI2, uiVal
+    UI4(u32),                               // VT_UI4, ulVal
+    UI8(u64),                               // VT_UI8, ullVal
+    Int(i32),                               // VT_INT, intVal
+    UInt(u32),                              // VT_UINT, uintVal
+    R4(f32),                                // VT_R4, fltVal
+    R8(f64),                                // VT_R8, dblVal
+    Bool(bool),                             // VT_BOOL, boolVal
+    Bstr(String),                           // VT_BSTR, bstrVal
+    Cy(Currency),                           // VT_CY, cyVal
+    Date(OleDate),                          // VT_DATE, date
+    Decimal(Decimal),                       // VT_DECIMAL, decVal
+    Error(i32),                             // VT_ERROR, scode
+    Dispatch(Dispatch),                     // VT_DISPATCH, pdispVal
+    Unknown(Unknown),                       // VT_UNKNOWN, punkVal
+    Nothing,                                // VT_DISPATCH, null pdispVal ("Nothing" in VB)
+    Array(SafeArray),                       // VT_ARRAY | vt, parray
+}
+
+impl Variant {
+    pub fn empty() -> Variant {
+        Variant::Empty
+    }
+
+    // what's passed for an optional parameter that's been left out, VT_ERROR with
+    // DISP_E_PARAMNOTFOUND, which works wherever the parameter is rather than only at the end
+    pub fn missing() -> Variant {
+        Variant::Error(hresult::DISP_E_PARAMNOTFOUND)
+    }
+
+    // the element type of this variant (for arrays, the type of its elements)
+    pub fn var_type(&self) -> VarType {
+        return match self {
+            Variant::Empty => VarType::Empty,
+            Variant::Null => VarType::Null,
+            Variant::I1(_) => VarType::I1,
+            Variant::I2(_) => VarType::I2,
+            Variant::I4(_) => VarType::I4,
+            Variant::I8(_) => VarType::I8,
+            Variant::UI1(_) => VarType::UI1,
+            Variant::UI2(_) => VarType::UI2,
+            Variant::UI4(_) => VarType::UI4,
+            Variant::UI8(_) => VarType::UI8,
+            Variant::Int(_) => VarType::Int,
+            Variant::UInt(_) => VarType::UInt,
+            Variant::R4(_) => VarType::R4,
+            Variant::R8(_) => VarType::R8,
+            Variant::Bool(_) => VarType::Bool,
+            Variant::Bstr(_) => VarType::Bstr,
+            Variant::Cy(_) => VarType::Cy,
+            Variant::Date(_) => VarType::Date,
+            Variant::Decimal(_) => VarType::Decimal,
+            Variant::Error(_) => VarType::Error,
+            Variant::Dispatch(_) => VarType::Dispatch,
+            Variant::Unknown(_) => VarType::Unknown,
+            Variant::Nothing => VarType::Dispatch,
+            Variant::Array(array) => array.vt(),
+        };
+    }
+
+    // the raw VARTYPE of this variant, including VT_ARRAY for arrays
+    pub fn vt(&self) -> u16 {
+        return match self {
+            Variant::Array(array) => VT_ARRAY_FLAG | array.vt().to_u16(),
+            _ => self.var_type().to_u16(),
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return matches!(self, Variant::Empty);
+    }
+
+    pub fn is_null(&self) -> bool {
+        return matches!(self, Variant::Null);
+    }
+
+    // a null object reference, which is what Set x = Nothing assigns
+    pub fn is_nothing(&self) -> bool {
+        return matches!(self, Variant::Nothing);
+    }
+
+    // a new reference to the object (for COM, AddRef'ed), the variant keeps its own
+    pub fn to_dispatch(&self) -> Result<Dispatch,Error> {
+        return match self {
+            Variant::Dispatch(dispatch) => Ok(dispatch.clone()),
+            _ => Err(Error::result("variant is not a dispatch"))
+        };
+    }
+
+    // moves the variant's reference to the object out, so there's no AddRef (or Release when the
+    // variant is dropped)
+    pub fn into_dispatch(self) -> Result<Dispatch,Error> {
+        return match self {
+            Variant::Dispatch(dispatch) => Ok(dispatch),
+            _ => Err(Error::result("variant is not a dispatch"))
+        };
+    }
+
+    // these coerce like VariantChangeType, see the TryFrom<&Variant> impls in convert.rs
+    pub fn to_i32(&self) -> Result<i32,Error> {
+        return i32::try_from(self);
+    }
+
+    pub fn to_decimal(&self) -> Result<Decimal,Error> {
+        return Decimal::try_from(self);
+    }
+
+    pub fn to_currency(&self) -> Result<Currency,Error> {
+        return Currency::try_from(self);
+    }
+
+    pub fn to_array(&self) -> Result<&SafeArray,Error> {
+        return match self {
+            Variant::Array(array) => Ok(array),
+            _ => Err(Error::result("variant is not an array"))
+        };
+    }
+
+    pub fn to_date_time(&self) -> Result<NaiveDateTime,Error> {
+        return NaiveDateTime::try_from(self);
+    }
+}
+
+impl fmt::Debug for Variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let vt = self.var_type();
+        match self {
+            Variant::Empty | Variant::Null => write!(f, "<{}>", vt),
+            Variant::I1(v) => write!(f, "({} {})", vt, v),
+            Variant::I2(v) => write!(f, "({} {})", vt, v),
+            Variant::I4(v) | Variant::Int(v) => write!(f, "({} {})", vt, v),
+            Variant::I8(v) => write!(f, "({} {})", vt, v),
+            Variant::UI1(v) => write!(f, "({} {})", vt, v),
+            Variant::UI2(v) => write!(f, "({} {})", vt, v),
+            Variant::UI4(v) | Variant::UInt(v) => write!(f, "({} {})", vt, v),
+            Variant::UI8(v) => write!(f, "({} {})", vt, v),
+            Variant::R4(v) => write!(f, "({} {})", vt, v),
+            Variant::R8(v) => write!(f, "({} {})", vt, v),
+            Variant::Date(v) => write!(f, "({} {})", vt, v),
+            Variant::Bool(v) => write!(f, "({} {})", vt, v),
+            Variant::Bstr(v) => write!(f, "({} {:?})", vt, v),
+            Variant::Cy(v) => write!(f, "({} {})", vt, v),
+            Variant::Decimal(v) => write!(f, "({} {})", vt, v),
+            Variant::Error(v) => write!(f, "({} {:#010x})", vt, v),
+            Variant::Dispatch(v) => write!(f, "({} {})", vt, v),
+            Variant::Unknown(v) => write!(f, "({} {})", vt, v),
+            Variant::Nothing => write!(f, "<{} nothing>", vt),
+            Variant::Array(array) => {
+                write!(f, "(vt_array {} ", vt)?;
+                array.fmt_dim(f, 0, 0, 1, true)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Empty => write!(f, "<empty>"),
+            Variant::Null => write!(f, "<null>"),
+            Variant::I1(v) => write!(f, "{}", v),
+            Variant::I2(v) => write!(f, "{}", v),
+            Variant::I4(v) | Variant::Int(v) => write!(f, "{}", v),
+            Variant::I8(v) => write!(f, "{}", v),
+            Variant::UI1(v) => write!(f, "{}", v),
+            Variant::UI2(v) => write!(f, "{}", v),
+            Variant::UI4(v) | Variant::UInt(v) => write!(f, "{}", v),
+            Variant::UI8(v) => write!(f, "{}", v),
+            Variant::R4(v) => write!(f, "{}", v),
+            Variant::R8(v) => write!(f, "{}", v),
+            Variant::Date(v) => write!(f, "{}", v),
+            Variant::Bool(v) => write!(f, "{}", v),
+            Variant::Bstr(v) => write!(f, "{}", v),
+            Variant::Cy(v) => write!(f, "{}", v),
+            Variant::Decimal(v) => write!(f, "{}", v),
+            Variant::Error(v) => write!(f, "<error {:#010x}>", v),
+            Variant::Dispatch(v) => write!(f, "<dispatch {}>", v),
+            Variant::Unknown(v) => write!(f, "<unknown {}>", v),
+            Variant::Nothing => write!(f, "<nothing>"),
+            Variant::Array(array) => array.fmt_dim(f, 0, 0, 1, false),
+        }
+    }
+}
+
+impl From<bool> for Variant {
+    fn from(value: bool) -> Variant {
+        Variant::Bool(value)
+    }
+}
+
+impl From<&str> for Variant {
+    fn from(value: &str) -> Variant {
+        Variant::Bstr(value.to_string())
+    }
+}
+
+impl From<String> for Variant {
+    fn from(value: String) -> Variant {
+        Variant::Bstr(value)
+    }
+}
+
+impl From<i8> for Variant {
+    fn from(value: i8) -> Variant {
+        Variant::I1(value)
+    }
+}
+
+impl From<u8> for Variant {
+    fn from(value: u8) -> Variant {
+        Variant::UI1(value)
+    }
+}
+
+impl From<i16> for Variant {
+    fn from(value: i16) -> Variant {
+        Variant::I2(value)
+    }
+}
+
+impl From<u16> for Variant {
+    fn from(value: u16) -> Variant {
+        Variant::UI2(value)
+    }
+}
+
+impl From<i32> for Variant {
+    fn from(value: i32) -> Variant {
+        Variant::I4(value)
+    }
+}
+
+impl From<u32> for Variant {
+    fn from(value: u32) -> Variant {
+        Variant::UI4(value)
+    }
+}
+
+impl From<i64> for Variant {
+    fn from(value: i64) -> Variant {
+        Variant::I8(value)
+    }
+}
+
+impl From<u64> for Variant {
+    fn from(value: u64) -> Variant {
+        Variant::UI8(value)
+    }
+}
+
+impl From<f32> for Variant {
+    fn from(value: f32) -> Variant {
+        Variant::R4(value)
+    }
+}
+
+impl From<f64> for Variant {
+    fn from(value: f64) -> Variant {
+        Variant::R8(value)
+    }
+}
+
+impl From<Currency> for Variant {
+    fn from(value: Currency) -> Variant {
+        Variant::Cy(value)
+    }
+}
+
+impl From<OleDate> for Variant {
+    fn from(value: OleDate) -> Variant {
+        Variant::Date(value)
+    }
+}
+
+impl From<NaiveDateTime> for Variant {
+    fn from(value: NaiveDateTime) -> Variant {
+        Variant::Date(OleDate::from(value))
+    }
+}
+
+impl From<NaiveDate> for Variant {
+    fn from(value: NaiveDate) -> Variant {
+        Variant::Date(OleDate::from(value))
+    }
+}
+
+impl From<Decimal> for Variant {
+    fn from(value: Decimal) -> Variant {
+        Variant::Decimal(value)
+    }
+}
+
+// the variant holds its own reference (AddRef) to the object, released when it's dropped
+impl From<Dispatch> for Variant {
+    fn from(value: Dispatch) -> Variant {
+        Variant::Dispatch(value)
+    }
+}
+
+impl From<&Dispatch> for Variant {
+    fn from(value: &Dispatch) -> Variant {
+        Variant::Dispatch(value.clone())
+    }
+}
+
+impl From<Unknown> for Variant {
+    fn from(value: Unknown) -> Variant {
+        Variant::Unknown(value)
+    }
+}
+
+impl From<SafeArray> for Variant {
+    fn from(value: SafeArray) -> Variant {
+        Variant::Array(value)
+    }
+}
+
+// one dimensional VT_ARRAY | VT_VARIANT with a lower bound of zero
+impl From<Vec<Variant>> for Variant {
+    fn from(value: Vec<Variant>) -> Variant {
+        let bound = ArrayBound::new(0, value.len() as u32);
+        Variant::Array(SafeArray { vt: VarType::Variant, bounds: vec![bound], elements: value })
+    }
+}
+
+// two dimensional VT_ARRAY | VT_VARIANT, fails if the rows are not all the same length
+impl TryFrom<Vec<Vec<Variant>>> for Variant {
+    type Error = Error;
+
+    fn try_from(value: Vec<Vec<Variant>>) -> Result<Variant,Error> {
+        Ok(Variant::Array(SafeArray::from_rows(VarType::Variant, value)?))
+    }
+}
+
+impl TryFrom<&Variant> for Vec<Vec<Variant>> {
+    type Error = Error;
+
+    fn try_from(value: &Variant) -> Result<Vec<Vec<Variant>>,Error> {
+        match value {
+            Variant::Array(array) => array.to_rows(),
+            _ => Err(Error::result("variant is not an array"))
+        }
+    }
+}
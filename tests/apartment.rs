@@ -0,0 +1,36 @@
+//
+// COM apartments on real threads, so windows only
+//
+
+#![cfg(windows)]
+#![allow(clippy::needless_return)]
+
+use std::thread;
+use hello_com_rust::{ApartmentKind, ComApartment};
+
+#[test]
+fn repeat_initialization() {
+    thread::spawn(|| {
+        let outer = ComApartment::sta().unwrap();
+        // S_FALSE, balanced by its own uninitialize
+        let inner = ComApartment::new(ApartmentKind::SingleThreaded, true).unwrap();
+        assert!(outer.is_owned() && inner.is_owned());
+        drop(inner);
+        drop(outer);
+        // everything was uninitialized, so the thread is free to join the other kind now
+        let mta = ComApartment::mta().unwrap();
+        assert_eq!((mta.kind(), mta.is_owned()), (ApartmentKind::MultiThreaded, true));
+    }).join().unwrap();
+}
+
+#[test]
+fn changed_mode() {
+    thread::spawn(|| {
+        let sta = ComApartment::sta().unwrap();
+        // RPC_E_CHANGED_MODE, the thread stays single threaded and isn't uninitialized by it
+        let mta = ComApartment::mta().unwrap();
+        assert_eq!((mta.kind(), mta.is_owned()), (ApartmentKind::SingleThreaded, false));
+        drop(mta);
+        drop(sta);
+    }).join().unwrap();
+}
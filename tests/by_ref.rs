@@ -0,0 +1,62 @@
+//
+// by-ref arguments get whatever the callee wrote back into them
+//
+
+#![allow(clippy::needless_return)]
+
+use std::str::FromStr;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Argument, Automation, Currency, Decimal, Variant};
+
+fn calculator() -> MockObject {
+    // Divide(dividend, divisor, [out] quotient, [out] remainder)
+    return MockObject::new("Calculator").with_method("Divide", |call| {
+        let dividend = Decimal::try_from(call.arg(0).unwrap())?;
+        let divisor = Decimal::try_from(call.arg(1).unwrap())?;
+        call.args[2] = Variant::from((dividend / divisor).trunc());
+        call.args[3] = Variant::from(dividend % divisor);
+        return Ok(Variant::from(true));
+    });
+}
+
+#[test]
+fn write_back() {
+    let calculator = calculator().to_dispatch();
+    let (dividend, divisor) = (Variant::from(Decimal::from_str("10.5").unwrap()), Variant::from(4));
+    let (mut quotient, mut remainder) = (Variant::Empty, Variant::from(Decimal::ZERO));
+    let result = calculator.call_method_by_ref("Divide", &mut [
+        Argument::ByVal(&dividend), Argument::ByVal(&divisor), Argument::ByRefVariant(&mut quotient), Argument::ByRef(&mut remainder),
+    ]).unwrap();
+    assert_eq!(result, Variant::from(true));
+    assert_eq!(quotient, Variant::from(Decimal::from(2)));
+    // a decimal written back through a by-ref decimal
+    assert_eq!(remainder, Variant::from(Decimal::from_str("2.5").unwrap()));
+}
+
+#[test]
+fn by_val_is_left_alone() {
+    let object = MockObject::new("Object").with_method("Clear", |call| {
+        for arg in call.args.iter_mut() {
+            *arg = Variant::Null;
+        }
+        return Ok(Variant::Empty);
+    });
+    let dispatch = object.to_dispatch();
+    let kept = Variant::from(Currency::from_raw(12_345));
+    let mut cleared = Variant::from("text");
+    dispatch.call_method_by_ref("Clear", &mut [Argument::ByVal(&kept), Argument::ByRef(&mut cleared)]).unwrap();
+    assert_eq!((kept, cleared), (Variant::from(Currency::from_raw(12_345)), Variant::Null));
+    // the callee saw the values as they were passed
+    assert_eq!(object.calls_to("Clear")[0].args, vec![Variant::from(Currency::from_raw(12_345)), Variant::from("text")]);
+}
+
+#[test]
+fn left_unchanged_on_error() {
+    let dispatch = calculator().to_dispatch();
+    let (dividend, divisor) = (Variant::from("ten"), Variant::from(4));
+    let (mut quotient, mut remainder) = (Variant::from(-1), Variant::from(-1));
+    assert!(dispatch.call_method_by_ref("Divide", &mut [
+        Argument::ByVal(&dividend), Argument::ByVal(&divisor), Argument::ByRef(&mut quotient), Argument::ByRef(&mut remainder),
+    ]).is_err());
+    assert_eq!((quotient, remainder), (Variant::from(-1), Variant::from(-1)));
+}
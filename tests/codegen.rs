@@ -0,0 +1,212 @@
+//
+// the wrappers generated for tests/fixtures/sdo.tlb are checked in as tests/fixtures/sdo.rs, which
+// is compared against what the generator makes now and compiled in here to drive a mock.  after a
+// change to the generator, regenerate it with
+//
+//   cargo run --bin tlbgen tests/fixtures/sdo.tlb tests/fixtures/sdo.rs
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::codegen;
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::typelib::*;
+use hello_com_rust::{InvokeKind, Variant};
+
+include!("fixtures/sdo.rs");
+
+use sdo_test::{AccountKind, AccountType, IFields, IWorkSpace, WorkSpace};
+
+#[test]
+fn generated_is_current() {
+    let library = TypeLibrary::parse(include_bytes!("fixtures/sdo.tlb")).unwrap();
+    assert_eq!(codegen::module_name(&library), "sdo_test");
+    assert_eq!(codegen::generate(&library), include_str!("fixtures/sdo.rs"));
+}
+
+#[test]
+fn enum_constants() {
+    assert_eq!(AccountType::sdoNominal, AccountType(-1));
+    assert_eq!(Variant::from(AccountType::sdoPurchase), Variant::I4(2));
+    let kind: AccountKind = AccountType::sdoSales;
+    assert_eq!(kind.0, 1);
+    assert_eq!(WorkSpace::CLSID, "{A1B2C3D4-0003-4000-8000-00AA00BB00CC}");
+    assert_eq!(IWorkSpace::IID, "{A1B2C3D4-0002-4000-8000-00AA00BB00CC}");
+}
+
+// the mock hands out dispids in the order members are added, so they're added in the order of the
+// memids in the fixture
+fn workspace(fields: &MockObject) -> MockObject {
+    let fields = fields.clone();
+    return MockObject::new("WorkSpace")
+        .with_method("Connect", |call| Ok(Variant::from(call.arg(0) == Some(&Variant::from("C:\\ACCDATA")))))
+        .with_property("Name", "Sage")
+        .with_method("Disconnect", |_| Ok(Variant::Empty))
+        .with_method("CreateObject", |call| Ok(Variant::from(MockObject::new(&call.arg(0).unwrap().to_string()).to_dispatch())))
+        .with_method("Fields", move |_| Ok(Variant::from(fields.to_dispatch())))
+        .with_property("Connected", true);
+}
+
+#[test]
+fn methods_and_properties() {
+    let fields = MockObject::new("Fields").with_items(vec![Variant::from("ACCOUNT_REF"), Variant::from("NAME")]);
+    let mock = workspace(&fields);
+    let workspace = IWorkSpace::from(mock.to_dispatch());
+
+    assert!(workspace.connect("C:\\ACCDATA", "manager", "", None).unwrap());
+    let connect = &mock.calls_to("Connect")[0];
+    assert_eq!(connect.kind, InvokeKind::Method);
+    assert_eq!(connect.args, vec![Variant::from("C:\\ACCDATA"), Variant::from("manager"), Variant::from(""), Variant::missing()]);
+    workspace.connect("C:\\ACCDATA", "manager", "", Some(&Variant::from("Company"))).unwrap();
+    assert_eq!(mock.calls_to("Connect")[1].arg(3), Some(&Variant::from("Company")));
+
+    assert_eq!(workspace.name().unwrap(), "Sage");
+    workspace.set_name("Renamed").unwrap();
+    assert_eq!(mock.property("Name"), Some(Variant::from("Renamed")));
+    assert!(workspace.connected().unwrap());
+
+    workspace.create_object("SalesRecord").unwrap();
+    assert_eq!(mock.calls_to("CreateObject")[0].arg(0), Some(&Variant::from("SalesRecord")));
+    workspace.disconnect().unwrap();
+    assert_eq!(mock.calls_to("Disconnect").len(), 1);
+}
+
+#[test]
+fn collections() {
+    let fields = MockObject::new("Fields").with_items(vec![Variant::from("ACCOUNT_REF"), Variant::from("NAME")]);
+    let workspace = IWorkSpace::from(workspace(&fields).to_dispatch());
+
+    let all: IFields = workspace.fields(None, Some(1)).unwrap();
+    assert_eq!(all.count().unwrap(), 2);
+    let names: Vec<Variant> = all.iter().map(|item| item.unwrap()).collect();
+    assert_eq!(names, vec![Variant::from("ACCOUNT_REF"), Variant::from("NAME")]);
+}
+
+#[test]
+fn errors_come_back() {
+    let mock = MockObject::new("WorkSpace")
+        .with_method("Connect", |_| Err(hello_com_rust::Error::com(hresult::E_FAIL, "bad password")));
+    let workspace = IWorkSpace::from(mock.to_dispatch());
+    assert_eq!(workspace.connect("C:\\ACCDATA", "manager", "", None).unwrap_err().hresult(), hresult::E_FAIL);
+    // Name's dispid isn't there at all
+    assert_eq!(workspace.name().unwrap_err().hresult(), hresult::DISP_E_MEMBERNOTFOUND);
+}
+
+fn base(vt: u16) -> TypeDesc {
+    return TypeDesc::Base(vt);
+}
+
+fn ptr(type_desc: TypeDesc) -> TypeDesc {
+    return TypeDesc::Ptr(Box::new(type_desc));
+}
+
+fn param(name: &str, type_desc: TypeDesc, flags: u16) -> Param {
+    return Param { name: name.to_string(), type_desc, flags, default: None };
+}
+
+fn function(name: &str, memid: i32, invoke_kind: InvokeKind, params: Vec<Param>) -> Function {
+    return Function { name: name.to_string(), memid, invoke_kind, doc: None, return_type: base(VT_HRESULT), params, optional_params: 0, flags: 0 };
+}
+
+fn type_info(name: &str, kind: TypeKind, flags: u16, implements: Vec<ImplType>, functions: Vec<Function>) -> TypeInfo {
+    return TypeInfo {
+        name: name.to_string(),
+        kind,
+        guid: Some("00024500-0000-0000-C000-000000000046".to_string()),
+        doc: None,
+        flags,
+        major_version: 1,
+        minor_version: 9,
+        implements,
+        alias: None,
+        functions,
+        variables: Vec::new(),
+    };
+}
+
+// a dual interface's functions are its vtable ones, results come back through [out, retval] and the
+// lcid parameters are filled in by IDispatch
+#[test]
+fn dual_interfaces() {
+    let idispatch = ImplType {
+        reference: TypeRef::Imported {
+            library: ImportedLibrary { name: "stdole2.tlb".to_string(), guid: Some("00020430-0000-0000-C000-000000000046".to_string()), major_version: 2, minor_version: 0 },
+            guid: Some("00020400-0000-0000-C000-000000000046".to_string()),
+            index: None,
+        },
+        flags: 0,
+    };
+    let workbooks = TypeRef::Local(1);
+    let mut query_interface = function("QueryInterface", 0x6000_0000, InvokeKind::Method, vec![]);
+    query_interface.flags = FUNCFLAG_FRESTRICTED;
+    let application = type_info("_Application", TypeKind::Interface, TYPEFLAG_FDUAL | TYPEFLAG_FDISPATCHABLE, vec![idispatch.clone()], vec![
+        query_interface,
+        function("Workbooks", 0x23c, InvokeKind::PropertyGet, vec![param("RHS", ptr(ptr(TypeDesc::UserDefined(workbooks))), PARAMFLAG_FOUT | PARAMFLAG_FRETVAL)]),
+        function("Visible", 0x22e, InvokeKind::PropertyGet, vec![
+            param("lcid", base(3), PARAMFLAG_FIN | PARAMFLAG_FLCID),
+            param("RHS", ptr(base(11)), PARAMFLAG_FOUT | PARAMFLAG_FRETVAL),
+        ]),
+        function("Visible", 0x22e, InvokeKind::PropertyPut, vec![
+            param("lcid", base(3), PARAMFLAG_FIN | PARAMFLAG_FLCID),
+            param("RHS", base(11), PARAMFLAG_FIN),
+        ]),
+        function("Calculate", 0x117, InvokeKind::Method, vec![]),
+        function("Run", 0x103, InvokeKind::Method, vec![
+            param("Macro", base(12), PARAMFLAG_FIN | PARAMFLAG_FOPT),
+            param("Result", ptr(base(12)), PARAMFLAG_FIN | PARAMFLAG_FOUT),
+        ]),
+    ]);
+    let workbooks = type_info("Workbooks", TypeKind::Interface, TYPEFLAG_FDUAL, vec![idispatch], vec![
+        function("Count", 0x76, InvokeKind::PropertyGet, vec![param("RHS", ptr(base(3)), PARAMFLAG_FOUT | PARAMFLAG_FRETVAL)]),
+        function("_NewEnum", -4, InvokeKind::PropertyGet, vec![param("RHS", ptr(base(13)), PARAMFLAG_FOUT | PARAMFLAG_FRETVAL)]),
+    ]);
+    let coclass = type_info("Application", TypeKind::CoClass, TYPEFLAG_FCANCREATE, vec![ImplType { reference: TypeRef::Local(0), flags: IMPLTYPEFLAG_FDEFAULT }], vec![]);
+    let library = TypeLibrary {
+        name: "Excel".to_string(),
+        doc: None,
+        guid: None,
+        major_version: 1,
+        minor_version: 9,
+        lcid: 0,
+        types: vec![application, workbooks, coclass],
+    };
+
+    let generated = codegen::generate(&library);
+    assert!(generated.contains("pub mod excel {"));
+    assert!(generated.contains("pub fn workbooks(&self) -> Result<Workbooks,Error> {"));
+    assert!(generated.contains("return Ok(Workbooks(value.into_dispatch()?));"));
+    assert!(generated.contains("pub fn visible(&self) -> Result<bool,Error> {"));
+    assert!(generated.contains("return Ok(bool::try_from(&value)?);"));
+    assert!(generated.contains("pub fn set_visible(&self, rhs: bool) -> Result<(),Error> {"));
+    assert!(generated.contains("self.0.invoke(558, InvokeKind::PropertyPut, &mut [], &[DISPID_PROPERTYPUT], &mut [Argument::ByVal(&Variant::from(rhs))])?;"));
+    assert!(generated.contains("pub fn calculate(&self) -> Result<(),Error> {"));
+    assert!(generated.contains("pub fn run(&self, macro_: Option<&Variant>, result: &mut Variant) -> Result<(),Error> {"));
+    assert!(generated.contains("Argument::ByRefVariant(result),"));
+    assert!(generated.contains("pub fn count(&self) -> Result<i32,Error> {"));
+    assert!(generated.contains("pub fn iter(&self) -> DispatchIter {"));
+    assert!(generated.contains("pub fn create() -> Result<_Application,Error> {"));
+    assert!(!generated.contains("query_interface"));
+    assert!(!generated.contains("new_enum"));
+}
+
+// names that are reserved words in rust, in any edition, get an underscore on the end
+#[test]
+fn reserved_words() {
+    let methods = type_info("IShapes", TypeKind::Interface, TYPEFLAG_FDUAL, vec![], vec![
+        function("Abstract", 1, InvokeKind::Method, vec![]),
+        function("Become", 2, InvokeKind::Method, vec![]),
+        function("Gen", 3, InvokeKind::Method, vec![]),
+    ]);
+    let mut kinds = type_info("ShapeKind", TypeKind::Enum, 0, vec![], vec![]);
+    kinds.variables = ["abstract", "become", "gen"].iter().enumerate().map(|(i, name)| Variable {
+        name: name.to_string(), memid: i as i32, doc: None, type_desc: base(3), flags: 0, value: Some(Variant::I4(i as i32)),
+    }).collect();
+    let library = TypeLibrary { name: "Shapes".to_string(), doc: None, guid: None, major_version: 1, minor_version: 0, lcid: 0, types: vec![methods, kinds] };
+
+    let generated = codegen::generate(&library);
+    for name in ["abstract", "become", "gen"] {
+        assert!(generated.contains(&format!("pub fn {}_(&self) -> Result<(),Error> {{", name)), "{}", generated);
+        assert!(generated.contains(&format!("pub const {}_: ShapeKind", name)), "{}", generated);
+    }
+}
@@ -0,0 +1,166 @@
+//
+// the async calls on a RemoteDispatch, driven by a minimal block_on so they're shown to need nothing
+// from any particular runtime
+//
+
+#![allow(clippy::needless_return)]
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Automation, ComFuture, ComHandle, ComThread, RemoteDispatch, Variant};
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+struct CountWakes(AtomicUsize);
+
+impl Wake for CountWakes {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+thread_local! {
+    static SHEET: RefCell<Option<MockObject>> = const { RefCell::new(None) };
+    static RANGE: RefCell<Option<MockObject>> = const { RefCell::new(None) };
+}
+
+fn range_refs(handle: &ComHandle) -> usize {
+    return handle.run(|| Ok(RANGE.with(|range| range.borrow().as_ref().unwrap().ref_count()))).unwrap();
+}
+
+fn calls(handle: &ComHandle, name: &'static str) -> usize {
+    return handle.run(move || Ok(SHEET.with(|sheet| sheet.borrow().as_ref().unwrap().calls_to(name).len()))).unwrap();
+}
+
+// a sheet whose Wait method tells the test it's started, then waits for the test to let it finish
+// before returning a range
+fn sheet(handle: &ComHandle) -> (RemoteDispatch, Receiver<()>, Sender<()>) {
+    let (started, on_start) = mpsc::channel();
+    let (finish, on_finish) = mpsc::channel::<()>();
+    let sheet = handle.create(move || {
+        let sheet = MockObject::new("Worksheet")
+            .with_property("Name", "Sheet1")
+            .with_method("Add", |call| Ok(Variant::from(i32::try_from(call.arg(0).unwrap())? + i32::try_from(call.arg(1).unwrap())?)));
+        let range = MockObject::new("Range").with_property("Address", "$A$1");
+        RANGE.with(|kept| *kept.borrow_mut() = Some(range.clone()));
+        let sheet = sheet.with_method("Wait", move |_| {
+            let _ = started.send(());
+            let _ = on_finish.recv();
+            return Ok(Variant::from(range.to_dispatch()));
+        });
+        SHEET.with(|kept| *kept.borrow_mut() = Some(sheet.clone()));
+        return Ok(sheet.to_dispatch());
+    }).unwrap();
+    return (sheet, on_start, finish);
+}
+
+fn send<T: Send>() {}
+
+#[test]
+fn get_put_and_call() {
+    // so it can be awaited in a task that moves between threads
+    send::<ComFuture<Variant>>();
+
+    let com = ComThread::spawn().unwrap();
+    let (sheet, _, _) = sheet(&com.handle());
+    block_on(sheet.put_property_async("Name", &Variant::from("Totals"))).unwrap();
+    assert_eq!(block_on(sheet.get_property_async("Name")).unwrap(), Variant::from("Totals"));
+    assert_eq!(block_on(sheet.call_method_async("Add", &[Variant::from(2), Variant::from(3)])).unwrap(), Variant::from(5));
+    assert!(block_on(sheet.call_method_async("Missing", &[])).is_err());
+}
+
+#[test]
+fn wakes_the_task() {
+    let com = ComThread::spawn().unwrap();
+    let (sheet, on_start, finish) = sheet(&com.handle());
+    let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+    let waker = Waker::from(wakes.clone());
+    let mut context = Context::from_waker(&waker);
+
+    let mut future = pin!(sheet.call_method_async("Wait", &[]));
+    on_start.recv().unwrap();
+    assert!(future.as_mut().poll(&mut context).is_pending());
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+
+    finish.send(()).unwrap();
+    while wakes.0.load(Ordering::SeqCst) == 0 {
+        thread::yield_now();
+    }
+    let Poll::Ready(result) = future.as_mut().poll(&mut context) else { panic!("woken before it finished") };
+    // objects in results arrive as proxies
+    let range = result.unwrap().into_dispatch().unwrap();
+    assert_eq!(range.get_property("Address").unwrap(), Variant::from("$A$1"));
+}
+
+#[test]
+fn cancel_queued() {
+    let com = ComThread::spawn().unwrap();
+    let handle = com.handle();
+    let (sheet, on_start, finish) = sheet(&handle);
+
+    let waiting = sheet.call_method_async("Wait", &[]);
+    on_start.recv().unwrap();
+    // queued behind the wait, and dropped before it gets a chance to run
+    drop(sheet.call_method_async("Add", &[Variant::from(1), Variant::from(1)]));
+    finish.send(()).unwrap();
+    drop(block_on(waiting).unwrap());
+    assert_eq!(calls(&handle, "Add"), 0);
+    assert_eq!(block_on(sheet.call_method_async("Add", &[Variant::from(1), Variant::from(1)])).unwrap(), Variant::from(2));
+}
+
+#[test]
+fn cancel_running() {
+    let com = ComThread::spawn().unwrap();
+    let handle = com.handle();
+    let (sheet, on_start, finish) = sheet(&handle);
+
+    // the call can't be stopped once it's started, but what it returns is released
+    let waiting = sheet.call_method_async("Wait", &[]);
+    on_start.recv().unwrap();
+    drop(waiting);
+    finish.send(()).unwrap();
+    assert_eq!((calls(&handle, "Wait"), range_refs(&handle)), (1, 0));
+
+    // and the same when it finished before the future was dropped, without ever being polled
+    let waiting = sheet.call_method_async("Wait", &[]);
+    on_start.recv().unwrap();
+    finish.send(()).unwrap();
+    assert_eq!(calls(&handle, "Wait"), 2);
+    assert_eq!(range_refs(&handle), 1);
+    drop(waiting);
+    assert_eq!(range_refs(&handle), 0);
+}
+
+#[test]
+fn stopped() {
+    let com = ComThread::spawn().unwrap();
+    let (sheet, _, _) = sheet(&com.handle());
+    drop(com);
+    assert!(block_on(sheet.get_property_async("Name")).is_err());
+    assert!(block_on(sheet.put_property_async("Name", &Variant::from("Totals"))).is_err());
+}
@@ -0,0 +1,247 @@
+//
+// objects living on a ComThread, used from other threads through proxies.  the mocks can't leave
+// the thread they're made on, so they're made in the jobs and kept in a thread local there
+//
+
+#![allow(clippy::needless_return)]
+
+use std::cell::RefCell;
+use std::thread;
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::typelib::{Function, Param, PARAMFLAG_FHASDEFAULT, PARAMFLAG_FIN, TypeDesc, TypeInfo, TypeKind, VT_VOID};
+use hello_com_rust::{Argument, Automation, ComHandle, ComThread, Dispatch, Error, InvokeKind, RemoteDispatch, SafeArray, Variant, VarType};
+
+thread_local! {
+    static MOCKS: RefCell<Vec<MockObject>> = const { RefCell::new(Vec::new()) };
+}
+
+// keeps the mock on the com thread for the test to look at later with mock()
+fn keep(object: MockObject) -> MockObject {
+    MOCKS.with(|mocks| mocks.borrow_mut().push(object.clone()));
+    return object;
+}
+
+fn mock(index: usize) -> MockObject {
+    return MOCKS.with(|mocks| mocks.borrow()[index].clone());
+}
+
+fn workbook(handle: &ComHandle) -> RemoteDispatch {
+    return handle.create(|| {
+        let sheet = keep(MockObject::new("Worksheet").with_property("Name", "Sheet1"));
+        let sheets = MockObject::new("Sheets").with_items(vec![Variant::from(sheet.to_dispatch())]);
+        let workbook = keep(MockObject::new("Workbook")
+            .with_property("ActiveSheet", sheet.to_dispatch())
+            .with_property("Sheets", sheets.to_dispatch())
+            .with_property("Saved", false)
+            .with_method("Double", |call| Ok(Variant::from(i32::try_from(call.arg(0).unwrap())? * 2)))
+            .with_method("Swap", |call| {
+                call.args.swap(0, 1);
+                return Ok(Variant::Empty);
+            })
+            .with_method("SameSheet", |call| {
+                let sheet = call.arg(0).unwrap().to_dispatch()?;
+                return Ok(Variant::from(sheet.get_property("Name")? == Variant::from("Sheet1")));
+            }));
+        return Ok(workbook.to_dispatch());
+    }).unwrap();
+}
+
+fn send_and_sync<T: Send + Sync>() {}
+
+#[test]
+fn calls_from_many_threads() {
+    send_and_sync::<ComHandle>();
+    send_and_sync::<RemoteDispatch>();
+
+    let com = ComThread::spawn().unwrap();
+    let workbook = workbook(&com.handle());
+
+    let threads: Vec<_> = (0..4).map(|n| {
+        let workbook = workbook.clone();
+        thread::spawn(move || {
+            for i in 0..25 {
+                assert_eq!(workbook.call_method("Double", &[Variant::from(n * 100 + i)]).unwrap(), Variant::from((n * 100 + i) * 2));
+                assert_eq!(workbook.eval("ActiveSheet.Name").unwrap(), Variant::from("Sheet1"));
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    workbook.put_property("Saved", &Variant::from(true)).unwrap();
+    assert_eq!(workbook.get_property("Saved").unwrap(), Variant::from(true));
+    let calls = com.handle().run(|| Ok(mock(1).calls_to("Double").len())).unwrap();
+    assert_eq!(calls, 100);
+}
+
+#[test]
+fn by_ref_arguments() {
+    let com = ComThread::spawn().unwrap();
+    let workbook = workbook(&com.handle());
+    let (mut a, mut b) = (Variant::from("a"), Variant::from(2));
+    workbook.call_method_by_ref("Swap", &mut [Argument::ByRef(&mut a), Argument::ByRefVariant(&mut b)]).unwrap();
+    assert_eq!((a, b), (Variant::from(2), Variant::from("a")));
+}
+
+#[test]
+fn resolve_in_one_trip() {
+    let com = ComThread::spawn().unwrap();
+    let workbook = Dispatch::new(workbook(&com.handle()));
+    workbook.resolve(&["Saved", "Double", "Swap"]).unwrap();
+    assert_eq!(workbook.dispid_cache().len(), 3);
+    assert_eq!(com.handle().run(|| Ok(mock(1).lookup_count())).unwrap(), 1);
+}
+
+// made on both sides, a TypeInfo can't be sent any more than the Variants in it
+fn workbook_info() -> TypeInfo {
+    let protect = Function {
+        name: "Protect".to_string(),
+        memid: 1,
+        invoke_kind: InvokeKind::Method,
+        doc: None,
+        return_type: TypeDesc::Base(VT_VOID),
+        params: vec![Param { name: "Password".to_string(), type_desc: TypeDesc::Base(8), flags: PARAMFLAG_FIN | PARAMFLAG_FHASDEFAULT, default: Some(Variant::from("secret")) }],
+        optional_params: 0,
+        flags: 0,
+    };
+    return TypeInfo {
+        name: "_Workbook".to_string(), kind: TypeKind::Dispatch, guid: None, doc: None, flags: 0, major_version: 1, minor_version: 0,
+        implements: vec![], alias: None, functions: vec![protect], variables: vec![],
+    };
+}
+
+#[test]
+fn type_info() {
+    let com = ComThread::spawn().unwrap();
+    let workbook = com.handle().create(|| Ok(MockObject::new("Workbook").with_type_info(workbook_info()).to_dispatch())).unwrap();
+    // the default value comes back along with the rest of it
+    assert_eq!(workbook.type_info().unwrap(), workbook_info());
+    assert_eq!(workbook.type_info().unwrap().functions[0].params[0].default, Some(Variant::from("secret")));
+
+    let plain = com.handle().create(|| Ok(MockObject::new("Workbook").to_dispatch())).unwrap();
+    assert_eq!(plain.type_info().unwrap_err().hresult(), hresult::E_NOTIMPL);
+}
+
+#[test]
+fn objects_and_collections() {
+    let com = ComThread::spawn().unwrap();
+    let handle = com.handle();
+    let workbook = workbook(&handle);
+
+    // objects in results come back as proxies, the same one for the same object
+    let active = workbook.get_property("ActiveSheet").unwrap().into_dispatch().unwrap();
+    let again = workbook.get_property("ActiveSheet").unwrap().into_dispatch().unwrap();
+    assert!(active == again);
+    let items: Vec<Dispatch> = workbook.get_property("Sheets").unwrap().into_dispatch().unwrap()
+        .iter().map(|item| item.unwrap().into_dispatch().unwrap()).collect();
+    assert!(items.len() == 1 && items[0] == active);
+
+    // and can be passed back, or on to another thread
+    assert_eq!(workbook.call_method("SameSheet", &[Variant::from(active.clone())]).unwrap(), Variant::from(true));
+    let remote = handle.remote(&active).unwrap();
+    let name = thread::spawn(move || String::try_from(remote.get_property("Name").unwrap()).unwrap()).join().unwrap();
+    assert_eq!(name, "Sheet1");
+
+    // objects from anywhere else can't be
+    let local = MockObject::new("Worksheet").to_dispatch();
+    assert_eq!(workbook.call_method("SameSheet", &[Variant::from(local.clone())]).unwrap_err().hresult(), hresult::E_INVALIDARG);
+    assert!(handle.remote(&local).is_err_and(|e| e.hresult() == hresult::E_INVALIDARG));
+}
+
+#[test]
+fn objects_in_arrays() {
+    let com = ComThread::spawn().unwrap();
+    let handle = com.handle();
+    let workbook = workbook(&handle);
+    let active = workbook.get_property("ActiveSheet").unwrap().into_dispatch().unwrap();
+
+    // an array of objects goes over and comes back with the same proxies in it
+    let array = SafeArray::from_vec(VarType::Dispatch, vec![Variant::from(active.clone()), Variant::Null]).unwrap();
+    let (mut sheets, mut other) = (Variant::Array(array), Variant::from(1));
+    workbook.call_method_by_ref("Swap", &mut [Argument::ByRefVariant(&mut sheets), Argument::ByRefVariant(&mut other)]).unwrap();
+    let Variant::Array(array) = other else { panic!("expected an array, got {:?}", other) };
+    assert_eq!((array.vt(), array.len()), (VarType::Dispatch, 2));
+    assert!(array.elements()[0].to_dispatch().unwrap() == active);
+    assert_eq!(array.elements()[1], Variant::Null);
+
+    // an object that isn't from the thread can't go in one
+    let local = SafeArray::from_vec(VarType::Variant, vec![Variant::from(MockObject::new("Worksheet").to_dispatch())]).unwrap();
+    assert_eq!(workbook.call_method("SameSheet", &[Variant::Array(local)]).unwrap_err().hresult(), hresult::E_INVALIDARG);
+
+    // and the sheet's released once the proxies in the array are gone too
+    let count = |handle: &ComHandle| handle.run(|| Ok(mock(0).ref_count())).unwrap();
+    drop(active);
+    let held = count(&handle);
+    drop(array);
+    assert_eq!(count(&handle), held - 1);
+}
+
+#[test]
+fn enumerator_errors() {
+    let com = ComThread::spawn().unwrap();
+    let workbook = com.handle().create(|| {
+        let sheets = MockObject::new("Sheets").with_items(Vec::new());
+        sheets.reject_calls(1, hresult::RPC_E_CALL_REJECTED);
+        return Ok(MockObject::new("Workbook").with_property("Sheets", sheets.to_dispatch()).to_dispatch());
+    }).unwrap();
+
+    // the failure getting the enumerator on the thread comes back rather than an empty collection
+    let sheets = workbook.get_property("Sheets").unwrap().into_dispatch().unwrap();
+    let items: Vec<Result<Variant,Error>> = sheets.iter().collect();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].as_ref().unwrap_err().hresult(), hresult::RPC_E_CALL_REJECTED);
+    assert_eq!(sheets.iter().count(), 0);
+}
+
+#[test]
+fn releases() {
+    let com = ComThread::spawn().unwrap();
+    let handle = com.handle();
+    let counts = |handle: &ComHandle| handle.run(|| Ok((mock(0).ref_count(), mock(1).ref_count()))).unwrap();
+
+    let workbook = workbook(&handle);
+    // the workbook's property holds the sheet, the thread holds the workbook
+    assert_eq!(counts(&handle), (2, 1));
+    let active = workbook.get_property("ActiveSheet").unwrap().into_dispatch().unwrap();
+    let more: Vec<Dispatch> = (0..3).map(|_| workbook.eval("ActiveSheet").unwrap().into_dispatch().unwrap()).collect();
+    assert_eq!(counts(&handle), (3, 1));
+
+    drop(active);
+    assert_eq!(counts(&handle), (3, 1));
+    drop(more);
+    assert_eq!(counts(&handle), (2, 1));
+    drop(workbook);
+    assert_eq!(counts(&handle), (2, 0));
+}
+
+#[test]
+fn run_on_the_thread() {
+    let com = ComThread::spawn().unwrap();
+    let handle = com.handle();
+    let thread = handle.run(|| Ok(thread::current().id())).unwrap();
+    assert_ne!(thread, thread::current().id());
+    assert!(handle.run(|| -> Result<(),Error> { Err(Error::result("failed")) }).is_err_and(|e| e == Error::result("failed")));
+
+    // a job that panics fails on its own and leaves the thread running
+    assert!(handle.run(|| -> Result<(),Error> { panic!("job panicked") }).is_err());
+    assert_eq!(handle.run(|| Ok(1)).unwrap(), 1);
+
+    // waiting on the thread from the thread itself would never return
+    let inner = handle.clone();
+    assert!(handle.run(move || Ok(inner.run(|| Ok(())).is_err())).unwrap());
+}
+
+#[test]
+fn stopped() {
+    let com = ComThread::spawn().unwrap();
+    let handle = com.handle();
+    let workbook = workbook(&handle);
+    let sheets = workbook.get_property("Sheets").unwrap().into_dispatch().unwrap();
+    drop(com);
+    assert!(handle.run(|| Ok(())).is_err());
+    assert!(workbook.get_property("ActiveSheet").is_err());
+    assert!(workbook.invoke(0, InvokeKind::PropertyGet, &mut [], &[], &mut []).is_err());
+    drop((workbook, sheets));
+}
@@ -0,0 +1,158 @@
+//
+// TryFrom<&Variant>, checked against what VariantChangeType gives under LOCALE_INVARIANT
+//
+
+#![allow(clippy::needless_return)]
+
+use chrono::{NaiveDate, NaiveDateTime};
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Currency, Decimal, Dispatch, Error, hresult, OleDate, Variant};
+
+fn date_time(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> NaiveDateTime {
+    return NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, s).unwrap();
+}
+
+fn is_mismatch(result: Result<impl std::fmt::Debug, Error>) -> bool {
+    return matches!(result, Err(e) if e.hresult() == hresult::DISP_E_TYPEMISMATCH);
+}
+
+fn is_overflow(result: Result<impl std::fmt::Debug, Error>) -> bool {
+    return matches!(result, Err(e) if e.hresult() == hresult::DISP_E_OVERFLOW);
+}
+
+#[test]
+fn integers() {
+    // VT_I8 used to be read from the wrong union member
+    assert_eq!(i32::try_from(&Variant::I8(70_000)).unwrap(), 70_000);
+    assert_eq!(Variant::I8(-5).to_i32().unwrap(), -5);
+    assert_eq!(i64::try_from(&Variant::UI4(u32::MAX)).unwrap(), u32::MAX as i64);
+    assert_eq!(u8::try_from(&Variant::I2(255)).unwrap(), 255);
+    assert_eq!(i16::try_from(Variant::Empty).unwrap(), 0);
+
+    assert!(is_overflow(i32::try_from(&Variant::I8(1 << 40))));
+    assert!(is_overflow(u8::try_from(&Variant::I4(256))));
+    assert!(is_overflow(u32::try_from(&Variant::I4(-1))));
+    assert!(is_overflow(i64::try_from(&Variant::UI8(u64::MAX))));
+    assert!(is_overflow(i32::try_from(&Variant::R8(1e300))));
+    assert!(is_overflow(i32::try_from(&Variant::R8(f64::NAN))));
+    assert!(is_mismatch(i32::try_from(&Variant::Null)));
+    assert!(is_mismatch(i32::try_from(&Variant::Error(0))));
+}
+
+#[test]
+fn bankers_rounding() {
+    let round = |v: f64| i32::try_from(&Variant::R8(v)).unwrap();
+    assert_eq!([round(0.5), round(1.5), round(2.5), round(-2.5), round(2.6), round(-0.4)], [0, 2, 2, -2, 3, 0]);
+    assert_eq!(i64::try_from(&Variant::Cy(Currency::from_raw(25_000))).unwrap(), 2);
+    assert_eq!(i64::try_from(&Variant::Cy(Currency::from_raw(35_000))).unwrap(), 4);
+    assert_eq!(u8::try_from(&Variant::Decimal(Decimal::new(-5, 1))).unwrap(), 0);
+    assert_eq!(Currency::try_from(&Variant::R8(0.00005)).unwrap(), Currency::from_raw(0));
+    assert_eq!(Currency::try_from(&Variant::R8(0.00015)).unwrap(), Currency::from_raw(2));
+}
+
+#[test]
+fn booleans() {
+    // VARIANT_TRUE is -1
+    assert_eq!(i32::try_from(&Variant::Bool(true)).unwrap(), -1);
+    assert_eq!(u16::try_from(&Variant::Bool(true)).unwrap(), u16::MAX);
+    assert_eq!(f64::try_from(&Variant::Bool(true)).unwrap(), -1.0);
+    assert!(bool::try_from(&Variant::I4(2)).unwrap());
+    assert!(bool::try_from(&Variant::R8(0.1)).unwrap());
+    assert!(!bool::try_from(&Variant::Empty).unwrap());
+    assert!(bool::try_from(&Variant::from(" TRUE ")).unwrap());
+    assert!(bool::try_from(&Variant::from("-1")).unwrap());
+    assert!(!bool::try_from(&Variant::from("0")).unwrap());
+    assert!(is_mismatch(bool::try_from(&Variant::from("yes"))));
+}
+
+#[test]
+fn strings_to_numbers() {
+    let parse = |s: &str| i32::try_from(&Variant::from(s));
+    assert_eq!(parse(" 42 ").unwrap(), 42);
+    assert_eq!(parse("-1,234").unwrap(), -1234);
+    assert_eq!(parse("(12)").unwrap(), -12);
+    assert_eq!(parse("&HFF").unwrap(), 255);
+    assert_eq!(parse("&o17").unwrap(), 15);
+    assert_eq!(parse("2.5").unwrap(), 2);
+    assert_eq!(parse("1e3").unwrap(), 1000);
+    assert!(is_overflow(parse("3000000000")));
+    for bad in ["", "abc", "1.2.3", ",1", "1e", "--1", "1 2", "12abc"] {
+        assert!(is_mismatch(parse(bad)), "{:?}", bad);
+    }
+    assert_eq!(f64::try_from(&Variant::from(".5")).unwrap(), 0.5);
+    assert_eq!(f64::try_from(&Variant::from("1.5E-3")).unwrap(), 0.0015);
+    assert_eq!(Decimal::try_from(&Variant::from("123.45")).unwrap(), Decimal::new(12345, 2));
+    assert_eq!(Currency::try_from(&Variant::from("1,000.5")).unwrap(), Currency::from_raw(10_005_000));
+}
+
+#[test]
+fn numbers_to_strings() {
+    let format = |v: Variant| String::try_from(&v).unwrap();
+    assert_eq!(format(Variant::I4(-7)), "-7");
+    assert_eq!(format(Variant::UI8(u64::MAX)), "18446744073709551615");
+    assert_eq!(format(Variant::Bool(true)), "True");
+    assert_eq!(format(Variant::Bool(false)), "False");
+    assert_eq!(format(Variant::Empty), "");
+    assert_eq!(format(Variant::R8(0.1 + 0.2)), "0.3");
+    assert_eq!(format(Variant::R8(2.5)), "2.5");
+    assert_eq!(format(Variant::R8(1e15)), "1E+15");
+    assert_eq!(format(Variant::R8(123456789012345.0)), "123456789012345");
+    assert_eq!(format(Variant::R8(0.00001)), "0.00001");
+    assert_eq!(format(Variant::R8(-1.5e-7)), "-1.5E-07");
+    assert_eq!(format(Variant::R4(0.1)), "0.1");
+    assert_eq!(format(Variant::R4(16777216.0)), "1.677722E+07");
+    assert_eq!(format(Variant::Cy(Currency::from_raw(12_500))), "1.25");
+    assert_eq!(format(Variant::Decimal(Decimal::new(1000, 2))), "10");
+    assert!(is_mismatch(String::try_from(&Variant::Null)));
+}
+
+#[test]
+fn floats_and_decimals() {
+    assert_eq!(f32::try_from(&Variant::R8(0.5)).unwrap(), 0.5);
+    assert!(is_overflow(f32::try_from(&Variant::R8(1e39))));
+    assert_eq!(f64::try_from(&Variant::Cy(Currency::from_raw(1))).unwrap(), 0.0001);
+    // doubles go through their 15 significant digits
+    assert_eq!(Decimal::try_from(&Variant::R8(0.1)).unwrap(), Decimal::new(1, 1));
+    assert_eq!(Variant::R8(2.75).to_decimal().unwrap(), Decimal::new(275, 2));
+    assert!(is_overflow(Decimal::try_from(&Variant::R8(1e30))));
+    assert!(is_overflow(Currency::try_from(&Variant::R8(1e19))));
+}
+
+#[test]
+fn dates() {
+    let noon = date_time(2024, 2, 29, 12, 0, 0);
+    assert_eq!(NaiveDateTime::try_from(&Variant::from("02/29/2024 12:00")).unwrap(), noon);
+    assert_eq!(NaiveDateTime::try_from(&Variant::from("2024-02-29T12:00:00")).unwrap(), noon);
+    assert_eq!(NaiveDateTime::try_from(&Variant::from("2/29/2024 12:00:00 PM")).unwrap(), noon);
+    assert_eq!(NaiveDate::try_from(&Variant::from("2024-02-29")).unwrap(), noon.date());
+    assert_eq!(NaiveDateTime::try_from(&Variant::from("06:30")).unwrap(), date_time(1899, 12, 30, 6, 30, 0));
+    assert_eq!(NaiveDateTime::try_from(&Variant::from("6:30:15 pm")).unwrap(), date_time(1899, 12, 30, 18, 30, 15));
+    assert!(is_mismatch(NaiveDateTime::try_from(&Variant::from("02/30/2024"))));
+
+    // numbers are days since the epoch
+    assert_eq!(NaiveDateTime::try_from(&Variant::R8(1.5)).unwrap(), date_time(1899, 12, 31, 12, 0, 0));
+    assert_eq!(OleDate::try_from(&Variant::I4(2)).unwrap(), OleDate::from_raw(2.0));
+    assert_eq!(f64::try_from(&Variant::from(noon)).unwrap(), OleDate::from(noon).to_raw());
+    assert!(is_overflow(OleDate::try_from(&Variant::R8(1e7))));
+
+    let format = |v: NaiveDateTime| String::try_from(&Variant::from(v)).unwrap();
+    assert_eq!(format(date_time(2024, 2, 29, 13, 5, 9)), "02/29/2024 13:05:09");
+    assert_eq!(format(date_time(2024, 2, 29, 0, 0, 0)), "02/29/2024");
+    assert_eq!(format(date_time(1899, 12, 30, 13, 5, 9)), "13:05:09");
+}
+
+#[test]
+fn objects() {
+    // an object converts through its default property
+    let cell = MockObject::new("Range").with_property("Address", "A1").with_property("Value", "42");
+    let value = Variant::from(cell.to_dispatch());
+    assert_eq!(i32::try_from(&value).unwrap(), 42);
+    assert_eq!(String::try_from(&value).unwrap(), "42");
+    assert_eq!(cell.calls_to("Value").len(), 2);
+    assert!(Dispatch::try_from(&value).unwrap() == cell.to_dispatch());
+
+    let no_value = Variant::from(MockObject::new("Workbook").to_dispatch());
+    assert_eq!(i32::try_from(&no_value).unwrap_err().hresult(), hresult::DISP_E_MEMBERNOTFOUND);
+    assert!(Dispatch::try_from(&Variant::I4(1)).is_err_and(|e| e.hresult() == hresult::DISP_E_TYPEMISMATCH));
+    assert!(is_mismatch(i32::try_from(&Variant::from(vec![Variant::I4(1)]))));
+}
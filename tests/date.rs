@@ -0,0 +1,70 @@
+//
+// OLE automation dates to and from chrono, including the odd way negative serials count time
+//
+
+#![allow(clippy::needless_return)]
+
+use chrono::{NaiveDate, NaiveDateTime};
+use hello_com_rust::OleDate;
+
+fn at(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32, ms: u32) -> NaiveDateTime {
+    return NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_milli_opt(h, min, s, ms).unwrap();
+}
+
+#[test]
+fn serials() {
+    assert_eq!(OleDate::from_raw(0.0).to_date_time().unwrap(), at(1899, 12, 30, 0, 0, 0, 0));
+    assert_eq!(OleDate::from_raw(1.5).to_date_time().unwrap(), at(1899, 12, 31, 12, 0, 0, 0));
+    assert_eq!(OleDate::from_raw(45_000.25).to_date_time().unwrap(), at(2023, 3, 15, 6, 0, 0, 0));
+    assert_eq!(OleDate::from(at(2023, 3, 15, 6, 0, 0, 0)).to_raw(), 45_000.25);
+}
+
+#[test]
+fn negative_serials() {
+    // the whole part counts back but the fraction still counts forward from midnight
+    assert_eq!(OleDate::from_raw(-1.25).to_date_time().unwrap(), at(1899, 12, 29, 6, 0, 0, 0));
+    assert_eq!(OleDate::from_raw(-0.5).to_date_time().unwrap(), at(1899, 12, 30, 12, 0, 0, 0));
+    assert_eq!(OleDate::from_raw(0.5).to_date_time().unwrap(), at(1899, 12, 30, 12, 0, 0, 0));
+    assert_eq!(OleDate::from(at(1899, 12, 29, 6, 0, 0, 0)).to_raw(), -1.25);
+    assert_eq!(OleDate::from(at(1899, 12, 30, 12, 0, 0, 0)).to_raw(), 0.5);
+}
+
+#[test]
+fn limits() {
+    let min = at(100, 1, 1, 0, 0, 0, 0);
+    assert_eq!(OleDate::from(min).to_raw(), OleDate::MIN);
+    assert_eq!(OleDate::from_raw(OleDate::MIN).to_date_time().unwrap(), min);
+
+    let max = at(9999, 12, 31, 23, 59, 59, 0);
+    assert_eq!(OleDate::from(max).to_date_time().unwrap(), max);
+    assert_eq!(OleDate::from(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap()).to_date().unwrap(), max.date());
+}
+
+#[test]
+fn out_of_range() {
+    for raw in [OleDate::MIN - 1.0, OleDate::MAX, OleDate::MAX + 1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(OleDate::from_raw(raw).to_date_time().is_err(), "{} should be out of range", raw);
+        assert!(OleDate::from_raw(raw).to_date().is_err());
+    }
+    // shown as the raw number when it isn't a date
+    assert_eq!(OleDate::from_raw(OleDate::MAX).to_string(), "2958466");
+}
+
+#[test]
+fn milliseconds() {
+    let with_millis = at(2024, 2, 29, 13, 45, 30, 123);
+    assert_eq!(OleDate::from(with_millis).to_date_time().unwrap(), with_millis);
+
+    // finer than a millisecond rounds to the nearest one
+    let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+    let up = date.and_hms_nano_opt(13, 45, 30, 123_600_000).unwrap();
+    assert_eq!(OleDate::from(up).to_date_time().unwrap(), at(2024, 2, 29, 13, 45, 30, 124));
+    let down = date.and_hms_nano_opt(13, 45, 30, 123_400_000).unwrap();
+    assert_eq!(OleDate::from(down).to_date_time().unwrap(), at(2024, 2, 29, 13, 45, 30, 123));
+
+    // and can carry all the way into the next day
+    let last = date.and_hms_nano_opt(23, 59, 59, 999_600_000).unwrap();
+    assert_eq!(OleDate::from(last).to_date_time().unwrap(), at(2024, 3, 1, 0, 0, 0, 0));
+    let before_epoch = NaiveDate::from_ymd_opt(1899, 12, 28).unwrap().and_hms_nano_opt(23, 59, 59, 999_900_000).unwrap();
+    assert_eq!(OleDate::from(before_epoch).to_date_time().unwrap(), at(1899, 12, 29, 0, 0, 0, 0));
+}
@@ -0,0 +1,69 @@
+//
+// exact VT_CY currencies, which only round when asked to
+//
+
+#![allow(clippy::needless_return)]
+
+use std::str::FromStr;
+use hello_com_rust::{Currency, Decimal, RoundingStrategy};
+
+fn decimal(value: &str) -> Decimal {
+    return Decimal::from_str(value).unwrap();
+}
+
+#[test]
+fn parse() {
+    assert_eq!("12.3456".parse::<Currency>().unwrap().to_raw(), 123_456);
+    assert_eq!(" -0.5 ".parse::<Currency>().unwrap().to_raw(), -5_000);
+    assert_eq!("922337203685477.5807".parse::<Currency>().unwrap(), Currency::MAX);
+    assert_eq!("-922337203685477.5808".parse::<Currency>().unwrap(), Currency::MIN);
+    assert!("922337203685477.5808".parse::<Currency>().is_err());
+    assert!("-922337203685477.5809".parse::<Currency>().is_err());
+    assert!("1.23456".parse::<Currency>().is_err());
+    assert!("twelve".parse::<Currency>().is_err());
+    assert_eq!(Currency::from_raw(123_456).to_string(), "12.3456");
+    assert_eq!(Currency::from_raw(10_000).to_string(), "1");
+}
+
+#[test]
+fn from_decimal() {
+    assert_eq!(Currency::from_decimal(&decimal("0.1234")).unwrap().to_raw(), 1_234);
+    // trailing zeros aren't decimal places that need rounding
+    assert_eq!(Currency::from_decimal(&decimal("0.123400000")).unwrap().to_raw(), 1_234);
+    assert!(Currency::from_decimal(&decimal("0.12345")).is_err());
+    assert!(Currency::from_decimal(&decimal("1000000000000000")).is_err());
+    assert!(Currency::try_from(decimal("-1000000000000000")).is_err());
+    assert_eq!(Decimal::from(Currency::from_raw(-1)), decimal("-0.0001"));
+}
+
+#[test]
+fn rounded() {
+    let half_even = RoundingStrategy::MidpointNearestEven;
+    assert_eq!(Currency::from_decimal_rounded(&decimal("0.12345"), half_even).unwrap().to_raw(), 1_234);
+    assert_eq!(Currency::from_decimal_rounded(&decimal("0.12355"), half_even).unwrap().to_raw(), 1_236);
+    assert_eq!(Currency::from_decimal_rounded(&decimal("0.12345"), RoundingStrategy::MidpointAwayFromZero).unwrap().to_raw(), 1_235);
+    assert_eq!(Currency::from_decimal_rounded(&decimal("-0.00005"), RoundingStrategy::MidpointAwayFromZero).unwrap().to_raw(), -1);
+    assert!(Currency::from_decimal_rounded(&decimal("922337203685477.58075"), half_even).is_err());
+}
+
+#[test]
+fn units() {
+    assert_eq!(Currency::from_units(3).unwrap().to_raw(), 30_000);
+    assert!(Currency::from_units(i64::MAX / 10_000 + 1).is_err());
+}
+
+#[test]
+fn checked_arithmetic() {
+    let one = Currency::from_units(1).unwrap();
+    let cent = "0.01".parse::<Currency>().unwrap();
+    assert_eq!(one.checked_add(cent).unwrap().to_string(), "1.01");
+    assert_eq!(one.checked_sub(cent).unwrap().to_string(), "0.99");
+    assert_eq!(cent.checked_neg().unwrap().to_string(), "-0.01");
+    assert_eq!(cent.checked_mul(250).unwrap().to_string(), "2.5");
+
+    assert!(Currency::MAX.checked_add(Currency::from_raw(1)).is_err());
+    assert!(Currency::MIN.checked_sub(Currency::from_raw(1)).is_err());
+    assert!(Currency::MIN.checked_neg().is_err());
+    assert!(Currency::MAX.checked_mul(2).is_err());
+    assert_eq!(Currency::MAX.checked_neg().unwrap().checked_sub(Currency::from_raw(1)).unwrap(), Currency::MIN);
+}
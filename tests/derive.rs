@@ -0,0 +1,178 @@
+//
+// structs filled in from, and written back to, mock objects with the derives
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::mock::MockObject;
+use chrono::NaiveDate;
+use hello_com_rust::{Decimal, Dispatch, Error, FromDispatch, hresult, IntoDispatch, OleDate, Variant};
+
+#[derive(Debug, PartialEq, FromDispatch, IntoDispatch)]
+#[dispatch(rename_all = "PascalCase")]
+struct Customer {
+    account_ref: String,
+    #[dispatch(rename = "NAME")]
+    company: String,
+    // read from a string
+    balance: Decimal,
+    // read from an I2
+    credit_limit: f64,
+    on_hold: bool,
+    #[dispatch(rename = "EMail")]
+    email: Option<String>,
+    telephone: Option<String>,
+    #[dispatch(default)]
+    discount: i32,
+    #[dispatch(skip)]
+    notes: Vec<String>,
+    #[dispatch(read_only)]
+    count: i64,
+    address: Address,
+}
+
+#[derive(Debug, PartialEq, FromDispatch, IntoDispatch)]
+struct Address {
+    street: String,
+    town: String,
+}
+
+fn record() -> (MockObject, MockObject) {
+    let address = MockObject::new("Address")
+        .with_property("Street", "1 High Street")
+        .with_property("Town", "Newcastle");
+    let record = MockObject::new("SalesRecord")
+        .with_property("AccountRef", "A001")
+        .with_property("Name", "Acme Ltd")
+        .with_property("Balance", "123.45")
+        .with_property("CreditLimit", Variant::I2(500))
+        .with_property("OnHold", Variant::I4(0))
+        .with_property("EMail", Variant::Null)
+        .with_property("Telephone", Variant::Empty)
+        .with_property("Count", 7)
+        .with_property("Address", address.to_dispatch());
+    return (record, address);
+}
+
+fn acme() -> Customer {
+    return Customer {
+        account_ref: "A001".to_string(),
+        company: "Acme Ltd".to_string(),
+        balance: Decimal::new(12345, 2),
+        credit_limit: 500.0,
+        on_hold: false,
+        email: None,
+        telephone: None,
+        discount: 0,
+        notes: vec![],
+        count: 7,
+        address: Address { street: "1 High Street".to_string(), town: "Newcastle".to_string() },
+    };
+}
+
+#[test]
+fn from_dispatch() {
+    let (record, _) = record();
+    assert_eq!(Customer::from_dispatch(&record.to_dispatch()).unwrap(), acme());
+    assert!(record.calls_to("Notes").is_empty());
+
+    record.set_property("EMail", "accounts@acme.test");
+    record.set_property("Discount", 5);
+    let customer = Customer::from_dispatch(&record.to_dispatch()).unwrap();
+    assert_eq!(customer.email.as_deref(), Some("accounts@acme.test"));
+    assert_eq!(customer.discount, 5);
+}
+
+#[test]
+fn into_dispatch() {
+    let (record, address) = record();
+    record.set_property("Discount", 0);
+    let mut customer = acme();
+    customer.company = "Acme Holdings".to_string();
+    customer.on_hold = true;
+    customer.telephone = Some("0191 000 0000".to_string());
+    customer.count = 8;
+    customer.address.town = "Gateshead".to_string();
+    customer.put_into(&record.to_dispatch()).unwrap();
+
+    assert_eq!(record.property("Name"), Some(Variant::from("Acme Holdings")));
+    assert_eq!(record.property("OnHold"), Some(Variant::from(true)));
+    assert_eq!(record.property("Balance"), Some(Variant::Decimal(Decimal::new(12345, 2))));
+    // None isn't written, and neither are read only or skipped fields
+    assert!(record.calls_to("EMail").iter().all(|call| !call.kind.is_put()));
+    assert_eq!(record.property("Count"), Some(Variant::from(7)));
+    assert!(record.calls_to("Notes").is_empty());
+    assert_eq!(record.property("Telephone"), Some(Variant::from("0191 000 0000")));
+    // the nested object is updated in place
+    assert_eq!(address.property("Town"), Some(Variant::from("Gateshead")));
+    assert_eq!(record.property("Address"), Some(Variant::from(address.to_dispatch())));
+}
+
+#[test]
+fn errors() {
+    let (record, _) = record();
+    record.set_property("Balance", "lots");
+    let err = Customer::from_dispatch(&record.to_dispatch()).unwrap_err();
+    assert!(matches!(&err, Error::Conversion(message) if message.starts_with("Balance: ")), "{:?}", err);
+
+    // a missing property that isn't optional is an error
+    let empty = MockObject::new("Address").with_property("Street", "1 High Street");
+    assert_eq!(Address::from_dispatch(&empty.to_dispatch()).unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+}
+
+#[derive(FromDispatch)]
+struct Workbook {
+    name: String,
+    // a collection of nested objects
+    sheets: Vec<Sheet>,
+    // any other object is kept as it is
+    application: Dispatch,
+}
+
+#[derive(Debug, PartialEq, FromDispatch)]
+struct Sheet {
+    name: String,
+    index: i32,
+}
+
+#[test]
+fn collections() {
+    let sheet = |name: &str, index: i32| Variant::from(MockObject::new("Worksheet").with_property("Name", name).with_property("Index", index).to_dispatch());
+    let sheets = MockObject::new("Sheets").with_items(vec![sheet("Sheet1", 1), sheet("Totals", 2)]);
+    let application = MockObject::new("Application").to_dispatch();
+    let workbook = MockObject::new("Workbook")
+        .with_property("Name", "Book1")
+        .with_property("Sheets", sheets.to_dispatch())
+        .with_property("Application", application.clone());
+
+    let workbook = Workbook::from_dispatch(&workbook.to_dispatch()).unwrap();
+    assert_eq!(workbook.sheets, vec![
+        Sheet { name: "Sheet1".to_string(), index: 1 },
+        Sheet { name: "Totals".to_string(), index: 2 },
+    ]);
+    assert!(workbook.application == application);
+    assert_eq!(workbook.name, "Book1");
+}
+
+#[derive(Debug, PartialEq, FromDispatch, IntoDispatch)]
+#[dispatch(rename_all = "PascalCase")]
+struct Invoice {
+    invoice_date: NaiveDate,
+    due: OleDate,
+}
+
+#[test]
+fn dates() {
+    let date = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+    let invoice = MockObject::new("Invoice")
+        .with_property("InvoiceDate", date)
+        .with_property("Due", OleDate::from(NaiveDate::from_ymd_opt(2024, 4, 30).unwrap()));
+    let mut read = Invoice::from_dispatch(&invoice.to_dispatch()).unwrap();
+    assert_eq!(read.invoice_date, date);
+
+    // and written back as dates
+    read.invoice_date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+    read.put_into(&invoice.to_dispatch()).unwrap();
+    assert_eq!(invoice.property("InvoiceDate"), Some(Variant::from(NaiveDate::from_ymd_opt(2024, 4, 1).unwrap())));
+    assert_eq!(Invoice::from_dispatch(&invoice.to_dispatch()).unwrap(), read);
+}
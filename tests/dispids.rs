@@ -0,0 +1,117 @@
+//
+// the dispid cache a Dispatch keeps, counted by the lookups that reach a mock
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Automation, DispidCache, Variant};
+
+fn field() -> MockObject {
+    return MockObject::new("Field")
+        .with_property("Name", "NAME")
+        .with_property("Value", "Acme Ltd")
+        .with_method("Item", |call| Ok(call.arg(0).cloned().unwrap_or_default()));
+}
+
+#[test]
+fn repeated_lookups_hit_the_cache() {
+    let field = field();
+    let dispatch = field.to_dispatch();
+    for _ in 0..10 {
+        assert_eq!(dispatch.get_property("Name").unwrap(), Variant::from("NAME"));
+        assert_eq!(dispatch.get_property("value").unwrap(), Variant::from("Acme Ltd"));
+    }
+    // names are case insensitive, so Value and value are the same lookup
+    assert_eq!(field.lookup_count(), 2);
+    assert_eq!(field.calls().len(), 20);
+
+    // clones share the cache
+    dispatch.clone().put_property("Name", &Variant::from("CODE")).unwrap();
+    assert_eq!(field.lookup_count(), 2);
+
+    // named parameters are cached along with their member
+    dispatch.call_method_named("Item", &[], &[("Index", Variant::from(1))]).unwrap();
+    dispatch.call_method_named("Item", &[], &[("Index", Variant::from(2))]).unwrap();
+    assert_eq!(field.lookup_count(), 3);
+
+    // but a separate Dispatch for the same object has its own
+    field.to_dispatch().get_property("Name").unwrap();
+    assert_eq!(field.lookup_count(), 4);
+}
+
+#[test]
+fn invalidate_re_resolves() {
+    let field = field();
+    let dispatch = field.to_dispatch();
+    dispatch.get_property("Name").unwrap();
+    dispatch.get_property("Name").unwrap();
+    assert_eq!(field.lookup_count(), 1);
+
+    dispatch.invalidate_dispids();
+    assert!(dispatch.dispid_cache().is_empty());
+    dispatch.get_property("Name").unwrap();
+    assert_eq!(field.lookup_count(), 2);
+
+    // forgetting one member only re-resolves that one
+    dispatch.get_property("Value").unwrap();
+    dispatch.dispid_cache().remove("name");
+    dispatch.get_property("Value").unwrap();
+    dispatch.get_property("Name").unwrap();
+    assert_eq!(field.lookup_count(), 4);
+}
+
+#[test]
+fn resolve_in_bulk() {
+    let field = field();
+    let dispatch = field.to_dispatch();
+    dispatch.get_property("Name").unwrap();
+
+    // the names not cached yet go in a single lookup
+    dispatch.resolve(&["Name", "Value", "Item"]).unwrap();
+    assert_eq!(field.lookup_count(), 2);
+    assert_eq!(dispatch.dispid_cache().len(), 3);
+    dispatch.resolve(&["Name", "Value", "Item"]).unwrap();
+    dispatch.get_property("Value").unwrap();
+    assert_eq!(field.lookup_count(), 2);
+
+    // any unknown name fails the lookup and nothing from it is cached
+    let dispatch = field.to_dispatch();
+    assert_eq!(dispatch.resolve(&["Name", "Missing"]).unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+    assert!(dispatch.dispid_cache().is_empty());
+}
+
+#[test]
+fn shared_between_objects() {
+    let (first, second) = (field(), field());
+    let cache = DispidCache::new();
+    let a = first.to_dispatch().with_dispid_cache(&cache);
+    let b = second.to_dispatch().with_dispid_cache(&cache);
+    assert_eq!(a.get_property("Value").unwrap(), b.get_property("Value").unwrap());
+    assert_eq!((first.lookup_count(), second.lookup_count()), (1, 0));
+    assert_eq!(cache.get("value"), Some(2));
+}
+
+#[test]
+fn cache_keys() {
+    let cache = DispidCache::new();
+    assert!(cache.is_empty());
+
+    // names are case insensitive, and parameters are kept with their member
+    cache.insert("Name", 1);
+    cache.insert_with_params("Item", &["Index", "Default"], &[2, 0, 1]);
+    assert_eq!(cache.get("NAME"), Some(1));
+    assert_eq!(cache.get_with_params("item", &["default", "index"]), Some(vec![2, 1, 0]));
+    assert_eq!(cache.get_with_params("Item", &["Missing"]), None);
+    assert_eq!(cache.len(), 4);
+
+    // clones share the same mappings
+    let shared = cache.clone();
+    shared.remove("item");
+    assert_eq!(cache.get("Item"), None);
+    assert_eq!(cache.get_with_params("Item", &["Index"]), None);
+    assert_eq!(cache.len(), 1);
+    shared.clear();
+    assert!(cache.is_empty());
+}
@@ -0,0 +1,505 @@
+#!/usr/bin/env python3
+#
+# writes sdo.tlb (MSFT) and sdo_sltg.tlb (SLTG), the fixtures tests/typelib.rs reads.  the real
+# SDO and Excel type libraries can't be shipped with the repo, so these are small stand-ins with
+# the same shapes (an enum, a record, dispinterfaces with optional and default parameters, a
+# coclass, aliases and a reference into stdole2.tlb), laid out the way midl and the vb5 era tools
+# lay out the two formats.  run it from anywhere, the files land next to it
+#
+
+import os
+import struct
+import uuid
+
+VT_EMPTY, VT_I2, VT_I4, VT_BSTR, VT_DISPATCH, VT_BOOL, VT_VARIANT, VT_UNKNOWN = 0, 2, 3, 8, 9, 11, 12, 13
+VT_VOID, VT_HRESULT, VT_PTR, VT_SAFEARRAY, VT_CARRAY, VT_USERDEFINED = 24, 25, 26, 27, 28, 29
+
+TKIND_ENUM, TKIND_RECORD, TKIND_DISPATCH, TKIND_COCLASS, TKIND_ALIAS = 0, 1, 4, 5, 6
+INVOKE_FUNC, INVOKE_PROPERTYGET, INVOKE_PROPERTYPUT = 1, 2, 4
+PARAMFLAG_FIN, PARAMFLAG_FOPT, PARAMFLAG_FHASDEFAULT = 0x1, 0x10, 0x20
+TYPEFLAG_FCANCREATE, TYPEFLAG_FDISPATCHABLE = 0x2, 0x1000
+FUNCFLAG_FRESTRICTED, FUNCFLAG_FHIDDEN = 0x1, 0x40
+VARFLAG_FREADONLY = 0x1
+
+STDOLE = "00020430-0000-0000-C000-000000000046"
+IDISPATCH = "00020400-0000-0000-C000-000000000046"
+
+#
+# the library.  types are ("base", vt), ("ptr", type), ("safearray", type), ("carray", type, count),
+# ("local", index) or ("idispatch",)
+#
+
+def base(vt): return ("base", vt)
+def ptr(t): return ("ptr", t)
+
+def param(name, type, flags=PARAMFLAG_FIN, default=None):
+    return {"name": name, "type": type, "flags": flags, "default": default}
+
+def func(name, memid, invkind, ret, params=(), optional=0, flags=0, doc=None):
+    return {"name": name, "memid": memid, "invkind": invkind, "ret": ret, "params": list(params), "optional": optional, "flags": flags, "doc": doc}
+
+def var(name, memid, type, kind, value=None, flags=0, doc=None):
+    return {"name": name, "memid": memid, "type": type, "kind": kind, "value": value, "flags": flags, "doc": doc}
+
+LIBRARY = {
+    "name": "SDOTest",
+    "doc": "Sage Data Objects test library",
+    "guid": "9C1D3A10-2B4E-4F5A-8C6D-7E8F90A1B2C3",
+    "version": (29, 0),
+    "lcid": 0x409,
+}
+
+TYPES = [
+    {"name": "AccountType", "kind": TKIND_ENUM, "guid": None, "flags": 0, "doc": "Kinds of account", "funcs": [], "vars": [
+        var("sdoSales", 0x40000000, base(VT_I4), "const", 1),
+        var("sdoPurchase", 0x40000001, base(VT_I4), "const", 2),
+        var("sdoNominal", 0x40000002, base(VT_I4), "const", -1),
+    ]},
+    {"name": "Bounds", "kind": TKIND_RECORD, "guid": None, "flags": 0, "doc": None, "funcs": [], "vars": [
+        var("Lower", 0x40000000, base(VT_I4), "field", 0),
+        var("Count", 0x40000001, base(VT_I4), "field", 4),
+        var("Reserved", 0x40000002, ("carray", base(VT_I2), 4), "field", 8),
+    ]},
+    {"name": "IFields", "kind": TKIND_DISPATCH, "guid": "A1B2C3D4-0001-4000-8000-00AA00BB00CC", "flags": TYPEFLAG_FDISPATCHABLE, "doc": None, "vars": [], "funcs": [
+        func("Count", 1, INVOKE_PROPERTYGET, base(VT_I4)),
+        func("Item", 0, INVOKE_PROPERTYGET, base(VT_VARIANT), [param("Index", base(VT_VARIANT))]),
+        func("_NewEnum", -4, INVOKE_PROPERTYGET, base(VT_UNKNOWN), flags=FUNCFLAG_FRESTRICTED | FUNCFLAG_FHIDDEN),
+    ]},
+    {"name": "IWorkSpace", "kind": TKIND_DISPATCH, "guid": "A1B2C3D4-0002-4000-8000-00AA00BB00CC", "flags": TYPEFLAG_FDISPATCHABLE, "doc": "A connection to a company", "funcs": [
+        func("Connect", 1, INVOKE_FUNC, base(VT_BOOL), [
+            param("DataPath", base(VT_BSTR)),
+            param("UserName", base(VT_BSTR)),
+            param("Password", base(VT_BSTR)),
+            param("Name", base(VT_VARIANT), PARAMFLAG_FIN | PARAMFLAG_FOPT),
+        ], optional=1, doc="Connects to the company in DataPath"),
+        func("Name", 2, INVOKE_PROPERTYGET, base(VT_BSTR)),
+        func("Name", 2, INVOKE_PROPERTYPUT, base(VT_VOID), [param("Value", base(VT_BSTR))]),
+        func("Disconnect", 3, INVOKE_FUNC, base(VT_VOID)),
+        func("CreateObject", 4, INVOKE_FUNC, ptr(("idispatch",)), [param("Name", base(VT_BSTR))]),
+        func("Fields", 5, INVOKE_PROPERTYGET, ptr(("local", 2)), [
+            param("Key", base(VT_BSTR), PARAMFLAG_FIN | PARAMFLAG_FOPT | PARAMFLAG_FHASDEFAULT, ("bstr", "ACCOUNT_REF")),
+            param("Flags", base(VT_I4), PARAMFLAG_FIN | PARAMFLAG_FOPT | PARAMFLAG_FHASDEFAULT, ("i4", 1)),
+        ], optional=2),
+    ], "vars": [
+        var("Connected", 6, base(VT_BOOL), "dispatch", flags=VARFLAG_FREADONLY),
+    ]},
+    {"name": "WorkSpace", "kind": TKIND_COCLASS, "guid": "A1B2C3D4-0003-4000-8000-00AA00BB00CC", "flags": TYPEFLAG_FCANCREATE, "doc": None, "funcs": [], "vars": [],
+     "implements": [(("local", 3), 1)]},
+    {"name": "AccountKind", "kind": TKIND_ALIAS, "guid": None, "flags": 0, "doc": None, "funcs": [], "vars": [], "alias": ("local", 0)},
+    {"name": "NameList", "kind": TKIND_ALIAS, "guid": None, "flags": 0, "doc": None, "funcs": [], "vars": [], "alias": ("safearray", base(VT_BSTR))},
+]
+
+
+def guid_bytes(text):
+    return uuid.UUID(text).bytes_le
+
+def pad4(data):
+    return data + b"\0" * (-len(data) % 4)
+
+def i16(v): return struct.pack("<h", v)
+def u16(v): return struct.pack("<H", v)
+def i32(v): return struct.pack("<i", v)
+def u32(v): return struct.pack("<I", v)
+
+
+#
+# MSFT
+#
+
+class Msft:
+    def __init__(self):
+        self.guids = b""
+        self.guid_offsets = {}
+        self.names = b""
+        self.name_offsets = {}
+        self.strings = b""
+        self.typedescs = b""
+        self.arraydescs = b""
+        self.custdata = b""
+        self.reftab = b""
+        self.impinfo = b""
+        self.impfiles = b""
+
+    def guid(self, text):
+        if text is None:
+            return -1
+        if text not in self.guid_offsets:
+            self.guid_offsets[text] = len(self.guids)
+            self.guids += guid_bytes(text) + i32(-1) + i32(-1)
+        return self.guid_offsets[text]
+
+    def name(self, text):
+        if text not in self.name_offsets:
+            self.name_offsets[text] = len(self.names)
+            # the hreftype, the next name with the same hash, the length with the hash above it
+            self.names += pad4(i32(-1) + i32(-1) + u32(len(text) | (0x1234 << 16)) + text.encode("latin-1"))
+        return self.name_offsets[text]
+
+    def string(self, text):
+        if text is None:
+            return -1
+        offset = len(self.strings)
+        self.strings += pad4(i16(len(text)) + text.encode("latin-1"))
+        return offset
+
+    def import_dispatch(self):
+        if not self.impinfo:
+            self.impfiles += pad4(i32(self.guid(STDOLE)) + u32(0) + u16(2) + u16(0) + u16(len("stdole2.tlb") << 2) + b"stdole2.tlb")
+            # the count and flags (the type is named by its guid), the file, the guid
+            self.impinfo += i32(1 | 0x10000 | (TKIND_DISPATCH << 24)) + i32(0) + i32(self.guid(IDISPATCH))
+        return 0 | 1
+
+    def type(self, t):
+        if t[0] == "base":
+            return -0x80000000 | (t[1] << 16) | t[1]
+        if t[0] == "local":
+            return self.typedesc(VT_USERDEFINED, t[1] * 0x64)
+        if t[0] == "idispatch":
+            return self.typedesc(VT_USERDEFINED, self.import_dispatch())
+        if t[0] == "ptr":
+            return self.typedesc(VT_PTR, self.type(t[1]))
+        if t[0] == "safearray":
+            return self.typedesc(VT_SAFEARRAY, self.type(t[1]))
+        if t[0] == "carray":
+            offset = len(self.arraydescs)
+            self.arraydescs += i32(self.type(t[1])) + u16(1) + u16(0) + u32(t[2]) + i32(0)
+            return self.typedesc(VT_CARRAY, offset)
+        raise ValueError(t)
+
+    def typedesc(self, vt, inner):
+        offset = len(self.typedescs)
+        self.typedescs += u16(vt) + u16(0x7ffe) + i32(inner)
+        return offset
+
+    def value(self, value):
+        kind, v = value if isinstance(value, tuple) else ("i4", value)
+        if kind == "i4" and 0 <= v < (1 << 26):
+            return -0x80000000 | (VT_I4 << 26) | v
+        offset = len(self.custdata)
+        if kind == "i4":
+            self.custdata += pad4(u16(VT_I4) + i32(v))
+        else:
+            self.custdata += pad4(u16(VT_BSTR) + i32(len(v)) + v.encode("latin-1"))
+        return offset
+
+    def members(self, t):
+        funcs, vars = t["funcs"], t["vars"]
+        records, memids, names = b"", [], []
+        for i, f in enumerate(funcs):
+            has_defaults = any(p["default"] is not None for p in f["params"])
+            fkccic = 4 | (f["invkind"] << 3) | (4 << 8) | (0x1000 if has_defaults else 0)
+            body = i32(self.type(f["ret"])) + i32(f["flags"]) + i16(0) + i16(0) + i32(fkccic) + i16(len(f["params"])) + i16(f["optional"])
+            if f["doc"] is not None:
+                body += i32(0) + i32(self.string(f["doc"]))
+            if has_defaults:
+                body += b"".join(i32(self.value(p["default"])) if p["default"] is not None else i32(-1) for p in f["params"])
+            body += b"".join(i32(self.type(p["type"])) + i32(self.name(p["name"])) + i32(p["flags"]) for p in f["params"])
+            records += u32((len(body) + 4) | (i << 16)) + body
+            memids.append(f["memid"])
+            # the second half of a property's get/put pair doesn't repeat the name
+            same = i > 0 and funcs[i - 1]["name"] == f["name"] and f["invkind"] != INVOKE_FUNC
+            names.append(-1 if same else self.name(f["name"]))
+        for i, v in enumerate(vars):
+            kind = {"field": 0, "const": 2, "dispatch": 3}[v["kind"]]
+            value = self.value(v["value"]) if kind == 2 else (v["value"] or 0)
+            body = i32(self.type(v["type"])) + i32(v["flags"]) + i16(kind) + i16(0) + i32(value)
+            if v["doc"] is not None:
+                body += i32(0) + i32(self.string(v["doc"]))
+            records += u32((len(body) + 4) | ((len(funcs) + i) << 16)) + body
+            memids.append(v["memid"])
+            names.append(self.name(v["name"]))
+        offsets = [0] * len(memids)
+        return i32(len(records)) + records + b"".join(i32(m) for m in memids) + b"".join(i32(n) for n in names) + b"".join(i32(o) for o in offsets)
+
+    def write(self):
+        typeinfos = []
+        member_blocks = []
+        for index, t in enumerate(TYPES):
+            datatype1, impl_count = -1, 0
+            if t["kind"] == TKIND_COCLASS:
+                datatype1 = len(self.reftab)
+                for j, (ref, flags) in enumerate(t["implements"]):
+                    last = j == len(t["implements"]) - 1
+                    self.reftab += i32(ref[1] * 0x64) + i32(flags) + i32(-1) + i32(-1 if last else len(self.reftab) + 16)
+                impl_count = len(t["implements"])
+            elif t["kind"] == TKIND_ALIAS:
+                datatype1 = self.type(t["alias"])
+            elif t["kind"] == TKIND_DISPATCH:
+                datatype1 = -1
+            members = self.members(t) if t["funcs"] or t["vars"] else None
+            member_blocks.append(members)
+            typeinfos.append({
+                "kind": t["kind"], "elements": len(t["funcs"]) | (len(t["vars"]) << 16), "guid": self.guid(t["guid"]),
+                "flags": t["flags"], "name": self.name(t["name"]), "doc": self.string(t["doc"]), "impls": impl_count,
+                "datatype1": datatype1,
+            })
+        library_name = self.name(LIBRARY["name"])
+        library_guid = self.guid(LIBRARY["guid"])
+        library_doc = self.string(LIBRARY["doc"])
+
+        segments = [b""] * 15
+        segments[3] = self.reftab
+        segments[1] = self.impinfo
+        segments[2] = self.impfiles
+        segments[4] = b"\xff" * 0x80
+        segments[5] = self.guids
+        segments[6] = b"\xff" * 0x200
+        segments[7] = self.names
+        segments[8] = self.strings
+        segments[9] = self.typedescs
+        segments[10] = self.arraydescs
+        segments[11] = self.custdata
+
+        header_len = 0x54 + len(TYPES) * 4 + 15 * 16
+        # the type info table's own size is known up front, member data goes after every segment
+        segments[0] = b"\0" * (len(TYPES) * 0x64)
+        offset = header_len
+        segment_offsets = []
+        for s in segments:
+            segment_offsets.append(offset if s else -1)
+            offset += len(s)
+        member_offsets = []
+        for block in member_blocks:
+            member_offsets.append(offset if block else -1)
+            offset += len(block or b"")
+
+        table = b""
+        for info, memoffset in zip(typeinfos, member_offsets):
+            table += (i32(info["kind"] | (4 << 11)) + i32(memoffset) + i32(0) + i32(-1) + i32(3) + i32(0) + u32(info["elements"])
+                + i32(0) * 4 + i32(info["guid"]) + i32(info["flags"]) + i32(info["name"]) + u32(0) + i32(info["doc"])
+                + i32(0) + i32(0) + i32(-1) + i16(info["impls"]) + i16(7 * 4) + i32(0) + i32(info["datatype1"]) + i32(0) + i32(0) + i32(-1))
+        assert len(table) == len(TYPES) * 0x64
+        segments[0] = table
+
+        version = LIBRARY["version"][0] | (LIBRARY["version"][1] << 16)
+        header = (b"MSFT" + u32(0x00010002) + i32(library_guid) + u32(LIBRARY["lcid"]) + u32(0) + i32(0x41) + u32(version) + u32(0)
+            + i32(len(TYPES)) + i32(library_doc) + i32(0) + i32(0) + i32(len(self.name_offsets)) + i32(len(self.names)) + i32(library_name)
+            + i32(-1) + i32(-1) + i32(0x20) + i32(0x80) + i32(-1) + i32(len(self.impinfo) // 12))
+        assert len(header) == 0x54
+        header += b"".join(i32(i * 0x64) for i in range(len(TYPES)))
+        for s, o in zip(segments, segment_offsets):
+            header += i32(o) + i32(len(s)) + i32(-1) + i32(0x0f)
+        assert len(header) == header_len
+        return header + b"".join(segments) + b"".join(b or b"" for b in member_blocks)
+
+
+#
+# SLTG
+#
+
+class Sltg:
+    def __init__(self):
+        self.names = b""
+        self.name_offsets = {}
+
+    def name(self, text):
+        # each name is preceded by a byte that isn't a letter or digit, which is how a parameter
+        # name pointing at its first letter is told apart from one pointing at its second
+        if text not in self.name_offsets:
+            self.names += b"\xff"
+            self.name_offsets[text] = len(self.names)
+            self.names += text.encode("latin-1") + b"\0"
+        return self.name_offsets[text]
+
+    def raw_name(self, text):
+        offset = len(self.names)
+        self.names += text.encode("latin-1") + b"\0"
+        return offset
+
+    @staticmethod
+    def string(text):
+        if text is None:
+            return u16(0xffff)
+        return u16(len(text)) + text.encode("latin-1")
+
+    def type_words(self, t, refs, data, flags=0):
+        # the words for a type, with data being the member data so far for things that need an offset
+        if t[0] == "base":
+            return u16(t[1] | flags)
+        if t[0] == "local":
+            return u16(VT_USERDEFINED | flags) + u16(refs.index(("local", t[1])) * 4)
+        if t[0] == "idispatch":
+            return u16(VT_USERDEFINED | flags) + u16(refs.index(("idispatch",)) * 4)
+        if t[0] == "ptr":
+            # a pointer to a local type uses the pointer flag, anything else a VT_PTR word
+            if t[1][0] == "local":
+                return self.type_words(t[1], refs, data, flags | 0xe00)
+            return u16(VT_PTR | flags) + self.type_words(t[1], refs, data)
+        if t[0] == "safearray":
+            return u16(VT_SAFEARRAY | flags) + u16(0) + self.type_words(t[1], refs, data)
+        raise ValueError(t)
+
+    def collect_refs(self, t):
+        refs = []
+        def visit(x):
+            if x[0] in ("local", "idispatch"):
+                if x not in refs:
+                    refs.append(x)
+            elif x[0] in ("ptr", "safearray", "carray"):
+                visit(x[1])
+        for f in t["funcs"]:
+            visit(f["ret"])
+            for p in f["params"]:
+                visit(p["type"])
+        for v in t["vars"]:
+            visit(v["type"])
+        for ref, _ in t.get("implements", []):
+            visit(ref)
+        if "alias" in t:
+            visit(t["alias"])
+        return refs
+
+    def ref_table(self, refs):
+        if not refs:
+            return b""
+        table = b"\xdf\x00" + b"\xff" * 0x40 + u16(0xffff) + u32(len(refs) * 8) + b"\0" * (len(refs) * 8) + u16(0xffff) + b"\x01" + u32(0)
+        for ref in refs:
+            if ref[0] == "local":
+                text = "*\\Rffff*#%x" % ref[1]
+            else:
+                text = "*\\R%x*#4" % self.stdole
+            table += self.string(text)
+        return table + b"\xdf"
+
+    def functions(self, funcs, refs, start, data):
+        # every function's fixed part, then its parameters, laid out one after the other
+        out = b""
+        for i, f in enumerate(funcs):
+            here = start + len(data) + len(out)
+            fixed_len = 0x18 if f["flags"] else 0x16
+            ret = self.type_words(f["ret"], refs, data)
+            inline_ret = len(ret) == 2
+            args = b""
+            extra = b""
+            for p in f["params"]:
+                words = self.type_words(p["type"], refs, data)
+                if p["type"] == base(VT_VARIANT) and p["flags"] & PARAMFLAG_FOPT:
+                    # the type at an offset, with the name pointing at its first letter
+                    args += u16(self.name(p["name"])) + u16(0)
+                    extra += words
+                else:
+                    args += u16(self.name(p["name"]) + 1) + words
+            arg_off = here + fixed_len + (0 if inline_ret else len(ret))
+            body_len = fixed_len + (0 if inline_ret else len(ret)) + len(args) + len(extra)
+            # patch the offsets of out of line parameter types now their position is known
+            if extra:
+                extra_at = arg_off + len(args)
+                args = args.replace(u16(self.name(f["params"][-1]["name"])) + u16(0), u16(self.name(f["params"][-1]["name"])) + u16(extra_at))
+            ret_at = here + fixed_len
+            next_at = here + body_len if i < len(funcs) - 1 else 0xffff
+            magic = 0xcb | (0x20 if f["flags"] else 0)
+            record = (bytes([magic, (f["invkind"] << 4) | 2]) + u16(next_at) + u16(self.name(f["name"])) + i32(f["memid"]) + u16(0xfffe) + u16(0xffff)
+                + u16(arg_off) + bytes([(len(f["params"]) << 3) | 4, (0x80 if inline_ret else 0) | (f["optional"] << 1)])
+                + (ret if inline_ret else u16(ret_at)) + u16(0))
+            if f["flags"]:
+                record += u16(f["flags"])
+            assert len(record) == fixed_len
+            out += record + (b"" if inline_ret else ret) + args + extra
+        return out
+
+    def variables(self, vars, refs, start, data):
+        out = b""
+        for i, v in enumerate(vars):
+            here = start + len(data) + len(out)
+            fixed_len = 0x12
+            extra = b""
+            if v["type"][0] == "carray":
+                # the type at an offset: VT_CARRAY, the offset of a 32 bit SAFEARRAY, the element type
+                array_at = here + fixed_len
+                extra = u16(1) + u16(0) + u32(2) + u32(0) + u32(0) + u32(v["type"][2]) + i32(0)
+                type_at = array_at + len(extra)
+                extra += u16(VT_CARRAY) + u16(array_at) + self.type_words(v["type"][1], refs, data)
+                type_field, flags = u16(type_at), 0
+            else:
+                type_field, flags = self.type_words(v["type"], refs, data), 0x02
+            if v["kind"] == "const":
+                flags |= 0x10 | 0x08
+                byte_offs = v["value"] & 0xffff
+            elif v["kind"] == "dispatch":
+                flags |= 0x40
+                byte_offs = 0
+            else:
+                byte_offs = v["value"]
+            if v["flags"] & VARFLAG_FREADONLY:
+                flags |= 0x80
+            next_at = here + fixed_len + len(extra) if i < len(vars) - 1 else 0xffff
+            out += (bytes([0x0a, flags]) + u16(next_at) + u16(self.name(v["name"])) + u16(byte_offs) + type_field
+                + i32(v["memid"]) + u16(0xfffe) + u16(0xffff)) + extra
+        return out
+
+    def type_block(self, t):
+        refs = self.collect_refs(t)
+        ref_table = self.ref_table(refs)
+        data = b""
+        impls_off = funcs_off = vars_off = 0xffff
+        alias = 0
+        if "implements" in t:
+            impls_off = 0
+            for j, (ref, flags) in enumerate(t["implements"]):
+                last = j == len(t["implements"]) - 1
+                data += (u16(0x004a) + u16(0xffff if last else len(data) + 0x16) + u16(0xffff) + bytes([flags, 0x80]) + u16(0x0012)
+                    + u16(refs.index(ref)) + u16(0x4000) + u16(0xfffe) + u16(0xffff) + u16(0x001d) + u16(0))
+        if "alias" in t:
+            alias = len(data)
+            data += self.type_words(t["alias"], refs, data)
+        if t["vars"]:
+            vars_off = len(data)
+            data += self.variables(t["vars"], refs, 0, data)
+        if t["funcs"]:
+            funcs_off = len(data)
+            data += self.functions(t["funcs"], refs, 0, data)
+
+        tail = (u16(len(t["funcs"])) + u16(len(t["vars"])) + u16(len(t.get("implements", []))) + u16(0)
+            + u16(funcs_off) + u16(vars_off) + u16(impls_off) + u16(0) + u16(0) + u16(0)
+            + u16(alias) + u16(0xffff) + u16(0) + u16(0) + u16(0) + u16(0) + u16(0) + u16(4) + u16(0) + u16(0) + u16(7 * 4)
+            + u16(0xffff) * 4 + u16(0) + u16(0))
+        assert len(tail) == 0x36
+
+        header_len = 0x22
+        href_table = header_len if ref_table else 0xffffffff
+        elem_table = header_len + len(ref_table)
+        flags = t["flags"]
+        header = (u16(0x0501) + u32(href_table) + u32(0xffffffff) + u32(elem_table) + u32(0xffffffff) + u16(1) + u16(0)
+            + u32(0xfffe0000) + bytes([0x02 | ((flags & 0x1f) << 3), flags >> 5, 0x02, t["kind"]]) + u32(0))
+        assert len(header) == header_len
+        member_header = u16(1) + u16(0xffff) + b"\x01" + u32(len(data))
+        return header + ref_table + member_header + data + tail
+
+    def write(self):
+        count = len(TYPES)
+        blocks = [len(TYPES) + 2]
+        self.stdole = self.raw_name("*\\G{%s}#2.0#0#C:\\Windows\\System32\\stdole2.tlb#" % STDOLE.lower())
+        library_name = self.name(LIBRARY["name"])
+        type_blocks = [self.type_block(t) for t in TYPES]
+
+        index_names = ["%s%s" % (chr(ord("A") + i), "A" * 9) for i in range(count + 1)]
+
+        library = (u16(0x51cc) + u16(0x0004) + u16(library_name) + u16(0xffff) + self.string(LIBRARY["doc"]) + self.string(None)
+            + u32(0) + u16(1) + u16(LIBRARY["lcid"]) + u32(0) + u16(0) + u16(LIBRARY["version"][0]) + u16(LIBRARY["version"][1])
+            + guid_bytes(LIBRARY["guid"]))
+        library += b"".join(u16(0xffff) if i % 2 else u16(i // 2) for i in range(0x20))
+        for i, t in enumerate(TYPES):
+            library += (u16(i) + self.string(index_names[i]) + u16(0xffff) + u16(0xffff) + u16(self.name(t["name"])) + u16(0)
+                + u16(0xffff) + u32(0) + u16(0xffff) + guid_bytes(t["guid"] or "00000000-0000-0000-0000-000000000000") + u16(t["kind"]))
+        name_table_at = len(library) + 6
+        library += u16(0x0003) + u32(name_table_at)
+        library += u16(0xffff) + b"\0" * (0x216 - 2) + u16(0)
+        library += self.names
+
+        all_blocks = type_blocks + [library]
+        header = (b"SLTG" + u16(count + 2) + u16(9) + u16(0) + u16(1) + u32(0x000204ff) + u32(0) + u32(0xc0) + u32(0x46000000)
+            + u32(0x44) + u32(0xffff0000))
+        for i, block in enumerate(all_blocks):
+            header += u32(len(block)) + u16(13 + i * 11) + u16(i + 2 if i < len(all_blocks) - 1 else 0)
+        header += b"\x01CompObj\0dir\0"
+        header += b"".join(name.encode("latin-1") + b"\0" for name in index_names)
+        header += b"\0" * 9
+        return header + b"".join(all_blocks)
+
+
+if __name__ == "__main__":
+    here = os.path.dirname(os.path.abspath(__file__))
+    with open(os.path.join(here, "sdo.tlb"), "wb") as f:
+        f.write(Msft().write())
+    with open(os.path.join(here, "sdo_sltg.tlb"), "wb") as f:
+        f.write(Sltg().write())
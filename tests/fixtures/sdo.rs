@@ -0,0 +1,134 @@
+// generated from the SDOTest 29.0 type library (Sage Data Objects test library) by hello-com-rust's codegen, don't edit
+
+#[allow(dead_code, unused_imports, non_camel_case_types, non_snake_case, non_upper_case_globals, clippy::all)]
+pub mod sdo_test {
+    use hello_com_rust::{Argument, Automation, Currency, Decimal, Dispatch, DispatchIter, DISPID_PROPERTYPUT, Error, InvokeKind, OleDate, Unknown, Variant};
+
+    // AccountType, Kinds of account
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct AccountType(pub i32);
+
+    impl AccountType {
+        pub const sdoSales: AccountType = AccountType(1);
+        pub const sdoPurchase: AccountType = AccountType(2);
+        pub const sdoNominal: AccountType = AccountType(-1);
+    }
+
+    impl From<AccountType> for Variant {
+        fn from(value: AccountType) -> Variant {
+            return Variant::I4(value.0);
+        }
+    }
+
+    // IFields
+    #[derive(Clone, PartialEq)]
+    pub struct IFields(pub Dispatch);
+
+    impl From<Dispatch> for IFields {
+        fn from(dispatch: Dispatch) -> IFields {
+            return IFields(dispatch);
+        }
+    }
+
+    impl IFields {
+        pub const IID: &'static str = "{A1B2C3D4-0001-4000-8000-00AA00BB00CC}";
+
+        pub fn dispatch(&self) -> &Dispatch {
+            return &self.0;
+        }
+
+        // the items of the collection, through its _NewEnum
+        pub fn iter(&self) -> DispatchIter {
+            return self.0.iter();
+        }
+
+        pub fn count(&self) -> Result<i32,Error> {
+            let value = self.0.invoke(1, InvokeKind::PropertyGet, &mut [], &[], &mut [])?;
+            return Ok(i32::try_from(&value)?);
+        }
+
+        pub fn item(&self, index: &Variant) -> Result<Variant,Error> {
+            let value = self.0.invoke(0, InvokeKind::PropertyGet, &mut [Argument::ByVal(&index.clone())], &[], &mut [])?;
+            return Ok(value);
+        }
+    }
+
+    // IWorkSpace, A connection to a company
+    #[derive(Clone, PartialEq)]
+    pub struct IWorkSpace(pub Dispatch);
+
+    impl From<Dispatch> for IWorkSpace {
+        fn from(dispatch: Dispatch) -> IWorkSpace {
+            return IWorkSpace(dispatch);
+        }
+    }
+
+    impl IWorkSpace {
+        pub const IID: &'static str = "{A1B2C3D4-0002-4000-8000-00AA00BB00CC}";
+
+        pub fn dispatch(&self) -> &Dispatch {
+            return &self.0;
+        }
+
+        // Connects to the company in DataPath
+        pub fn connect(&self, data_path: &str, user_name: &str, password: &str, name: Option<&Variant>) -> Result<bool,Error> {
+            let value = self.0.invoke(1, InvokeKind::Method, &mut [
+                Argument::ByVal(&Variant::from(data_path)),
+                Argument::ByVal(&Variant::from(user_name)),
+                Argument::ByVal(&Variant::from(password)),
+                Argument::ByVal(&name.map_or_else(Variant::missing, |name| name.clone())),
+            ], &[], &mut [])?;
+            return Ok(bool::try_from(&value)?);
+        }
+
+        pub fn name(&self) -> Result<String,Error> {
+            let value = self.0.invoke(2, InvokeKind::PropertyGet, &mut [], &[], &mut [])?;
+            return Ok(String::try_from(&value)?);
+        }
+
+        pub fn set_name(&self, value: &str) -> Result<(),Error> {
+            self.0.invoke(2, InvokeKind::PropertyPut, &mut [], &[DISPID_PROPERTYPUT], &mut [Argument::ByVal(&Variant::from(value))])?;
+            return Ok(());
+        }
+
+        pub fn disconnect(&self) -> Result<(),Error> {
+            self.0.invoke(3, InvokeKind::Method, &mut [], &[], &mut [])?;
+            return Ok(());
+        }
+
+        pub fn create_object(&self, name: &str) -> Result<Dispatch,Error> {
+            let value = self.0.invoke(4, InvokeKind::Method, &mut [Argument::ByVal(&Variant::from(name))], &[], &mut [])?;
+            return Ok(value.into_dispatch()?);
+        }
+
+        pub fn fields(&self, key: Option<&str>, flags: Option<i32>) -> Result<IFields,Error> {
+            let value = self.0.invoke(5, InvokeKind::PropertyGet, &mut [
+                Argument::ByVal(&key.map_or_else(Variant::missing, |key| Variant::from(key))),
+                Argument::ByVal(&flags.map_or_else(Variant::missing, |flags| Variant::from(flags))),
+            ], &[], &mut [])?;
+            return Ok(IFields(value.into_dispatch()?));
+        }
+
+        pub fn connected(&self) -> Result<bool,Error> {
+            let value = self.0.invoke(6, InvokeKind::PropertyGet, &mut [], &[], &mut [])?;
+            return Ok(bool::try_from(&value)?);
+        }
+    }
+
+    // WorkSpace
+    pub struct WorkSpace;
+
+    impl WorkSpace {
+        pub const CLSID: &'static str = "{A1B2C3D4-0003-4000-8000-00AA00BB00CC}";
+
+        // creates one, only real COM objects can be created so this is windows only
+        #[cfg(windows)]
+        pub fn create() -> Result<IWorkSpace,Error> {
+            let dispatch = hello_com_rust::co_create_dispatch(&hello_com_rust::clsid_from_string(WorkSpace::CLSID)?)?;
+            return Ok(IWorkSpace(dispatch));
+        }
+    }
+
+    // AccountKind
+    pub type AccountKind = AccountType;
+}
@@ -0,0 +1,148 @@
+//
+// iterating collections, with _NewEnum when there is one and Count/Item when there isn't
+//
+
+#![allow(clippy::needless_return)]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Argument, Automation, Dispatch, Enumerator, Error, InvokeKind, Variant};
+
+fn names(names: &[&str]) -> Vec<Variant> {
+    return names.iter().map(|name| Variant::from(*name)).collect();
+}
+
+// a collection without _NewEnum, only Count and a 1-based Item
+fn indexed(items: Vec<Variant>) -> MockObject {
+    let count = items.len() as i32;
+    return MockObject::new("Fields")
+        .with_property("Count", count)
+        .with_method("Item", move |call| {
+            let index = i32::try_from(call.arg(0).unwrap())?;
+            return match usize::try_from(index - 1).ok().and_then(|i| items.get(i)) {
+                Some(item) => Ok(item.clone()),
+                None => Err(Error::com(hresult::DISP_E_BADINDEX, format!("no item {}", index))),
+            };
+        });
+}
+
+// passes everything through to the collection, noting the size of each batch its enumerator's
+// asked for and how many items came back
+#[derive(Clone)]
+struct Batches {
+    collection: Dispatch,
+    batches: Rc<RefCell<Vec<(usize, usize)>>>,
+}
+
+struct BatchesEnumerator {
+    enumerator: Box<dyn Enumerator>,
+    batches: Rc<RefCell<Vec<(usize, usize)>>>,
+}
+
+impl Enumerator for BatchesEnumerator {
+    fn next_batch(&mut self, count: usize) -> Result<Vec<Variant>,Error> {
+        let items = self.enumerator.next_batch(count)?;
+        self.batches.borrow_mut().push((count, items.len()));
+        return Ok(items);
+    }
+}
+
+impl Automation for Batches {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        return self.collection.get_ids_of_names(name, param_names);
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        return self.collection.invoke(dispid, kind, arguments, named_dispids, named_arguments);
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return self.collection.identity();
+    }
+
+    fn new_enum(&self) -> Result<Option<Box<dyn Enumerator>>,Error> {
+        let Some(enumerator) = self.collection.new_enum()? else { return Ok(None) };
+        return Ok(Some(Box::new(BatchesEnumerator { enumerator, batches: self.batches.clone() })));
+    }
+}
+
+#[test]
+fn with_new_enum() {
+    let sheets = MockObject::new("Sheets").with_items(names(&["a", "b", "c", "d", "e"]));
+    let batches = Rc::new(RefCell::new(Vec::new()));
+    let dispatch = Dispatch::new(Batches { collection: sheets.to_dispatch(), batches: batches.clone() });
+
+    let iter = dispatch.iter().with_batch_size(2);
+    assert!(iter.is_enumerator());
+    assert_eq!(iter.collect::<Result<Vec<Variant>,Error>>().unwrap(), names(&["a", "b", "c", "d", "e"]));
+    // fetched two at a time, a short batch being the end
+    assert_eq!(*batches.borrow(), vec![(2, 2), (2, 2), (2, 1)]);
+    // and never by index
+    assert!(sheets.calls().is_empty());
+
+    batches.borrow_mut().clear();
+    assert_eq!(dispatch.iter().count(), 5);
+    assert_eq!(*batches.borrow(), vec![(64, 5)]);
+}
+
+#[test]
+fn without_new_enum() {
+    let fields = indexed(names(&["NAME", "ADDRESS_1", "BALANCE"]));
+    let dispatch = fields.to_dispatch();
+    let iter = dispatch.iter();
+    assert!(!iter.is_enumerator());
+    assert_eq!(iter.size_hint(), (3, Some(3)));
+    assert_eq!(iter.collect::<Result<Vec<Variant>,Error>>().unwrap(), names(&["NAME", "ADDRESS_1", "BALANCE"]));
+
+    // one Count then Item(1) to Item(3)
+    let indexes: Vec<Variant> = fields.calls_to("Item").into_iter().map(|call| call.args[0].clone()).collect();
+    assert_eq!(indexes, vec![Variant::from(1), Variant::from(2), Variant::from(3)]);
+    assert_eq!(fields.calls_to("Count").len(), 1);
+
+    // a borrowed dispatch iterates the same way
+    let mut seen = Vec::new();
+    for field in &dispatch {
+        seen.push(field.unwrap());
+    }
+    assert_eq!(seen.len(), 3);
+}
+
+#[test]
+fn empty() {
+    let sheets = MockObject::new("Sheets").with_items(Vec::new());
+    assert_eq!(sheets.to_dispatch().iter().count(), 0);
+
+    let fields = indexed(Vec::new());
+    assert_eq!(fields.to_dispatch().iter().count(), 0);
+    assert!(fields.calls_to("Item").is_empty());
+}
+
+#[test]
+fn errors_end_it() {
+    // Count says there are more than Item can give
+    let fields = indexed(names(&["NAME"])).with_property("Count", 3);
+    let items: Vec<Result<Variant,Error>> = fields.to_dispatch().iter().collect();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].as_ref().unwrap(), &Variant::from("NAME"));
+    assert_eq!(items[1].as_ref().unwrap_err().hresult(), hresult::DISP_E_BADINDEX);
+
+    // neither _NewEnum nor Count
+    let object = MockObject::new("Application");
+    let items: Vec<Result<Variant,Error>> = object.to_dispatch().iter().collect();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].as_ref().unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+
+    // _NewEnum failing for any other reason is an error, not a reason to fall back to Count/Item
+    let sheets = MockObject::new("Sheets").with_items(names(&["a", "b"]));
+    sheets.reject_calls(1, hresult::RPC_E_CALL_REJECTED);
+    let items: Vec<Result<Variant,Error>> = sheets.to_dispatch().iter().collect();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].as_ref().unwrap_err().hresult(), hresult::RPC_E_CALL_REJECTED);
+    assert!(sheets.calls().is_empty());
+}
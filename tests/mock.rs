@@ -0,0 +1,110 @@
+//
+// the mock backend itself: members, the calls it records, its errors and rejections
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::hresult;
+use hello_com_rust::mock::{MockCall, MockObject};
+use hello_com_rust::{Argument, Automation, DISPID_VALUE, Error, InvokeKind, Variant};
+
+fn account() -> MockObject {
+    return MockObject::new("Account")
+        .with_property("Name", "Acme Ltd")
+        .with_property("Value", 100)
+        .with_method("Add", |call| {
+            let total = i32::try_from(call.arg(0).unwrap())? + i32::try_from(call.arg(1).unwrap())?;
+            return Ok(Variant::from(total));
+        });
+}
+
+#[test]
+fn properties() {
+    let account = account();
+    let dispatch = account.to_dispatch();
+    assert_eq!(dispatch.get_property("name").unwrap(), Variant::from("Acme Ltd"));
+    dispatch.put_property("Name", &Variant::from("Acme Holdings")).unwrap();
+    assert_eq!(dispatch.get_property("Name").unwrap(), Variant::from("Acme Holdings"));
+    assert_eq!(account.property("NAME"), Some(Variant::from("Acme Holdings")));
+    assert_eq!(account.property("Missing"), None);
+
+    // set from the test side, keeping its dispid
+    let dispid = dispatch.get_id_of_name("Name").unwrap();
+    account.set_property("Name", "Acme Group");
+    assert_eq!(account.to_dispatch().get_id_of_name("Name").unwrap(), dispid);
+    assert_eq!(dispatch.get_property("Name").unwrap(), Variant::from("Acme Group"));
+
+    // a member called Value is also the default member
+    assert_eq!(dispatch.invoke(DISPID_VALUE, InvokeKind::PropertyGet, &mut [], &[], &mut []).unwrap(), Variant::from(100));
+}
+
+#[test]
+fn methods_and_calls() {
+    let account = account();
+    let dispatch = account.to_dispatch();
+    assert_eq!(dispatch.call_method("Add", &[Variant::from(2), Variant::from(3)]).unwrap(), Variant::from(5));
+    dispatch.put_property("Name", &Variant::from("Acme Holdings")).unwrap();
+    dispatch.get_property("Value").unwrap();
+
+    assert_eq!(account.calls(), vec![
+        MockCall { name: String::from("Add"), kind: InvokeKind::Method, args: vec![Variant::from(2), Variant::from(3)], named_args: vec![], value: None },
+        MockCall { name: String::from("Name"), kind: InvokeKind::PropertyPut, args: vec![], named_args: vec![], value: Some(Variant::from("Acme Holdings")) },
+        MockCall { name: String::from("Value"), kind: InvokeKind::PropertyGet, args: vec![], named_args: vec![], value: None },
+    ]);
+    assert_eq!(account.calls_to("add").len(), 1);
+    account.clear_calls();
+    assert!(account.calls().is_empty());
+
+    // a handler's errors come back from the call
+    let error = dispatch.call_method("Add", &[Variant::from("two"), Variant::from(3)]).unwrap_err();
+    assert_eq!(error.hresult(), hresult::DISP_E_TYPEMISMATCH);
+    assert_eq!(account.calls_to("Add").len(), 1);
+}
+
+#[test]
+fn unknown_members() {
+    let account = account();
+    let dispatch = account.to_dispatch();
+    assert_eq!(dispatch.get_property("Balance").unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+    assert_eq!(dispatch.put_property("Balance", &Variant::from(1)).unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+    assert_eq!(dispatch.call_method("Balance", &[]).unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+    assert_eq!(dispatch.invoke(99, InvokeKind::Method, &mut [], &[], &mut []).unwrap_err().hresult(), hresult::DISP_E_MEMBERNOTFOUND);
+
+    // a named argument the member never looked up
+    let error = dispatch.invoke(dispatch.get_id_of_name("Add").unwrap(), InvokeKind::Method, &mut [], &[42], &mut [Argument::ByVal(&Variant::from(1))]).unwrap_err();
+    assert!(matches!(error, Error::Argument { hresult: hresult::DISP_E_PARAMNOTFOUND, index: 0, .. }));
+    assert!(account.calls().is_empty());
+}
+
+#[test]
+fn rejected_calls() {
+    let account = account();
+    let dispatch = account.to_dispatch();
+    account.reject_calls(2, hresult::RPC_E_CALL_REJECTED);
+
+    // lookups are turned away as well as invokes
+    assert_eq!(dispatch.get_property("Name").unwrap_err().hresult(), hresult::RPC_E_CALL_REJECTED);
+    dispatch.resolve(&["Name"]).unwrap_err();
+    assert_eq!(dispatch.get_property("Name").unwrap(), Variant::from("Acme Ltd"));
+    account.reject_calls(1, hresult::RPC_E_SERVERCALL_RETRYLATER);
+    assert_eq!(dispatch.get_property("Name").unwrap_err().hresult(), hresult::RPC_E_SERVERCALL_RETRYLATER);
+    assert_eq!(dispatch.get_property("Name").unwrap(), Variant::from("Acme Ltd"));
+
+    // the rejected ones never reached the members
+    assert_eq!(account.rejected_count(), 3);
+    assert_eq!(account.calls().len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "Account was released more times than it was referenced")]
+fn released_twice() {
+    let account = account();
+    let dispatch = account.to_dispatch();
+    let clone = dispatch.clone();
+    // a stray release leaves the count one short, so it runs out before the last reference goes
+    account.release();
+    assert_eq!(account.ref_count(), 1);
+    drop(clone);
+    assert_eq!(account.ref_count(), 0);
+    drop(dispatch);
+}
@@ -0,0 +1,153 @@
+//
+// how named and positional arguments are laid out for the object, the way IDispatch::Invoke wants
+// them: positional in the caller's order, named paired up with their dispids, and a put's value
+// always the first named argument with DISPID_PROPERTYPUT
+//
+
+#![allow(clippy::needless_return)]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Argument, Automation, Dispatch, DISPID_PROPERTYPUT, Error, hresult, InvokeKind, Variant};
+
+// exactly what reached the object
+#[derive(Clone, Debug, PartialEq)]
+struct Invoked {
+    dispid: i32,
+    kind: InvokeKind,
+    args: Vec<Variant>,
+    named_dispids: Vec<i32>,
+    named_args: Vec<Variant>,
+}
+
+#[derive(Default)]
+struct Log {
+    lookups: Vec<(String, Vec<String>)>,
+    invokes: Vec<Invoked>,
+}
+
+// members are numbered from 1 in the order given and each one's parameters from 100, so Open's
+// Password would be 100 + its index
+#[derive(Clone)]
+struct Spy {
+    members: Vec<(&'static str, Vec<&'static str>)>,
+    log: Rc<RefCell<Log>>,
+}
+
+impl Automation for Spy {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        self.log.borrow_mut().lookups.push((name.to_string(), param_names.iter().map(|p| p.to_string()).collect()));
+        let Some(index) = self.members.iter().position(|(member, _)| member.eq_ignore_ascii_case(name)) else {
+            return Err(Error::com(hresult::DISP_E_UNKNOWNNAME, name));
+        };
+        let mut dispids = vec![index as i32 + 1];
+        for param_name in param_names {
+            match self.members[index].1.iter().position(|p| p.eq_ignore_ascii_case(param_name)) {
+                Some(param) => dispids.push(100 + param as i32),
+                None => return Err(Error::com(hresult::DISP_E_UNKNOWNNAME, *param_name)),
+            }
+        }
+        return Ok(dispids);
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        self.log.borrow_mut().invokes.push(Invoked {
+            dispid,
+            kind,
+            args: arguments.iter().map(|a| a.value().clone()).collect(),
+            named_dispids: named_dispids.to_vec(),
+            named_args: named_arguments.iter().map(|a| a.value().clone()).collect(),
+        });
+        return Ok(Variant::Empty);
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return Rc::as_ptr(&self.log) as usize;
+    }
+}
+
+fn workbooks() -> (Dispatch, Rc<RefCell<Log>>) {
+    let log = Rc::new(RefCell::new(Log::default()));
+    let spy = Spy {
+        members: vec![
+            ("Open", vec!["Filename", "UpdateLinks", "ReadOnly", "Format", "Password"]),
+            ("Item", vec!["Index"]),
+            ("Value", vec!["RangeValueDataType"]),
+        ],
+        log: log.clone(),
+    };
+    return (Dispatch::new(spy), log);
+}
+
+#[test]
+fn positional_then_named() {
+    let (workbooks, log) = workbooks();
+    workbooks.call_method_named("Open", &[Variant::from("book.xlsx"), Variant::from(0)], &[
+        ("Password", Variant::from("secret")),
+        ("ReadOnly", Variant::from(true)),
+    ]).unwrap();
+
+    let log = log.borrow();
+    // every name resolved in one lookup
+    assert_eq!(log.lookups, vec![(String::from("Open"), vec![String::from("Password"), String::from("ReadOnly")])]);
+    assert_eq!(log.invokes, vec![Invoked {
+        dispid: 1,
+        kind: InvokeKind::Method,
+        args: vec![Variant::from("book.xlsx"), Variant::from(0)],
+        // in the order they were given, not the order the method declares them
+        named_dispids: vec![104, 102],
+        named_args: vec![Variant::from("secret"), Variant::from(true)],
+    }]);
+}
+
+#[test]
+fn puts_lead_with_the_value() {
+    let (range, log) = workbooks();
+    range.put_property("Value", &Variant::from(42)).unwrap();
+    range.put_property_named("Value", &Variant::from(43), &[("RangeValueDataType", Variant::from(10))]).unwrap();
+    range.put_property_with_args("Item", &[Variant::from(1), Variant::from("a")], &Variant::from(44)).unwrap();
+
+    let invokes = &log.borrow().invokes;
+    assert_eq!(invokes[0], Invoked {
+        dispid: 3, kind: InvokeKind::PropertyPut, args: vec![],
+        named_dispids: vec![DISPID_PROPERTYPUT], named_args: vec![Variant::from(42)],
+    });
+    assert_eq!(invokes[1], Invoked {
+        dispid: 3, kind: InvokeKind::PropertyPut, args: vec![],
+        named_dispids: vec![DISPID_PROPERTYPUT, 100], named_args: vec![Variant::from(43), Variant::from(10)],
+    });
+    // the index arguments stay positional
+    assert_eq!(invokes[2], Invoked {
+        dispid: 2, kind: InvokeKind::PropertyPut, args: vec![Variant::from(1), Variant::from("a")],
+        named_dispids: vec![DISPID_PROPERTYPUT], named_args: vec![Variant::from(44)],
+    });
+}
+
+#[test]
+fn unknown_parameter() {
+    let (workbooks, log) = workbooks();
+    let error = workbooks.call_method_named("Open", &[], &[("Pasword", Variant::from("secret"))]).unwrap_err();
+    assert_eq!(error.hresult(), hresult::DISP_E_UNKNOWNNAME);
+    assert!(log.borrow().invokes.is_empty());
+}
+
+#[test]
+fn mock_sees_names() {
+    let workbooks = MockObject::new("Workbooks").with_method("Open", |call| {
+        return Ok(Variant::from(format!("{} {:?}", call.arg(0).unwrap(), call.named_arg("readonly"))));
+    });
+    let result = workbooks.to_dispatch().call_method_named("Open", &[Variant::from("book.xlsx")], &[
+        ("Password", Variant::from("secret")),
+        ("ReadOnly", Variant::from(true)),
+    ]).unwrap();
+    assert_eq!(result, Variant::from("book.xlsx Some((vt_bool true))"));
+
+    let call = &workbooks.calls_to("Open")[0];
+    assert_eq!(call.args, vec![Variant::from("book.xlsx")]);
+    assert_eq!(call.named_args, vec![(String::from("Password"), Variant::from("secret")), (String::from("ReadOnly"), Variant::from(true))]);
+}
@@ -0,0 +1,112 @@
+//
+// dotted paths parsed on their own and evaluated against a mock excel
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Automation, InvokeKind, MemberPath, PathSegment, Variant};
+
+fn segment(name: &str, args: Vec<Variant>) -> PathSegment {
+    return PathSegment { name: name.to_string(), args };
+}
+
+#[test]
+fn parse() {
+    let path = MemberPath::parse("Workbooks.Item(1).ActiveSheet.Name").unwrap();
+    assert_eq!(path.segments(), vec![
+        segment("Workbooks", vec![]),
+        segment("Item", vec![Variant::I4(1)]),
+        segment("ActiveSheet", vec![]),
+        segment("Name", vec![]),
+    ]);
+
+    let path = MemberPath::parse(" Range( \"A1:B2\" , -2, 2.5, 1e3, 3000000000, true, FALSE, \"say \"\"hi\"\"\" ) . Add() ").unwrap();
+    assert_eq!(path.segments(), vec![
+        segment("Range", vec![
+            Variant::from("A1:B2"),
+            Variant::I4(-2),
+            Variant::R8(2.5),
+            Variant::R8(1000.0),
+            Variant::I8(3_000_000_000),
+            Variant::Bool(true),
+            Variant::Bool(false),
+            Variant::from("say \"hi\""),
+        ]),
+        segment("Add", vec![]),
+    ]);
+    assert_eq!(path.to_string(), "Range(\"A1:B2\", -2, 2.5, 1000, 3000000000, True, False, \"say \"\"hi\"\"\").Add");
+}
+
+#[test]
+fn parse_errors() {
+    for bad in ["", "Workbooks.", ".Name", "Item(1", "Item(1,)", "Item(x)", "Range(\"A1)", "Name Value", "1Name", "Item(1) Name"] {
+        let err = MemberPath::parse(bad).unwrap_err();
+        assert_eq!(err.hresult(), hresult::E_INVALIDARG, "{:?}", bad);
+    }
+    let message = MemberPath::parse("Item(1;2)").unwrap_err().to_string();
+    assert!(message.contains("at 6"), "{}", message);
+}
+
+fn excel() -> (MockObject, MockObject) {
+    let sheet = MockObject::new("Worksheet")
+        .with_property("Name", "Sheet1")
+        .with_method("Range", |call| {
+            let range = MockObject::new("Range").with_property("Address", call.arg(0).cloned().unwrap_or_default());
+            return Ok(Variant::from(range.to_dispatch()));
+        })
+        .with_method("Cells", |call| Ok(Variant::from(call.args.len() as i32)));
+    let workbook = MockObject::new("Workbook").with_property("ActiveSheet", sheet.to_dispatch());
+    let workbooks = MockObject::new("Workbooks").with_items(vec![Variant::from(workbook.to_dispatch())]);
+    let excel = MockObject::new("Application")
+        .with_property("Workbooks", workbooks.to_dispatch())
+        .with_property("ActiveSheet", sheet.to_dispatch())
+        .with_property("Visible", false);
+    return (excel, sheet);
+}
+
+#[test]
+fn eval() {
+    let (excel, _) = excel();
+    let excel = excel.to_dispatch();
+    assert_eq!(excel.eval("Workbooks.Item(1).ActiveSheet.Name").unwrap(), Variant::from("Sheet1"));
+    assert_eq!(excel.eval("ActiveSheet.Range(\"A1\").Address").unwrap(), Variant::from("A1"));
+    assert_eq!(excel.eval("ActiveSheet.Cells(3, 1)").unwrap(), Variant::I4(2));
+    assert_eq!(excel.eval("Visible").unwrap(), Variant::from(false));
+    // the path can be parsed once and used on any object
+    let name = MemberPath::parse("ActiveSheet.Name").unwrap();
+    assert_eq!(name.eval(&excel).unwrap(), Variant::from("Sheet1"));
+}
+
+#[test]
+fn set() {
+    let (excel, sheet) = excel();
+    let dispatch = excel.to_dispatch();
+    dispatch.set("Workbooks.Item(1).ActiveSheet.Name", &Variant::from("Totals")).unwrap();
+    assert_eq!(sheet.property("Name"), Some(Variant::from("Totals")));
+    dispatch.set("Visible", &Variant::from(true)).unwrap();
+    assert_eq!(excel.property("Visible"), Some(Variant::from(true)));
+
+    // indexes on the last step go with the put
+    dispatch.set("ActiveSheet.Cells(3, 1)", &Variant::from("Total")).unwrap();
+    let put = sheet.calls_to("Cells").pop().unwrap();
+    assert_eq!(put.kind, InvokeKind::PropertyPut);
+    assert_eq!(put.args, vec![Variant::I4(3), Variant::I4(1)]);
+    assert_eq!(put.value, Some(Variant::from("Total")));
+}
+
+#[test]
+fn eval_errors() {
+    let (excel, _) = excel();
+    let excel = excel.to_dispatch();
+    let err = excel.eval("ActiveSheet.Name.Length").unwrap_err();
+    assert_eq!(err.to_string(), conversion_error("ActiveSheet.Name is vt_bstr, not an object"));
+    assert_eq!(excel.eval("ActiveSheet.Missing").unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+    assert_eq!(excel.eval("Workbooks.Item(2).Name").unwrap_err().hresult(), hresult::DISP_E_BADINDEX);
+    assert_eq!(excel.set("ActiveSheet.", &Variant::Empty).unwrap_err().hresult(), hresult::E_INVALIDARG);
+}
+
+fn conversion_error(message: &str) -> String {
+    return hello_com_rust::Error::result(message).to_string();
+}
@@ -0,0 +1,99 @@
+//
+// indexed property gets and puts, and object references assigned with putref, against mocks
+//
+
+#![allow(clippy::needless_return)]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Automation, InvokeKind, VarType, Variant};
+
+// a sheet whose Cells(row, col) can be read and written
+fn sheet() -> MockObject {
+    let cells: Rc<RefCell<HashMap<(i32, i32), Variant>>> = Rc::new(RefCell::new(HashMap::new()));
+    return MockObject::new("Worksheet").with_property("Name", "Sheet1").with_method("Cells", move |call| {
+        let cell = (i32::try_from(call.arg(0).unwrap())?, i32::try_from(call.arg(1).unwrap())?);
+        if call.kind.is_put() {
+            cells.borrow_mut().insert(cell, call.value.clone().unwrap());
+            return Ok(Variant::Empty);
+        }
+        return Ok(cells.borrow().get(&cell).cloned().unwrap_or_default());
+    });
+}
+
+#[test]
+fn indexed_gets_and_puts() {
+    let sheet = sheet();
+    let dispatch = sheet.to_dispatch();
+    dispatch.put_property_with_args("Cells", &[Variant::from(2), Variant::from(3)], &Variant::from("total")).unwrap();
+    assert_eq!(dispatch.get_property_with_args("Cells", &[Variant::from(2), Variant::from(3)]).unwrap(), Variant::from("total"));
+    assert_eq!(dispatch.get_property_with_args("Cells", &[Variant::from(3), Variant::from(2)]).unwrap(), Variant::Empty);
+
+    // the index arguments arrive in order ahead of the value, which is kept apart from them
+    let calls = sheet.calls_to("Cells");
+    assert_eq!((calls[0].kind, &calls[0].args, &calls[0].value), (InvokeKind::PropertyPut, &vec![Variant::from(2), Variant::from(3)], &Some(Variant::from("total"))));
+    assert_eq!((calls[1].kind, &calls[1].args, &calls[1].value), (InvokeKind::PropertyGet, &vec![Variant::from(2), Variant::from(3)], &None));
+    assert!(calls[0].named_args.is_empty());
+}
+
+#[test]
+fn indexed_paths() {
+    let sheet = sheet();
+    let dispatch = sheet.to_dispatch();
+    dispatch.set("Cells(1, 1)", &Variant::from(42)).unwrap();
+    assert_eq!(dispatch.eval("Cells(1, 1)").unwrap(), Variant::from(42));
+    assert_eq!(sheet.calls_to("Cells")[0].value, Some(Variant::from(42)));
+}
+
+#[test]
+fn plain_properties_take_no_index() {
+    let dispatch = sheet().to_dispatch();
+    assert_eq!(dispatch.get_property_with_args("Name", &[Variant::from(1)]).unwrap_err().hresult(), hresult::DISP_E_BADPARAMCOUNT);
+    assert_eq!(dispatch.put_property_with_args("Name", &[Variant::from(1)], &Variant::from("x")).unwrap_err().hresult(), hresult::DISP_E_BADPARAMCOUNT);
+    assert_eq!(dispatch.get_property("Name").unwrap(), Variant::from("Sheet1"));
+}
+
+#[test]
+fn put_ref_assigns_objects() {
+    let range = MockObject::new("Range").with_property("Address", "$A$1:$B$4");
+    let chart = MockObject::new("Chart").with_property("DataSource", Variant::Null);
+    let dispatch = chart.to_dispatch();
+    dispatch.put_property_ref("DataSource", &Variant::from(range.to_dispatch())).unwrap();
+
+    // sent as a putref, not a put, with the object as the value
+    let call = chart.calls_to("DataSource").remove(0);
+    assert_eq!(call.kind, InvokeKind::PropertyPutRef);
+    assert!(call.value.unwrap().to_dispatch().unwrap() == range.to_dispatch());
+    dispatch.put_property("DataSource", &Variant::Null).unwrap();
+    assert_eq!(chart.calls_to("DataSource")[1].kind, InvokeKind::PropertyPut);
+
+    // the chart holds the only reference left once the caller's is dropped, until it's replaced
+    dispatch.put_property_ref("DataSource", &Variant::from(range.to_dispatch())).unwrap();
+    chart.clear_calls();
+    assert_eq!(range.ref_count(), 1);
+    let source = dispatch.eval("DataSource.Address").unwrap();
+    assert_eq!(source, Variant::from("$A$1:$B$4"));
+    assert_eq!(range.ref_count(), 1);
+    dispatch.put_property("DataSource", &Variant::Null).unwrap();
+    assert_eq!(range.ref_count(), 0);
+}
+
+#[test]
+fn put_ref_nothing() {
+    // Set chart.DataSource = Nothing, a putref of a null object that reads back as one
+    let range = MockObject::new("Range");
+    let chart = MockObject::new("Chart").with_property("DataSource", range.to_dispatch());
+    let dispatch = chart.to_dispatch();
+    dispatch.put_property_ref("DataSource", &Variant::Nothing).unwrap();
+    let call = chart.calls_to("DataSource").remove(0);
+    assert_eq!(call.kind, InvokeKind::PropertyPutRef);
+    assert_eq!(call.value, Some(Variant::Nothing));
+    assert_eq!(dispatch.get_property("DataSource").unwrap(), Variant::Nothing);
+    assert_eq!(Variant::Nothing.vt(), VarType::Dispatch.to_u16());
+
+    chart.clear_calls();
+    assert_eq!(range.ref_count(), 0);
+}
@@ -0,0 +1,72 @@
+//
+// reading raw windows VARIANTs, including by-ref ones the way a callee leaves them
+//
+
+#![cfg(windows)]
+#![allow(clippy::needless_return)]
+
+use std::ptr;
+use std::str::FromStr;
+use hello_com_rust::{Decimal, Variant};
+use windows::Win32::System::Com::{IDispatch, SAFEARRAY};
+use windows::Win32::System::Variant::{VARENUM, VARIANT, VariantClear, VT_ARRAY, VT_BYREF, VT_DECIMAL, VT_DISPATCH, VT_I4};
+
+fn by_ref(vt: VARENUM, target: *mut std::ffi::c_void) -> VARIANT {
+    let mut raw = VARIANT::default();
+    unsafe {
+        let inner = &mut *raw.Anonymous.Anonymous;
+        inner.vt = VARENUM(vt.0 | VT_BYREF.0);
+        inner.Anonymous.byref = target;
+    }
+    return raw;
+}
+
+#[test]
+fn by_ref_decimal() {
+    // a by-ref decimal points at the whole VARIANT, which the decimal overlays
+    let value = Decimal::from_str("-1234.5678").unwrap();
+    let mut storage = Variant::from(value).to_variant().unwrap();
+    let raw = by_ref(VT_DECIMAL, &mut storage as *mut VARIANT as *mut _);
+    assert_eq!(Variant::try_from(&raw).unwrap(), Variant::from(value));
+    unsafe { VariantClear(&mut storage).unwrap() };
+}
+
+#[test]
+fn by_ref_int() {
+    let mut storage: i32 = 42;
+    let raw = by_ref(VT_I4, &mut storage as *mut i32 as *mut _);
+    assert_eq!(Variant::try_from(&raw).unwrap(), Variant::from(42));
+}
+
+#[test]
+fn null_by_ref_array() {
+    let raw = by_ref(VARENUM(VT_ARRAY.0 | VT_I4.0), ptr::null_mut());
+    assert_eq!(Variant::try_from(&raw).unwrap(), Variant::Null);
+
+    let mut unset: *mut SAFEARRAY = ptr::null_mut();
+    let raw = by_ref(VARENUM(VT_ARRAY.0 | VT_I4.0), &mut unset as *mut *mut SAFEARRAY as *mut _);
+    assert_eq!(Variant::try_from(&raw).unwrap(), Variant::Null);
+
+    let mut array = Variant::from(vec![Variant::from(1)]).to_variant().unwrap();
+    let mut psa = unsafe { (*array.Anonymous.Anonymous).Anonymous.parray };
+    let raw = by_ref(VARENUM(VT_ARRAY.0 | 12), &mut psa as *mut *mut SAFEARRAY as *mut _);
+    assert_eq!(Variant::try_from(&raw).unwrap(), Variant::from(vec![Variant::from(1)]));
+    unsafe { VariantClear(&mut array).unwrap() };
+}
+
+#[test]
+fn nothing() {
+    // a VT_DISPATCH with no object, which is how Set x = Nothing goes over the wire
+    let mut raw = Variant::Nothing.to_variant().unwrap();
+    unsafe {
+        let inner = &*raw.Anonymous.Anonymous;
+        assert_eq!(inner.vt, VT_DISPATCH);
+        assert!(inner.Anonymous.pdispVal.is_none());
+    }
+    assert_eq!(Variant::try_from(&raw).unwrap(), Variant::Nothing);
+    unsafe { VariantClear(&mut raw).unwrap() };
+
+    let mut unset: Option<IDispatch> = None;
+    let raw = by_ref(VT_DISPATCH, &mut unset as *mut Option<IDispatch> as *mut _);
+    assert_eq!(Variant::try_from(&raw).unwrap(), Variant::Nothing);
+}
@@ -0,0 +1,291 @@
+//
+// recording a session against mocks and replaying it without them
+//
+
+#![allow(clippy::needless_return)]
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::record::{Recorder, Replay};
+use hello_com_rust::typelib::{TypeInfo, TypeKind};
+use hello_com_rust::{Argument, Automation, Dispatch, Error, InvokeKind, Variant};
+
+// somewhere to record to that can still be read afterwards
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        return Ok(());
+    }
+}
+
+impl Buffer {
+    fn replay(&self) -> Replay {
+        return Replay::new(self.0.borrow().as_slice()).unwrap();
+    }
+
+    fn lines(&self) -> Vec<String> {
+        return String::from_utf8(self.0.borrow().clone()).unwrap().lines().map(String::from).collect();
+    }
+}
+
+fn workbook() -> MockObject {
+    let sheets: Vec<Variant> = ["Sheet1", "Sheet2"].iter()
+        .map(|name| Variant::from(MockObject::new("Worksheet").with_property("Name", *name).to_dispatch()))
+        .collect();
+    let active = sheets[0].clone();
+    let chart = MockObject::new("Chart").with_property("Title", "Sales");
+    return MockObject::new("Workbook")
+        .with_property("Name", "book.xlsx")
+        .with_property("ActiveSheet", active)
+        .with_property("Sheets", MockObject::new("Sheets").with_items(sheets).to_dispatch())
+        .with_method("Save", |call| {
+            return match call.named_arg("Password") {
+                Some(password) => Ok(Variant::from(format!("saved with {}", password))),
+                None => Ok(Variant::from("saved")),
+            };
+        })
+        .with_method("Protect", |_| Err(Error::com(hresult::E_FAIL, "the workbook is already protected")))
+        // Divide(a, b, [out] quotient, [out] chart)
+        .with_method("Divide", move |call| {
+            let (a, b) = (i32::try_from(call.arg(0).unwrap())?, i32::try_from(call.arg(1).unwrap())?);
+            call.args[2] = Variant::from(a / b);
+            call.args[3] = Variant::from(chart.to_dispatch());
+            return Ok(Variant::from(a % b));
+        })
+}
+
+// the same things done to the recorded object and the replayed one, giving everything that came
+// back from them
+fn session(workbook: &Dispatch) -> Vec<String> {
+    let mut seen = Vec::new();
+    seen.push(workbook.get_property("Name").unwrap().to_string());
+    seen.push(workbook.eval("ActiveSheet.Name").unwrap().to_string());
+    for sheet in workbook.get_property("Sheets").unwrap().into_dispatch().unwrap().iter().with_batch_size(1) {
+        seen.push(sheet.unwrap().into_dispatch().unwrap().get_property("Name").unwrap().to_string());
+    }
+    seen.push(workbook.call_method_named("Save", &[], &[("Password", Variant::from("secret"))]).unwrap().to_string());
+    seen.push(workbook.call_method("Save", &[]).unwrap().to_string());
+    seen.push(workbook.call_method("Protect", &[]).unwrap_err().to_string());
+    seen.push(workbook.get_property("Author").unwrap_err().to_string());
+    workbook.put_property("Name", &Variant::from("renamed.xlsx")).unwrap();
+    seen.push(workbook.get_property("Name").unwrap().to_string());
+
+    let (a, b) = (Variant::from(7), Variant::from(2));
+    let (mut quotient, mut chart) = (Variant::Empty, Variant::Empty);
+    let remainder = workbook.call_method_by_ref("Divide", &mut [
+        Argument::ByVal(&a), Argument::ByVal(&b), Argument::ByRef(&mut quotient), Argument::ByRefVariant(&mut chart),
+    ]).unwrap();
+    seen.push(format!("{} {}", quotient, remainder));
+    // the object written back is recorded too
+    seen.push(chart.to_dispatch().unwrap().get_property("Title").unwrap().to_string());
+    return seen;
+}
+
+#[test]
+fn record_and_replay() {
+    let buffer = Buffer::default();
+    let recorder = Recorder::new(buffer.clone());
+    let workbook = workbook();
+    let recorded = session(&recorder.record(&workbook.to_dispatch()));
+    recorder.finish().unwrap();
+    assert_eq!(recorded, vec![
+        "book.xlsx", "Sheet1", "Sheet1", "Sheet2", "saved with secret", "saved",
+        "the workbook is already protected (0x80004005)", "Workbook has no member Author (0x80020006)", "renamed.xlsx", "3 1", "Sales",
+    ]);
+    // the calls really were made
+    assert_eq!(workbook.property("Name"), Some(Variant::from("renamed.xlsx")));
+
+    let replay = buffer.replay();
+    assert_eq!(session(&replay.root()), recorded);
+    replay.finish().unwrap();
+}
+
+#[test]
+fn divergence() {
+    let buffer = Buffer::default();
+    let recorder = Recorder::new(buffer.clone());
+    let recorded = recorder.record(&workbook().to_dispatch());
+    recorded.call_method_named("Save", &[], &[("Password", Variant::from("secret"))]).unwrap();
+    recorded.get_property("Name").unwrap();
+    recorder.finish().unwrap();
+
+    // a different argument
+    let replay = buffer.replay();
+    let error = replay.root().call_method_named("Save", &[], &[("Password", Variant::from("guess"))]).unwrap_err();
+    assert!(error.to_string().starts_with(r#"replay expected Method Save(Password:={"type":"vt_bstr","value":"secret"}) on object 0 but got Method Save(Password:={"type":"vt_bstr","value":"guess"})"#), "{}", error);
+
+    // a different member, or the calls out of order
+    let replay = buffer.replay();
+    let error = replay.root().get_property("Name").unwrap_err();
+    assert!(error.to_string().contains("but got PropertyGet Name() on object 0"), "{}", error);
+
+    // past the end
+    let replay = buffer.replay();
+    let root = replay.root();
+    root.call_method_named("Save", &[], &[("Password", Variant::from("secret"))]).unwrap();
+    root.get_property("Name").unwrap();
+    let error = root.get_property("Name").unwrap_err();
+    assert!(error.to_string().starts_with("replay expected the end of the recording"), "{}", error);
+    replay.finish().unwrap();
+}
+
+#[test]
+fn finish_with_leftovers() {
+    let buffer = Buffer::default();
+    let recorder = Recorder::new(buffer.clone());
+    let recorded = recorder.record(&workbook().to_dispatch());
+    recorded.get_property("Name").unwrap();
+    recorded.eval("ActiveSheet.Name").unwrap();
+    recorder.finish().unwrap();
+
+    let replay = buffer.replay();
+    replay.root().get_property("Name").unwrap();
+    let error = replay.finish().unwrap_err();
+    assert_eq!(error.to_string(), "2 recorded events were never replayed, starting with PropertyGet ActiveSheet() on object 0 (0x80004005)");
+}
+
+// only knows the parameters it's given, the way a real object does
+#[derive(Clone)]
+struct Params {
+    object: Dispatch,
+    params: Vec<&'static str>,
+}
+
+impl Automation for Params {
+    fn get_ids_of_names(&self, name: &str, param_names: &[&str]) -> Result<Vec<i32>,Error> {
+        if let Some(unknown) = param_names.iter().find(|p| !self.params.iter().any(|known| known.eq_ignore_ascii_case(p))) {
+            return Err(Error::com(hresult::DISP_E_UNKNOWNNAME, format!("{} has no parameter {}", name, unknown)));
+        }
+        return self.object.get_ids_of_names(name, param_names);
+    }
+
+    fn invoke(&self, dispid: i32, kind: InvokeKind, arguments: &mut [Argument], named_dispids: &[i32], named_arguments: &mut [Argument]) -> Result<Variant,Error> {
+        return self.object.invoke(dispid, kind, arguments, named_dispids, named_arguments);
+    }
+
+    fn clone_object(&self) -> Box<dyn Automation> {
+        return Box::new(self.clone());
+    }
+
+    fn identity(&self) -> usize {
+        return self.object.identity();
+    }
+}
+
+#[test]
+fn lookup_failures_in_invoke() {
+    let buffer = Buffer::default();
+    let recorder = Recorder::new(buffer.clone());
+    let workbook = recorder.record(&Dispatch::new(Params { object: workbook().to_dispatch(), params: vec![] }));
+    let other = recorder.record(&MockObject::new("Other").with_method("Save", |_| Ok(Variant::Empty)).to_dispatch());
+
+    // dispids are shared by the whole session, so ones looked up on another object reach the
+    // invoke, where the real lookup fails
+    let save = other.get_ids_of_names("Save", &["Password"]).unwrap();
+    let secret = Variant::from("secret");
+    let error = workbook.invoke(save[0], InvokeKind::Method, &mut [], &save[1..], &mut [Argument::ByVal(&secret)]).unwrap_err();
+    assert_eq!(error.to_string(), "Save has no parameter Password (0x80020006)");
+    let name = other.get_ids_of_names("Title", &[]).unwrap_err();
+    assert_eq!(name.hresult(), hresult::DISP_E_UNKNOWNNAME);
+    recorder.finish().unwrap();
+
+    // both failures are in the log, with no invoke for the call that never happened
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains(r#""event":"lookup","object":0,"name":"Save","params":["Password"]"#), "{}", lines[0]);
+    assert!(lines[1].contains(r#""event":"lookup","object":1,"name":"Title""#), "{}", lines[1]);
+
+    let replay = buffer.replay();
+    let save = replay.object(1).get_ids_of_names("Save", &["Password"]).unwrap();
+    let error = replay.root().invoke(save[0], InvokeKind::Method, &mut [], &save[1..], &mut [Argument::ByVal(&secret)]).unwrap_err();
+    assert_eq!(error.to_string(), "Save has no parameter Password (0x80020006)");
+    assert_eq!(replay.object(1).get_ids_of_names("Title", &[]).unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+    replay.finish().unwrap();
+}
+
+#[test]
+fn objects_keep_their_numbers() {
+    let buffer = Buffer::default();
+    let recorder = Recorder::new(buffer.clone());
+    let workbook = workbook();
+    let recorded = recorder.record(&workbook.to_dispatch());
+    // the same sheet gets the same number however it's reached
+    let first = recorded.get_property("ActiveSheet").unwrap();
+    let second = recorded.get_property("ActiveSheet").unwrap();
+    assert!(first == second);
+    drop((first, second));
+    // and a new one once nothing's holding it, the identity could belong to another object by then
+    recorded.get_property("ActiveSheet").unwrap();
+    recorder.finish().unwrap();
+
+    let results: Vec<String> = buffer.lines().iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["result"]["Ok"].to_string())
+        .collect();
+    assert_eq!(results, vec![r#"{"type":"vt_dispatch","value":1}"#, r#"{"type":"vt_dispatch","value":1}"#, r#"{"type":"vt_dispatch","value":2}"#]);
+    // nothing the recorder kept hold of
+    drop(recorded);
+    assert_eq!(workbook.ref_count(), 0);
+}
+
+#[test]
+fn bulk_lookups_and_type_info() {
+    let buffer = Buffer::default();
+    let recorder = Recorder::new(buffer.clone());
+    let info = TypeInfo {
+        name: "_Workbook".to_string(), kind: TypeKind::Dispatch, guid: None, doc: None, flags: 0,
+        major_version: 1, minor_version: 0, implements: vec![], alias: None, functions: vec![], variables: vec![],
+    };
+    let workbook = workbook().with_type_info(info.clone());
+    let recorded = recorder.record(&workbook.to_dispatch());
+
+    // resolved in one lookup on the real object, with nothing logged for it
+    recorded.resolve(&["Name", "Save", "Protect"]).unwrap();
+    assert_eq!(workbook.lookup_count(), 1);
+    assert_eq!(recorded.get_property("Name").unwrap(), Variant::from("book.xlsx"));
+    // a failure's logged against the name that failed
+    assert_eq!(recorded.resolve(&["Sheets", "Author"]).unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+    // and the type info comes from the real object, unrecorded
+    assert_eq!(recorded.type_info().unwrap(), info);
+    recorder.finish().unwrap();
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains(r#""event":"lookup","object":0,"name":"Author""#), "{}", lines[1]);
+
+    let replay = buffer.replay();
+    let root = replay.root();
+    root.resolve(&["Name", "Save", "Protect"]).unwrap();
+    assert_eq!(root.get_property("Name").unwrap(), Variant::from("book.xlsx"));
+    assert_eq!(root.resolve(&["Sheets", "Author"]).unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+    assert_eq!(root.type_info().unwrap_err().hresult(), hresult::E_NOTIMPL);
+    replay.finish().unwrap();
+}
+
+#[test]
+fn enumerator_failures() {
+    let sheets = MockObject::new("Sheets").with_items(Vec::new());
+    sheets.reject_calls(1, hresult::RPC_E_CALL_REJECTED);
+    let buffer = Buffer::default();
+    let recorder = Recorder::new(buffer.clone());
+    let recorded = recorder.record(&sheets.to_dispatch());
+    assert_eq!(recorded.iter().next().unwrap().unwrap_err().hresult(), hresult::RPC_E_CALL_REJECTED);
+    assert_eq!(recorded.iter().count(), 0);
+    recorder.finish().unwrap();
+
+    // replayed as the same failure, then the empty collection
+    let replay = buffer.replay();
+    let root = replay.root();
+    assert_eq!(root.iter().next().unwrap().unwrap_err().hresult(), hresult::RPC_E_CALL_REJECTED);
+    assert_eq!(root.iter().count(), 0);
+    replay.finish().unwrap();
+}
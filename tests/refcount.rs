@@ -0,0 +1,101 @@
+//
+// every reference to an object that's taken is released exactly once, checked with the mock's
+// reference counts
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::record::Recorder;
+use hello_com_rust::{Automation, Dispatch, FromDispatch, Variant};
+
+#[test]
+fn clone_and_drop() {
+    let object = MockObject::new("Workbook");
+    let dispatch = object.to_dispatch();
+    assert_eq!(object.ref_count(), 1);
+    let clone = dispatch.clone();
+    assert_eq!(object.ref_count(), 2);
+    drop(dispatch);
+    assert_eq!(object.ref_count(), 1);
+    drop(clone);
+    assert_eq!((object.ref_count(), object.add_ref_count()), (0, 2));
+}
+
+#[test]
+fn into_dispatch_moves() {
+    let object = MockObject::new("Workbook");
+    let value = Variant::from(object.to_dispatch());
+    let dispatch = value.into_dispatch().unwrap();
+    assert_eq!((object.ref_count(), object.add_ref_count()), (1, 1));
+    let dispatch = Dispatch::try_from(Variant::from(dispatch)).unwrap();
+    assert_eq!((object.ref_count(), object.add_ref_count()), (1, 1));
+
+    // to_dispatch leaves the variant its own reference
+    let value = Variant::from(dispatch);
+    let copy = value.to_dispatch().unwrap();
+    assert_eq!((object.ref_count(), object.add_ref_count()), (2, 2));
+    drop((value, copy));
+    assert_eq!(object.ref_count(), 0);
+}
+
+#[test]
+fn results() {
+    let sheet = MockObject::new("Worksheet").with_property("Name", "Sheet1");
+    let sheets = MockObject::new("Sheets").with_items(vec![Variant::from(sheet.to_dispatch())]);
+    let workbook = MockObject::new("Workbook")
+        .with_property("ActiveSheet", sheet.to_dispatch())
+        .with_property("Sheets", sheets.to_dispatch());
+    // the properties and the collection hold theirs
+    assert_eq!(sheet.ref_count(), 2);
+
+    let dispatch = workbook.to_dispatch();
+    for _ in 0..3 {
+        let active = dispatch.get_property("ActiveSheet").unwrap().into_dispatch().unwrap();
+        assert_eq!(active.get_property("Name").unwrap(), Variant::from("Sheet1"));
+        assert_eq!(dispatch.eval("ActiveSheet.Name").unwrap(), Variant::from("Sheet1"));
+        let all: Vec<Dispatch> = dispatch.get_property("Sheets").unwrap().into_dispatch().unwrap()
+            .iter().map(|item| item.unwrap().into_dispatch().unwrap()).collect();
+        assert_eq!(all.len(), 1);
+        assert_eq!(sheet.ref_count(), 4);
+    }
+    assert_eq!((sheet.ref_count(), sheets.ref_count()), (2, 1));
+
+    // replacing a property releases the old value
+    workbook.set_property("ActiveSheet", Variant::Null);
+    assert_eq!(sheet.ref_count(), 1);
+    drop(dispatch);
+    assert_eq!(workbook.ref_count(), 0);
+}
+
+#[derive(FromDispatch)]
+struct Workbook {
+    active_sheet: Sheet,
+    sheets: Vec<Sheet>,
+}
+
+#[derive(FromDispatch)]
+struct Sheet {
+    name: String,
+}
+
+#[test]
+fn wrappers() {
+    let sheet = MockObject::new("Worksheet").with_property("Name", "Sheet1");
+    let sheets = MockObject::new("Sheets").with_items(vec![Variant::from(sheet.to_dispatch())]);
+    let workbook = MockObject::new("Workbook")
+        .with_property("Active_Sheet", sheet.to_dispatch())
+        .with_property("Sheets", sheets.to_dispatch());
+
+    let read = Workbook::from_dispatch(&workbook.to_dispatch()).unwrap();
+    assert_eq!((read.active_sheet.name.as_str(), read.sheets.len()), ("Sheet1", 1));
+    assert_eq!((sheet.ref_count(), sheets.ref_count(), workbook.ref_count()), (2, 1, 0));
+
+    // recording wraps every object it hands out, and releases them all the same
+    let recorder = Recorder::new(Vec::new());
+    let recorded = recorder.record(&workbook.to_dispatch());
+    let active = recorded.get_property("Active_Sheet").unwrap().into_dispatch().unwrap();
+    assert_eq!(active.get_property("Name").unwrap(), Variant::from("Sheet1"));
+    drop((recorded, active));
+    assert_eq!((sheet.ref_count(), workbook.ref_count()), (2, 0));
+}
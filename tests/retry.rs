@@ -0,0 +1,165 @@
+//
+// retrying the calls a busy server rejects, against mocks told to reject them
+//
+
+#![allow(clippy::needless_return)]
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{Argument, Automation, Error, RetryPolicy, SafeArray, Variant, VarType};
+
+// a clock that only moves when the policy waits, so nothing here waits for real or depends on
+// how long the calls take.  each test has its own thread, and so its own clock
+thread_local! {
+    static STARTED: Instant = Instant::now();
+    static SLEPT: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+fn now() -> Instant {
+    return STARTED.with(|started| *started) + SLEPT.with(Cell::get);
+}
+
+fn sleep(delay: Duration) {
+    SLEPT.with(|slept| slept.set(slept.get() + delay));
+}
+
+fn policy() -> RetryPolicy {
+    return RetryPolicy::new().with_backoff(Duration::from_millis(1), Duration::from_millis(4)).with_clock(now, sleep);
+}
+
+#[test]
+fn delays() {
+    let policy = RetryPolicy::new();
+    let delays: Vec<Option<u64>> = (1..=10).map(|attempt| policy.retry_delay(attempt, Duration::ZERO).map(|d| d.as_millis() as u64)).collect();
+    assert_eq!(delays, vec![Some(100), Some(200), Some(400), Some(800), Some(1600), Some(2000), Some(2000), Some(2000), Some(2000), None]);
+
+    let policy = RetryPolicy::new().with_max_attempts(3).with_deadline(Duration::from_secs(1));
+    assert_eq!(policy.retry_delay(1, Duration::from_millis(850)), Some(Duration::from_millis(100)));
+    assert_eq!(policy.retry_delay(1, Duration::from_millis(950)), None);
+    assert_eq!(policy.retry_delay(3, Duration::ZERO), None);
+
+    assert!(RetryPolicy::is_retryable(&Error::com(hresult::RPC_E_CALL_REJECTED, "rejected")));
+    assert!(RetryPolicy::is_retryable(&Error::com(hresult::RPC_E_SERVERCALL_RETRYLATER, "busy")));
+    assert!(!RetryPolicy::is_retryable(&Error::com(hresult::E_FAIL, "failed")));
+}
+
+#[test]
+fn retries_rejected_calls() {
+    let excel = MockObject::new("Application").with_property("Visible", false);
+    excel.reject_calls(3, hresult::RPC_E_CALL_REJECTED);
+    let dispatch = excel.to_dispatch().with_retry(policy());
+    assert_eq!(dispatch.get_property("Visible").unwrap(), Variant::from(false));
+    assert_eq!((excel.rejected_count(), excel.calls().len()), (3, 1));
+
+    excel.reject_calls(2, hresult::RPC_E_SERVERCALL_RETRYLATER);
+    dispatch.put_property("Visible", &Variant::from(true)).unwrap();
+    assert_eq!(excel.property("Visible"), Some(Variant::from(true)));
+    assert_eq!((excel.rejected_count(), excel.calls().len()), (5, 2));
+
+    // and getting a collection's enumerator
+    let sheets = MockObject::new("Sheets").with_items(vec![Variant::from("Sheet1")]);
+    sheets.reject_calls(2, hresult::RPC_E_CALL_REJECTED);
+    let items = sheets.to_dispatch().with_retry(policy()).iter().collect::<Result<Vec<Variant>,Error>>().unwrap();
+    assert_eq!((items, sheets.rejected_count()), (vec![Variant::from("Sheet1")], 2));
+
+    // without a policy the rejection is returned as it is
+    excel.reject_calls(1, hresult::RPC_E_CALL_REJECTED);
+    let error = excel.to_dispatch().get_property("Visible").unwrap_err();
+    assert_eq!(error.hresult(), hresult::RPC_E_CALL_REJECTED);
+}
+
+#[test]
+fn gives_up() {
+    let excel = MockObject::new("Application").with_property("Visible", false);
+    excel.reject_calls(100, hresult::RPC_E_CALL_REJECTED);
+    let dispatch = excel.to_dispatch().with_retry(policy().with_max_attempts(4));
+    assert_eq!(dispatch.get_property("Visible").unwrap_err().hresult(), hresult::RPC_E_CALL_REJECTED);
+    assert_eq!((excel.rejected_count(), excel.calls().len()), (4, 0));
+    assert_eq!(SLEPT.with(Cell::get), Duration::from_millis(1 + 2 + 4));
+
+    // the deadline stops it sooner, the third attempt would start after 20 + 40ms
+    let excel = MockObject::new("Application").with_property("Visible", false);
+    excel.reject_calls(100, hresult::RPC_E_SERVERCALL_RETRYLATER);
+    let dispatch = excel.to_dispatch().with_retry(RetryPolicy::new()
+        .with_backoff(Duration::from_millis(20), Duration::from_secs(1))
+        .with_deadline(Duration::from_millis(50))
+        .with_clock(now, sleep));
+    assert_eq!(dispatch.get_property("Visible").unwrap_err().hresult(), hresult::RPC_E_SERVERCALL_RETRYLATER);
+    assert_eq!(excel.rejected_count(), 2);
+    assert_eq!(SLEPT.with(Cell::get), Duration::from_millis(1 + 2 + 4 + 20));
+}
+
+#[test]
+fn other_errors_fail_straight_away() {
+    let excel = MockObject::new("Application")
+        .with_method("Quit", |_| Err(Error::com(hresult::E_FAIL, "failed")));
+    let dispatch = excel.to_dispatch().with_retry(policy());
+    assert_eq!(dispatch.call_method("Quit", &[]).unwrap_err().hresult(), hresult::E_FAIL);
+    assert_eq!(excel.calls_to("Quit").len(), 1);
+    assert_eq!(dispatch.get_property("Missing").unwrap_err().hresult(), hresult::DISP_E_UNKNOWNNAME);
+}
+
+#[test]
+fn objects_from_calls() {
+    let sheet = MockObject::new("Worksheet").with_property("Name", "Sheet1");
+    let sheets = MockObject::new("Sheets").with_items(vec![Variant::from(sheet.to_dispatch())]);
+    let workbook = MockObject::new("Workbook")
+        .with_property("ActiveSheet", sheet.to_dispatch())
+        .with_property("Sheets", sheets.to_dispatch());
+    let dispatch = workbook.to_dispatch().with_retry(policy());
+
+    // the sheet's rejections are retried whether it came from a property, a path or iterating
+    sheet.reject_calls(2, hresult::RPC_E_CALL_REJECTED);
+    let active = dispatch.get_property("ActiveSheet").unwrap().into_dispatch().unwrap();
+    assert_eq!(active.get_property("Name").unwrap(), Variant::from("Sheet1"));
+    sheet.reject_calls(2, hresult::RPC_E_CALL_REJECTED);
+    assert_eq!(dispatch.eval("ActiveSheet.Name").unwrap(), Variant::from("Sheet1"));
+    for item in &dispatch.get_property("Sheets").unwrap().into_dispatch().unwrap() {
+        sheet.reject_calls(2, hresult::RPC_E_CALL_REJECTED);
+        let item = item.unwrap().into_dispatch().unwrap();
+        assert!(item == active);
+        assert_eq!(item.get_property("Name").unwrap(), Variant::from("Sheet1"));
+    }
+    assert_eq!(sheet.rejected_count(), 6);
+
+    drop((dispatch, active));
+    assert_eq!((workbook.ref_count(), sheet.ref_count()), (0, 2));
+}
+
+#[test]
+fn objects_in_arrays_and_by_ref() {
+    let sheet = MockObject::new("Worksheet").with_property("Name", "Sheet1");
+    let sheets = SafeArray::from_vec(VarType::Variant, vec![Variant::from(sheet.to_dispatch()), Variant::from(1)]).unwrap();
+    let active = sheet.to_dispatch();
+    let workbook = MockObject::new("Workbook")
+        .with_property("Sheets", Variant::Array(sheets))
+        .with_method("GetActiveSheet", move |call| {
+            call.args[0] = Variant::from(active.clone());
+            return Ok(Variant::Empty);
+        })
+        .with_method("Touch", |_| Ok(Variant::Empty));
+    let dispatch = workbook.to_dispatch().with_retry(policy().with_max_attempts(3));
+
+    // an object in an array that comes back
+    let sheets = dispatch.get_property("Sheets").unwrap();
+    let array = sheets.to_array().unwrap();
+    assert_eq!((array.vt(), array.elements()[1].clone()), (VarType::Variant, Variant::from(1)));
+    sheet.reject_calls(2, hresult::RPC_E_CALL_REJECTED);
+    assert_eq!(array.elements()[0].to_dispatch().unwrap().get_property("Name").unwrap(), Variant::from("Sheet1"));
+
+    // and one written back to a by-ref argument
+    let mut written = Variant::Empty;
+    dispatch.call_method_by_ref("GetActiveSheet", &mut [Argument::ByRefVariant(&mut written)]).unwrap();
+    sheet.reject_calls(2, hresult::RPC_E_CALL_REJECTED);
+    assert_eq!(written.to_dispatch().unwrap().get_property("Name").unwrap(), Variant::from("Sheet1"));
+    assert_eq!(sheet.rejected_count(), 4);
+
+    // one the call left as it was isn't wrapped a second time, which would retry each attempt
+    let mut unchanged = written.clone();
+    dispatch.call_method_by_ref("Touch", &mut [Argument::ByRefVariant(&mut unchanged)]).unwrap();
+    sheet.reject_calls(100, hresult::RPC_E_CALL_REJECTED);
+    assert!(unchanged.to_dispatch().unwrap().get_property("Name").is_err());
+    assert_eq!(sheet.rejected_count(), 4 + 3);
+}
@@ -0,0 +1,87 @@
+//
+// multi-dimensional SAFEARRAYs: indexing with lower bounds, element types and 2-D rows
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{hresult, ArrayBound, SafeArray, VarType, Variant};
+
+fn ints(values: std::ops::Range<i32>) -> Vec<Variant> {
+    return values.map(Variant::from).collect();
+}
+
+#[test]
+fn offsets() {
+    // 3 x 2 with lower bounds of 1 and -1, the first dimension varying fastest
+    let array = SafeArray::new(VarType::I4, vec![ArrayBound::new(1, 3), ArrayBound::new(-1, 2)], ints(0..6)).unwrap();
+    assert_eq!(array.offset(&[1, -1]), Some(0));
+    assert_eq!(array.offset(&[3, -1]), Some(2));
+    assert_eq!(array.offset(&[1, 0]), Some(3));
+    assert_eq!(array.offset(&[3, 0]), Some(5));
+    for indices in [[0, -1], [4, -1], [1, -2], [1, 1]] {
+        assert_eq!(array.offset(&indices), None, "{:?} is out of bounds", indices);
+    }
+    assert_eq!(array.offset(&[1]), None);
+    assert_eq!(array.offset(&[1, -1, 0]), None);
+    assert_eq!(array.get(&[2, 0]), Some(&Variant::from(4)));
+    assert_eq!(array.get(&[0, 0]), None);
+}
+
+#[test]
+fn set() {
+    let mut array = SafeArray::new(VarType::I4, vec![ArrayBound::new(5, 2)], ints(0..2)).unwrap();
+    array.set(&[6], Variant::from(42)).unwrap();
+    assert_eq!(array.elements(), &[Variant::from(0), Variant::from(42)]);
+    assert_eq!(array.set(&[4], Variant::from(1)).unwrap_err().hresult(), hresult::DISP_E_BADINDEX);
+    assert_eq!(array.set(&[7], Variant::from(1)).unwrap_err().hresult(), hresult::DISP_E_BADINDEX);
+    assert_eq!(array.set(&[5], Variant::from("one")).unwrap_err().hresult(), hresult::DISP_E_TYPEMISMATCH);
+    assert!(array.set(&[5], Variant::Null).is_err());
+
+    let mut variants = SafeArray::from_vec(VarType::Variant, ints(0..2)).unwrap();
+    variants.set(&[0], Variant::from("any")).unwrap();
+    variants.set(&[1], Variant::Null).unwrap();
+}
+
+#[test]
+fn null_objects() {
+    // new and set agree that a null reference can go in an array of objects
+    let sheet = MockObject::new("Worksheet").to_dispatch();
+    let mut array = SafeArray::from_vec(VarType::Dispatch, vec![Variant::from(sheet.clone()), Variant::Null]).unwrap();
+    array.set(&[0], Variant::Null).unwrap();
+    array.set(&[1], Variant::from(sheet)).unwrap();
+    assert!(array.set(&[0], Variant::from(1)).is_err());
+    assert!(SafeArray::from_vec(VarType::Unknown, vec![Variant::Null]).is_ok());
+    array.set(&[1], Variant::Nothing).unwrap();
+    assert!(SafeArray::from_vec(VarType::Unknown, vec![Variant::Nothing]).is_ok());
+    assert!(SafeArray::from_vec(VarType::Dispatch, vec![Variant::Empty]).is_err());
+}
+
+#[test]
+fn bounds_must_match() {
+    assert_eq!(SafeArray::new(VarType::I4, vec![ArrayBound::new(0, 2), ArrayBound::new(0, 2)], ints(0..3)).unwrap_err().hresult(), hresult::E_INVALIDARG);
+    assert_eq!(SafeArray::new(VarType::I4, vec![], vec![]).unwrap_err().hresult(), hresult::E_INVALIDARG);
+    assert!(SafeArray::new(VarType::I4, vec![ArrayBound::new(0, u32::MAX), ArrayBound::new(0, u32::MAX)], vec![]).is_err());
+}
+
+#[test]
+fn rows() {
+    let rows = vec![ints(0..3), ints(3..6)];
+    let array = SafeArray::from_rows(VarType::I4, rows.clone()).unwrap();
+    assert_eq!(array.bounds(), &[ArrayBound::new(0, 2), ArrayBound::new(0, 3)]);
+    // stored column by column
+    assert_eq!(array.elements(), &[0, 3, 1, 4, 2, 5].map(Variant::from));
+    assert_eq!(array.get(&[1, 2]), Some(&Variant::from(5)));
+    assert_eq!(array.to_rows().unwrap(), rows);
+
+    let value = Variant::try_from(rows.clone()).unwrap();
+    assert_eq!(Vec::<Vec<Variant>>::try_from(&value).unwrap(), rows);
+
+    // ragged rows can't make a rectangle
+    assert_eq!(SafeArray::from_rows(VarType::I4, vec![ints(0..3), ints(3..5)]).unwrap_err().hresult(), hresult::E_INVALIDARG);
+    assert!(Variant::try_from(vec![ints(0..1), ints(1..3)]).is_err());
+
+    let empty = SafeArray::from_rows(VarType::Variant, vec![]).unwrap();
+    assert_eq!(empty.to_rows().unwrap(), Vec::<Vec<Variant>>::new());
+    assert_eq!(SafeArray::from_vec(VarType::I4, ints(0..2)).unwrap().to_rows().unwrap_err().hresult(), hresult::E_INVALIDARG);
+}
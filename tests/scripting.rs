@@ -0,0 +1,60 @@
+//
+// real COM servers that ship with windows: Scripting.Dictionary and MSXML
+//
+
+#![cfg(windows)]
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::{clsid_from_prog_id, co_create_dispatch, Automation, ComApartment, Dispatch, Variant};
+
+fn dictionary() -> Dispatch {
+    let clsid = clsid_from_prog_id("Scripting.Dictionary").unwrap();
+    return co_create_dispatch(&clsid).unwrap();
+}
+
+#[test]
+fn indexed_item() {
+    let _apartment = ComApartment::sta().unwrap();
+    let dictionary = dictionary();
+    dictionary.call_method("Add", &[Variant::from("a"), Variant::from(1)]).unwrap();
+
+    // Item(key) is an indexed property, read and written with the key as its argument
+    assert_eq!(dictionary.get_property_with_args("Item", &[Variant::from("a")]).unwrap(), Variant::from(1));
+    dictionary.put_property_with_args("Item", &[Variant::from("a")], &Variant::from(2)).unwrap();
+    assert_eq!(dictionary.get_property_with_args("Item", &[Variant::from("a")]).unwrap(), Variant::from(2));
+
+    // and putting a key that isn't there yet adds it
+    dictionary.put_property_with_args("Item", &[Variant::from("b")], &Variant::from("two")).unwrap();
+    assert_eq!(dictionary.get_property("Count").unwrap(), Variant::from(2));
+    assert_eq!(dictionary.get_property_with_args("Item", &[Variant::from("b")]).unwrap(), Variant::from("two"));
+}
+
+#[test]
+fn put_ref() {
+    let _apartment = ComApartment::sta().unwrap();
+    let document = co_create_dispatch(&clsid_from_prog_id("MSXML2.DOMDocument.6.0").unwrap()).unwrap();
+    let schemas = co_create_dispatch(&clsid_from_prog_id("MSXML2.XMLSchemaCache.6.0").unwrap()).unwrap();
+
+    // schemas can only be assigned by reference, like vb's Set doc.schemas = cache
+    document.put_property_ref("schemas", &Variant::from(schemas)).unwrap();
+    assert!(document.get_property("schemas").unwrap().to_dispatch().is_ok());
+
+    // and Set doc.schemas = Nothing clears it
+    document.put_property_ref("schemas", &Variant::Nothing).unwrap();
+    assert!(document.get_property("schemas").unwrap().to_dispatch().is_err());
+}
+
+#[test]
+fn iterate_keys() {
+    let _apartment = ComApartment::sta().unwrap();
+    let dictionary = dictionary();
+    for (key, value) in [("a", 1), ("b", 2), ("c", 3)] {
+        dictionary.call_method("Add", &[Variant::from(key), Variant::from(value)]).unwrap();
+    }
+
+    // a dictionary's _NewEnum walks its keys
+    let items = dictionary.iter().with_batch_size(2);
+    assert!(items.is_enumerator());
+    let keys: Vec<Variant> = items.collect::<Result<_,_>>().unwrap();
+    assert_eq!(keys, vec![Variant::from("a"), Variant::from("b"), Variant::from("c")]);
+}
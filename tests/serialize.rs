@@ -0,0 +1,113 @@
+//
+// variants to and from json, both the plain values and the lossless tagged encoding
+//
+
+#![allow(clippy::needless_return)]
+
+use chrono::NaiveDate;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::{ArrayBound, Currency, Decimal, SafeArray, Tagged, Variant, VarType};
+use serde_json::json;
+
+fn to_json(value: &Variant) -> serde_json::Value {
+    return serde_json::to_value(value).unwrap();
+}
+
+fn from_json(value: serde_json::Value) -> Variant {
+    return serde_json::from_value(value).unwrap();
+}
+
+#[test]
+fn plain_values() {
+    assert_eq!(to_json(&Variant::Empty), json!(null));
+    assert_eq!(to_json(&Variant::Null), json!(null));
+    assert_eq!(to_json(&Variant::Nothing), json!(null));
+    assert_eq!(to_json(&Variant::UI1(7)), json!(7));
+    assert_eq!(to_json(&Variant::I8(-3_000_000_000)), json!(-3_000_000_000i64));
+    assert_eq!(to_json(&Variant::R8(2.5)), json!(2.5));
+    assert_eq!(to_json(&Variant::Bool(true)), json!(true));
+    assert_eq!(to_json(&Variant::from("Acme")), json!("Acme"));
+    assert_eq!(to_json(&Variant::Cy(Currency::from_raw(12_500))), json!("1.25"));
+    assert_eq!(to_json(&Variant::Decimal(Decimal::new(-12345, 3))), json!("-12.345"));
+    assert_eq!(to_json(&Variant::Error(0x800A07FAu32 as i32)), json!({"error": -2146826246}));
+
+    let noon = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().and_hms_opt(12, 0, 0).unwrap();
+    assert_eq!(to_json(&Variant::from(noon)), json!("2024-02-29T12:00:00"));
+    let later = noon + chrono::Duration::milliseconds(250);
+    assert_eq!(to_json(&Variant::from(later)), json!("2024-02-29T12:00:00.250"));
+
+    assert!(serde_json::to_value(Variant::from(MockObject::new("Workbook").to_dispatch())).is_err());
+}
+
+#[test]
+fn arrays() {
+    let list = Variant::from(vec![Variant::I4(1), Variant::from("two")]);
+    assert_eq!(to_json(&list), json!([1, "two"]));
+    assert_eq!(from_json(json!([1, "two"])), list);
+
+    // 2-D arrays are lists of rows, whatever their bounds
+    let rows = vec![vec![Variant::I4(1), Variant::I4(2)], vec![Variant::I4(3), Variant::I4(4)]];
+    let array = Variant::Array(SafeArray::from_rows(VarType::Variant, rows).unwrap());
+    assert_eq!(to_json(&array), json!([[1, 2], [3, 4]]));
+    assert_eq!(from_json(json!([[1, 2], [3, 4]])), array);
+    let based = SafeArray::new(VarType::I4, vec![ArrayBound::new(1, 2), ArrayBound::new(1, 1)], vec![Variant::I4(5), Variant::I4(6)]).unwrap();
+    assert_eq!(to_json(&Variant::Array(based)), json!([[5], [6]]));
+
+    // ragged lists stay a list of lists
+    let ragged = from_json(json!([[1], [2, 3]]));
+    let elements = ragged.to_array().unwrap();
+    assert_eq!(elements.dims(), 1);
+    assert_eq!(elements.elements()[1], Variant::from(vec![Variant::I4(2), Variant::I4(3)]));
+}
+
+#[test]
+fn deserialize() {
+    assert_eq!(from_json(json!(null)), Variant::Null);
+    assert_eq!(from_json(json!(true)), Variant::Bool(true));
+    assert_eq!(from_json(json!(42)), Variant::I4(42));
+    assert_eq!(from_json(json!(-3_000_000_000i64)), Variant::I8(-3_000_000_000));
+    assert_eq!(from_json(json!(u64::MAX)), Variant::UI8(u64::MAX));
+    assert_eq!(from_json(json!(0.5)), Variant::R8(0.5));
+    assert_eq!(from_json(json!("1.25")), Variant::from("1.25"));
+    assert_eq!(from_json(json!({"error": -2146826246})), Variant::Error(0x800A07FAu32 as i32));
+    assert!(serde_json::from_value::<Variant>(json!({"name": "Acme"})).is_err());
+    assert!(serde_json::from_value::<Variant>(json!({"error": 1, "name": "Acme"})).is_err());
+
+    // fields of a struct
+    #[derive(serde::Deserialize)]
+    struct Row {
+        name: Variant,
+        balance: Variant,
+    }
+    let row: Row = serde_json::from_str(r#"{"name": "Acme", "balance": 12.5}"#).unwrap();
+    assert_eq!((row.name, row.balance), (Variant::from("Acme"), Variant::R8(12.5)));
+}
+
+#[test]
+fn tagged() {
+    let values = vec![
+        Variant::Empty,
+        Variant::Null,
+        Variant::Nothing,
+        Variant::UI1(7),
+        Variant::Int(-1),
+        Variant::R4(f32::INFINITY),
+        Variant::Cy(Currency::from_raw(12_500)),
+        Variant::Decimal(Decimal::new(-12345, 3)),
+        Variant::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().into()),
+        Variant::Error(-2146826246),
+        Variant::Array(SafeArray::new(VarType::UI2, vec![ArrayBound::new(1, 2)], vec![Variant::UI2(1), Variant::UI2(2)]).unwrap()),
+    ];
+    for value in values {
+        let json = serde_json::to_string(&Tagged(value.clone())).unwrap();
+        let back: Tagged = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.0, value, "{}", json);
+    }
+    assert_eq!(serde_json::to_value(Tagged(Variant::UI1(7))).unwrap(), json!({"type": "vt_ui1", "value": 7}));
+    assert_eq!(serde_json::to_value(Tagged(Variant::Empty)).unwrap(), json!({"type": "vt_empty"}));
+    assert_eq!(serde_json::to_value(Tagged(Variant::Nothing)).unwrap(), json!({"type": "vt_dispatch"}));
+
+    assert!(serde_json::to_string(&Tagged(Variant::from(MockObject::new("Workbook").to_dispatch()))).is_err());
+    assert!(serde_json::from_value::<Tagged>(json!({"type": "vt_dispatch", "value": 0})).is_err());
+    assert!(serde_json::from_value::<Tagged>(json!({"type": "vt_i1", "value": 1000})).is_err());
+}
@@ -0,0 +1,131 @@
+//
+// what Dispatch::type_info hands back, checked with descriptions built by hand (or read from the
+// type library fixture) and given to a mock
+//
+
+#![allow(clippy::needless_return)]
+
+use serde_json::json;
+use hello_com_rust::hresult;
+use hello_com_rust::mock::MockObject;
+use hello_com_rust::typelib::*;
+use hello_com_rust::{Automation, InvokeKind, Variant};
+
+fn param(name: &str, type_desc: TypeDesc, flags: u16, default: Option<Variant>) -> Param {
+    return Param { name: name.to_string(), type_desc, flags, default };
+}
+
+// the way a running object describes itself, with the types it uses known only by name
+fn worksheet() -> TypeInfo {
+    let range = TypeRef::Named { name: "Range".to_string(), guid: Some("00020846-0000-0000-C000-000000000046".to_string()) };
+    return TypeInfo {
+        name: "_Worksheet".to_string(),
+        kind: TypeKind::Dispatch,
+        guid: Some("000208D8-0000-0000-C000-000000000046".to_string()),
+        doc: None,
+        flags: TYPEFLAG_FDUAL | TYPEFLAG_FDISPATCHABLE,
+        major_version: 1,
+        minor_version: 9,
+        implements: vec![ImplType { reference: TypeRef::Named { name: "IDispatch".to_string(), guid: None }, flags: 0 }],
+        alias: None,
+        functions: vec![
+            Function {
+                name: "Range".to_string(),
+                memid: 1,
+                invoke_kind: InvokeKind::PropertyGet,
+                doc: Some("The cells at an address".to_string()),
+                return_type: TypeDesc::Ptr(Box::new(TypeDesc::UserDefined(range))),
+                params: vec![
+                    param("Cell1", TypeDesc::Base(12), PARAMFLAG_FIN, None),
+                    param("Cell2", TypeDesc::Base(12), PARAMFLAG_FIN | PARAMFLAG_FOPT, None),
+                ],
+                optional_params: 1,
+                flags: 0,
+            },
+            Function {
+                name: "Protect".to_string(),
+                memid: 2,
+                invoke_kind: InvokeKind::Method,
+                doc: None,
+                return_type: TypeDesc::Base(VT_VOID),
+                params: vec![param("DrawingObjects", TypeDesc::Base(11), PARAMFLAG_FIN | PARAMFLAG_FHASDEFAULT, Some(Variant::Bool(true)))],
+                optional_params: 0,
+                flags: 0,
+            },
+        ],
+        variables: vec![Variable {
+            name: "Index".to_string(),
+            memid: 3,
+            doc: None,
+            type_desc: TypeDesc::Base(3),
+            flags: VARFLAG_FREADONLY,
+            value: None,
+        }],
+    };
+}
+
+#[test]
+fn from_a_mock() {
+    let dispatch = MockObject::new("Worksheet").with_type_info(worksheet()).to_dispatch();
+    let info = dispatch.type_info().unwrap();
+    assert_eq!(info, worksheet());
+    assert!(info.is_dual());
+
+    let range = info.functions_named("range").next().unwrap();
+    assert_eq!((range.memid, range.invoke_kind), (1, InvokeKind::PropertyGet));
+    let params: Vec<(&str,bool)> = range.params.iter().map(|p| (p.name.as_str(), p.is_optional())).collect();
+    assert_eq!(params, vec![("Cell1", false), ("Cell2", true)]);
+    assert_eq!(info.functions[1].params[0].default, Some(Variant::Bool(true)));
+    assert!(info.variable("index").unwrap().is_read_only());
+}
+
+// a type known only by name has nothing to resolve it to, but is still spelt out
+#[test]
+fn named_references() {
+    let library = TypeLibrary { name: "Excel".to_string(), doc: None, guid: None, major_version: 1, minor_version: 9, lcid: 0, types: vec![] };
+    let info = worksheet();
+    let range = &info.functions[0].return_type;
+    assert_eq!(library.type_desc_name(range), "Range*");
+    match range.pointee() {
+        TypeDesc::UserDefined(reference) => assert!(library.resolve(reference).is_none()),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn not_described() {
+    let dispatch = MockObject::new("Worksheet").to_dispatch();
+    assert_eq!(dispatch.type_info().unwrap_err().hresult(), hresult::E_NOTIMPL);
+}
+
+#[test]
+fn to_json() {
+    let json = serde_json::to_value(worksheet()).unwrap();
+    assert_eq!(json["name"], "_Worksheet");
+    assert_eq!(json["kind"], "Dispatch");
+    assert_eq!(json["implements"][0]["reference"], json!({"Named": {"name": "IDispatch", "guid": null}}));
+
+    let range = &json["functions"][0];
+    assert_eq!(range["memid"], 1);
+    assert_eq!(range["invoke_kind"], "PropertyGet");
+    assert_eq!(range["doc"], "The cells at an address");
+    assert_eq!(range["params"][1], json!({"name": "Cell2", "type_desc": {"Base": 12}, "flags": 0x11, "default": null}));
+    assert_eq!(range["return_type"]["Ptr"]["UserDefined"]["Named"]["name"], "Range");
+
+    // values keep their variant type
+    assert_eq!(json["functions"][1]["params"][0]["default"], json!({"type": "vt_bool", "value": true}));
+    assert_eq!(json["variables"][0]["flags"], VARFLAG_FREADONLY);
+}
+
+// a description read from a type library dumps the same way, constants and all
+#[test]
+fn library_to_json() {
+    let library = TypeLibrary::parse(include_bytes!("fixtures/sdo.tlb")).unwrap();
+    let json = serde_json::to_value(&library).unwrap();
+    let accounts = &json["types"][0];
+    assert_eq!(accounts["name"], "AccountType");
+    assert_eq!(accounts["variables"][2]["value"], json!({"type": "vt_i4", "value": -1}));
+    let workspace = library.find("IWorkSpace").unwrap().clone();
+    let dispatch = MockObject::new("WorkSpace").with_type_info(workspace.clone()).to_dispatch();
+    assert_eq!(serde_json::to_value(dispatch.type_info().unwrap()).unwrap(), serde_json::to_value(&workspace).unwrap());
+}
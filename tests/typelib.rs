@@ -0,0 +1,195 @@
+//
+// reads the fixtures made by tests/fixtures/make_typelibs.py, one type library in each format
+// with the same content, so most checks run against both
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::hresult;
+use hello_com_rust::typelib::*;
+use hello_com_rust::{InvokeKind, Variant};
+
+const MSFT: &[u8] = include_bytes!("fixtures/sdo.tlb");
+const SLTG: &[u8] = include_bytes!("fixtures/sdo_sltg.tlb");
+
+fn both() -> Vec<TypeLibrary> {
+    return vec![TypeLibrary::parse(MSFT).unwrap(), TypeLibrary::parse(SLTG).unwrap()];
+}
+
+#[test]
+fn library() {
+    for lib in both() {
+        assert_eq!(lib.name, "SDOTest");
+        assert_eq!(lib.doc.as_deref(), Some("Sage Data Objects test library"));
+        assert_eq!(lib.guid.as_deref(), Some("9C1D3A10-2B4E-4F5A-8C6D-7E8F90A1B2C3"));
+        assert_eq!((lib.major_version, lib.minor_version), (29, 0));
+        assert_eq!(lib.lcid, 0x409);
+        let names: Vec<(&str,TypeKind)> = lib.types.iter().map(|t| (t.name.as_str(), t.kind)).collect();
+        assert_eq!(names, vec![
+            ("AccountType", TypeKind::Enum),
+            ("Bounds", TypeKind::Record),
+            ("IFields", TypeKind::Dispatch),
+            ("IWorkSpace", TypeKind::Dispatch),
+            ("WorkSpace", TypeKind::CoClass),
+            ("AccountKind", TypeKind::Alias),
+            ("NameList", TypeKind::Alias),
+        ]);
+        assert_eq!(lib.types_of(TypeKind::Dispatch).count(), 2);
+        assert_eq!(lib.find("iworkspace").map(|t| t.name.as_str()), Some("IWorkSpace"));
+        assert!(lib.find("Missing").is_none());
+    }
+}
+
+#[test]
+fn enum_constants() {
+    for lib in both() {
+        let accounts = lib.find("AccountType").unwrap();
+        assert_eq!(accounts.guid, None);
+        let values: Vec<(&str,i32)> = accounts.variables.iter()
+            .map(|v| (v.name.as_str(), v.value.as_ref().unwrap().to_i32().unwrap()))
+            .collect();
+        assert_eq!(values, vec![("sdoSales", 1), ("sdoPurchase", 2), ("sdoNominal", -1)]);
+        assert_eq!(accounts.variable("sdoNominal").unwrap().type_desc, TypeDesc::Base(3));
+    }
+}
+
+#[test]
+fn record_fields() {
+    for lib in both() {
+        let bounds = lib.find("Bounds").unwrap();
+        let fields: Vec<(&str,String)> = bounds.variables.iter().map(|v| (v.name.as_str(), lib.type_desc_name(&v.type_desc))).collect();
+        assert_eq!(fields, vec![("Lower", "long".to_string()), ("Count", "long".to_string()), ("Reserved", "short[4]".to_string())]);
+        assert!(bounds.variables.iter().all(|v| v.value.is_none()));
+    }
+}
+
+#[test]
+fn dispinterface_methods() {
+    for lib in both() {
+        let workspace = lib.find("IWorkSpace").unwrap();
+        assert_eq!(workspace.guid.as_deref(), Some("A1B2C3D4-0002-4000-8000-00AA00BB00CC"));
+        let functions: Vec<(&str,i32,InvokeKind)> = workspace.functions.iter().map(|f| (f.name.as_str(), f.memid, f.invoke_kind)).collect();
+        assert_eq!(functions, vec![
+            ("Connect", 1, InvokeKind::Method),
+            ("Name", 2, InvokeKind::PropertyGet),
+            ("Name", 2, InvokeKind::PropertyPut),
+            ("Disconnect", 3, InvokeKind::Method),
+            ("CreateObject", 4, InvokeKind::Method),
+            ("Fields", 5, InvokeKind::PropertyGet),
+        ]);
+        assert_eq!(workspace.functions_named("name").count(), 2);
+
+        let connect = &workspace.functions[0];
+        assert_eq!(lib.type_desc_name(&connect.return_type), "VARIANT_BOOL");
+        let params: Vec<(&str,String,bool)> = connect.params.iter()
+            .map(|p| (p.name.as_str(), lib.type_desc_name(&p.type_desc), p.is_optional()))
+            .collect();
+        assert_eq!(params, vec![
+            ("DataPath", "BSTR".to_string(), false),
+            ("UserName", "BSTR".to_string(), false),
+            ("Password", "BSTR".to_string(), false),
+            ("Name", "VARIANT".to_string(), true),
+        ]);
+        assert_eq!(connect.optional_params, 1);
+
+        let put = &workspace.functions[2];
+        assert_eq!(lib.type_desc_name(&put.return_type), "void");
+        assert_eq!(put.params[0].name, "Value");
+
+        assert_eq!(lib.type_desc_name(&workspace.functions[4].return_type), "IDispatch*");
+        let fields = &workspace.functions[5];
+        assert_eq!(lib.type_desc_name(&fields.return_type), "IFields*");
+        match fields.return_type.pointee() {
+            TypeDesc::UserDefined(reference) => assert_eq!(lib.resolve(reference).map(|t| t.name.as_str()), Some("IFields")),
+            other => panic!("{:?}", other),
+        }
+        assert!(fields.params.iter().all(|p| p.is_optional() && !p.is_out() && !p.is_retval()));
+
+        let connected = workspace.variable("Connected").unwrap();
+        assert_eq!(connected.memid, 6);
+        assert!(connected.is_read_only());
+    }
+}
+
+#[test]
+fn hidden_new_enum() {
+    for lib in both() {
+        let fields = lib.find("IFields").unwrap();
+        let new_enum = fields.functions.iter().find(|f| f.memid == -4).unwrap();
+        assert_eq!(new_enum.name, "_NewEnum");
+        assert!(new_enum.is_hidden());
+        assert_eq!(new_enum.flags & FUNCFLAG_FRESTRICTED, FUNCFLAG_FRESTRICTED);
+        assert!(!fields.functions[0].is_hidden());
+    }
+}
+
+#[test]
+fn coclass_and_aliases() {
+    for lib in both() {
+        let coclass = lib.find("WorkSpace").unwrap();
+        assert_eq!(coclass.flags & TYPEFLAG_FCANCREATE, TYPEFLAG_FCANCREATE);
+        assert_eq!(coclass.implements.len(), 1);
+        assert!(coclass.implements[0].is_default());
+        assert!(!coclass.implements[0].is_source());
+        assert_eq!(lib.type_name(&coclass.implements[0].reference), "IWorkSpace");
+
+        let kind = lib.find("AccountKind").unwrap();
+        assert_eq!(kind.alias, Some(TypeDesc::UserDefined(TypeRef::Local(0))));
+        let names = lib.find("NameList").unwrap();
+        assert_eq!(lib.type_desc_name(names.alias.as_ref().unwrap()), "SAFEARRAY(BSTR)");
+    }
+}
+
+// only the MSFT format carries default values and member help strings
+#[test]
+fn msft_defaults_and_docs() {
+    let lib = TypeLibrary::parse(MSFT).unwrap();
+    assert_eq!(lib.find("AccountType").unwrap().doc.as_deref(), Some("Kinds of account"));
+    let workspace = lib.find("IWorkSpace").unwrap();
+    assert_eq!(workspace.doc.as_deref(), Some("A connection to a company"));
+    assert_eq!(workspace.functions[0].doc.as_deref(), Some("Connects to the company in DataPath"));
+    let defaults: Vec<Option<Variant>> = workspace.functions[5].params.iter().map(|p| p.default.clone()).collect();
+    assert_eq!(defaults, vec![Some(Variant::Bstr("ACCOUNT_REF".to_string())), Some(Variant::I4(1))]);
+    assert!(workspace.functions[0].params.iter().all(|p| p.default.is_none()));
+}
+
+#[test]
+fn sltg_imports_by_index() {
+    let lib = TypeLibrary::parse(SLTG).unwrap();
+    let create = &lib.find("IWorkSpace").unwrap().functions[4];
+    match create.return_type.pointee() {
+        TypeDesc::UserDefined(TypeRef::Imported { library, index, .. }) => {
+            assert_eq!(library.name, "stdole2.tlb");
+            assert_eq!((library.major_version, library.minor_version), (2, 0));
+            assert_eq!(*index, Some(4));
+        },
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn not_a_type_library() {
+    let err = TypeLibrary::parse(b"MZ\x90\x00 this is a dll").unwrap_err();
+    assert_eq!(err.hresult(), hresult::TYPE_E_CANTLOADLIBRARY);
+    let err = TypeLibrary::open("tests/fixtures/missing.tlb").unwrap_err();
+    assert_eq!(err.hresult(), hresult::TYPE_E_CANTLOADLIBRARY);
+}
+
+// no truncation of either fixture panics, and losing more than the trailing tables is an error
+#[test]
+fn truncated() {
+    for data in [MSFT, SLTG] {
+        for len in 0..data.len() {
+            let result = TypeLibrary::parse(&data[..len]);
+            if len < data.len() / 2 {
+                assert!(result.is_err(), "{} bytes parsed", len);
+            }
+        }
+    }
+}
+
+#[test]
+fn open_file() {
+    let lib = TypeLibrary::open("tests/fixtures/sdo.tlb").unwrap();
+    assert_eq!(lib, TypeLibrary::parse(MSFT).unwrap());
+}
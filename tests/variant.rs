@@ -0,0 +1,90 @@
+//
+// the platform neutral Variant model, which needs no COM runtime
+//
+
+#![allow(clippy::needless_return)]
+
+use hello_com_rust::hresult;
+use hello_com_rust::{SafeArray, VarType, Variant, VT_ARRAY_FLAG};
+
+#[test]
+fn var_types() {
+    for vt in 0..=0x50u16 {
+        match VarType::from_u16(vt) {
+            Some(var_type) => {
+                assert_eq!(var_type.to_u16(), vt);
+                assert_eq!(VarType::from_name(var_type.name()), Some(var_type));
+            },
+            None => assert!(vt == 15 || vt > 23, "vt {} should be known", vt),
+        }
+    }
+    assert_eq!(VarType::I4.to_string(), "vt_i4");
+    assert_eq!(VarType::from_name("vt_nothing"), None);
+    assert!(VarType::Variant.is_array_element() && VarType::Bstr.is_array_element());
+    assert!(!VarType::Empty.is_array_element() && !VarType::Null.is_array_element());
+}
+
+#[test]
+fn from_values() {
+    let cases: Vec<(Variant, VarType)> = vec![
+        (Variant::from(true), VarType::Bool),
+        (Variant::from("text"), VarType::Bstr),
+        (Variant::from(String::from("text")), VarType::Bstr),
+        (Variant::from(-1i8), VarType::I1),
+        (Variant::from(1u8), VarType::UI1),
+        (Variant::from(-1i16), VarType::I2),
+        (Variant::from(1u16), VarType::UI2),
+        (Variant::from(-1i32), VarType::I4),
+        (Variant::from(1u32), VarType::UI4),
+        (Variant::from(-1i64), VarType::I8),
+        (Variant::from(1u64), VarType::UI8),
+        (Variant::from(1.5f32), VarType::R4),
+        (Variant::from(1.5f64), VarType::R8),
+        (Variant::Int(1), VarType::Int),
+        (Variant::UInt(1), VarType::UInt),
+        (Variant::Error(hresult::E_FAIL), VarType::Error),
+        (Variant::Empty, VarType::Empty),
+        (Variant::Null, VarType::Null),
+    ];
+    for (value, vt) in cases {
+        assert_eq!(value.var_type(), vt);
+        assert_eq!(value.vt(), vt.to_u16());
+    }
+    assert!(Variant::default().is_empty() && Variant::empty().is_empty());
+    assert!(Variant::Null.is_null() && !Variant::Empty.is_null());
+    assert_eq!(Variant::missing(), Variant::Error(hresult::DISP_E_PARAMNOTFOUND));
+}
+
+#[test]
+fn arrays() {
+    let value = Variant::from(vec![Variant::from(1), Variant::from("two")]);
+    assert_eq!(value.var_type(), VarType::Variant);
+    assert_eq!(value.vt(), VT_ARRAY_FLAG | VarType::Variant.to_u16());
+    assert_eq!(value.to_array().unwrap().len(), 2);
+
+    let ints = SafeArray::from_vec(VarType::I4, vec![Variant::from(1), Variant::from(2)]).unwrap();
+    let value = Variant::from(ints);
+    assert_eq!(value.vt(), VT_ARRAY_FLAG | VarType::I4.to_u16());
+    assert!(SafeArray::from_vec(VarType::I4, vec![Variant::from("one")]).is_err());
+    assert!(SafeArray::from_vec(VarType::Empty, vec![]).is_err());
+    assert!(Variant::from(1).to_array().is_err());
+}
+
+#[test]
+fn formatting() {
+    assert_eq!(format!("{:?}", Variant::from(42)), "(vt_i4 42)");
+    assert_eq!(format!("{:?}", Variant::from("hi")), "(vt_bstr \"hi\")");
+    assert_eq!(format!("{:?}", Variant::Null), "<vt_null>");
+    assert_eq!(format!("{:?}", Variant::Error(hresult::E_FAIL)), "(vt_error 0x80004005)");
+    assert_eq!(Variant::from(42).to_string(), "42");
+    assert_eq!(Variant::Empty.to_string(), "<empty>");
+    assert_eq!(Variant::from(vec![Variant::from(1), Variant::from("a")]).to_string(), "[1, a]");
+    let rows = Variant::try_from(vec![vec![Variant::from(1), Variant::from(2)], vec![Variant::from(3), Variant::from(4)]]).unwrap();
+    assert_eq!(rows.to_string(), "[[1, 2], [3, 4]]");
+}
+
+#[test]
+fn not_objects() {
+    assert!(Variant::from(1).to_dispatch().is_err());
+    assert!(Variant::Null.into_dispatch().is_err());
+}